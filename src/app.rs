@@ -0,0 +1,8563 @@
+// Pinnothera's command line application
+
+// Standard Library Imports
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+// Third Party Imports
+use aws_sdk_sns::error::{CreateTopicError, SubscribeError};
+use aws_sdk_sns::model::{MessageAttributeValue, Tag};
+use aws_sdk_sns::Client as SNSClient;
+use aws_sdk_sqs::error::{CreateQueueError, ListQueueTagsError};
+use aws_sdk_sqs::model::QueueAttributeName;
+use aws_sdk_sqs::Client as SQSClient;
+use aws_sdk_sts::error::GetCallerIdentityError;
+use aws_sdk_sts::Client as STSClient;
+use aws_smithy_http::result::SdkError;
+use aws_smithy_types::date_time::{DateTime, Format as DateTimeFormat};
+use aws_smithy_types::retry::ProvideErrorKind;
+use easy_error::Terminator;
+use tokio::task::{JoinError, JoinHandle};
+
+// Project-Level Imports
+use crate::events::{EventStream, PinnEvent, StartupFailure, StartupFailureReport};
+use crate::logging::{debug, error, error_detail, info, warning};
+use crate::name_hook::NameHookRequest;
+use crate::report::ResourceStatus;
+use crate::settings::Settings;
+use crate::{
+    checkpoint, control, events, logging, name_hook, naming, plan, regions, report, retry, smoke,
+    stats, types,
+};
+use crate::{
+    AWSService, AppContext, AwsClients, CLIArgs, DeadLetterConfig, EnvName, PinnCommand,
+    PinnConfig, PinnError, PurgeArgs, RunState, SNSTopicARN, SQSQueueARN, SQSQueueConfig,
+    SQSQueueURL, SmokeTestArgs, TopicCell, TopicConfig,
+};
+
+// <editor-fold desc="// AWS Account Utilities ...">
+
+/// The environment variable the AWS account id is read from
+/// when neither `--aws-account-id` nor `--aws-role-arn` supply it
+const ACCOUNT_ID_ENV_VAR: &str = "AWS_ACCOUNT_ID";
+
+/// The AWS account id set in the `AWS_ACCOUNT_ID` environment
+/// variable, if it's set to a valid (12-digit) account id
+fn env_aws_account_id() -> Option<String> {
+    let account_id: String = std::env::var(ACCOUNT_ID_ENV_VAR).ok()?.trim().to_string();
+
+    match account_id.as_str() {
+        "" => None,
+        id if id.len() == 12 && id.chars().all(|c| c.is_ascii_digit()) => Some(account_id),
+        _ => {
+            warning!(
+                "Ignoring {}=\"{}\", which is not a 12-digit AWS account id",
+                ACCOUNT_ID_ENV_VAR,
+                account_id
+            );
+            None
+        }
+    }
+}
+
+/// Resolve the AWS account id from `--aws-account-id`, the account of
+/// the `--aws-role-arn` role, the `AWS_ACCOUNT_ID` environment variable,
+/// or (unless `--no-sts` was specified) the identity of the configured
+/// credentials, degrading to no account id if STS can't be reached or
+/// denies the request (i.e. per an SCP), but not if it rejects the
+/// configured credentials themselves
+async fn resolve_aws_account_id(
+    run: &RunState,
+    args: &CLIArgs,
+    env: EnvName,
+    region: Option<&str>,
+    sts_client: STSClient,
+) -> Result<Option<String>, SdkError<GetCallerIdentityError>> {
+    if let Some(account_id) = &args.aws_account_id {
+        info!(
+            "Using AWS account id \"{}\" from `--aws-account-id`",
+            account_id
+        );
+        return Ok(Some(account_id.to_string()));
+    } else if let Some(account_id) = args
+        .aws_role_arn
+        .as_ref()
+        .and_then(CLIArgs::role_account_id)
+    {
+        info!(
+            "Using AWS account id \"{}\" from `--aws-role-arn`",
+            account_id
+        );
+        return Ok(Some(account_id));
+    } else if let Some(account_id) = env_aws_account_id() {
+        info!(
+            "Using AWS account id \"{}\" from the {} environment variable",
+            account_id, ACCOUNT_ID_ENV_VAR
+        );
+        return Ok(Some(account_id));
+    } else if region.is_none() {
+        return Ok(None);
+    } else if args.dry_run {
+        info!(
+            "Not resolving the AWS account id via STS in a dry run, pass `--aws-account-id` (or set {}) to include queue access policies in the plan",
+            ACCOUNT_ID_ENV_VAR
+        );
+        return Ok(None);
+    } else if args.no_sts {
+        warning!(
+            "Not resolving the AWS account id via STS (`--no-sts` was specified), pass `--aws-account-id` to enable queue access policies and topic conflict descriptions"
+        );
+        return Ok(None);
+    } else if stats::record_api_call(run, "GetCallerIdentity", "the current AWS identity").is_err()
+    {
+        return Ok(None);
+    }
+
+    match sts_client.get_caller_identity().send().await {
+        Ok(identity) => {
+            let account_id: Option<String> = identity.account().map(str::to_string);
+
+            if let Some(account_id) = &account_id {
+                info!(
+                    "Using AWS account id \"{}\" from STS (the current AWS identity)",
+                    account_id
+                );
+            }
+
+            Ok(account_id)
+        }
+        Err(error) if !is_credential_error(&error) => {
+            warning!(
+                "Could not resolve the AWS account id via STS, continuing without one (queue access policies can't be created outside of local environments), pass `--aws-account-id` or set {} to avoid the STS call: {}",
+                ACCOUNT_ID_ENV_VAR,
+                logging::error_chain(&error)
+            );
+            print_endpoint_error_hint(run, args.service_endpoint_url(AWSService::Sts, env), &error);
+            Ok(None)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+// </editor-fold desc="// AWS Account Utilities ...">
+
+// <editor-fold desc="// Task Utilities ...">
+
+/// Await the supplied (context, task) pairs in parallel, resolving
+/// each task to its exit code and surfacing any panic that occurred
+/// inside of it along with the context it was processing
+async fn join_tasks(run: &RunState, tasks: Vec<(String, JoinHandle<u8>)>) -> Vec<u8> {
+    let (contexts, handles): (Vec<String>, Vec<JoinHandle<u8>>) = tasks.into_iter().unzip();
+
+    futures_util::future::join_all(handles)
+        .await
+        .into_iter()
+        .zip(contexts)
+        .map(|(result, context)| match result {
+            Ok(value) => value,
+            Err(error) => {
+                let reason = describe_join_error(error);
+                error!("Task for {} did not complete: {}", context, &reason);
+                report::record_failure(run, "task", context, reason);
+                1
+            }
+        })
+        .collect()
+}
+
+fn describe_join_error(error: JoinError) -> String {
+    if error.is_cancelled() {
+        return "task was cancelled".to_string();
+    }
+
+    match error.try_into_panic() {
+        Ok(payload) => {
+            let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                message.to_string()
+            } else if let Some(message) = payload.downcast_ref::<String>() {
+                message.clone()
+            } else {
+                "<non-string panic payload>".to_string()
+            };
+
+            format!("task panicked: {}", message)
+        }
+        Err(error) => error.to_string(),
+    }
+}
+
+// </editor-fold desc="// Task Utilities ...">
+
+// <editor-fold desc="// Error Utilities ...">
+
+/// Error codes AWS (or an AWS-compatible endpoint) returns
+/// when a request's signature or credentials are rejected
+const SIGNING_ERROR_CODES: [&str; 6] = [
+    "AuthFailure",
+    "IncompleteSignature",
+    "InvalidClientTokenId",
+    "InvalidSignatureException",
+    "SignatureDoesNotMatch",
+    "UnrecognizedClientException",
+];
+
+/// Error codes AWS returns when a request's signing time is
+/// too far from its own (i.e. because of local clock drift)
+const CLOCK_SKEW_ERROR_CODES: [&str; 3] = [
+    "RequestTimeTooSkewed",
+    "RequestExpired",
+    "RequestInTheFuture",
+];
+
+/// Whether the supplied error means AWS rejected the request's
+/// credentials or signature (including its signing time), which
+/// every subsequent request would fail with as well
+fn is_credential_error<E: ProvideErrorKind + std::fmt::Display>(error: &SdkError<E>) -> bool {
+    match error {
+        SdkError::ServiceError { err, .. } => err
+            .code()
+            .map(|code| {
+                SIGNING_ERROR_CODES.contains(&code) || CLOCK_SKEW_ERROR_CODES.contains(&code)
+            })
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// The number of seconds the local clock is ahead of (or, if negative,
+/// behind) that of the server that returned the supplied error, if the
+/// error was caused by clock skew and the server reported its time
+fn clock_skew<E: ProvideErrorKind + std::fmt::Display>(error: &SdkError<E>) -> Option<i64> {
+    let (err, raw) = match error {
+        SdkError::ServiceError { err, raw } => (err, raw),
+        _ => return None,
+    };
+
+    let skewed = err
+        .code()
+        .map(|code| CLOCK_SKEW_ERROR_CODES.contains(&code))
+        .unwrap_or(false)
+        || err.to_string().contains("Signature expired");
+
+    if !skewed {
+        return None;
+    }
+
+    let server_time = raw
+        .http()
+        .headers()
+        .get("date")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::from_str(value, DateTimeFormat::HttpDate).ok())?;
+
+    Some(DateTime::from(SystemTime::now()).secs() - server_time.secs())
+}
+
+/// A concise, single-line description of the supplied error
+fn error_message<E: std::error::Error + 'static>(error: &SdkError<E>) -> String {
+    match error {
+        SdkError::ServiceError { err, .. } => err.to_string(),
+        _ => logging::error_chain(error),
+    }
+}
+
+/// Print a hint about the likely cause of the supplied error
+/// (if one is known) for the user to check before retrying
+fn print_error_hint<E: ProvideErrorKind + std::fmt::Display>(
+    context: &AppContext,
+    error: &SdkError<E>,
+) {
+    print_endpoint_error_hint(&context.run, context.aws_endpoint_url(), error)
+}
+
+/// Print a hint about the likely cause of the supplied error (if one
+/// is known), for a request sent to the supplied custom endpoint (if any)
+fn print_endpoint_error_hint<E: ProvideErrorKind + std::fmt::Display>(
+    run: &RunState,
+    endpoint: Option<&str>,
+    error: &SdkError<E>,
+) {
+    let code = match error {
+        SdkError::ServiceError { err, .. } => err.code(),
+        _ => None,
+    };
+
+    if let Some(skew) = clock_skew(error) {
+        if !run.clock_skew_reported.swap(true, Ordering::Relaxed) {
+            warning!(
+                "AWS rejected a request's signing time ({}), this machine's clock is {}s {} the server's, check that its time is synchronized (i.e. via NTP)",
+                code.unwrap_or("Signature expired"),
+                skew.abs(),
+                match skew > 0 {
+                    true => "ahead of",
+                    false => "behind",
+                },
+            );
+        }
+        return;
+    }
+
+    if let (Some(code), Some(endpoint)) = (code, endpoint) {
+        if SIGNING_ERROR_CODES.contains(&code) {
+            warning!(
+                "The custom endpoint \"{}\" rejected the request's signature ({}), check that the configured AWS region and credentials match what the endpoint expects",
+                endpoint, code,
+            );
+        }
+    }
+}
+
+/// Print which resource (and which of its configured KMS keys) the
+/// supplied error concerns, if it was caused by the key being missing
+/// or unusable (SQS and SNS don't name the resource, only the key)
+fn print_kms_error_hint<E: ProvideErrorKind + std::fmt::Display>(
+    kind: &str,
+    resource: &str,
+    kms_key_id: Option<&str>,
+    error: &SdkError<E>,
+) {
+    let (kms_key_id, err) = match (kms_key_id, error) {
+        (Some(key), SdkError::ServiceError { err, .. }) => (key, err),
+        _ => return,
+    };
+
+    let code: &str = err.code().unwrap_or_default();
+
+    if code.to_lowercase().starts_with("kms") || err.to_string().to_lowercase().contains("kms") {
+        error!(
+            "The KMS key \"{}\" configured for {} \"{}\" does not exist or can't be used ({}), check the key's id and its key policy",
+            kms_key_id, kind, resource, code,
+        );
+    }
+}
+
+// </editor-fold desc="// Error Utilities ...">
+
+// <editor-fold desc="// Interactive Utilities ...">
+
+/// Ask the user to confirm a destructive action, treating anything other
+/// than an explicit "yes" (or a non-interactive session) as a refusal
+fn confirm_destructive_action<T: AsRef<str>>(prompt: T, assume_yes: bool, yes_flag: &str) -> bool {
+    if assume_yes {
+        return true;
+    } else if !std::io::stdin().is_terminal() {
+        error!("Refusing to {} without confirmation in a non-interactive session (pass `{}` to proceed)", prompt.as_ref(), yes_flag);
+        return false;
+    }
+
+    print!("Are you sure you want to {}? [y/N] ", prompt.as_ref());
+    std::io::stdout().flush().ok();
+
+    let mut answer = String::new();
+
+    match std::io::stdin().lock().read_line(&mut answer) {
+        Ok(_) => matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"),
+        Err(_) => false,
+    }
+}
+
+// </editor-fold desc="// Interactive Utilities ...">
+
+// <editor-fold desc="// Startup Failure Utilities ...">
+
+/// Report a fatal failure that occurred before any work could be done
+/// to the event stream (if one is configured) and, under `--output json`,
+/// as a JSON document on stderr, returning the exit code pinnothera
+/// should exit with
+fn startup_failure(
+    run: &RunState,
+    args: &CLIArgs,
+    category: StartupFailure,
+    messages: Vec<String>,
+    exit_code: u8,
+) -> ExitCode {
+    let report = StartupFailureReport {
+        category,
+        messages,
+        exit_code,
+    };
+
+    if args.output == "json" {
+        eprintln!("{}", report.to_document());
+    }
+
+    events::emit(
+        run,
+        PinnEvent::RunFailed {
+            category: report.category,
+            messages: report.messages,
+            exit_code: report.exit_code,
+        },
+    );
+
+    ExitCode::from(exit_code)
+}
+
+// </editor-fold desc="// Startup Failure Utilities ...">
+
+// <editor-fold desc="// Attribute Utilities ...">
+
+/// Merge the supplied raw (pass-through) attributes of a resource into
+/// its typed ones, letting the typed ones win (with a warning) on conflict
+fn merge_raw_attributes<R: AsRef<str>>(
+    resource: R,
+    mut typed: HashMap<String, String>,
+    raw: &BTreeMap<String, String>,
+) -> HashMap<String, String> {
+    raw.iter().for_each(|(name, value)| {
+        if typed.contains_key(name) {
+            warning!(
+                "The raw attribute \"{}\" of \"{}\" is shadowed by its typed setting and will be ignored",
+                name,
+                resource.as_ref()
+            );
+        } else {
+            typed.insert(name.to_string(), value.to_string());
+        }
+    });
+
+    typed
+}
+
+/// Determine whether the supplied desired attribute value differs from the
+/// existing one, comparing JSON documents (i.e. policies) structurally and
+/// anything else (i.e. booleans, which AWS reports capitalized or not) case
+/// insensitively
+fn attribute_value_differs(desired: &str, existing: &str) -> bool {
+    match (
+        serde_json::from_str::<serde_json::Value>(desired),
+        serde_json::from_str::<serde_json::Value>(existing),
+    ) {
+        (Ok(desired), Ok(existing)) => desired != existing,
+        _ => !desired.eq_ignore_ascii_case(existing),
+    }
+}
+
+// </editor-fold desc="// Attribute Utilities ...">
+
+// <editor-fold desc="// Tagging Utilities ...">
+
+/// The longest value AWS allows for a single tag
+const MAX_TAG_VALUE_LENGTH: usize = 256;
+
+/// The tags to set on the supplied queue's resources: those
+/// set via `--tag`, overridden by those in its configuration
+/// (less any dropped per `--tag-overflow drop-inherited`)
+fn resource_tags(context: &AppContext, config: &SQSQueueConfig) -> HashMap<String, String> {
+    let mut tags: HashMap<String, String> = context.settings.tags.iter().cloned().collect();
+
+    tags.extend(config.tags.clone());
+    tags.retain(|key, _| !config.dropped_tags.contains(key));
+
+    tags
+}
+
+/// Fail (as invalid) every queue whose merged tags would exceed AWS's
+/// limit or, with `--tag-overflow drop-inherited`, leave enough of its
+/// inherited tags off its resources to bring it within the limit
+pub(crate) fn resolve_tag_overflows(
+    settings: &Settings,
+    pinn_config: &mut PinnConfig,
+) -> Result<(), PinnError> {
+    let run_tags: BTreeMap<String, String> = settings.tags.iter().cloned().collect();
+    let drop_inherited: bool = settings.tag_overflow == "drop-inherited";
+    let mut problems: Vec<String> = Vec::new();
+
+    for (queue, overflow) in pinn_config.tag_overflows(&run_tags) {
+        match (drop_inherited, overflow.dropped()) {
+            (true, Some(dropped)) => {
+                warning!(
+                    "Dropping inherited tag(s) {:?} of queue \"{}\" (and its dead-letter queue and topics) to keep it within the limit of {} tags",
+                    &dropped,
+                    &queue,
+                    types::TagOverflow::LIMIT,
+                );
+                pinn_config.drop_tags(&queue, dropped);
+            }
+            (true, None) => problems.push(format!(
+                "{} (its own tags exceed the limit even without any inherited ones)",
+                overflow.describe(&queue)
+            )),
+            (false, _) => problems.push(format!(
+                "{} (pass `--tag-overflow drop-inherited` to drop inherited tags instead)",
+                overflow.describe(&queue)
+            )),
+        }
+    }
+
+    match problems.is_empty() {
+        true => Ok(()),
+        false => Err(PinnError::InvalidConfig { problems }),
+    }
+}
+
+/// The supplied tags along with pinnothera's bookkeeping tags: the one
+/// marking the queue as managed by pinnothera, and the one recording
+/// the supplied tags' keys (as many as fit in a tag's value), so that a
+/// later run can remove any of them that are no longer configured
+/// without touching anyone else's
+fn with_bookkeeping_tags(tags: &HashMap<String, String>) -> HashMap<String, String> {
+    let mut tagged: HashMap<String, String> = tags.clone();
+
+    tagged.insert(
+        types::MANAGED_BY_TAG.to_string(),
+        types::MANAGED_BY.to_string(),
+    );
+
+    if tags.is_empty() {
+        return tagged;
+    }
+
+    let mut managed: String = String::new();
+
+    for key in tags.keys().collect::<BTreeSet<&String>>() {
+        if managed.len() + key.len() + 1 > MAX_TAG_VALUE_LENGTH {
+            warning!(
+                "Not every tag key fits in the `{}` tag, so tags from \"{}\" onwards won't be removed if they're unconfigured later",
+                types::MANAGED_TAG_KEYS_TAG,
+                key
+            );
+            break;
+        }
+
+        if !managed.is_empty() {
+            managed.push(',');
+        }
+
+        managed.push_str(key);
+    }
+
+    tagged.insert(types::MANAGED_TAG_KEYS_TAG.to_string(), managed);
+    tagged
+}
+
+/// The keys of the tags pinnothera set on a resource with
+/// the supplied tags (per its bookkeeping tag), if any
+fn managed_tag_keys(existing: &HashMap<String, String>) -> BTreeSet<String> {
+    existing
+        .get(types::MANAGED_TAG_KEYS_TAG)
+        .map(|keys| {
+            keys.split(',')
+                .filter(|key| !key.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The existing tags of the queue with the supplied (resolved) name and URL
+async fn list_queue_tags(
+    context: &AppContext,
+    queue: &str,
+    queue_url: &str,
+) -> Result<Result<HashMap<String, String>, SdkError<ListQueueTagsError>>, PinnError> {
+    Ok(
+        retry::with_retries(&context.run, "ListQueueTags", queue, || {
+            context.sqs.list_queue_tags().queue_url(queue_url).send()
+        })
+        .await?
+        .map(|response| response.tags.unwrap_or_default()),
+    )
+}
+
+/// Report that the tags of the supplied resource couldn't be set,
+/// only failing the resource if `--strict-tags` was specified
+fn tagging_failed<E>(
+    context: &AppContext,
+    service: &'static str,
+    operation: &'static str,
+    resource: &str,
+    error: SdkError<E>,
+) -> Result<(), PinnError>
+where
+    E: ProvideErrorKind + std::error::Error + Send + Sync + 'static,
+{
+    events::operation_failed(&context.run, operation, resource, error_message(&error));
+
+    if context.settings.strict_tags {
+        error_detail!(
+            "Could not tag \"{}\": {}",
+            resource,
+            logging::error_chain(&error)
+        );
+        print_error_hint(context, &error);
+        return Err(PinnError::aws(service, operation, resource, error));
+    }
+
+    warning!(
+        "Could not tag \"{}\" (pass `--strict-tags` to fail it instead): {}",
+        resource,
+        error_message(&error)
+    );
+
+    Ok(())
+}
+
+/// Reconcile the tags of the existing queue with the supplied (resolved)
+/// name and URL with the supplied ones: setting any it's missing (or has
+/// with another value), and removing only those pinnothera set on an
+/// earlier run that are no longer configured, so that tags set by other
+/// tooling are left alone
+async fn tag_queue(
+    context: &AppContext,
+    queue: &str,
+    queue_url: &str,
+    tags: &HashMap<String, String>,
+) -> Result<(), PinnError> {
+    let existing: HashMap<String, String> = match list_queue_tags(context, queue, queue_url).await?
+    {
+        Ok(existing) => existing,
+        Err(error) => return tagging_failed(context, "sqs", "ListQueueTags", queue, error),
+    };
+
+    let desired: HashMap<String, String> = with_bookkeeping_tags(tags);
+    let previously_managed: BTreeSet<String> = managed_tag_keys(&existing);
+    let diff: TagDiff = TagDiff::new(&desired, &existing);
+
+    let untagged: Vec<String> = diff
+        .extra
+        .into_keys()
+        .filter(|key| {
+            previously_managed.contains(key) || types::RESERVED_TAGS.contains(&key.as_str())
+        })
+        .collect();
+
+    let tagged: Vec<(String, String)> = diff
+        .missing
+        .into_iter()
+        .chain(
+            diff.changed
+                .into_iter()
+                .map(|(key, (_, value))| (key, value)),
+        )
+        .collect::<BTreeMap<String, String>>()
+        .into_iter()
+        .collect();
+
+    // Untagging first keeps the queue within AWS's limit
+    for keys in untagged.chunks(types::MAX_RESOURCE_TAGS) {
+        match retry::with_retries(&context.run, "UntagQueue", queue, || {
+            context
+                .sqs
+                .untag_queue()
+                .queue_url(queue_url)
+                .set_tag_keys(Some(keys.to_vec()))
+                .send()
+        })
+        .await?
+        {
+            Ok(_) => {
+                info!(
+                    "Removed tag(s) no longer configured from queue \"{}\": {:?}",
+                    queue, keys
+                );
+                events::operation_succeeded(
+                    &context.run,
+                    "UntagQueue",
+                    queue,
+                    Some(queue_url),
+                    None,
+                );
+            }
+            Err(error) => return tagging_failed(context, "sqs", "UntagQueue", queue, error),
+        }
+    }
+
+    for batch in tagged.chunks(types::MAX_RESOURCE_TAGS) {
+        let batch: HashMap<String, String> = batch.iter().cloned().collect();
+
+        match retry::with_retries(&context.run, "TagQueue", queue, || {
+            context
+                .sqs
+                .tag_queue()
+                .queue_url(queue_url)
+                .set_tags(Some(batch.clone()))
+                .send()
+        })
+        .await?
+        {
+            Ok(_) => {
+                info!(
+                    "Tagged queue \"{}\" with: {:?}",
+                    queue,
+                    batch.keys().collect::<BTreeSet<&String>>()
+                );
+                events::operation_succeeded(&context.run, "TagQueue", queue, Some(queue_url), None);
+            }
+            Err(error) => return tagging_failed(context, "sqs", "TagQueue", queue, error),
+        }
+    }
+
+    Ok(())
+}
+
+/// Set the supplied tags on the (pinnothera-managed)
+/// topic with the supplied (resolved) name and ARN
+async fn tag_topic(
+    context: &AppContext,
+    topic: &str,
+    topic_arn: &str,
+    tags: &HashMap<String, String>,
+) -> Result<(), PinnError> {
+    if tags.is_empty() {
+        return Ok(());
+    }
+
+    let sns_tags: Vec<Tag> = tags
+        .iter()
+        .map(|(key, value)| Tag::builder().key(key).value(value).build())
+        .collect();
+
+    match retry::with_retries(&context.run, "TagResource", topic, || {
+        context
+            .sns
+            .tag_resource()
+            .resource_arn(topic_arn)
+            .set_tags(Some(sns_tags.clone()))
+            .send()
+    })
+    .await?
+    {
+        Ok(_) => {
+            info!("Tagged topic \"{}\" with: {:?}", topic, tags.keys());
+            events::operation_succeeded(&context.run, "TagResource", topic, None, Some(topic_arn));
+            Ok(())
+        }
+        Err(error) => tagging_failed(context, "sns", "TagResource", topic, error),
+    }
+}
+
+/// How a resource's existing tags differ from its desired ones
+#[derive(Debug, Default, PartialEq)]
+struct TagDiff {
+    /// Desired tags the resource doesn't have at all
+    missing: BTreeMap<String, String>,
+    /// Desired tags the resource has with another value,
+    /// as (existing value, desired value)
+    changed: BTreeMap<String, (String, String)>,
+    /// Tags the resource has that aren't desired
+    extra: BTreeMap<String, String>,
+}
+
+impl TagDiff {
+    /// Compare the supplied existing tags against the supplied desired ones
+    fn new(desired: &HashMap<String, String>, existing: &HashMap<String, String>) -> TagDiff {
+        let mut diff: TagDiff = TagDiff::default();
+
+        for (key, value) in desired {
+            match existing.get(key) {
+                None => {
+                    diff.missing.insert(key.clone(), value.clone());
+                }
+                Some(current) if current != value => {
+                    diff.changed
+                        .insert(key.clone(), (current.clone(), value.clone()));
+                }
+                Some(_) => (),
+            }
+        }
+
+        diff.extra = existing
+            .iter()
+            .filter(|(key, _)| !desired.contains_key(*key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        diff
+    }
+
+    /// Describe each difference (other than extra tags, if they're
+    /// to be ignored), in the same form as differing attributes
+    fn describe(&self, ignore_extra: bool) -> Vec<String> {
+        let missing = self
+            .missing
+            .iter()
+            .map(|(key, value)| format!("tag \"{}\": missing (configured: \"{}\")", key, value));
+        let changed = self.changed.iter().map(|(key, (current, value))| {
+            format!(
+                "tag \"{}\": \"{}\" (configured: \"{}\")",
+                key, current, value
+            )
+        });
+        let extra = self
+            .extra
+            .iter()
+            .filter(|(key, _)| !ignore_extra && !types::RESERVED_TAGS.contains(&key.as_str()))
+            .map(|(key, value)| format!("tag \"{}\": \"{}\" (not configured)", key, value));
+
+        missing.chain(changed).chain(extra).collect()
+    }
+}
+
+/// Compare the tags of the supplied existing queue against the
+/// supplied desired tags, describing any differences
+async fn queue_tag_differences(
+    context: &AppContext,
+    queue: &str,
+    queue_url: &str,
+    tags: &HashMap<String, String>,
+) -> Result<Vec<String>, PinnError> {
+    let existing: HashMap<String, String> = match list_queue_tags(context, queue, queue_url).await?
+    {
+        Ok(existing) => existing,
+        Err(error) => {
+            error_detail!(
+                "Could not get the tags of queue \"{}\": {}",
+                queue,
+                logging::error_chain(&error)
+            );
+            print_error_hint(context, &error);
+            events::operation_failed(&context.run, "ListQueueTags", queue, error_message(&error));
+            return Err(PinnError::aws("sqs", "ListQueueTags", queue, error));
+        }
+    };
+
+    Ok(TagDiff::new(tags, &existing).describe(context.settings.ignore_extra_tags))
+}
+
+/// Compare the tags of the supplied existing (pinnothera-managed)
+/// topic against the supplied desired tags, describing any differences
+async fn topic_tag_differences(
+    context: &AppContext,
+    topic: &str,
+    topic_arn: &str,
+    tags: &HashMap<String, String>,
+) -> Result<Vec<String>, PinnError> {
+    let existing: HashMap<String, String> =
+        match retry::with_retries(&context.run, "ListTagsForResource", topic, || {
+            context
+                .sns
+                .list_tags_for_resource()
+                .resource_arn(topic_arn)
+                .send()
+        })
+        .await?
+        {
+            Ok(response) => response
+                .tags
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|tag| Some((tag.key?, tag.value?)))
+                .collect(),
+            Err(error) => {
+                error_detail!(
+                    "Could not get the tags of topic \"{}\": {}",
+                    topic,
+                    logging::error_chain(&error)
+                );
+                print_error_hint(context, &error);
+                events::operation_failed(
+                    &context.run,
+                    "ListTagsForResource",
+                    topic,
+                    error_message(&error),
+                );
+                return Err(PinnError::aws("sns", "ListTagsForResource", topic, error));
+            }
+        };
+
+    Ok(TagDiff::new(tags, &existing).describe(context.settings.ignore_extra_tags))
+}
+
+// </editor-fold desc="// Tagging Utilities ...">
+
+// <editor-fold desc="// Dry-Run Utilities ...">
+
+/// Report the supplied operation as one that would have been performed
+/// if the current run weren't a dry run, returning a placeholder ARN
+/// for the affected resource
+fn dry_run<R: AsRef<str>>(
+    context: &AppContext,
+    operation: &'static str,
+    kind: &'static str,
+    resource: R,
+    action: &str,
+) -> String {
+    let resource: &str = resource.as_ref();
+
+    info!("[dry-run] Would {}: \"{}\"", action, resource);
+    stats::record_planned_operation(&context.run, kind, resource);
+    events::emit(
+        &context.run,
+        PinnEvent::OperationNotAttempted {
+            operation,
+            resource: resource.to_string(),
+            reason: "dry run".to_string(),
+        },
+    );
+
+    format!("<arn of {}>", resource)
+}
+
+/// Print (with `--explain`) each of the supplied reasons the
+/// supplied resource is being planned or ensured the way it is
+fn explain<R: AsRef<str>>(
+    context: &AppContext,
+    kind: &'static str,
+    resource: R,
+    reasons: &[String],
+) {
+    if !context.settings.explain {
+        return;
+    }
+
+    reasons
+        .iter()
+        .for_each(|reason| info!("[explain] {} \"{}\": {}", kind, resource.as_ref(), reason));
+}
+
+/// The supplied existing and desired values of an attribute, as
+/// explained (with `--explain`) when the existing value is updated
+/// (JSON documents, i.e. policies, are compacted onto a single line)
+fn explain_change(name: &str, existing: Option<&String>, desired: &str) -> String {
+    let display = |value: &str| -> String {
+        match serde_json::from_str::<serde_json::Value>(value) {
+            _ if value.is_empty() => "(unset)".to_string(),
+            Ok(document @ (serde_json::Value::Object(_) | serde_json::Value::Array(_))) => {
+                document.to_string()
+            }
+            _ => format!("{:?}", value),
+        }
+    };
+
+    format!(
+        "{} differs: {} -> {}",
+        name,
+        display(existing.map(|value| value.as_str()).unwrap_or_default()),
+        display(desired),
+    )
+}
+
+// </editor-fold desc="// Dry-Run Utilities ...">
+
+// <editor-fold desc="// Resource Naming Utilities ...">
+
+/// The request the name hook (if any) receives for
+/// the supplied configured queue or topic name
+fn name_hook_request<T: AsRef<str>>(
+    context: &AppContext,
+    kind: &'static str,
+    name: T,
+    env: EnvName,
+    fifo: bool,
+) -> NameHookRequest {
+    NameHookRequest {
+        base_name: name.as_ref().to_string(),
+        kind,
+        env: env.as_suffix().to_string(),
+        namespace: context.settings.namespace.clone(),
+        fifo,
+    }
+}
+
+/// Resolve the supplied configured queue or topic name into the name used
+/// in AWS, either as supplied by the name hook or sanitized and/or suffixed
+fn resolve_name<T: AsRef<str>>(
+    context: &AppContext,
+    kind: &'static str,
+    name: T,
+    env: EnvName,
+    fifo: bool,
+) -> String {
+    if let Some(hooked) =
+        context.hooked_name(&name_hook_request(context, kind, name.as_ref(), env, fifo))
+    {
+        return hooked;
+    }
+
+    let (name, resolved) = apply_env_name(context, kind, name, env, fifo);
+
+    if !env.is_unknown() {
+        info!(
+            "{} {} \"{}\" as \"{}\" per in-cluster configuration...",
+            match context.settings.env_naming().position {
+                naming::EnvPosition::Prefix => "Prefixing",
+                naming::EnvPosition::Suffix => "Suffixing",
+            },
+            kind,
+            name.as_str(),
+            resolved.as_str(),
+        );
+    }
+
+    resolved
+}
+
+/// Resolve the supplied configured queue or topic name into the
+/// name used in AWS (exactly like `resolve_name`) without logging it
+fn resolved_name<T: AsRef<str>>(
+    context: &AppContext,
+    kind: &'static str,
+    name: T,
+    env: EnvName,
+    fifo: bool,
+) -> String {
+    context
+        .hooked_name(&name_hook_request(context, kind, name.as_ref(), env, fifo))
+        .unwrap_or_else(|| apply_env_name(context, kind, name, env, fifo).1)
+}
+
+/// The supplied configured queue or topic name sanitized, and the
+/// sanitized name with the supplied environment applied to it (per
+/// `--env-position` and `--env-delimiter`), leaving topics configured
+/// by ARN untouched
+fn apply_env_name<T: AsRef<str>>(
+    context: &AppContext,
+    kind: &'static str,
+    name: T,
+    env: EnvName,
+    fifo: bool,
+) -> (String, String) {
+    if kind == "topic" && naming::is_topic_arn(name.as_ref()) {
+        return (name.as_ref().to_string(), name.as_ref().to_string());
+    }
+
+    let name: String = context
+        .settings
+        .sanitize(kind, naming::strip_fifo_extension(name.as_ref()));
+
+    let resolved: String = naming::apply_env_name(
+        &name,
+        match env.is_unknown() {
+            true => None,
+            false => Some(env.as_suffix()),
+        },
+        &context.settings.env_naming(),
+        fifo,
+    );
+
+    (name, resolved)
+}
+
+/// Why the supplied configured queue or topic name resolves to the
+/// name used in AWS that it does, as explained by `--explain`
+fn explain_name<T: AsRef<str>>(
+    context: &AppContext,
+    kind: &'static str,
+    name: T,
+    env: EnvName,
+    config: &SQSQueueConfig,
+) -> String {
+    let request = name_hook_request(context, kind, name.as_ref(), env, config.is_fifo());
+
+    if let Some(hooked) = context.hooked_name(&request) {
+        return format!("named \"{}\" by `--name-hook`", hooked);
+    }
+
+    let (sanitized, resolved) = apply_env_name(context, kind, name.as_ref(), env, config.is_fifo());
+    let position: &str = context.settings.env_naming().position.as_str();
+    let mut reasons: Vec<String> = Vec::new();
+
+    if sanitized != naming::strip_fifo_extension(name.as_ref()) {
+        reasons.push("sanitized per `--sanitize-names`".to_string());
+    }
+
+    let verbatim: bool = match kind {
+        "topic" => config.is_verbatim_topic(name.as_ref()),
+        _ => config.is_verbatim(),
+    };
+
+    reasons.push(match (env.is_unknown(), &config.env) {
+        (true, _) if verbatim => format!("not {}ed (used verbatim per `suffix: false`)", position),
+        (true, _) => format!("not {}ed (the environment is unknown)", position),
+        (false, Some(_)) => format!(
+            "{}ed with \"{}\" per the queue's `env` override",
+            position,
+            env.as_suffix()
+        ),
+        (false, None) => format!(
+            "{}ed with \"{}\" (the cluster's environment)",
+            position,
+            env.as_suffix()
+        ),
+    });
+
+    if config.is_fifo() {
+        reasons.push("given the `.fifo` extension per `fifo: true`".to_string());
+    }
+
+    format!("named \"{}\": {}", resolved, reasons.join(", "))
+}
+
+/// Run the configured name hook (if any) for every queue
+/// and topic in the configuration before any are created
+pub(crate) fn resolve_hooked_names(
+    context: &mut AppContext,
+    pinn_config: &PinnConfig,
+) -> Result<(), PinnError> {
+    let command: &str = match &context.settings.name_hook {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+
+    let mut requests: Vec<NameHookRequest> = Vec::new();
+
+    pinn_config.iter().for_each(|(queue, config)| {
+        let env: EnvName = config.env_name(context.env);
+
+        // Verbatim names are used as configured, so they're never hooked
+        if queue.as_str() != "unsubscribed" && !config.is_verbatim() {
+            requests.push(name_hook_request(
+                context,
+                "queue",
+                queue,
+                env,
+                config.is_fifo(),
+            ));
+        }
+
+        if let (Some(dead_letter), false) = (&config.dead_letter, config.is_verbatim()) {
+            requests.push(name_hook_request(
+                context,
+                "queue",
+                dead_letter.queue_name(queue),
+                env,
+                config.is_fifo(),
+            ));
+        }
+
+        config
+            .topics
+            .iter()
+            .filter(|topic| !topic.is_verbatim() && !topic.is_arn())
+            .for_each(|topic| {
+                requests.push(name_hook_request(
+                    context,
+                    "topic",
+                    topic.as_str(),
+                    env,
+                    config.is_fifo(),
+                ))
+            });
+    });
+
+    context.hooked_names = name_hook::resolve_names(&context.run, command, requests)?;
+
+    Ok(())
+}
+
+// </editor-fold desc="// Resource Naming Utilities ...">
+
+// <editor-fold desc="// SNS Topic Utilities ...">
+
+/// The attributes pinnothera expects every topic it manages to have
+fn expected_topic_attributes(fifo: bool) -> [(&'static str, &'static str); 1] {
+    [("FifoTopic", if fifo { "true" } else { "false" })]
+}
+
+/// Determine whether the supplied error indicates that a topic with the
+/// requested name already exists, but with different attributes (i.e. it
+/// exists as a FIFO topic but is configured as a standard one)
+fn is_topic_conflict(error: &SdkError<CreateTopicError>) -> bool {
+    match error {
+        SdkError::ServiceError { err, .. } => {
+            err.is_invalid_parameter_exception()
+                && err
+                    .message()
+                    .map(|message| message.to_lowercase().contains("already exists"))
+                    .unwrap_or(false)
+        }
+        _ => false,
+    }
+}
+
+/// Describe how the attributes of the existing topic with the supplied
+/// name differ from the expected ones (where that's determinable)
+async fn describe_topic_conflict<T: AsRef<str>>(
+    context: &AppContext,
+    topic: T,
+    fifo: bool,
+) -> Vec<String> {
+    let topic: &str = topic.as_ref();
+
+    let topic_arn = match (context.aws_region(), context.aws_account_id()) {
+        (Some(region), Some(account_id)) => {
+            format!(
+                "arn:{}:sns:{}:{}:{}",
+                regions::partition(region).name,
+                region,
+                account_id,
+                topic
+            )
+        }
+        _ => {
+            info!(
+                "Cannot describe the attributes of topic \"{}\" without a region and account id",
+                topic
+            );
+            return Vec::new();
+        }
+    };
+
+    let attributes = match retry::with_retries(&context.run, "GetTopicAttributes", topic, || {
+        context
+            .sns
+            .get_topic_attributes()
+            .topic_arn(&topic_arn)
+            .send()
+    })
+    .await
+    {
+        Ok(Ok(response)) => response.attributes.unwrap_or_default(),
+        Ok(Err(error)) => {
+            events::operation_failed(
+                &context.run,
+                "GetTopicAttributes",
+                topic,
+                error_message(&error),
+            );
+            return Vec::new();
+        }
+        Err(_) => return Vec::new(),
+    };
+
+    expected_topic_attributes(fifo)
+        .iter()
+        .filter_map(|(name, expected)| {
+            let existing: &str = attributes
+                .get(*name)
+                .map(|value| value.as_str())
+                .unwrap_or(expected);
+
+            match existing.eq_ignore_ascii_case(expected) {
+                true => None,
+                false => Some(format!(
+                    "{}: \"{}\" (configured: \"{}\")",
+                    name, existing, expected
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Ensure the existence of the supplied (configured) topic, creating it
+/// at most once per run no matter how many queues subscribe to it (with
+/// any concurrent callers awaiting the outcome of the first one)
+async fn create_topic<T: AsRef<str>>(
+    context: Arc<AppContext>,
+    topic: T,
+    env: EnvName,
+    config: &SQSQueueConfig,
+) -> Result<SNSTopicARN, Arc<PinnError>> {
+    // Topics configured by ARN (i.e. ones in other accounts)
+    // are subscribed to as-is, never created or looked up
+    if naming::is_topic_arn(topic.as_ref()) {
+        explain(
+            &context,
+            "topic",
+            topic.as_ref(),
+            &["configured by ARN (only subscribed to, never created or modified)".to_string()],
+        );
+        report::record(
+            &context.run,
+            "topic",
+            topic.as_ref(),
+            ResourceStatus::Existing,
+            Some("configured by ARN (not managed by pinnothera)".to_string()),
+        );
+        return Ok(topic.as_ref().to_string());
+    }
+
+    // Likewise, topics in other accounts are only ever subscribed to
+    if let Some(account_id) = config.topic_account_id(topic.as_ref()) {
+        let region: &str = match context.aws_region() {
+            Some(region) => region,
+            None => {
+                return Err(Arc::new(PinnError::config(format!(
+                    "The ARN of topic \"{}\" (in account {}) can't be determined without `--aws-region`",
+                    topic.as_ref(),
+                    account_id
+                ))))
+            }
+        };
+        let topic_arn: SNSTopicARN =
+            account_topic_arn(&context, region, account_id, topic.as_ref(), env, config);
+
+        explain(
+            &context,
+            "topic",
+            &topic_arn,
+            &[format!(
+                "in account {} (only subscribed to, never created or modified)",
+                account_id
+            )],
+        );
+        report::record(
+            &context.run,
+            "topic",
+            &topic_arn,
+            ResourceStatus::Existing,
+            Some(format!(
+                "in account {} (not managed by pinnothera)",
+                account_id
+            )),
+        );
+        return Ok(topic_arn);
+    }
+
+    let env: EnvName = config.topic_name_env(topic.as_ref(), env);
+    let reasons: Vec<String> = explain_topic(&context, topic.as_ref(), env, config);
+    let external: bool = config.is_external_topic(topic.as_ref());
+    let kms_key_id: Option<String> = config.topic_kms_key_id(topic.as_ref()).map(str::to_string);
+    let topic: String = resolve_name(&context, "topic", topic, env, config.is_fifo());
+
+    let cell: Arc<TopicCell> = context.topic_cell(&topic);
+
+    cell.get_or_init(|| async {
+        explain(&context, "topic", &topic, &reasons);
+
+        let result = match external {
+            true => find_external_topic(&context, topic.clone()).await,
+            false => {
+                stats::timed(
+                    &context.run,
+                    "topic",
+                    topic.clone(),
+                    ensure_topic(&context, topic.clone(), config, kms_key_id.as_deref()),
+                )
+                .await
+            }
+        };
+
+        match &result {
+            Ok(_) if external => report::record(
+                &context.run,
+                "topic",
+                &topic,
+                ResourceStatus::Existing,
+                Some("external (not managed by pinnothera)".to_string()),
+            ),
+            Ok(_) if context.settings.dry_run => {
+                report::record(&context.run, "topic", &topic, ResourceStatus::Planned, None)
+            }
+            Ok(_) if context.settings.check => report::record(
+                &context.run,
+                "topic",
+                &topic,
+                ResourceStatus::Existing,
+                None,
+            ),
+            Ok(_) => report::record(&context.run, "topic", &topic, ResourceStatus::Ensured, None),
+            Err(error) => report::record_error(&context.run, "topic", &topic, error),
+        }
+
+        result.map_err(Arc::new)
+    })
+    .await
+    .clone()
+}
+
+/// Where the supplied (configured) topic is configured, and which
+/// settings shape it, as explained by `--explain`
+fn explain_topic(
+    context: &AppContext,
+    topic: &str,
+    env: EnvName,
+    config: &SQSQueueConfig,
+) -> Vec<String> {
+    if !context.settings.explain {
+        return Vec::new();
+    }
+
+    let mut reasons: Vec<String> = config
+        .source
+        .iter()
+        .map(|source| format!("configured in {}", source))
+        .collect();
+
+    reasons.push(explain_name(context, "topic", topic, env, config));
+
+    if config.is_fifo() {
+        reasons.push("FIFO per its queue's `fifo: true`".to_string());
+    }
+
+    if config.is_external_topic(topic) {
+        reasons.push(
+            "external per its `external: true` (only subscribed to, never created or modified)"
+                .to_string(),
+        );
+    }
+
+    if let Some(key) = config.topic_kms_key_id(topic) {
+        reasons.push(format!(
+            "encrypted with KMS key \"{}\" per its `kms_key_id`",
+            key
+        ));
+    }
+
+    if !config.raw_topic_attributes.is_empty() {
+        reasons.push(format!(
+            "raw attribute(s) {:?} per its queue's `raw_topic_attributes`",
+            config.raw_topic_attributes.keys().collect::<Vec<&String>>()
+        ));
+    }
+
+    let tags: HashMap<String, String> = resource_tags(context, config);
+
+    if !tags.is_empty() && !config.is_external_topic(topic) {
+        reasons.push(format!(
+            "tagged with {:?} per `--tag` and its queue's `tags`",
+            tags.keys().collect::<BTreeSet<&String>>()
+        ));
+    }
+
+    reasons
+}
+
+async fn ensure_topic(
+    context: &AppContext,
+    topic: String,
+    config: &SQSQueueConfig,
+    kms_key_id: Option<&str>,
+) -> Result<SNSTopicARN, PinnError> {
+    info!("Ensuring existence of topic: \"{}\"", &topic);
+
+    let mut attributes: HashMap<String, String> = HashMap::new();
+
+    if config.is_fifo() {
+        attributes.insert("FifoTopic".to_string(), "true".to_string());
+    }
+
+    let attributes = merge_raw_attributes(&topic, attributes, &config.raw_topic_attributes);
+
+    if context.settings.dry_run {
+        plan::record_topic(
+            &context.run,
+            &topic,
+            attributes
+                .into_iter()
+                .chain(kms_key_id.map(|key| ("KmsMasterKeyId".to_string(), key.to_string()))),
+            &resource_tags(context, config),
+        );
+        return Ok(dry_run(
+            context,
+            "CreateTopic",
+            "topic",
+            &topic,
+            "create topic",
+        ));
+    }
+
+    if context.settings.check {
+        let mut desired: HashMap<String, String> = attributes;
+
+        if let Some(key) = kms_key_id {
+            desired.insert("KmsMasterKeyId".to_string(), key.to_string());
+        }
+
+        return check_topic(
+            context,
+            topic,
+            config.is_fifo(),
+            desired,
+            &resource_tags(context, config),
+        )
+        .await;
+    }
+
+    let resp = match retry::with_retries(&context.run, "CreateTopic", &topic, || {
+        context
+            .sns
+            .create_topic()
+            .name(&topic)
+            .set_attributes(Some(attributes.clone()))
+            .send()
+    })
+    .await?
+    {
+        Ok(response) => response,
+        Err(error) if is_topic_conflict(&error) => {
+            let differences = describe_topic_conflict(context, &topic, config.is_fifo()).await;
+
+            warning!(
+                "Topic \"{}\" exists with incompatible type/attributes{}, it must be resolved manually (pinnothera will not delete or recreate it)",
+                &topic,
+                match differences.is_empty() {
+                    true => String::new(),
+                    false => format!(" {:?}", &differences),
+                },
+            );
+            events::operation_failed(
+                &context.run,
+                "CreateTopic",
+                &topic,
+                "topic exists with incompatible type/attributes",
+            );
+            return Err(PinnError::IncompatibleTopic { topic });
+        }
+        Err(error) => {
+            error_detail!(
+                "Could not create topic \"{}\": {}",
+                &topic,
+                logging::error_chain(&error)
+            );
+            print_error_hint(context, &error);
+            events::operation_failed(&context.run, "CreateTopic", &topic, error_message(&error));
+            return Err(PinnError::aws("sns", "CreateTopic", &topic, error));
+        }
+    };
+
+    match resp.topic_arn() {
+        None => {
+            error!(
+                "Creation of topic \"{}\" did not return an error, but did not return an ARN as expected",
+                &topic
+            );
+            Err(PinnError::missing_field("CreateTopic", &topic, "TopicArn"))
+        }
+        Some(value) => {
+            info!(
+                topic_arn = value,
+                "Topic \"{}\" exists with ARN: \"{}\"", &topic, value,
+            );
+            events::operation_succeeded(&context.run, "CreateTopic", &topic, None, Some(value));
+
+            if let Some(key) = kms_key_id {
+                set_topic_encryption(context, &topic, value, key).await?;
+            }
+
+            tag_topic(context, &topic, value, &resource_tags(context, config)).await?;
+            Ok(value.to_string())
+        }
+    }
+}
+
+/// Encrypt the supplied (resolved) topic with the supplied KMS key (or
+/// re-encrypt it with the key, if it's encrypted with another one), which
+/// is set separately since `CreateTopic` fails for an existing topic if
+/// any of the attributes passed to it differ from the topic's own
+async fn set_topic_encryption(
+    context: &AppContext,
+    topic: &str,
+    topic_arn: &str,
+    kms_key_id: &str,
+) -> Result<(), PinnError> {
+    match retry::with_retries(&context.run, "SetTopicAttributes", topic, || {
+        context
+            .sns
+            .set_topic_attributes()
+            .topic_arn(topic_arn)
+            .attribute_name("KmsMasterKeyId")
+            .attribute_value(kms_key_id)
+            .send()
+    })
+    .await?
+    {
+        Ok(_) => {
+            info!(
+                "Topic \"{}\" is encrypted with KMS key \"{}\"",
+                topic, kms_key_id
+            );
+            events::operation_succeeded(
+                &context.run,
+                "SetTopicAttributes",
+                topic,
+                None,
+                Some(topic_arn),
+            );
+            Ok(())
+        }
+        Err(error) => {
+            error_detail!(
+                "Could not set the KMS key of topic \"{}\": {}",
+                topic,
+                logging::error_chain(&error)
+            );
+            print_error_hint(context, &error);
+            print_kms_error_hint("topic", topic, Some(kms_key_id), &error);
+            events::operation_failed(
+                &context.run,
+                "SetTopicAttributes",
+                topic,
+                error_message(&error),
+            );
+            Err(PinnError::aws("sns", "SetTopicAttributes", topic, error))
+        }
+    }
+}
+
+/// Find the ARN of the existing topic with the supplied (resolved) name
+/// by paging through every topic in the account, for `--check` runs
+/// (and external topics) lacking the region and account id needed to
+/// construct it
+async fn find_topic_arn(context: &AppContext, topic: &str) -> Result<Option<String>, PinnError> {
+    let suffix: String = format!(":{}", topic);
+    let mut next_token: Option<String> = None;
+
+    loop {
+        let resp = match retry::with_retries(&context.run, "ListTopics", topic, || {
+            context
+                .sns
+                .list_topics()
+                .set_next_token(next_token.clone())
+                .send()
+        })
+        .await?
+        {
+            Ok(response) => response,
+            Err(error) => {
+                error_detail!(
+                    "Could not list topics while looking for topic \"{}\": {}",
+                    topic,
+                    logging::error_chain(&error)
+                );
+                print_error_hint(context, &error);
+                events::operation_failed(&context.run, "ListTopics", topic, error_message(&error));
+                return Err(PinnError::aws("sns", "ListTopics", topic, error));
+            }
+        };
+
+        let existing = resp
+            .topics()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|entry| entry.topic_arn())
+            .find(|arn| arn.ends_with(&suffix));
+
+        if let Some(arn) = existing {
+            return Ok(Some(arn.to_string()));
+        }
+
+        next_token = resp.next_token().map(str::to_string);
+
+        if next_token.is_none() {
+            return Ok(None);
+        }
+    }
+}
+
+/// Find the ARN of the supplied (resolved) topic owned outside of
+/// pinnothera, without creating it or checking any of its attributes
+async fn find_external_topic(
+    context: &AppContext,
+    topic: String,
+) -> Result<SNSTopicARN, PinnError> {
+    info!("Looking up external topic: \"{}\"", &topic);
+
+    let topic_arn: String = match (context.aws_region(), context.aws_account_id()) {
+        (Some(region), Some(account_id)) => {
+            format!(
+                "arn:{}:sns:{}:{}:{}",
+                regions::partition(region).name,
+                region,
+                account_id,
+                topic
+            )
+        }
+        _ if context.settings.dry_run => {
+            info!(
+                "[dry-run] Not looking up the ARN of external topic: \"{}\"",
+                &topic
+            );
+            return Ok(format!("<arn of {}>", topic));
+        }
+        _ => match find_topic_arn(context, &topic).await? {
+            Some(arn) => arn,
+            None => {
+                warning!(
+                    "External topic \"{}\" does not exist (pinnothera will not create it)",
+                    &topic
+                );
+                return Err(PinnError::MissingResource {
+                    kind: "topic",
+                    resource: topic,
+                });
+            }
+        },
+    };
+
+    info!(
+        topic_arn = topic_arn.as_str(),
+        "Using external topic \"{}\" with ARN: \"{}\"", &topic, &topic_arn,
+    );
+
+    Ok(topic_arn)
+}
+
+/// The ARN of the supplied (resolved) topic, constructed from the region
+/// and account id if possible (or else found by listing every topic in
+/// the account), returning `None` if it doesn't exist
+async fn lookup_topic_arn(context: &AppContext, topic: &str) -> Result<Option<String>, PinnError> {
+    match (context.aws_region(), context.aws_account_id()) {
+        (Some(region), Some(account_id)) => Ok(Some(format!(
+            "arn:{}:sns:{}:{}:{}",
+            regions::partition(region).name,
+            region,
+            account_id,
+            topic
+        ))),
+        _ => find_topic_arn(context, topic).await,
+    }
+}
+
+/// Verify (without creating or modifying anything) that the supplied
+/// (resolved) topic exists with the supplied attributes and tags,
+/// returning its ARN
+async fn check_topic(
+    context: &AppContext,
+    topic: String,
+    fifo: bool,
+    mut desired: HashMap<String, String>,
+    tags: &HashMap<String, String>,
+) -> Result<SNSTopicARN, PinnError> {
+    info!("Checking topic: \"{}\"", &topic);
+
+    let topic_arn: String = match lookup_topic_arn(context, &topic).await? {
+        Some(arn) => arn,
+        None => {
+            warning!("Topic \"{}\" does not exist", &topic);
+            return Err(PinnError::MissingResource {
+                kind: "topic",
+                resource: topic,
+            });
+        }
+    };
+
+    let existing: HashMap<String, String> =
+        match retry::with_retries(&context.run, "GetTopicAttributes", &topic, || {
+            context
+                .sns
+                .get_topic_attributes()
+                .topic_arn(&topic_arn)
+                .send()
+        })
+        .await?
+        {
+            Ok(response) => response.attributes.unwrap_or_default(),
+            Err(SdkError::ServiceError { ref err, .. }) if err.is_not_found_exception() => {
+                warning!("Topic \"{}\" does not exist", &topic);
+                return Err(PinnError::MissingResource {
+                    kind: "topic",
+                    resource: topic,
+                });
+            }
+            Err(error) => {
+                error_detail!(
+                    "Could not get the attributes of topic \"{}\": {}",
+                    &topic,
+                    logging::error_chain(&error)
+                );
+                print_error_hint(context, &error);
+                events::operation_failed(
+                    &context.run,
+                    "GetTopicAttributes",
+                    &topic,
+                    error_message(&error),
+                );
+                return Err(PinnError::aws("sns", "GetTopicAttributes", &topic, error));
+            }
+        };
+
+    // Standard topics don't report `FifoTopic` at all,
+    // so its absence has to be checked for explicitly
+    expected_topic_attributes(fifo)
+        .iter()
+        .for_each(|(name, expected)| {
+            desired.insert(name.to_string(), expected.to_string());
+        });
+
+    let mut differences: Vec<String> = desired
+        .iter()
+        .filter_map(|(name, value)| {
+            let current: &str = match (name.as_str(), existing.get(name)) {
+                (_, Some(current)) => current.as_str(),
+                ("FifoTopic", None) => "false",
+                (_, None) => "",
+            };
+
+            match attribute_value_differs(value, current) {
+                false => None,
+                true => Some(format!(
+                    "{}: \"{}\" (configured: \"{}\")",
+                    name, current, value
+                )),
+            }
+        })
+        .collect();
+
+    differences.extend(topic_tag_differences(context, &topic, &topic_arn, tags).await?);
+
+    if !differences.is_empty() {
+        differences.sort_unstable();
+        warning!(
+            "Topic \"{}\" differs from its configuration: {:?}",
+            &topic,
+            &differences
+        );
+        return Err(PinnError::DriftedResource {
+            kind: "topic",
+            resource: topic,
+            differences,
+        });
+    }
+
+    info!(
+        topic_arn = topic_arn.as_str(),
+        "Topic \"{}\" exists as configured with ARN: \"{}\"", &topic, &topic_arn,
+    );
+
+    Ok(topic_arn)
+}
+
+// </editor-fold desc="// SNS Topic Utilities ...">
+
+// <editor-fold desc="// SQS Queue Utilities ...">
+
+/// The `Sid` of the access policy statement allowing SNS to send to a queue
+const SNS_SEND_SID: &str = "pinnothera-sns-send";
+
+/// The `Sid` of the access policy statement allowing the account full access to a queue
+const ACCOUNT_ACCESS_SID: &str = "pinnothera-account-access";
+
+/// The `Sid` of the access policy statement denying non-TLS access to a queue
+const SECURE_TRANSPORT_SID: &str = "pinnothera-deny-insecure-transport";
+
+/// The `Sid` of the access policy statement allowing
+/// topics configured by ARN to send to a queue
+const FOREIGN_SNS_SEND_SID: &str = "pinnothera-foreign-sns-send";
+
+/// The `Sid`s of every access policy statement pinnothera manages
+const MANAGED_POLICY_SIDS: [&str; 4] = [
+    SNS_SEND_SID,
+    FOREIGN_SNS_SEND_SID,
+    ACCOUNT_ACCESS_SID,
+    SECURE_TRANSPORT_SID,
+];
+
+/// How long to wait between attempts to recreate a recently deleted queue
+const QUEUE_RECREATE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long to wait between checks of whether a pending subscription was confirmed
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Resolve the supplied configured queue name into the
+/// sanitized and/or env-suffixed name used in AWS
+fn resolve_queue_name<T: AsRef<str>>(
+    context: &AppContext,
+    queue: T,
+    env: EnvName,
+    fifo: bool,
+) -> String {
+    resolve_name(context, "queue", queue, env, fifo)
+}
+
+/// The `aws:SourceArn` condition value allowing SNS to send to the
+/// supplied queue: the ARN(s) of the queue's own topics, or (with
+/// `--broad-policy`) a wildcard matching any topic in the account
+/// with the queue's environment, or `None` if the queue has no topics
+/// (other than ones in other accounts, see `foreign_sns_source_arns`)
+fn sns_source_arns(
+    context: &AppContext,
+    region: &str,
+    account_id: &str,
+    env: EnvName,
+    config: &SQSQueueConfig,
+) -> Option<String> {
+    let partition: &str = regions::partition(region).name;
+
+    if context.settings.broad_policy {
+        // Topics named by a name hook can't be assumed to share
+        // the queue's environment, so allow any topic in the account
+        let source_topics: String = match context.settings.name_hook {
+            Some(_) => "*".to_string(),
+            None => naming::apply_env_name(
+                "*",
+                Some(env.as_suffix()),
+                &context.settings.env_naming(),
+                config.is_fifo(),
+            ),
+        };
+
+        // Topics used verbatim don't share the queue's environment,
+        // so they're allowed alongside the wildcard individually
+        let verbatim_arns: BTreeSet<String> = config
+            .topics
+            .iter()
+            .filter(|topic| topic.is_verbatim() && !topic.is_foreign())
+            .map(|topic| {
+                format!(
+                    "arn:{partition}:sns:{region}:{account_id}:{}",
+                    resolved_name(
+                        context,
+                        "topic",
+                        topic.as_str(),
+                        EnvName::Unknown,
+                        config.is_fifo()
+                    )
+                )
+            })
+            .collect();
+
+        return match (
+            context.settings.name_hook.is_some(),
+            verbatim_arns.is_empty(),
+        ) {
+            (false, false) => serde_json::to_string(
+                &std::iter::once(format!(
+                    "arn:{partition}:sns:{region}:{account_id}:{source_topics}"
+                ))
+                .chain(verbatim_arns)
+                .collect::<Vec<String>>(),
+            )
+            .ok(),
+            _ => Some(format!(
+                r#""arn:{partition}:sns:{region}:{account_id}:{source_topics}""#
+            )),
+        };
+    }
+
+    let source_arns: BTreeSet<String> = config
+        .topics
+        .iter()
+        .filter(|topic| !topic.is_foreign())
+        .map(|topic| {
+            format!(
+                "arn:{partition}:sns:{region}:{account_id}:{}",
+                resolved_name(
+                    context,
+                    "topic",
+                    topic.as_str(),
+                    config.topic_name_env(topic.as_str(), env),
+                    config.is_fifo()
+                )
+            )
+        })
+        .collect();
+
+    match source_arns.len() {
+        0 => None,
+        _ => serde_json::to_string(&source_arns).ok(),
+    }
+}
+
+/// The ARN of the supplied (configured) topic of the supplied queue
+/// in the account given by its `account_id`, in the supplied region
+fn account_topic_arn(
+    context: &AppContext,
+    region: &str,
+    account_id: &str,
+    topic: &str,
+    env: EnvName,
+    config: &SQSQueueConfig,
+) -> SNSTopicARN {
+    format!(
+        "arn:{}:sns:{}:{}:{}",
+        regions::partition(region).name,
+        region,
+        account_id,
+        resolved_name(
+            context,
+            "topic",
+            topic,
+            config.topic_name_env(topic, env),
+            config.is_fifo()
+        )
+    )
+}
+
+/// The `aws:SourceArn` condition value allowing the supplied queue's
+/// topics in other accounts (configured by ARN or with an `account_id`)
+/// to send to it, or `None` if it has no such topics
+fn foreign_sns_source_arns(
+    context: &AppContext,
+    region: &str,
+    env: EnvName,
+    config: &SQSQueueConfig,
+) -> Option<String> {
+    let source_arns: BTreeSet<String> = config
+        .topics
+        .iter()
+        .filter(|topic| topic.is_foreign())
+        .map(|topic| match topic.account_id() {
+            Some(account_id) => {
+                account_topic_arn(context, region, account_id, topic.as_str(), env, config)
+            }
+            None => topic.to_string(),
+        })
+        .collect();
+
+    match source_arns.len() {
+        0 => None,
+        _ => serde_json::to_string(&source_arns).ok(),
+    }
+}
+
+/// Ensure the existence of the dead-letter queue configured for the
+/// supplied (configured) queue name, returning its URL and ARN
+async fn create_dead_letter_queue<T: AsRef<str>>(
+    context: &AppContext,
+    queue: T,
+    dead_letter: &DeadLetterConfig,
+    env: EnvName,
+    config: &SQSQueueConfig,
+    tags: &HashMap<String, String>,
+) -> Result<(SQSQueueURL, SQSQueueARN), PinnError> {
+    let dead_letter_queue: String = dead_letter.queue_name(queue.as_ref());
+
+    info!(
+        "Ensuring existence of dead-letter queue \"{}\" for queue \"{}\"",
+        &dead_letter_queue,
+        queue.as_ref()
+    );
+
+    // A FIFO queue's dead-letter queue must itself be a FIFO
+    // queue, and is encrypted the same way as the queue itself
+    let attributes: HashMap<QueueAttributeName, String> = config
+        .fifo_attributes()
+        .into_iter()
+        .chain(config.encryption_attributes())
+        .map(|(name, value)| (QueueAttributeName::from(name.as_str()), value))
+        .collect();
+
+    ensure_queue(
+        context,
+        resolve_queue_name(
+            context,
+            dead_letter_queue,
+            config.queue_name_env(env),
+            config.is_fifo(),
+        ),
+        attributes,
+        false,
+        tags,
+    )
+    .await
+}
+
+async fn create_queue<T: AsRef<str>>(
+    context: Arc<AppContext>,
+    queue: T,
+    env: EnvName,
+    config: &SQSQueueConfig,
+) -> Result<(SQSQueueURL, SQSQueueARN), PinnError> {
+    info!("Ensuring existence of queue: \"{}\"", queue.as_ref());
+
+    let tags: HashMap<String, String> = resource_tags(&context, config);
+
+    // Ensure the queue's dead-letter queue (if any) exists
+    // first, so that the queue's redrive policy can target it
+    let redrive_policy: Option<String> = match &config.dead_letter {
+        Some(dead_letter) => {
+            match create_dead_letter_queue(
+                &context,
+                queue.as_ref(),
+                dead_letter,
+                env,
+                config,
+                &tags,
+            )
+            .await
+            {
+                Ok((_, dead_letter_arn)) => Some(dead_letter.redrive_policy(dead_letter_arn)),
+                Err(error) => {
+                    let queue: String = resolved_name(
+                        &context,
+                        "queue",
+                        queue,
+                        config.queue_name_env(env),
+                        config.is_fifo(),
+                    );
+
+                    match error {
+                        PinnError::MissingResource { .. } | PinnError::DriftedResource { .. } => {
+                            report::record(&context.run,
+                                "queue",
+                                queue,
+                                ResourceStatus::Drifted,
+                                Some("its dead-letter queue is missing or differs from its configuration".to_string()),
+                            )
+                        }
+                        _ => report::record_failure(&context.run,
+                            "queue",
+                            queue,
+                            "its dead-letter queue could not be ensured",
+                        ),
+                    }
+
+                    return Err(error);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let reasons: Vec<String> = explain_queue(&context, queue.as_ref(), env, config);
+    let queue: String = resolve_queue_name(
+        &context,
+        queue,
+        config.queue_name_env(env),
+        config.is_fifo(),
+    );
+
+    // If a usable region and account id were provided,
+    // set the queue policy to allow the queue's topics
+    // (or, with `--broad-policy`, any SNS topic in the
+    // same region/account/suffix) to send messages to it
+    let (aws_region, aws_account_id) = (context.region.clone(), context.account_id.clone());
+
+    // If requested, deny any access that doesn't use TLS (an explicit
+    // Deny only matches insecure requests, so SNS delivery, which always
+    // uses TLS, is unaffected)
+    let enforce_secure_transport: bool = config
+        .enforce_secure_transport
+        .unwrap_or(context.settings.enforce_secure_transport);
+
+    // A queue's own configured policy takes precedence over
+    // `--policy-template`, which takes precedence over the
+    // built-in policy below
+    let configured_policy: Option<Result<String, String>> = match (&aws_region, &aws_account_id) {
+        (Some(region), Some(account_id)) => config
+            .policy
+            .as_ref()
+            .map(|custom| custom.render(region, account_id, &queue, env.as_suffix()))
+            .or_else(|| {
+                context.settings.policy_template.as_ref().map(|template| {
+                    types::render_policy(template, region, account_id, &queue, env.as_suffix())
+                })
+            }),
+        _ => None,
+    };
+
+    let verbatim_policy: bool = configured_policy.is_some();
+
+    let policy: String = match (&aws_region, &aws_account_id, configured_policy) {
+        (_, _, Some(Ok(value))) => value,
+        (_, _, Some(Err(reason))) => {
+            error!(
+                "Cannot render the configured access policy for queue '{}': {}",
+                &queue, &reason
+            );
+            report::record_failure(&context.run, "queue", &queue, &reason);
+            return Err(PinnError::PolicyError { queue, reason });
+        }
+        (Some(region), Some(account_id), None) => {
+            let partition: &str = regions::partition(region).name;
+
+            let sns_send_statement: String =
+                match sns_source_arns(&context, region, account_id, env, config) {
+                    None => String::new(),
+                    Some(source_arns) => format!(
+                        r#"
+            {{
+                "Sid": "{SNS_SEND_SID}",
+                "Action": "sqs:SendMessage",
+                "Effect": "Allow",
+                "Resource": "arn:{partition}:sqs:{region}:{account_id}:{queue}",
+                "Condition": {{
+                    "ArnLike": {{
+                        "aws:SourceArn": {source_arns}
+                    }}
+                }},
+                "Principal": {{
+                    "Service": "sns.amazonaws.com"
+                }}
+            }},"#
+                    ),
+                };
+
+            let foreign_sns_send_statement: String =
+                match foreign_sns_source_arns(&context, region, env, config) {
+                    None => String::new(),
+                    Some(source_arns) => format!(
+                        r#"
+            {{
+                "Sid": "{FOREIGN_SNS_SEND_SID}",
+                "Action": "sqs:SendMessage",
+                "Effect": "Allow",
+                "Resource": "arn:{partition}:sqs:{region}:{account_id}:{queue}",
+                "Condition": {{
+                    "ArnEquals": {{
+                        "aws:SourceArn": {source_arns}
+                    }}
+                }},
+                "Principal": {{
+                    "Service": "sns.amazonaws.com"
+                }}
+            }},"#
+                    ),
+                };
+
+            let secure_transport_statement: String = match enforce_secure_transport {
+                false => String::new(),
+                true => format!(
+                    r#",
+            {{
+                "Sid": "{SECURE_TRANSPORT_SID}",
+                "Effect": "Deny",
+                "Principal": "*",
+                "Action": "SQS:*",
+                "Resource": "arn:{partition}:sqs:{region}:{account_id}:{queue}",
+                "Condition": {{
+                    "Bool": {{
+                        "aws:SecureTransport": "false"
+                    }}
+                }}
+            }}"#
+                ),
+            };
+
+            format!(
+                r#"{{
+        "Version": "2008-10-17",
+        "Statement": [{sns_send_statement}{foreign_sns_send_statement}
+            {{
+                "Sid": "{ACCOUNT_ACCESS_SID}",
+                "Effect": "Allow",
+                "Principal": {{
+                    "AWS": "arn:{partition}:iam::{account_id}:root"
+                }},
+                "Action": "SQS:*",
+                "Resource": "arn:{partition}:sqs:{region}:{account_id}:{queue}"
+            }}{secure_transport_statement}
+        ]
+    }}"#
+            )
+        }
+        _ => {
+            if env.is_local() || env.is_unknown() {
+                String::new()
+            } else {
+                error!("Cannot create a valid access policy for queue '{}' with values: [aws-region: {:?}, aws-account-id: {:?}]", &queue, &aws_region, &aws_account_id, );
+                let reason: String = format!(
+                    "unusable values [aws-region: {:?}, aws-account-id: {:?}]",
+                    &aws_region, &aws_account_id
+                );
+                report::record_failure(&context.run, "queue", &queue, &reason);
+                return Err(PinnError::PolicyError { queue, reason });
+            }
+        }
+    };
+
+    if !policy.is_empty() {
+        debug!(
+            "Rendered access policy for queue \"{}\": {}",
+            &queue, &policy
+        );
+    }
+
+    let mut typed: HashMap<String, String> =
+        HashMap::from([(QueueAttributeName::Policy.as_str().to_string(), policy)]);
+
+    typed.extend(config.fifo_attributes());
+    typed.extend(config.encryption_attributes());
+
+    config
+        .queue_attributes()
+        .into_iter()
+        .for_each(|(_, attribute, value, _)| {
+            typed.insert(attribute.to_string(), value.to_string());
+        });
+
+    if let Some(value) = redrive_policy {
+        typed.insert(
+            QueueAttributeName::RedrivePolicy.as_str().to_string(),
+            value,
+        );
+    }
+
+    let attributes: HashMap<QueueAttributeName, String> =
+        merge_raw_attributes(&queue, typed, &config.raw_attributes)
+            .into_iter()
+            .map(|(name, value)| (QueueAttributeName::from(name.as_str()), value))
+            .collect();
+
+    explain(&context, "queue", &queue, &reasons);
+
+    match partially_applied_topics(&context, &queue, env, config).await {
+        missing if missing.is_empty() => {
+            ensure_queue(&context, queue, attributes, verbatim_policy, &tags).await
+        }
+        missing => repair_queue(&context, queue, attributes, verbatim_policy, &tags, missing).await,
+    }
+}
+
+/// Where the supplied (configured) queue is configured, and which of its
+/// settings and pinnothera's flags shape it, as explained by `--explain`
+fn explain_queue(
+    context: &AppContext,
+    queue: &str,
+    env: EnvName,
+    config: &SQSQueueConfig,
+) -> Vec<String> {
+    if !context.settings.explain {
+        return Vec::new();
+    }
+
+    let mut reasons: Vec<String> = config
+        .source
+        .iter()
+        .map(|source| format!("configured in {}", source))
+        .collect();
+
+    reasons.push(explain_name(
+        context,
+        "queue",
+        queue,
+        config.queue_name_env(env),
+        config,
+    ));
+
+    if let Some(settings) = config.fifo_settings() {
+        reasons.push(format!(
+            "FIFO{}{} per `fifo`",
+            match settings.content_based_deduplication {
+                true => " with content-based deduplication",
+                false => " deduplicated by producer-supplied ids",
+            },
+            match settings.high_throughput {
+                true => " in high throughput mode",
+                false => "",
+            },
+        ));
+    }
+
+    if let Some(dead_letter) = &config.dead_letter {
+        reasons.push(format!(
+            "redrives to dead-letter queue \"{}\" after {} receive(s) per `dead_letter`",
+            resolved_name(
+                context,
+                "queue",
+                dead_letter.queue_name(queue),
+                config.queue_name_env(env),
+                config.is_fifo()
+            ),
+            dead_letter.max_receive_count,
+        ));
+    }
+
+    let (built_in, verbatim): (bool, bool) = match (context.aws_region(), context.aws_account_id())
+    {
+        (Some(_), Some(_)) if config.policy.is_some() => {
+            reasons.push("access policy from the queue's `policy`".to_string());
+            (false, true)
+        }
+        (Some(_), Some(_)) if context.settings.policy_template.is_some() => {
+            reasons.push("access policy rendered from `--policy-template`".to_string());
+            (false, true)
+        }
+        (Some(_), Some(_)) if context.settings.broad_policy => {
+            reasons.push("built-in access policy, allowing any topic in the account with the queue's environment to send to it per `--broad-policy`".to_string());
+            (true, false)
+        }
+        (Some(_), Some(_)) => {
+            reasons.push(format!(
+                "built-in access policy, allowing its {} topic(s) to send to it",
+                config.topics.len()
+            ));
+            (true, false)
+        }
+        _ => {
+            reasons.push(
+                "no access policy (there's no region and account id to build one with)".to_string(),
+            );
+            (false, false)
+        }
+    };
+
+    match (built_in, config.enforce_secure_transport) {
+        (true, Some(true)) => reasons.push(
+            "access policy denies non-TLS access per the queue's `enforce_secure_transport`"
+                .to_string(),
+        ),
+        (true, None) if context.settings.enforce_secure_transport => reasons.push(
+            "access policy denies non-TLS access per `--enforce-secure-transport`".to_string(),
+        ),
+        _ => (),
+    }
+
+    match (
+        built_in || verbatim,
+        verbatim || context.settings.replace_policy,
+    ) {
+        (false, _) => (),
+        (true, true) => reasons.push(format!(
+            "replaces any existing access policy{}",
+            match verbatim {
+                true => " (configured policies are used verbatim)",
+                false => " per `--replace-policy`",
+            }
+        )),
+        (true, false) => reasons.push(
+            "merged into any existing access policy, keeping statements pinnothera doesn't manage"
+                .to_string(),
+        ),
+    }
+
+    config
+        .queue_attributes()
+        .into_iter()
+        .for_each(|(setting, attribute, value, _)| {
+            reasons.push(format!("{} of {} per `{}`", attribute, value, setting))
+        });
+
+    match (&config.kms_key_id, config.sse) {
+        (Some(key), _) => reasons.push(format!(
+            "encrypted with KMS key \"{}\" per `kms_key_id`",
+            key
+        )),
+        (None, Some(true)) => {
+            reasons.push("SQS-managed server-side encryption per `sse: true`".to_string())
+        }
+        (None, Some(false)) => {
+            reasons.push("SQS-managed server-side encryption disabled per `sse: false`".to_string())
+        }
+        (None, None) => (),
+    }
+
+    if !config.raw_attributes.is_empty() {
+        reasons.push(format!(
+            "raw attribute(s) {:?} per `raw_attributes`",
+            config.raw_attributes.keys().collect::<Vec<&String>>()
+        ));
+    }
+
+    let tags: HashMap<String, String> = resource_tags(context, config);
+
+    if !tags.is_empty() {
+        reasons.push(format!(
+            "tagged with {:?} per `--tag` and `tags`",
+            tags.keys().collect::<BTreeSet<&String>>()
+        ));
+    }
+
+    if context.settings.no_update_existing {
+        reasons.push(
+            "differing attributes of an existing queue fail it rather than being updated per `--no-update-existing`"
+                .to_string(),
+        );
+    }
+
+    reasons
+}
+
+/// Ensure the existence of the supplied (resolved) queue name with
+/// the supplied attributes (replacing rather than merging into an
+/// existing queue's access policy if the policy is a configured one
+/// or was rendered from `--policy-template`),
+/// returning its URL and ARN
+async fn ensure_queue(
+    context: &AppContext,
+    queue: String,
+    attributes: HashMap<QueueAttributeName, String>,
+    custom_policy: bool,
+    tags: &HashMap<String, String>,
+) -> Result<(SQSQueueURL, SQSQueueARN), PinnError> {
+    match create_or_reuse_queue(context, queue.clone(), attributes, custom_policy, tags).await {
+        Ok((queue_url, queue_arn, status)) => {
+            report::record(&context.run, "queue", &queue, status, None);
+            Ok((queue_url, queue_arn))
+        }
+        Err(error) => {
+            report::record_error(&context.run, "queue", &queue, &error);
+            Err(error)
+        }
+    }
+}
+
+/// Create the supplied (resolved) queue name with the supplied attributes,
+/// or reconcile the attributes of the existing queue with that name,
+/// returning its URL and ARN and what was done to ensure it
+async fn create_or_reuse_queue(
+    context: &AppContext,
+    queue: String,
+    attributes: HashMap<QueueAttributeName, String>,
+    custom_policy: bool,
+    tags: &HashMap<String, String>,
+) -> Result<(SQSQueueURL, SQSQueueARN, ResourceStatus), PinnError> {
+    if context.settings.dry_run {
+        plan::record_queue(
+            &context.run,
+            &queue,
+            attributes
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.clone())),
+            tags,
+        );
+        let queue_arn = dry_run(context, "CreateQueue", "queue", &queue, "create queue");
+        return Ok((
+            format!("<url of {}>", &queue),
+            queue_arn,
+            ResourceStatus::Planned,
+        ));
+    }
+
+    if context.settings.check {
+        info!("Checking queue: \"{}\"", &queue);
+        return reuse_existing_queue(context, queue, attributes, custom_policy, tags).await;
+    }
+
+    let mut waited = Duration::ZERO;
+    let create_tags: HashMap<String, String> = with_bookkeeping_tags(tags);
+
+    let resp = loop {
+        match retry::with_retries(&context.run, "CreateQueue", &queue, || {
+            context
+                .sqs
+                .create_queue()
+                .queue_name(&queue)
+                .set_attributes(Some(attributes.clone()))
+                .set_tags(Some(create_tags.clone()))
+                .send()
+        })
+        .await?
+        {
+            Ok(response) => break response,
+            Err(SdkError::ServiceError { ref err, .. }) if err.is_queue_deleted_recently() => {
+                waited = wait_out_queue_cooldown(context, &queue, waited).await?;
+            }
+            Err(error) => {
+                return handle_create_queue_error(
+                    context,
+                    error,
+                    queue,
+                    attributes,
+                    custom_policy,
+                    tags,
+                )
+                .await;
+            }
+        }
+    };
+
+    let queue_url = match resp.queue_url().map(naming::normalize_queue_url) {
+        Some(value) => {
+            events::operation_succeeded(&context.run, "CreateQueue", &queue, Some(&value), None);
+            value
+        }
+        None => {
+            error!(
+                "Creation of queue \"{}\" did not return an error, but did not return a URL as expected",
+                &queue
+            );
+            return Err(PinnError::missing_field("CreateQueue", &queue, "QueueUrl"));
+        }
+    };
+
+    // SQS ignores the tags passed to `CreateQueue` for an existing
+    // queue (which it doesn't distinguish from a created one)
+    tag_queue(context, &queue, &queue_url, tags).await?;
+
+    let (queue_url, queue_arn) = get_queue_arn_from_url(context, queue, queue_url).await?;
+
+    Ok((queue_url, queue_arn, ResourceStatus::Ensured))
+}
+
+async fn get_queue_arn_from_url(
+    context: &AppContext,
+    queue: String,
+    url: String,
+) -> Result<(SQSQueueURL, SQSQueueARN), PinnError> {
+    let attributes = match retry::with_retries(&context.run, "GetQueueAttributes", &queue, || {
+        context
+            .sqs
+            .get_queue_attributes()
+            .queue_url(&url)
+            .attribute_names(QueueAttributeName::QueueArn)
+            .send()
+    })
+    .await?
+    {
+        Ok(response) => response.attributes.unwrap_or_default(),
+        Err(error) => {
+            events::operation_failed(
+                &context.run,
+                "GetQueueAttributes",
+                &queue,
+                error_message(&error),
+            );
+            return Err(PinnError::aws("sqs", "GetQueueAttributes", &queue, error));
+        }
+    };
+
+    let queue_arn = match attributes.get(&QueueAttributeName::QueueArn) {
+        None => {
+            error!(
+                "ARN retrieval attempt for queue URL \"{}\" did not return an error, but did not return an associated ARN as expected",
+                &url,
+            );
+            return Err(PinnError::missing_field(
+                "GetQueueAttributes",
+                &queue,
+                "QueueArn",
+            ));
+        }
+        Some(value) => {
+            info!(
+                queue_url = url.as_str(),
+                queue_arn = value.as_str(),
+                "Queue \"{}\" exists with URL & ARN: [url: \"{}\", arn: \"{}\"]",
+                &queue,
+                &url,
+                value
+            );
+            events::operation_succeeded(
+                &context.run,
+                "GetQueueAttributes",
+                &queue,
+                Some(&url),
+                Some(value),
+            );
+            value.to_string()
+        }
+    };
+
+    Ok((url, queue_arn))
+}
+
+/// Determine whether the supplied existing value of a queue attribute
+/// differs from its configured value, ignoring unconfigured (empty)
+/// values and insignificant formatting differences in JSON documents
+fn queue_attribute_differs(
+    attribute: &QueueAttributeName,
+    desired: &str,
+    existing: Option<&String>,
+) -> bool {
+    if desired.is_empty() {
+        return false;
+    }
+
+    let existing: &str = existing.map(|value| value.as_str()).unwrap_or_default();
+
+    match attribute {
+        QueueAttributeName::Policy | QueueAttributeName::RedrivePolicy => {
+            match (
+                serde_json::from_str::<serde_json::Value>(desired),
+                serde_json::from_str::<serde_json::Value>(existing),
+            ) {
+                (Ok(desired), Ok(existing)) => desired != existing,
+                _ => desired != existing,
+            }
+        }
+        _ => desired != existing,
+    }
+}
+
+/// Determine whether the supplied statement of an existing access policy
+/// is one pinnothera manages, either by its `Sid` or (for statements
+/// written before pinnothera assigned them `Sid`s) by being identical
+/// to one of the supplied desired statements other than its `Sid`
+/// and `Condition` (which older versions set more broadly)
+fn is_managed_statement(statement: &serde_json::Value, desired: &[serde_json::Value]) -> bool {
+    match statement.get("Sid").and_then(|sid| sid.as_str()) {
+        Some(sid) => MANAGED_POLICY_SIDS.contains(&sid),
+        None => {
+            let mut statement = statement.clone();
+            if let Some(fields) = statement.as_object_mut() {
+                fields.remove("Condition");
+            }
+
+            desired.iter().any(|candidate| {
+                let mut candidate = candidate.clone();
+                if let Some(fields) = candidate.as_object_mut() {
+                    fields.remove("Sid");
+                    fields.remove("Condition");
+                }
+                candidate == statement
+            })
+        }
+    }
+}
+
+/// Merge the supplied desired access policy into the supplied existing
+/// one, replacing the statements pinnothera manages while keeping any
+/// others (i.e. ones allowing S3 event notifications) as they are
+fn merge_queue_policy(desired: &str, existing: &str) -> String {
+    let (mut merged, existing) = match (
+        serde_json::from_str::<serde_json::Value>(desired),
+        serde_json::from_str::<serde_json::Value>(existing),
+    ) {
+        (Ok(desired), Ok(existing)) => (desired, existing),
+        _ => return desired.to_string(),
+    };
+
+    let desired_statements: Vec<serde_json::Value> = merged
+        .get("Statement")
+        .and_then(|statements| statements.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let foreign: Vec<serde_json::Value> = existing
+        .get("Statement")
+        .and_then(|statements| statements.as_array())
+        .map(|statements| {
+            statements
+                .iter()
+                .filter(|statement| !is_managed_statement(statement, &desired_statements))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if foreign.is_empty() {
+        return desired.to_string();
+    }
+
+    if let Some(statements) = merged
+        .get_mut("Statement")
+        .and_then(|statements| statements.as_array_mut())
+    {
+        statements.extend(foreign);
+    }
+
+    merged.to_string()
+}
+
+/// Converge the attributes of the supplied existing queue on their
+/// configured values, or refuse to (failing the queue) if updating
+/// existing queues has been disabled via `--no-update-existing`
+async fn reconcile_queue_attributes(
+    context: &AppContext,
+    queue: &str,
+    queue_url: &str,
+    attributes: &HashMap<QueueAttributeName, String>,
+    custom_policy: bool,
+) -> Result<bool, PinnError> {
+    let existing: HashMap<QueueAttributeName, String> =
+        match retry::with_retries(&context.run, "GetQueueAttributes", queue, || {
+            context
+                .sqs
+                .get_queue_attributes()
+                .queue_url(queue_url)
+                .set_attribute_names(Some(attributes.keys().cloned().collect()))
+                .send()
+        })
+        .await?
+        {
+            Ok(response) => response.attributes.unwrap_or_default(),
+            Err(error) => {
+                error_detail!(
+                    "Queue \"{}\" exists, but could not retrieve its attributes: {}",
+                    queue,
+                    logging::error_chain(&error)
+                );
+                print_error_hint(context, &error);
+                events::operation_failed(
+                    &context.run,
+                    "GetQueueAttributes",
+                    queue,
+                    error_message(&error),
+                );
+                return Err(PinnError::aws("sqs", "GetQueueAttributes", queue, error));
+            }
+        };
+
+    // A configured (bespoke or templated) policy is used verbatim, so
+    // it always replaces the existing one rather than being merged into it
+    let replace_policy: bool = custom_policy || context.settings.replace_policy;
+
+    let differing: HashMap<QueueAttributeName, String> = attributes
+        .iter()
+        .map(|(name, value)| match (name, existing.get(name)) {
+            (QueueAttributeName::Policy, Some(current)) if !replace_policy && !value.is_empty() => {
+                (name, merge_queue_policy(value, current))
+            }
+            _ => (name, value.clone()),
+        })
+        .filter(|(name, value)| queue_attribute_differs(name, value, existing.get(name)))
+        .map(|(name, value)| (name.clone(), value))
+        .collect();
+
+    if differing.is_empty() {
+        return Ok(false);
+    }
+
+    let mut names: Vec<String> = differing
+        .keys()
+        .map(|name| name.as_str().to_string())
+        .collect();
+    names.sort_unstable();
+
+    warning!(
+        "Queue \"{}\" exists with attribute(s) differing from its configuration: {:?}",
+        queue,
+        &names,
+    );
+
+    explain(
+        context,
+        "queue",
+        queue,
+        &differing
+            .iter()
+            .map(|(name, value)| explain_change(name.as_str(), existing.get(name), value))
+            .collect::<Vec<String>>(),
+    );
+
+    if context.settings.check {
+        return Err(PinnError::DriftedResource {
+            kind: "queue",
+            resource: queue.to_string(),
+            differences: names,
+        });
+    }
+
+    if context.settings.no_update_existing {
+        error!(
+            "Not updating the differing attribute(s) of queue \"{}\" (`--no-update-existing` was specified)",
+            queue
+        );
+        events::operation_failed(
+            &context.run,
+            "SetQueueAttributes",
+            queue,
+            format!("attribute(s) differ from the configuration: {:?}", &names),
+        );
+        return Err(PinnError::QueueConflict {
+            queue: queue.to_string(),
+            reason: format!(
+                "has attribute(s) {:?} differing from its configuration (`--no-update-existing` was specified)",
+                &names
+            ),
+        });
+    }
+
+    match retry::with_retries(&context.run, "SetQueueAttributes", queue, || {
+        context
+            .sqs
+            .set_queue_attributes()
+            .queue_url(queue_url)
+            .set_attributes(Some(differing.clone()))
+            .send()
+    })
+    .await?
+    {
+        Ok(_) => {
+            info!(
+                "Updated attribute(s) {:?} of queue \"{}\" to match its configuration",
+                &names, queue
+            );
+            events::operation_succeeded(
+                &context.run,
+                "SetQueueAttributes",
+                queue,
+                Some(queue_url),
+                None,
+            );
+            Ok(true)
+        }
+        Err(error) => {
+            error_detail!(
+                "Could not update the attributes of queue \"{}\": {}",
+                queue,
+                logging::error_chain(&error)
+            );
+            print_error_hint(context, &error);
+            print_kms_error_hint(
+                "queue",
+                queue,
+                differing
+                    .get(&QueueAttributeName::KmsMasterKeyId)
+                    .map(String::as_str),
+                &error,
+            );
+            events::operation_failed(
+                &context.run,
+                "SetQueueAttributes",
+                queue,
+                error_message(&error),
+            );
+            Err(PinnError::aws("sqs", "SetQueueAttributes", queue, error))
+        }
+    }
+}
+
+/// Wait (a while longer) for SQS to allow the supplied recently
+/// deleted queue to be recreated, returning how long has been spent
+/// waiting in total, or failing once `--queue-recreate-wait-secs` is up
+async fn wait_out_queue_cooldown(
+    context: &AppContext,
+    queue: &str,
+    waited: Duration,
+) -> Result<Duration, PinnError> {
+    let cap = Duration::from_secs(context.settings.queue_recreate_wait_secs);
+
+    if waited >= cap {
+        error!(
+            "Queue \"{}\" was deleted too recently to be recreated, and was still cooling down after {}s (see `--queue-recreate-wait-secs`)",
+            queue,
+            waited.as_secs(),
+        );
+        events::operation_failed(
+            &context.run,
+            "CreateQueue",
+            queue,
+            "queue was deleted recently",
+        );
+        return Err(PinnError::QueueConflict {
+            queue: queue.to_string(),
+            reason: format!(
+                "was deleted too recently to be recreated (still cooling down after {}s)",
+                waited.as_secs()
+            ),
+        });
+    }
+
+    let delay = QUEUE_RECREATE_POLL_INTERVAL.min(cap - waited);
+
+    info!(
+        "Queue \"{}\" was deleted recently, waiting {}s for SQS's 60 second cooldown before recreating it ...",
+        queue,
+        delay.as_secs(),
+    );
+
+    tokio::time::sleep(delay).await;
+
+    Ok(waited + delay)
+}
+
+async fn handle_create_queue_error(
+    context: &AppContext,
+    error: SdkError<CreateQueueError>,
+    queue: String,
+    attributes: HashMap<QueueAttributeName, String>,
+    custom_policy: bool,
+    tags: &HashMap<String, String>,
+) -> Result<(SQSQueueURL, SQSQueueARN, ResourceStatus), PinnError> {
+    if let SdkError::ServiceError { ref err, .. } = error {
+        // SQS only reports an existing name when the queue's
+        // attributes differ from the requested ones, so they
+        // have to be reconciled rather than simply accepted
+        if err.is_queue_name_exists() {
+            return reuse_existing_queue(context, queue, attributes, custom_policy, tags).await;
+        }
+    };
+
+    error_detail!(
+        "Could not create queue \"{}\": {}",
+        &queue,
+        logging::error_chain(&error)
+    );
+    print_error_hint(context, &error);
+    print_kms_error_hint(
+        "queue",
+        &queue,
+        attributes
+            .get(&QueueAttributeName::KmsMasterKeyId)
+            .map(String::as_str),
+        &error,
+    );
+    events::operation_failed(&context.run, "CreateQueue", &queue, error_message(&error));
+
+    Err(PinnError::aws("sqs", "CreateQueue", &queue, error))
+}
+
+/// Reconcile the attributes of the existing queue with the supplied
+/// (resolved) name with the supplied attributes (or, in a `--check`
+/// run, only compare them and its tags), returning its URL and ARN
+/// and whether it had to be updated
+async fn reuse_existing_queue(
+    context: &AppContext,
+    queue: String,
+    attributes: HashMap<QueueAttributeName, String>,
+    custom_policy: bool,
+    tags: &HashMap<String, String>,
+) -> Result<(SQSQueueURL, SQSQueueARN, ResourceStatus), PinnError> {
+    let queue_url: SQSQueueURL = match context.settings.check {
+        true => find_indexed_queue_url(context, &queue).await?,
+        false => get_queue_url(context, &queue).await?,
+    };
+
+    let mut differences: Vec<String> = Vec::new();
+
+    let status: ResourceStatus =
+        match reconcile_queue_attributes(context, &queue, &queue_url, &attributes, custom_policy)
+            .await
+        {
+            Ok(true) => ResourceStatus::Updated,
+            Ok(false) => ResourceStatus::Existing,
+            Err(PinnError::DriftedResource {
+                differences: attributes,
+                ..
+            }) => {
+                differences.extend(attributes);
+                ResourceStatus::Drifted
+            }
+            Err(error) => return Err(error),
+        };
+
+    // SQS ignores the tags passed to `CreateQueue` for
+    // an existing queue, so they have to be set separately
+    if context.settings.check {
+        let tag_differences: Vec<String> =
+            queue_tag_differences(context, &queue, &queue_url, tags).await?;
+
+        if !tag_differences.is_empty() {
+            warning!(
+                "Queue \"{}\" exists with tag(s) differing from its configuration: {:?}",
+                &queue,
+                &tag_differences,
+            );
+            differences.extend(tag_differences);
+        }
+    } else {
+        tag_queue(context, &queue, &queue_url, tags).await?;
+    }
+
+    // A drifted queue still exists, so its
+    // subscriptions can (and should) be checked too
+    let status: ResourceStatus = match differences.is_empty() {
+        true => status,
+        false => {
+            let error: PinnError = PinnError::DriftedResource {
+                kind: "queue",
+                resource: queue.clone(),
+                differences,
+            };
+            report::record_error(&context.run, "queue", &queue, &error);
+            ResourceStatus::Drifted
+        }
+    };
+
+    let (queue_url, queue_arn) = get_queue_arn_from_url(context, queue, queue_url).await?;
+
+    Ok((queue_url, queue_arn, status))
+}
+
+/// The (resolved) names of the topics in the queue's own account that
+/// the supplied (resolved) queue isn't subscribed to, if the queue
+/// existed before the run created anything (per the run's listing of
+/// every queue) and is tagged as one pinnothera manages, meaning that an
+/// earlier run was interrupted before it finished subscribing the queue
+async fn partially_applied_topics(
+    context: &AppContext,
+    queue: &str,
+    env: EnvName,
+    config: &SQSQueueConfig,
+) -> Vec<String> {
+    if context.settings.dry_run || context.settings.check {
+        return Vec::new();
+    }
+
+    let topics: Vec<String> = config
+        .topics
+        .iter()
+        .filter(|topic| !topic.is_foreign() && topic.role_arn().is_none())
+        .map(|topic| {
+            resolved_name(
+                context,
+                "topic",
+                topic.as_str(),
+                config.topic_name_env(topic.as_str(), env),
+                config.is_fifo(),
+            )
+        })
+        .collect();
+
+    if topics.is_empty() {
+        return Vec::new();
+    }
+
+    // The queues are listed before any of them is created, so
+    // a queue only the current run created is never listed
+    let queue_url: SQSQueueURL = match queue_url_index(context)
+        .await
+        .as_ref()
+        .ok()
+        .and_then(|urls| urls.get(queue))
+    {
+        Some(url) => url.clone(),
+        None => return Vec::new(),
+    };
+
+    let subscribed: BTreeSet<&str> = match subscription_index(context).await {
+        Ok(subscriptions) => subscriptions
+            .keys()
+            .filter(|(_, endpoint)| {
+                endpoint.rsplit(':').next() == Some(queue)
+                    && context.aws_account_id().is_none_or(|account_id| {
+                        naming::arn_account_id(endpoint) == Some(account_id)
+                    })
+            })
+            .filter_map(|(topic_arn, _)| topic_arn.rsplit(':').next())
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+
+    let missing: Vec<String> = topics
+        .into_iter()
+        .filter(|topic| !subscribed.contains(topic.as_str()))
+        .collect();
+
+    if missing.is_empty() {
+        return Vec::new();
+    }
+
+    match list_queue_tags(context, queue, &queue_url).await {
+        Ok(Ok(tags))
+            if tags.get(types::MANAGED_BY_TAG).map(String::as_str) == Some(types::MANAGED_BY) =>
+        {
+            missing
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Repair the supplied (resolved) queue, which an earlier run only
+/// partially applied (leaving it without its subscriptions to the
+/// supplied topics): re-validating its attributes (including its
+/// access policy) and its tags as well, rather than only subscribing
+/// it to the topics, returning its URL and ARN
+async fn repair_queue(
+    context: &AppContext,
+    queue: String,
+    attributes: HashMap<QueueAttributeName, String>,
+    custom_policy: bool,
+    tags: &HashMap<String, String>,
+    missing: Vec<String>,
+) -> Result<(SQSQueueURL, SQSQueueARN), PinnError> {
+    warning!(
+        "Repairing queue \"{}\", which an earlier run left without its subscription(s) to {:?}",
+        &queue,
+        &missing
+    );
+
+    match reuse_existing_queue(context, queue.clone(), attributes, custom_policy, tags).await {
+        Ok((queue_url, queue_arn, _)) => {
+            report::record(
+                &context.run,
+                "queue",
+                &queue,
+                ResourceStatus::Repairing,
+                Some(format!("was missing its subscription(s) to {:?}", missing)),
+            );
+            Ok((queue_url, queue_arn))
+        }
+        Err(error) => {
+            report::record_error(&context.run, "queue", &queue, &error);
+            Err(error)
+        }
+    }
+}
+
+/// Look up the URL of the supplied (resolved) queue name, failing
+/// if it doesn't exist
+async fn get_queue_url(context: &AppContext, queue: &str) -> Result<SQSQueueURL, PinnError> {
+    let resp = match retry::with_retries(&context.run, "GetQueueUrl", queue, || {
+        context.sqs.get_queue_url().queue_name(queue).send()
+    })
+    .await?
+    {
+        Ok(response) => response,
+        Err(SdkError::ServiceError { ref err, .. })
+            if context.settings.check && err.is_queue_does_not_exist() =>
+        {
+            warning!("Queue \"{}\" does not exist", queue);
+            return Err(PinnError::MissingResource {
+                kind: "queue",
+                resource: queue.to_string(),
+            });
+        }
+        Err(get_url_error) => {
+            error_detail!(
+                "Queue \"{}\" exists, but could not retrieve its URL: {}",
+                queue,
+                logging::error_chain(&get_url_error)
+            );
+            print_error_hint(context, &get_url_error);
+            events::operation_failed(
+                &context.run,
+                "GetQueueUrl",
+                queue,
+                error_message(&get_url_error),
+            );
+            return Err(PinnError::aws("sqs", "GetQueueUrl", queue, get_url_error));
+        }
+    };
+
+    match resp.queue_url().map(naming::normalize_queue_url) {
+        Some(value) => Ok(value),
+        None => {
+            error!(
+                "URL retrieval attempt for queue \"{}\" did not return an error, but did not return a URL as expected",
+                queue
+            );
+            Err(PinnError::missing_field("GetQueueUrl", queue, "QueueUrl"))
+        }
+    }
+}
+
+/// The URL of every queue by name, listing them (once
+/// per run) if they haven't been yet, or why they couldn't be
+async fn queue_url_index(context: &AppContext) -> &Result<BTreeMap<String, SQSQueueURL>, String> {
+    context
+        .queue_urls
+        .get_or_init(|| async {
+            list_queue_urls(context)
+                .await
+                .map_err(|error| format!("{:?}", error))
+        })
+        .await
+}
+
+/// Look up the URL of the supplied (resolved) queue name among every
+/// queue's, listing them (once per run) if they haven't been yet, or
+/// individually if it isn't listed (or they couldn't be listed)
+async fn find_indexed_queue_url(
+    context: &AppContext,
+    queue: &str,
+) -> Result<SQSQueueURL, PinnError> {
+    let index = queue_url_index(context).await;
+
+    match index.as_ref().ok().and_then(|urls| urls.get(queue)) {
+        Some(url) => Ok(url.clone()),
+        None => get_queue_url(context, queue).await,
+    }
+}
+
+// </editor-fold desc="// SQS Queue Utilities ...">
+
+// <editor-fold desc="// Environment Suffix Utilities ...">
+
+/// The names of every queue visible to the current AWS identity
+async fn list_queue_names(context: &AppContext) -> Result<BTreeSet<String>, Terminator> {
+    Ok(list_queue_urls(context).await?.into_keys().collect())
+}
+
+/// The URL of every queue visible to the current AWS identity, by name
+async fn list_queue_urls(
+    context: &AppContext,
+) -> Result<BTreeMap<String, SQSQueueURL>, Terminator> {
+    let mut urls: BTreeMap<String, SQSQueueURL> = BTreeMap::new();
+    let mut next_token: Option<String> = None;
+
+    loop {
+        let resp = match retry::with_retries(&context.run, "ListQueues", "all queues", || {
+            context
+                .sqs
+                .list_queues()
+                .set_next_token(next_token.clone())
+                .max_results(1000)
+                .send()
+        })
+        .await?
+        {
+            Ok(response) => response,
+            Err(error) => {
+                error_detail!("Could not list queues: {}", logging::error_chain(&error));
+                print_error_hint(context, &error);
+                events::operation_failed(
+                    &context.run,
+                    "ListQueues",
+                    "all queues",
+                    error_message(&error),
+                );
+                return Err(error.into());
+            }
+        };
+
+        resp.queue_urls()
+            .unwrap_or_default()
+            .iter()
+            .map(naming::normalize_queue_url)
+            .for_each(|url| {
+                urls.insert(naming::queue_name_from_url(&url), url);
+            });
+
+        next_token = resp.next_token().map(str::to_string);
+
+        if next_token.is_none() {
+            return Ok(urls);
+        }
+    }
+}
+
+/// Guard against accidentally creating a parallel set of unsuffixed queues
+/// (i.e. due to a typo in the environment name) alongside existing ones
+/// suffixed with a known environment, requiring confirmation to proceed
+async fn check_env_suffix(
+    context: &AppContext,
+    args: &CLIArgs,
+    pinn_config: &PinnConfig,
+) -> Result<(), Terminator> {
+    let (cluster_env, settings): (EnvName, &Settings) = (context.env, &context.settings);
+
+    if let (Some(env_name), true) = (&args.env_name, cluster_env.is_unknown()) {
+        let suggestion = EnvName::KNOWN
+            .iter()
+            .map(|env| env.as_suffix())
+            .find(|suffix| naming::edit_distance(env_name.to_lowercase(), suffix) <= 1);
+
+        warning!(
+            "The environment name \"{}\" is not a known environment, queue and topic names will not be suffixed{}",
+            env_name,
+            match suggestion {
+                Some(suffix) => format!(" (did you mean \"{}\"?)", suffix),
+                None => String::new(),
+            },
+        );
+    }
+
+    // Checking for existing queues would consult AWS, which a dry run never does
+    if args.skip_suffix_check || settings.name_hook.is_some() || settings.dry_run {
+        return Ok(());
+    }
+
+    let planned: Vec<(String, bool)> = pinn_config
+        .iter()
+        .filter(|(queue, config)| {
+            queue.as_str() != "unsubscribed"
+                && config.env_name(cluster_env).is_unknown()
+                && !config.is_verbatim()
+                && !settings.is_paused(queue, config)
+        })
+        .map(|(queue, config)| {
+            let queue: &str = naming::strip_fifo_extension(queue);
+            match settings.sanitize_replacement() {
+                Some(replacement) => (naming::sanitize_name(queue, replacement), config.is_fifo()),
+                None => (queue.to_string(), config.is_fifo()),
+            }
+        })
+        .collect();
+
+    if planned.is_empty() {
+        return Ok(());
+    }
+
+    let existing = list_queue_names(context).await?;
+    let scheme: naming::EnvNaming = settings.env_naming();
+
+    let new: Vec<&(String, bool)> = planned
+        .iter()
+        .filter(|(queue, fifo)| {
+            !existing.contains(&naming::apply_env_name(queue, None, &scheme, *fifo))
+        })
+        .collect();
+
+    let suspicious: Vec<(&str, usize)> = EnvName::KNOWN
+        .iter()
+        .map(|env| env.as_suffix())
+        .map(|suffix| {
+            (
+                suffix,
+                new.iter()
+                    .filter(|(queue, fifo)| {
+                        existing.contains(&naming::apply_env_name(
+                            queue,
+                            Some(suffix),
+                            &scheme,
+                            *fifo,
+                        ))
+                    })
+                    .count(),
+            )
+        })
+        .filter(|(_, count)| *count > 0)
+        .collect();
+
+    if suspicious.is_empty() {
+        return Ok(());
+    }
+
+    suspicious.iter().for_each(|(suffix, count)| {
+        warning!(
+            "About to create {} queue(s) without an environment suffix, {} identical base name(s) exist with suffix \"{}\"",
+            new.len(),
+            count,
+            suffix,
+        )
+    });
+
+    let action: String = format!(
+        "create {} queue(s) without an environment suffix",
+        new.len()
+    );
+
+    if settings.check
+        || confirm_destructive_action(&action, args.confirm_new_suffix, "--confirm-new-suffix")
+    {
+        return Ok(());
+    }
+
+    error!("Aborted, no queues or topics were created");
+    Err(PinnError::Unconfirmed {
+        action,
+        flag: "--confirm-new-suffix",
+    }
+    .into())
+}
+
+// </editor-fold desc="// Environment Suffix Utilities ...">
+
+// <editor-fold desc="// First Run Guard Utilities ...">
+
+/// Guard against pinnothera's first-ever run in a guarded environment (`prod`
+/// or any passed via `--guarded-env`) creating resources unacknowledged,
+/// returning whether the run must be limited to printing its plan because
+/// none of the queues configured for a guarded environment exist yet and
+/// `--bootstrap` wasn't passed
+async fn check_first_run(
+    context: &AppContext,
+    args: &CLIArgs,
+    pinn_config: &PinnConfig,
+) -> Result<bool, Terminator> {
+    let (cluster_env, settings): (EnvName, &Settings) = (context.env, &context.settings);
+
+    if args.bootstrap || settings.dry_run || settings.check {
+        return Ok(false);
+    }
+
+    let guarded: Vec<(String, EnvName)> = pinn_config
+        .iter()
+        .filter(|(queue, config)| {
+            queue.as_str() != "unsubscribed" && !settings.is_paused(queue, config)
+        })
+        .map(|(queue, config)| {
+            let env: EnvName = config.env_name(cluster_env);
+            (
+                resolved_name(
+                    context,
+                    "queue",
+                    queue,
+                    config.queue_name_env(env),
+                    config.is_fifo(),
+                ),
+                env,
+            )
+        })
+        .filter(|(_, env)| args.is_guarded_env(*env))
+        .collect();
+
+    if guarded.is_empty() {
+        return Ok(false);
+    }
+
+    let existing = list_queue_names(context).await?;
+
+    if guarded.iter().any(|(queue, _)| existing.contains(queue)) {
+        return Ok(false);
+    }
+
+    let envs: BTreeSet<&str> = guarded.iter().map(|(_, env)| env.as_suffix()).collect();
+
+    warning!(
+        "None of the {} queue(s) configured for guarded environment(s) {:?} exist yet, this appears to be pinnothera's first run there; printing the plan instead of creating anything",
+        guarded.len(),
+        envs,
+    );
+
+    Ok(true)
+}
+
+// </editor-fold desc="// First Run Guard Utilities ...">
+
+// <editor-fold desc="// SQS Queue Purge Utilities ...">
+
+/// Look up the URL of the supplied (resolved) queue
+/// name, returning `None` if it doesn't exist
+async fn find_queue_url(
+    context: &AppContext,
+    queue: &str,
+) -> Result<Option<SQSQueueURL>, Terminator> {
+    let client: &SQSClient = &context.sqs;
+
+    let queue_url = match retry::with_retries(&context.run, "GetQueueUrl", queue, || {
+        client.get_queue_url().queue_name(queue).send()
+    })
+    .await?
+    {
+        Ok(response) => match response.queue_url().map(naming::normalize_queue_url) {
+            Some(value) => value,
+            None => {
+                let error = PinnError::missing_field("GetQueueUrl", queue, "queue URL");
+                error!("{}", error);
+                return Err(error.into());
+            }
+        },
+        Err(SdkError::ServiceError { ref err, .. }) if err.is_queue_does_not_exist() => {
+            return Ok(None);
+        }
+        Err(error) => {
+            error_detail!(
+                "Could not retrieve the URL of queue \"{}\": {}",
+                queue,
+                logging::error_chain(&error)
+            );
+            print_error_hint(context, &error);
+            return Err(error.into());
+        }
+    };
+
+    Ok(Some(queue_url))
+}
+
+/// Look up the URL and approximate message count of the supplied
+/// (resolved) queue name, returning `None` if it doesn't exist
+async fn get_purge_target<T: AsRef<str>>(
+    context: &AppContext,
+    queue: T,
+) -> Result<Option<(SQSQueueURL, Option<String>)>, Terminator> {
+    let queue: &str = queue.as_ref();
+    let client: &SQSClient = &context.sqs;
+
+    let queue_url: SQSQueueURL = match find_queue_url(context, queue).await? {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+
+    let message_count = retry::with_retries(&context.run, "GetQueueAttributes", queue, || {
+        client
+            .get_queue_attributes()
+            .queue_url(&queue_url)
+            .attribute_names(QueueAttributeName::ApproximateNumberOfMessages)
+            .send()
+    })
+    .await
+    .ok()
+    .and_then(Result::ok)
+    .and_then(|response| response.attributes)
+    .and_then(|attributes| {
+        attributes
+            .get(&QueueAttributeName::ApproximateNumberOfMessages)
+            .cloned()
+    });
+
+    Ok(Some((queue_url, message_count)))
+}
+
+async fn purge_queue<T: AsRef<str>>(
+    context: Arc<AppContext>,
+    queue: T,
+    queue_url: SQSQueueURL,
+) -> u8 {
+    let queue: &str = queue.as_ref();
+
+    match retry::with_retries(&context.run, "PurgeQueue", queue, || {
+        context.sqs.purge_queue().queue_url(&queue_url).send()
+    })
+    .await
+    {
+        Ok(Ok(_)) => {
+            info!("Purged queue \"{}\"", queue);
+            events::operation_succeeded(&context.run, "PurgeQueue", queue, Some(&queue_url), None);
+            0
+        }
+        Ok(Err(SdkError::ServiceError { ref err, .. })) if err.is_purge_queue_in_progress() => {
+            warning!(
+                "A purge of queue \"{}\" is already in progress (SQS allows one purge per queue every 60 seconds)",
+                queue
+            );
+            0
+        }
+        Ok(Err(error)) => {
+            error_detail!(
+                "Could not purge queue \"{}\": {}",
+                queue,
+                logging::error_chain(&error)
+            );
+            print_error_hint(&context, &error);
+            events::operation_failed(&context.run, "PurgeQueue", queue, error_message(&error));
+            1
+        }
+        Err(_) => 1,
+    }
+}
+
+async fn purge_queues(
+    context: Arc<AppContext>,
+    pinn_config: &PinnConfig,
+    purge_args: &PurgeArgs,
+) -> u8 {
+    let queues: Vec<String> = pinn_config
+        .iter()
+        .filter(|(queue, _)| queue.as_str() != "unsubscribed" && purge_args.selects(queue))
+        .map(|(queue, config)| {
+            resolve_queue_name(
+                &context,
+                queue,
+                config.queue_name_env(config.env_name(context.env)),
+                config.is_fifo(),
+            )
+        })
+        .collect();
+
+    let mut failures: u8 = 0;
+    let mut targets: Vec<(String, SQSQueueURL)> = Vec::new();
+
+    for queue in queues {
+        match get_purge_target(&context, &queue).await {
+            Ok(Some((queue_url, message_count))) => {
+                info!(
+                    "Queue \"{}\" will be purged (approximate message count: {})",
+                    &queue,
+                    message_count.as_deref().unwrap_or("unknown"),
+                );
+                targets.push((queue, queue_url));
+            }
+            Ok(None) => info!("Queue \"{}\" does not exist, skipping it", &queue),
+            Err(_) => failures = failures.saturating_add(1),
+        }
+    }
+
+    if targets.is_empty() {
+        info!("No existing queues were selected, nothing to purge");
+        return failures;
+    } else if context.settings.dry_run || context.settings.check {
+        targets.iter().for_each(|(queue, _)| {
+            dry_run(&context, "PurgeQueue", "queue", queue, "purge queue");
+        });
+        return failures;
+    } else if !confirm_destructive_action(
+        format!("purge {} queue(s)", targets.len()),
+        purge_args.yes,
+        "--yes",
+    ) {
+        error!("Purge aborted, no queues were purged");
+        return failures.saturating_add(1);
+    }
+
+    let tasks: Vec<(String, JoinHandle<u8>)> = targets
+        .into_iter()
+        .map(|(queue, queue_url)| {
+            let task_context = context.clone();
+            (
+                format!("queue \"{}\"", &queue),
+                tokio::spawn(async move { purge_queue(task_context, queue, queue_url).await }),
+            )
+        })
+        .collect();
+
+    join_tasks(&context.run, tasks)
+        .await
+        .iter()
+        .fold(failures, |total, result| total.saturating_add(*result))
+}
+
+// </editor-fold desc="// SQS Queue Purge Utilities ...">
+
+// <editor-fold desc="// Smoke Test Utilities ...">
+
+/// How long to wait before polling a queue for test messages again
+/// after a poll that didn't receive any (i.e. one that only received
+/// messages that aren't test messages, which are returned immediately)
+const SMOKE_TEST_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The filter policy of the supplied queue's subscription to the
+/// supplied topic, from either the topic's entry or the queue's
+/// `FilterPolicy` raw subscription attribute
+fn subscription_filter_policy(
+    config: &SQSQueueConfig,
+    topic: &TopicConfig,
+) -> Option<serde_json::Value> {
+    topic.filter_policy().cloned().or_else(|| {
+        config
+            .raw_subscription_attributes
+            .get("FilterPolicy")
+            .and_then(|value| serde_json::from_str(value).ok())
+    })
+}
+
+/// Publish a test message carrying the supplied token
+/// to the supplied (resolved) topic, with the supplied
+/// message attributes
+async fn publish_test_message(
+    context: &AppContext,
+    topic: &str,
+    topic_arn: &str,
+    token: &str,
+    fifo: bool,
+    attributes: &BTreeMap<String, (&'static str, String)>,
+) -> Result<(), String> {
+    let message: String = serde_json::json!({
+        "pinnothera_smoke_test": token,
+        "topic": topic,
+    })
+    .to_string();
+
+    let message_attributes: HashMap<String, MessageAttributeValue> = attributes
+        .iter()
+        .map(|(name, (data_type, value))| {
+            (
+                name.to_string(),
+                MessageAttributeValue::builder()
+                    .data_type(*data_type)
+                    .string_value(value)
+                    .build(),
+            )
+        })
+        .collect();
+
+    match retry::with_retries(&context.run, "Publish", topic, || {
+        let request = context
+            .sns
+            .publish()
+            .topic_arn(topic_arn)
+            .message(&message)
+            .set_message_attributes(Some(message_attributes.clone()));
+
+        match fifo {
+            true => request
+                .message_group_id("pinnothera-smoke-test")
+                .message_deduplication_id(token),
+            false => request,
+        }
+        .send()
+    })
+    .await
+    .map_err(|error| error.to_string())?
+    {
+        Ok(_) => {
+            info!(
+                "Published test message \"{}\" to topic \"{}\"",
+                token, topic
+            );
+            events::operation_succeeded(&context.run, "Publish", topic, None, Some(topic_arn));
+            Ok(())
+        }
+        Err(error) => {
+            error_detail!(
+                "Could not publish a test message to topic \"{}\": {}",
+                topic,
+                logging::error_chain(&error)
+            );
+            print_error_hint(context, &error);
+            events::operation_failed(&context.run, "Publish", topic, error_message(&error));
+            Err(error_message(&error))
+        }
+    }
+}
+
+/// Return the supplied message (which isn't one of the smoke test's) to
+/// the supplied queue immediately, rather than once its visibility
+/// timeout elapses
+async fn release_message(context: &AppContext, queue: &str, queue_url: &str, receipt_handle: &str) {
+    if let Ok(Err(error)) =
+        retry::with_retries(&context.run, "ChangeMessageVisibility", queue, || {
+            context
+                .sqs
+                .change_message_visibility()
+                .queue_url(queue_url)
+                .receipt_handle(receipt_handle)
+                .visibility_timeout(0)
+                .send()
+        })
+        .await
+    {
+        warning!(
+            "Could not return a message that isn't a test message to queue \"{}\" (it will reappear once its visibility timeout elapses): {}",
+            queue,
+            error_message(&error)
+        );
+    }
+}
+
+/// Delete the supplied test message from the supplied queue
+async fn delete_test_message(
+    context: &AppContext,
+    queue: &str,
+    queue_url: &str,
+    receipt_handle: &str,
+) -> Result<(), String> {
+    retry::with_retries(&context.run, "DeleteMessage", queue, || {
+        context
+            .sqs
+            .delete_message()
+            .queue_url(queue_url)
+            .receipt_handle(receipt_handle)
+            .send()
+    })
+    .await
+    .map_err(|error| error.to_string())?
+    .map(|_| ())
+    .map_err(|error| error_message(&error))
+}
+
+/// Publish a test message to each of the supplied (configured) queue's
+/// topics, then poll the queue until every message arrives (deleting
+/// each one) or the supplied timeout elapses
+async fn smoke_test_queue(
+    context: Arc<AppContext>,
+    queue: String,
+    config: SQSQueueConfig,
+    timeout: Duration,
+) -> smoke::QueueSmokeTest {
+    let env: EnvName = config.env_name(context.env);
+    let resolved_queue: String = resolve_queue_name(
+        &context,
+        &queue,
+        config.queue_name_env(env),
+        config.is_fifo(),
+    );
+
+    let mut result = smoke::QueueSmokeTest {
+        queue,
+        resolved_queue: resolved_queue.clone(),
+        topics: Vec::new(),
+        error: None,
+    };
+
+    let queue_url: SQSQueueURL = match find_queue_url(&context, &resolved_queue).await {
+        Ok(Some(value)) => value,
+        Ok(None) => {
+            error!("Queue \"{}\" does not exist", &resolved_queue);
+            result.error = Some("the queue does not exist".to_string());
+            return result;
+        }
+        Err(_) => {
+            result.error = Some("could not look up the queue's URL".to_string());
+            return result;
+        }
+    };
+
+    // The token, index (in the result's topics), and send
+    // time of each test message that hasn't arrived yet
+    let mut pending: Vec<(String, usize, Instant)> = Vec::new();
+
+    for topic in config.topics.iter() {
+        let resolved_topic: String = resolved_name(
+            &context,
+            "topic",
+            topic.as_str(),
+            config.topic_name_env(topic.as_str(), env),
+            config.is_fifo(),
+        );
+
+        let mut delivery = smoke::TopicDelivery {
+            topic: topic.to_string(),
+            resolved_topic: resolved_topic.clone(),
+            status: smoke::DeliveryStatus::Planned,
+            latency_ms: None,
+            detail: None,
+        };
+
+        if topic.is_external() || topic.is_foreign() {
+            delivery.status = smoke::DeliveryStatus::Skipped;
+            delivery.detail = Some(
+                match (topic.is_arn(), topic.account_id().is_some()) {
+                    (true, _) => "topics configured by ARN aren't published to",
+                    (false, true) => "topics in other accounts aren't published to",
+                    (false, false) => "external topics aren't published to",
+                }
+                .to_string(),
+            );
+            result.topics.push(delivery);
+            continue;
+        }
+
+        let attributes: BTreeMap<String, (&'static str, String)> = match subscription_filter_policy(
+            &config, topic,
+        ) {
+            None => BTreeMap::new(),
+            Some(_)
+                if config
+                    .raw_subscription_attributes
+                    .get("FilterPolicyScope")
+                    .map(|scope| scope == "MessageBody")
+                    .unwrap_or(false) =>
+            {
+                delivery.detail = Some(
+                        "may be filtered out (filter policies scoped to message bodies aren't supported)"
+                            .to_string(),
+                    );
+                BTreeMap::new()
+            }
+            Some(policy) => match smoke::sample_message_attributes(&policy) {
+                Ok(value) => value,
+                Err(reason) => {
+                    warning!(
+                        "The test message for topic \"{}\" may be filtered out of queue \"{}\": {}",
+                        &resolved_topic,
+                        &resolved_queue,
+                        &reason
+                    );
+                    delivery.detail = Some(format!("may be filtered out: {}", reason));
+                    BTreeMap::new()
+                }
+            },
+        };
+
+        if context.settings.dry_run || context.settings.check {
+            dry_run(
+                &context,
+                "Publish",
+                "topic",
+                &resolved_topic,
+                "publish a test message to topic",
+            );
+            result.topics.push(delivery);
+            continue;
+        }
+
+        let topic_arn: String = match lookup_topic_arn(&context, &resolved_topic).await {
+            Ok(Some(value)) => value,
+            Ok(None) => {
+                delivery.status = smoke::DeliveryStatus::Failed;
+                delivery.detail = Some("the topic does not exist".to_string());
+                result.topics.push(delivery);
+                continue;
+            }
+            Err(error) => {
+                delivery.status = smoke::DeliveryStatus::Failed;
+                delivery.detail = Some(logging::error_chain(&error));
+                result.topics.push(delivery);
+                continue;
+            }
+        };
+
+        let token: String = smoke::message_token();
+
+        match publish_test_message(
+            &context,
+            &resolved_topic,
+            &topic_arn,
+            &token,
+            config.is_fifo(),
+            &attributes,
+        )
+        .await
+        {
+            Ok(_) => pending.push((token, result.topics.len(), Instant::now())),
+            Err(reason) => {
+                delivery.status = smoke::DeliveryStatus::Failed;
+                delivery.detail = Some(format!("could not publish: {}", reason));
+            }
+        }
+
+        result.topics.push(delivery);
+    }
+
+    let deadline: Instant = Instant::now() + timeout;
+
+    while !pending.is_empty() {
+        let remaining: Duration = deadline.saturating_duration_since(Instant::now());
+
+        if remaining.is_zero() {
+            break;
+        }
+
+        let messages =
+            match retry::with_retries(&context.run, "ReceiveMessage", &resolved_queue, || {
+                context
+                    .sqs
+                    .receive_message()
+                    .queue_url(&queue_url)
+                    .max_number_of_messages(10)
+                    .wait_time_seconds(remaining.as_secs().min(20) as i32)
+                    .send()
+            })
+            .await
+            {
+                Ok(Ok(response)) => response.messages.unwrap_or_default(),
+                Ok(Err(error)) => {
+                    error_detail!(
+                        "Could not receive messages from queue \"{}\": {}",
+                        &resolved_queue,
+                        logging::error_chain(&error)
+                    );
+                    print_error_hint(&context, &error);
+                    events::operation_failed(
+                        &context.run,
+                        "ReceiveMessage",
+                        &resolved_queue,
+                        error_message(&error),
+                    );
+
+                    pending.drain(..).for_each(|(_, index, _)| {
+                        result.topics[index].status = smoke::DeliveryStatus::Failed;
+                        result.topics[index].detail = Some(format!(
+                            "could not poll the queue: {}",
+                            error_message(&error)
+                        ));
+                    });
+                    break;
+                }
+                Err(_) => break,
+            };
+
+        let awaited: usize = pending.len();
+
+        for message in messages {
+            let (body, receipt_handle) = match (message.body(), message.receipt_handle()) {
+                (Some(body), Some(receipt_handle)) => (body, receipt_handle),
+                _ => continue,
+            };
+
+            let position = pending
+                .iter()
+                .position(|(token, _, _)| body.contains(token.as_str()));
+
+            let (token, index, sent) = match position {
+                Some(position) => pending.remove(position),
+                None => {
+                    release_message(&context, &resolved_queue, &queue_url, receipt_handle).await;
+                    continue;
+                }
+            };
+
+            let delivery = &mut result.topics[index];
+
+            delivery.status = smoke::DeliveryStatus::Delivered;
+            delivery.latency_ms = Some(sent.elapsed().as_millis() as u64);
+
+            info!(
+                "Test message \"{}\" from topic \"{}\" arrived in queue \"{}\" after {}ms",
+                &token,
+                &delivery.resolved_topic,
+                &resolved_queue,
+                delivery.latency_ms.unwrap_or_default()
+            );
+
+            if let Err(reason) =
+                delete_test_message(&context, &resolved_queue, &queue_url, receipt_handle).await
+            {
+                warning!(
+                    "Could not delete test message \"{}\" from queue \"{}\": {}",
+                    &token,
+                    &resolved_queue,
+                    &reason
+                );
+                delivery.detail = Some(format!("could not delete the test message: {}", reason));
+            }
+        }
+
+        if pending.len() == awaited {
+            tokio::time::sleep(
+                SMOKE_TEST_POLL_INTERVAL.min(deadline.saturating_duration_since(Instant::now())),
+            )
+            .await;
+        }
+    }
+
+    pending.into_iter().for_each(|(token, index, _)| {
+        let delivery = &mut result.topics[index];
+
+        error!(
+            "Test message \"{}\" from topic \"{}\" did not arrive in queue \"{}\" within {}s",
+            &token,
+            &delivery.resolved_topic,
+            &resolved_queue,
+            timeout.as_secs()
+        );
+        delivery.status = smoke::DeliveryStatus::TimedOut;
+        delivery.detail = Some(format!("did not arrive within {}s", timeout.as_secs()));
+    });
+
+    result
+}
+
+/// Smoke test the selected queues, refusing to publish test messages
+/// in production environments unless forced, and print the results
+/// in the supplied `--output` format
+async fn smoke_test_queues(
+    context: Arc<AppContext>,
+    pinn_config: &PinnConfig,
+    smoke_args: &SmokeTestArgs,
+    output: &str,
+) -> u8 {
+    if let Some(queue) = &smoke_args.queue {
+        if queue == "unsubscribed" || !pinn_config.contains_key(queue) {
+            error!("Queue \"{}\" is not in the configuration", queue);
+            return 1;
+        }
+    }
+
+    let selected: Vec<(String, SQSQueueConfig)> = pinn_config
+        .iter()
+        .filter(|(queue, _)| queue.as_str() != "unsubscribed" && smoke_args.selects(queue))
+        .map(|(queue, config)| (queue.to_string(), config.clone()))
+        .collect();
+
+    let production: Vec<&str> = selected
+        .iter()
+        .filter(|(_, config)| config.env_name(context.env) == EnvName::Prod)
+        .map(|(queue, _)| queue.as_str())
+        .collect();
+
+    if !production.is_empty() && !smoke_args.force {
+        error!(
+            "Refusing to publish test messages for production queue(s) {:?} (pass `--force` to smoke test them anyway)",
+            &production
+        );
+        return 1;
+    }
+
+    let timeout: Duration = Duration::from_secs(smoke_args.timeout_secs);
+
+    let tasks: Vec<(String, JoinHandle<smoke::QueueSmokeTest>)> = selected
+        .into_iter()
+        .map(|(queue, config)| {
+            let task_context = context.clone();
+            (
+                queue.clone(),
+                tokio::spawn(logging::with_queue_context(
+                    queue.clone(),
+                    smoke_test_queue(task_context, queue, config, timeout),
+                )),
+            )
+        })
+        .collect();
+
+    let mut results: Vec<smoke::QueueSmokeTest> = Vec::new();
+
+    for (queue, task) in tasks {
+        match task.await {
+            Ok(result) => results.push(result),
+            Err(error) => {
+                let reason = describe_join_error(error);
+                error!(
+                    "Task for queue \"{}\" did not complete: {}",
+                    &queue, &reason
+                );
+                results.push(smoke::QueueSmokeTest {
+                    queue: queue.clone(),
+                    resolved_queue: queue,
+                    topics: Vec::new(),
+                    error: Some(reason),
+                });
+            }
+        }
+    }
+
+    let report = smoke::SmokeTestReport::new(smoke_args.timeout_secs, results);
+
+    match output {
+        "json" => match serde_json::to_string_pretty(&report) {
+            Ok(document) => println!("{}", document),
+            Err(error) => error!("Could not serialize the smoke test report: {}", error),
+        },
+        _ => smoke::print_report(&report),
+    }
+
+    match report.passed {
+        true => 0,
+        false => 1,
+    }
+}
+
+// </editor-fold desc="// Smoke Test Utilities ...">
+
+// <editor-fold desc="// SNS->SQS Subscription Utilities ...">
+
+/// Determine whether the supplied (desired) subscription attribute value
+/// differs from the existing one, comparing JSON-valued attributes (i.e.
+/// `FilterPolicy`) structurally rather than textually
+fn subscription_attribute_differs(name: &str, desired: &str, existing: Option<&String>) -> bool {
+    let existing: &str = match existing {
+        Some(value) => value.as_str(),
+        None => return true,
+    };
+
+    match name {
+        "FilterPolicy" => match (
+            serde_json::from_str::<serde_json::Value>(desired),
+            serde_json::from_str::<serde_json::Value>(existing),
+        ) {
+            (Ok(desired), Ok(existing)) => desired != existing,
+            _ => desired != existing,
+        },
+        _ => !desired.eq_ignore_ascii_case(existing),
+    }
+}
+
+/// The supplied attributes of the supplied subscription whose
+/// configured values differ from the existing ones
+async fn differing_subscription_attributes(
+    context: &AppContext,
+    sns: &SNSClient,
+    subscription_arn: &str,
+    subscription_name: &str,
+    desired: HashMap<String, String>,
+) -> Result<HashMap<String, String>, String> {
+    if desired.is_empty() {
+        return Ok(desired);
+    }
+
+    let existing: HashMap<String, String> = match retry::with_retries(
+        &context.run,
+        "GetSubscriptionAttributes",
+        subscription_name,
+        || {
+            sns.get_subscription_attributes()
+                .subscription_arn(subscription_arn)
+                .send()
+        },
+    )
+    .await
+    .map_err(|error| error.to_string())?
+    {
+        Ok(response) => response.attributes.unwrap_or_default(),
+        Err(error) => {
+            error_detail!(
+                "Could not get the attributes of subscription \"{}\": {}",
+                subscription_name,
+                logging::error_chain(&error)
+            );
+            print_error_hint(context, &error);
+            events::operation_failed(
+                &context.run,
+                "GetSubscriptionAttributes",
+                subscription_name,
+                error_message(&error),
+            );
+            return Err(error_message(&error));
+        }
+    };
+
+    Ok(desired
+        .into_iter()
+        .filter(|(name, value)| {
+            let differs: bool = subscription_attribute_differs(name, value, existing.get(name));
+
+            match differs {
+                true => explain(
+                    context,
+                    "subscription",
+                    subscription_name,
+                    &[explain_change(name, existing.get(name), value)],
+                ),
+                false => debug!(
+                    "Subscription \"{}\" already has the configured {}",
+                    subscription_name, name
+                ),
+            }
+
+            differs
+        })
+        .collect())
+}
+
+/// Converge the supplied attributes of the supplied subscription on
+/// their configured values (whether or not the subscription was just
+/// created), leaving any that already match untouched, and returning
+/// whether any were updated (or why they couldn't be)
+async fn reconcile_subscription_attributes(
+    context: &AppContext,
+    sns: &SNSClient,
+    subscription_arn: &str,
+    subscription_name: &str,
+    desired: HashMap<String, String>,
+) -> Result<bool, String> {
+    let differing: HashMap<String, String> = differing_subscription_attributes(
+        context,
+        sns,
+        subscription_arn,
+        subscription_name,
+        desired,
+    )
+    .await?;
+
+    let (mut updated, mut failures): (bool, Vec<String>) = (false, Vec::new());
+
+    for (name, value) in differing {
+        match retry::with_retries(
+            &context.run,
+            "SetSubscriptionAttributes",
+            subscription_name,
+            || {
+                sns.set_subscription_attributes()
+                    .subscription_arn(subscription_arn)
+                    .attribute_name(&name)
+                    .attribute_value(&value)
+                    .send()
+            },
+        )
+        .await
+        {
+            Ok(Ok(_)) => {
+                info!(
+                    "Set {} of subscription \"{}\" to {}",
+                    name, subscription_name, &value
+                );
+                events::operation_succeeded(
+                    &context.run,
+                    "SetSubscriptionAttributes",
+                    subscription_name,
+                    None,
+                    Some(subscription_arn),
+                );
+                updated = true;
+            }
+            Ok(Err(error)) => {
+                error_detail!(
+                    "Could not set attribute \"{}\" of subscription \"{}\": {}",
+                    name,
+                    subscription_name,
+                    logging::error_chain(&error)
+                );
+                print_error_hint(context, &error);
+                events::operation_failed(
+                    &context.run,
+                    "SetSubscriptionAttributes",
+                    subscription_name,
+                    error_message(&error),
+                );
+                failures.push(format!("could not set {}: {}", name, error_message(&error)));
+            }
+            Err(error) => {
+                failures.push(error.to_string());
+                return Err(failures.join("; "));
+            }
+        }
+    }
+
+    match failures.is_empty() {
+        true => Ok(updated),
+        false => Err(failures.join("; ")),
+    }
+}
+
+/// The subscription attributes pinnothera itself manages for
+/// the supplied topic entry of the supplied queue's configuration
+fn typed_subscription_attributes(
+    context: &AppContext,
+    topic_config: &TopicConfig,
+    config: &SQSQueueConfig,
+) -> HashMap<String, String> {
+    let mut attributes: HashMap<String, String> = HashMap::new();
+
+    if let Some(filter_policy) = topic_config.filter_policy() {
+        attributes.insert("FilterPolicy".to_string(), filter_policy.to_string());
+    }
+
+    if let Some(raw) = config
+        .raw_message_delivery
+        .or_else(|| context.settings.raw_message_delivery.then_some(true))
+    {
+        attributes.insert("RawMessageDelivery".to_string(), raw.to_string());
+    }
+
+    attributes
+}
+
+/// Find the ARN of the existing (confirmed) subscription of the
+/// supplied queue to the supplied topic, paging through all of
+/// the topic's subscriptions
+async fn find_subscription(
+    context: &AppContext,
+    sns: &SNSClient,
+    topic_arn: &str,
+    queue_arn: &str,
+    subscription_name: &str,
+) -> Result<Option<String>, Terminator> {
+    let mut next_token: Option<String> = None;
+
+    loop {
+        let resp = match retry::with_retries(
+            &context.run,
+            "ListSubscriptionsByTopic",
+            subscription_name,
+            || {
+                sns.list_subscriptions_by_topic()
+                    .topic_arn(topic_arn)
+                    .set_next_token(next_token.clone())
+                    .send()
+            },
+        )
+        .await?
+        {
+            Ok(response) => response,
+            Err(error) => {
+                error_detail!(
+                    "Could not list the subscriptions of topic \"{}\": {}",
+                    topic_arn,
+                    logging::error_chain(&error)
+                );
+                print_error_hint(context, &error);
+                events::operation_failed(
+                    &context.run,
+                    "ListSubscriptionsByTopic",
+                    subscription_name,
+                    error_message(&error),
+                );
+                return Err(error.into());
+            }
+        };
+
+        let existing = resp
+            .subscriptions()
+            .unwrap_or_default()
+            .iter()
+            .filter(|subscription| {
+                subscription.protocol() == Some("sqs") && subscription.endpoint() == Some(queue_arn)
+            })
+            .filter_map(|subscription| subscription.subscription_arn())
+            .find(|arn| arn.starts_with("arn:"));
+
+        if let Some(arn) = existing {
+            return Ok(Some(arn.to_string()));
+        }
+
+        next_token = resp.next_token().map(str::to_string);
+
+        if next_token.is_none() {
+            return Ok(None);
+        }
+    }
+}
+
+/// Which of the supplied queue's settings (and of pinnothera's flags)
+/// shape its subscription to the supplied topic, as explained by `--explain`
+fn explain_subscription(
+    context: &AppContext,
+    topic_config: &TopicConfig,
+    config: &SQSQueueConfig,
+) -> Vec<String> {
+    if !context.settings.explain {
+        return Vec::new();
+    }
+
+    let mut reasons: Vec<String> = config
+        .source
+        .iter()
+        .map(|source| format!("configured in {}", source))
+        .collect();
+
+    if topic_config.filter_policy().is_some() {
+        reasons.push("filter policy per the topic entry's `filter_policy`".to_string());
+    }
+
+    match config.raw_message_delivery {
+        Some(raw) => reasons.push(format!(
+            "raw message delivery {} per the queue's `raw_message_delivery`",
+            if raw { "enabled" } else { "disabled" }
+        )),
+        None if context.settings.raw_message_delivery => {
+            reasons.push("raw message delivery enabled per `--raw-message-delivery`".to_string())
+        }
+        None => (),
+    }
+
+    if !config.raw_subscription_attributes.is_empty() {
+        reasons.push(format!(
+            "raw attribute(s) {:?} per the queue's `raw_subscription_attributes`",
+            config
+                .raw_subscription_attributes
+                .keys()
+                .collect::<Vec<&String>>()
+        ));
+    }
+
+    if context.settings.force_resubscribe {
+        reasons.push("subscribed even if already subscribed per `--force-resubscribe`".to_string());
+    }
+
+    reasons
+}
+
+/// Verify (without creating or modifying anything) that the supplied
+/// queue is subscribed to the supplied topic with the supplied attributes,
+/// looking the subscription up among every subscription in the account
+/// if it's `indexed` (i.e. made with the run's own SNS client)
+async fn check_subscription(
+    context: &AppContext,
+    sns: &SNSClient,
+    topic_arn: &str,
+    queue_arn: &str,
+    subscription_name: &str,
+    desired: HashMap<String, String>,
+    indexed: bool,
+) -> Result<(ResourceStatus, Option<String>), (ResourceStatus, String)> {
+    let existing: Result<Option<String>, String> = match indexed {
+        true => find_indexed_subscription(context, topic_arn, queue_arn, subscription_name).await,
+        false => find_subscription(context, sns, topic_arn, queue_arn, subscription_name).await,
+    }
+    .map_err(|error| events::error_chain(&error).join(": "));
+
+    let arn: String = match existing {
+        Ok(Some(arn)) => arn,
+        Ok(None) => {
+            warning!("Subscription \"{}\" does not exist", subscription_name);
+            return Err((ResourceStatus::Missing, "not subscribed".to_string()));
+        }
+        Err(error) => return Err((ResourceStatus::Failed, error)),
+    };
+
+    let mut differing: Vec<String> =
+        differing_subscription_attributes(context, sns, &arn, subscription_name, desired)
+            .await
+            .map_err(|reason| (ResourceStatus::Failed, reason))?
+            .into_keys()
+            .collect();
+
+    if differing.is_empty() {
+        info!(
+            subscription_arn = arn.as_str(),
+            "Subscription \"{}\" exists as configured", subscription_name
+        );
+        return Ok((ResourceStatus::Existing, Some(arn)));
+    }
+
+    differing.sort_unstable();
+
+    warning!(
+        "Subscription \"{}\" exists with attribute(s) differing from its configuration: {:?}",
+        subscription_name,
+        &differing,
+    );
+
+    Err((
+        ResourceStatus::Drifted,
+        format!(
+            "attribute(s) {:?} differ from the configuration",
+            &differing
+        ),
+    ))
+}
+
+/// Determine whether the supplied `Subscribe` error is due to the
+/// subscription already existing, but with different attributes
+fn is_subscription_conflict(error: &SdkError<SubscribeError>) -> bool {
+    match error {
+        SdkError::ServiceError { err, .. } => {
+            err.is_invalid_parameter_exception()
+                && err
+                    .message()
+                    .map(|message| message.to_lowercase().contains("already exists"))
+                    .unwrap_or(false)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the supplied error is AWS rejecting a subscription
+/// because only one of the queue and topic is FIFO
+fn is_subscription_incompatibility(error: &SdkError<SubscribeError>) -> bool {
+    match error {
+        SdkError::ServiceError { err, .. } => {
+            err.is_invalid_parameter_exception()
+                && err
+                    .message()
+                    .map(|message| message.to_lowercase().contains("fifo"))
+                    .unwrap_or(false)
+        }
+        _ => false,
+    }
+}
+
+/// Converge the attributes of the supplied existing subscription on
+/// their configured values, returning whether any had to be updated
+async fn reconcile_existing_subscription(
+    context: &AppContext,
+    sns: &SNSClient,
+    subscription_arn: String,
+    subscription_name: &str,
+    desired: HashMap<String, String>,
+) -> Result<(ResourceStatus, Option<String>), (ResourceStatus, String)> {
+    match reconcile_subscription_attributes(
+        context,
+        sns,
+        &subscription_arn,
+        subscription_name,
+        desired,
+    )
+    .await
+    {
+        Ok(true) => Ok((ResourceStatus::Updated, Some(subscription_arn))),
+        Ok(false) => Ok((ResourceStatus::Existing, Some(subscription_arn))),
+        Err(reason) => Err((ResourceStatus::Failed, reason)),
+    }
+}
+
+/// Wait (for up to `--confirmation-timeout-secs`) for the supplied
+/// subscription, which SNS reported as pending confirmation, to be
+/// confirmed, returning its ARN if it is, and otherwise reporting it
+/// as pending (or, with `--require-confirmed`, as failed)
+async fn await_subscription_confirmation(
+    context: &AppContext,
+    sns: &SNSClient,
+    topic_arn: &str,
+    queue_arn: &str,
+    subscription_name: &str,
+) -> Result<(ResourceStatus, Option<String>), (ResourceStatus, String)> {
+    let deadline: Instant =
+        Instant::now() + Duration::from_secs(context.settings.confirmation_timeout_secs);
+
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        if remaining.is_zero() {
+            break;
+        }
+
+        tokio::time::sleep(remaining.min(CONFIRMATION_POLL_INTERVAL)).await;
+
+        // Pending subscriptions have no ARN yet, so
+        // they're never found until they're confirmed
+        let confirmed: Result<Option<String>, String> =
+            find_subscription(context, sns, topic_arn, queue_arn, subscription_name)
+                .await
+                .map_err(|error| events::error_chain(&error).join(": "));
+
+        match confirmed {
+            Ok(Some(arn)) => {
+                info!(
+                    subscription_arn = arn.as_str(),
+                    "Subscription \"{}\" was confirmed with ARN: \"{}\"", subscription_name, &arn
+                );
+                return Ok((ResourceStatus::Created, Some(arn)));
+            }
+            Ok(None) => (),
+            Err(reason) => return Err((ResourceStatus::Failed, reason)),
+        }
+    }
+
+    let reason: String = match context.settings.confirmation_timeout_secs {
+        0 => "pending confirmation".to_string(),
+        secs => format!("still pending confirmation after {}s", secs),
+    };
+
+    match context.settings.require_confirmed {
+        true => {
+            error_detail!(
+                "Subscription \"{}\" is {} (per `--require-confirmed`)",
+                subscription_name,
+                &reason
+            );
+            events::operation_failed(&context.run, "Subscribe", subscription_name, &reason);
+            Err((ResourceStatus::Failed, reason))
+        }
+        false => {
+            warning!(
+                "Subscription \"{}\" is {}, it won't receive messages until it's confirmed",
+                subscription_name,
+                &reason
+            );
+            Ok((ResourceStatus::Pending, None))
+        }
+    }
+}
+
+/// The SNS client to subscribe to a topic with: the run's own, or
+/// one acting as the topic's `role_arn` (assuming it the first time
+/// it's needed) and/or in the topic's own region (if it's in another
+/// region than the run's), or why the client couldn't be created
+async fn topic_sns_client(
+    context: &AppContext,
+    role_arn: Option<&str>,
+    region: Option<&str>,
+) -> Result<SNSClient, String> {
+    if role_arn.is_none() && region.is_none() {
+        return Ok(context.sns.clone());
+    }
+
+    context
+        .topic_client_cell(role_arn, region)
+        .get_or_init(|| async {
+            let client: Result<SNSClient, String> = match &context.topic_client_factory {
+                Some(factory) => factory.client(role_arn, region).await,
+                None => Err(String::from(
+                    "no SNS client was supplied for topics with their own role or region",
+                )),
+            };
+
+            client.inspect_err(|reason| match role_arn {
+                Some(_) => error_detail!("Could not assume the role to subscribe as: {}", reason),
+                None => error_detail!(
+                    "Could not create an SNS client for region \"{}\": {}",
+                    region.unwrap_or_default(),
+                    reason
+                ),
+            })
+        })
+        .await
+        .clone()
+}
+
+/// Ensure the supplied queue is subscribed to the supplied topic (and
+/// that the topic exists), returning what was done to ensure it and
+/// the subscription's ARN, or why it couldn't be ensured and whether
+/// that's a failure or (in a `--check` run) drift
+async fn create_subscription<T: AsRef<str>>(
+    context: Arc<AppContext>,
+    queue_arn: T,
+    topic_config: TopicConfig,
+    env: EnvName,
+    config: &SQSQueueConfig,
+) -> Result<(ResourceStatus, Option<String>), (ResourceStatus, String)> {
+    let (queue_arn, topic): (&str, &str) = (queue_arn.as_ref(), topic_config.as_str());
+    let topic_arn = match create_topic(context.clone(), topic, env, config).await {
+        Ok(arn) => arn,
+        Err(error) if matches!(*error, PinnError::IncompatibleTopic { .. }) => {
+            warning!(
+                "Skipping subscription of queue \"{}\" to topic \"{}\" due to the topic's conflict",
+                queue_arn,
+                topic,
+            );
+            events::emit(
+                &context.run,
+                PinnEvent::OperationNotAttempted {
+                    operation: "Subscribe",
+                    resource: format!("{} -> {}", topic, queue_arn),
+                    reason: "skipped due to topic conflict".to_string(),
+                },
+            );
+            return Err((
+                ResourceStatus::Failed,
+                format!("skipped, topic \"{}\" has a conflict", topic),
+            ));
+        }
+        Err(error)
+            if matches!(
+                *error,
+                PinnError::MissingResource { .. } | PinnError::DriftedResource { .. }
+            ) =>
+        {
+            return Err((
+                ResourceStatus::Drifted,
+                format!("not checked, topic \"{}\" is missing or differs", topic),
+            ));
+        }
+        Err(_) => {
+            return Err((
+                ResourceStatus::Failed,
+                format!("topic \"{}\" could not be ensured", topic),
+            ));
+        }
+    };
+
+    info!(
+        queue_arn,
+        topic_arn = topic_arn.as_str(),
+        "Ensuring queue \"{}\" is subscribed to topic [name: \"{}\", arn: \"{}\"] ...",
+        queue_arn,
+        topic,
+        &topic_arn,
+    );
+
+    let subscription_name = format!("{} -> {}", topic, queue_arn);
+
+    explain(
+        &context,
+        "subscription",
+        &subscription_name,
+        &explain_subscription(&context, &topic_config, config),
+    );
+
+    // Subscriptions AWS would reject with a generic `InvalidParameter`
+    // error (i.e. of a standard queue to a FIFO topic) fail up front
+    if let Some(reason) = naming::subscription_incompatibility(queue_arn, &topic_arn) {
+        error!(
+            "Could not subscribe queue \"{}\" to topic \"{}\": {}",
+            queue_arn, topic, &reason
+        );
+        events::operation_failed(&context.run, "Subscribe", &subscription_name, &reason);
+        return Err((ResourceStatus::Failed, reason));
+    }
+
+    // Every attribute the subscription should have, which `Subscribe`
+    // is given directly so that a new subscription never exists (even
+    // briefly, or after a failed run) without its filter policy
+    let desired: HashMap<String, String> = merge_raw_attributes(
+        &subscription_name,
+        typed_subscription_attributes(&context, &topic_config, config),
+        &config.raw_subscription_attributes,
+    );
+
+    if context.settings.dry_run {
+        plan::record_subscription(&context.run, &topic_arn, queue_arn, desired);
+        dry_run(
+            &context,
+            "Subscribe",
+            "subscription",
+            &subscription_name,
+            "subscribe queue to topic",
+        );
+        return Ok((ResourceStatus::Planned, None));
+    }
+
+    // Topics in other accounts may have to be subscribed to as a role
+    // in their account (per the topic entry's `role_arn`), and topics
+    // in other regions (per their ARN) with a client in their region
+    let topic_region: Option<&str> =
+        naming::arn_region(&topic_arn).filter(|region| Some(*region) != context.aws_region());
+
+    let sns: SNSClient =
+        match topic_sns_client(&context, topic_config.role_arn(), topic_region).await {
+            Ok(client) => client,
+            Err(reason) => {
+                let operation: &str = match topic_config.role_arn() {
+                    Some(_) => "AssumeRole",
+                    None => "Subscribe",
+                };
+                events::operation_failed(&context.run, operation, &subscription_name, &reason);
+                return Err((ResourceStatus::Failed, reason));
+            }
+        };
+
+    if context.settings.check {
+        // Subscriptions made with the run's own client (to topics in its
+        // own account) are among those it can list, so they're looked up
+        // in a single listing of the account's subscriptions
+        let indexed: bool = topic_config.role_arn().is_none()
+            && topic_region.is_none()
+            && naming::arn_account_id(&topic_arn) == naming::arn_account_id(queue_arn);
+
+        return check_subscription(
+            &context,
+            &sns,
+            &topic_arn,
+            queue_arn,
+            &subscription_name,
+            desired,
+            indexed,
+        )
+        .await;
+    }
+
+    if !context.settings.force_resubscribe {
+        let existing: Result<Option<String>, String> =
+            find_subscription(&context, &sns, &topic_arn, queue_arn, &subscription_name)
+                .await
+                .map_err(|error| events::error_chain(&error).join(": "));
+
+        match existing {
+            Ok(Some(arn)) => {
+                info!(
+                    subscription_arn = arn.as_str(),
+                    "Queue \"{}\" is already subscribed to topic w/ ARN: \"{}\"", queue_arn, &arn
+                );
+                events::emit(
+                    &context.run,
+                    PinnEvent::OperationNotAttempted {
+                        operation: "Subscribe",
+                        resource: subscription_name.clone(),
+                        reason: "already subscribed".to_string(),
+                    },
+                );
+
+                return reconcile_existing_subscription(
+                    &context,
+                    &sns,
+                    arn,
+                    &subscription_name,
+                    desired,
+                )
+                .await;
+            }
+            Ok(None) => (),
+            Err(error) => return Err((ResourceStatus::Failed, error)),
+        }
+    }
+
+    let subscription = match retry::with_retries(
+        &context.run,
+        "Subscribe",
+        &subscription_name,
+        || {
+            sns.subscribe()
+                .topic_arn(&topic_arn)
+                .protocol("sqs")
+                .endpoint(queue_arn)
+                .set_attributes(Some(desired.clone()))
+                .send()
+        },
+    )
+    .await
+    .map_err(|error| (ResourceStatus::Failed, error.to_string()))?
+    {
+        Ok(response) => response,
+        Err(error) if is_subscription_conflict(&error) => {
+            // The queue is already subscribed (i.e. with `--force-resubscribe`,
+            // or by a concurrent run), just with different attributes
+            info!(
+                "Queue \"{}\" is already subscribed to topic \"{}\" with differing attributes, reconciling them...",
+                queue_arn,
+                topic
+            );
+
+            let existing: Result<Option<String>, String> =
+                find_subscription(&context, &sns, &topic_arn, queue_arn, &subscription_name)
+                    .await
+                    .map_err(|error| events::error_chain(&error).join(": "));
+
+            return match existing {
+                Ok(Some(arn)) => {
+                    reconcile_existing_subscription(
+                        &context,
+                        &sns,
+                        arn,
+                        &subscription_name,
+                        desired,
+                    )
+                    .await
+                }
+                Ok(None) => Err((
+                    ResourceStatus::Failed,
+                    "subscription exists with differing attributes, but could not be found"
+                        .to_string(),
+                )),
+                Err(error) => Err((ResourceStatus::Failed, error)),
+            };
+        }
+        Err(error) if is_subscription_incompatibility(&error) => {
+            // The ARNs didn't reveal why (i.e. a name hook named a FIFO
+            // topic without its extension), so describe it generically
+            let reason: String = naming::subscription_incompatibility(queue_arn, &topic_arn)
+                .unwrap_or_else(|| {
+                    format!(
+                        "cannot subscribe queue \"{}\" to topic \"{}\", as only one of them is FIFO",
+                        queue_arn.rsplit(':').next().unwrap_or(queue_arn),
+                        topic_arn.rsplit(':').next().unwrap_or(&topic_arn),
+                    )
+                });
+
+            error_detail!(
+                "Could not subscribe queue \"{}\" to topic \"{}\": {}",
+                queue_arn,
+                topic,
+                logging::error_chain(&error)
+            );
+            events::operation_failed(&context.run, "Subscribe", &subscription_name, &reason);
+            return Err((ResourceStatus::Failed, reason));
+        }
+        Err(error) => {
+            let denied: bool = matches!(
+                &error,
+                SdkError::ServiceError { err, .. } if err.is_authorization_error_exception()
+            );
+
+            error_detail!(
+                "Could not subscribe queue \"{}\" to topic \"{}\": {}",
+                queue_arn,
+                topic,
+                logging::error_chain(&error)
+            );
+
+            // The topic is in another account (per its `account_id` or ARN)
+            let foreign_account: Option<&str> = topic_config
+                .account_id()
+                .or_else(|| naming::arn_account_id(&topic_arn))
+                .filter(|account_id| Some(*account_id) != naming::arn_account_id(queue_arn));
+
+            if let (true, Some(account_id)) = (denied, foreign_account) {
+                warning!(
+                    "Subscribing to topic \"{}\" was denied, either the topic's policy must allow account {} to subscribe to it, or its `role_arn` must be a role in account {} that's allowed to",
+                    topic,
+                    context.aws_account_id().unwrap_or("(unknown)"),
+                    account_id
+                );
+            }
+
+            print_error_hint(&context, &error);
+            events::operation_failed(
+                &context.run,
+                "Subscribe",
+                &subscription_name,
+                error_message(&error),
+            );
+            return Err((
+                ResourceStatus::Failed,
+                match (denied, foreign_account) {
+                    (true, Some(account_id)) => format!(
+                        "subscribe denied: the policy of topic \"{}\" in account {} doesn't allow account {} to subscribe to it",
+                        topic,
+                        account_id,
+                        naming::arn_account_id(queue_arn).unwrap_or("(unknown)"),
+                    ),
+                    (true, None) => format!("subscribe denied: {}", error_message(&error)),
+                    (false, _) => error_message(&error),
+                },
+            ));
+        }
+    };
+
+    match subscription.subscription_arn {
+        None => {
+            error!(
+                "Subscription of topic \"{}\" to queue ARN \"{}\" did not return an error, but did not return a subscription ARN either",
+                topic, queue_arn
+            );
+            events::operation_failed(
+                &context.run,
+                "Subscribe",
+                &subscription_name,
+                "no subscription ARN was returned",
+            );
+            Err((
+                ResourceStatus::Failed,
+                "no subscription ARN was returned".to_string(),
+            ))
+        }
+        // Subscriptions awaiting confirmation (i.e. to some topics in other
+        // accounts) have a placeholder (i.e. "pending confirmation") instead
+        Some(placeholder) if !placeholder.starts_with("arn:") => {
+            info!(
+                "Subscription of queue \"{}\" to topic \"{}\" is pending confirmation",
+                queue_arn, topic
+            );
+            events::operation_succeeded(&context.run, "Subscribe", &subscription_name, None, None);
+
+            await_subscription_confirmation(
+                &context,
+                &sns,
+                &topic_arn,
+                queue_arn,
+                &subscription_name,
+            )
+            .await
+        }
+        Some(arn) => {
+            info!(
+                subscription_arn = arn.as_str(),
+                "Queue \"{}\" is subscribed to topic w/ ARN: \"{}\"", queue_arn, &arn
+            );
+            events::operation_succeeded(
+                &context.run,
+                "Subscribe",
+                &subscription_name,
+                None,
+                Some(&arn),
+            );
+
+            // Subscribing (with identical attributes) is idempotent, so with
+            // `--force-resubscribe` there's no telling whether the
+            // subscription already existed
+            match context.settings.force_resubscribe {
+                true => Ok((ResourceStatus::Ensured, Some(arn))),
+                false => Ok((ResourceStatus::Created, Some(arn))),
+            }
+        }
+    }
+}
+
+/// The ARNs of the supplied queue's (confirmed) subscriptions,
+/// along with the ARN of each one's topic, paging through every
+/// subscription in the account
+async fn list_queue_subscriptions(
+    context: &AppContext,
+    queue_arn: &str,
+) -> Result<Vec<(String, String)>, String> {
+    Ok(list_sqs_subscriptions(context, queue_arn)
+        .await?
+        .into_iter()
+        .filter(|(_, _, endpoint)| endpoint == queue_arn)
+        .map(|(arn, topic_arn, _)| (arn, topic_arn))
+        .collect())
+}
+
+/// The ARNs of every (confirmed) SQS subscription in the account, along
+/// with the ARNs of each one's topic and queue, paging through every
+/// subscription in the account on behalf of the supplied resource
+async fn list_sqs_subscriptions(
+    context: &AppContext,
+    resource: &str,
+) -> Result<Vec<(String, SNSTopicARN, SQSQueueARN)>, String> {
+    let mut subscriptions: Vec<(String, SNSTopicARN, SQSQueueARN)> = Vec::new();
+    let mut next_token: Option<String> = None;
+
+    loop {
+        let resp = match retry::with_retries(&context.run, "ListSubscriptions", resource, || {
+            context
+                .sns
+                .list_subscriptions()
+                .set_next_token(next_token.clone())
+                .send()
+        })
+        .await
+        .map_err(|error| error.to_string())?
+        {
+            Ok(response) => response,
+            Err(error) => {
+                error_detail!(
+                    "Could not list the subscriptions of \"{}\": {}",
+                    resource,
+                    logging::error_chain(&error)
+                );
+                print_error_hint(context, &error);
+                events::operation_failed(
+                    &context.run,
+                    "ListSubscriptions",
+                    resource,
+                    error_message(&error),
+                );
+                return Err(error_message(&error));
+            }
+        };
+
+        subscriptions.extend(
+            resp.subscriptions()
+                .unwrap_or_default()
+                .iter()
+                .filter(|subscription| subscription.protocol() == Some("sqs"))
+                .filter_map(|subscription| {
+                    match (
+                        subscription.subscription_arn(),
+                        subscription.topic_arn(),
+                        subscription.endpoint(),
+                    ) {
+                        (Some(arn), Some(topic_arn), Some(endpoint)) if arn.starts_with("arn:") => {
+                            Some((arn.to_string(), topic_arn.to_string(), endpoint.to_string()))
+                        }
+                        _ => None,
+                    }
+                }),
+        );
+
+        next_token = resp.next_token().map(str::to_string);
+
+        if next_token.is_none() {
+            return Ok(subscriptions);
+        }
+    }
+}
+
+/// The ARN of every (confirmed) SQS subscription in the account by the
+/// ARNs of its topic and queue, listing them (once per run) if they
+/// haven't been yet, or why they couldn't be
+async fn subscription_index(
+    context: &AppContext,
+) -> &Result<HashMap<(SNSTopicARN, SQSQueueARN), String>, String> {
+    context
+        .subscriptions
+        .get_or_init(|| async {
+            list_sqs_subscriptions(context, "all subscriptions")
+                .await
+                .map(|subscriptions| {
+                    subscriptions
+                        .into_iter()
+                        .map(|(arn, topic_arn, endpoint)| ((topic_arn, endpoint), arn))
+                        .collect()
+                })
+        })
+        .await
+}
+
+/// Find the ARN of the existing (confirmed) subscription of the supplied
+/// queue to the supplied topic among every subscription in the account,
+/// listing them (once per run) if they haven't been yet, or paging
+/// through the topic's subscriptions if they couldn't be listed
+async fn find_indexed_subscription(
+    context: &AppContext,
+    topic_arn: &str,
+    queue_arn: &str,
+    subscription_name: &str,
+) -> Result<Option<String>, Terminator> {
+    match subscription_index(context).await {
+        Ok(subscriptions) => Ok(subscriptions
+            .get(&(topic_arn.to_string(), queue_arn.to_string()))
+            .cloned()),
+        Err(_) => {
+            find_subscription(
+                context,
+                &context.sns,
+                topic_arn,
+                queue_arn,
+                subscription_name,
+            )
+            .await
+        }
+    }
+}
+
+/// Unsubscribe the supplied queue from every topic suffixed with its
+/// environment that's no longer in its configured list of topics (or,
+/// in a `--check` run, report each such subscription as drifted),
+/// returning whether every such subscription was pruned
+async fn prune_subscriptions(
+    context: &AppContext,
+    queue: &str,
+    queue_arn: &str,
+    env: EnvName,
+    config: &SQSQueueConfig,
+) -> bool {
+    if context.settings.dry_run {
+        info!(
+            "[dry-run] Not looking up subscriptions of queue \"{}\" to prune",
+            queue
+        );
+        return true;
+    }
+
+    if env.is_unknown() {
+        warning!(
+            "Not pruning subscriptions of queue \"{}\" (its environment is unknown, so no topic is known to be pinnothera's)",
+            queue
+        );
+        return true;
+    }
+
+    let configured: BTreeSet<String> = config
+        .topics
+        .iter()
+        .map(|topic| {
+            resolved_name(
+                context,
+                "topic",
+                topic.as_str(),
+                config.topic_name_env(topic.as_str(), env),
+                config.is_fifo(),
+            )
+        })
+        .collect();
+
+    let subscriptions: Vec<(String, String)> =
+        match list_queue_subscriptions(context, queue_arn).await {
+            Ok(value) => value,
+            Err(reason) => {
+                report::record_queue_error(
+                    &context.run,
+                    queue,
+                    format!("could not list subscriptions to prune: {}", reason),
+                );
+                return false;
+            }
+        };
+
+    let scheme: naming::EnvNaming = context.settings.env_naming();
+    let mut pruned_all: bool = true;
+
+    for (subscription_arn, topic_arn) in subscriptions {
+        let topic: &str = topic_arn.rsplit(':').next().unwrap_or_default();
+
+        // Only topics carrying the queue's environment are eligible,
+        // so that foreign subscriptions are never touched
+        if configured.contains(topic)
+            || configured.contains(&topic_arn)
+            || !naming::has_env_name(topic, env.as_suffix(), &scheme)
+        {
+            continue;
+        }
+
+        let subscription_name: String = format!("{} -> {}", topic, queue_arn);
+
+        if context.settings.check {
+            warning!(
+                "Subscription \"{}\" is to a topic that's no longer configured for the queue",
+                &subscription_name
+            );
+            report::record(
+                &context.run,
+                "subscription",
+                &subscription_name,
+                ResourceStatus::Drifted,
+                Some("topic is no longer configured for the queue".to_string()),
+            );
+            continue;
+        }
+
+        match retry::with_retries(&context.run, "Unsubscribe", &subscription_name, || {
+            context
+                .sns
+                .unsubscribe()
+                .subscription_arn(&subscription_arn)
+                .send()
+        })
+        .await
+        {
+            Ok(Ok(_)) => {
+                info!(
+                    subscription_arn = subscription_arn.as_str(),
+                    "Pruned subscription \"{}\" (its topic is no longer configured for the queue)",
+                    &subscription_name
+                );
+                events::operation_succeeded(
+                    &context.run,
+                    "Unsubscribe",
+                    &subscription_name,
+                    None,
+                    Some(&subscription_arn),
+                );
+                report::record(
+                    &context.run,
+                    "subscription",
+                    &subscription_name,
+                    ResourceStatus::Pruned,
+                    None,
+                );
+            }
+            Ok(Err(error)) => {
+                error_detail!(
+                    "Could not prune subscription \"{}\": {}",
+                    &subscription_name,
+                    logging::error_chain(&error)
+                );
+                print_error_hint(context, &error);
+                events::operation_failed(
+                    &context.run,
+                    "Unsubscribe",
+                    &subscription_name,
+                    error_message(&error),
+                );
+                report::record_failure(
+                    &context.run,
+                    "subscription",
+                    &subscription_name,
+                    format!("could not prune: {}", error_message(&error)),
+                );
+                report::record_queue_error(
+                    &context.run,
+                    queue,
+                    format!(
+                        "could not prune subscription to topic \"{}\": {}",
+                        topic,
+                        error_message(&error)
+                    ),
+                );
+                pruned_all = false;
+            }
+            Err(_) => pruned_all = false,
+        }
+    }
+
+    pruned_all
+}
+
+/// The per-topic results of applying a single queue's configuration
+#[derive(Debug, Default)]
+struct QueueOutcome {
+    /// The topics that were ensured (and subscribed to, if applicable)
+    succeeded: Vec<String>,
+    /// The topics that could not be ensured or subscribed to
+    failed: Vec<String>,
+    /// Whether any subscription to a topic no longer configured
+    /// for the queue could not be pruned
+    prune_failed: bool,
+}
+
+/// Apply the supplied queue's configuration, failing outright if
+/// the queue itself can't be ensured, and otherwise reporting the
+/// outcome of each of its topics
+async fn apply_queue_configuration<T: AsRef<str>>(
+    context: Arc<AppContext>,
+    queue: T,
+    config: SQSQueueConfig,
+) -> Result<QueueOutcome, ()> {
+    // Create a convenient place to accumulate
+    // the task handles we're about to create
+    let mut tasks: Vec<(String, JoinHandle<u8>)> = Vec::new();
+
+    // Resolve the environment the queue (and its topics) should
+    // be suffixed with, honoring any per-queue override
+    let env: EnvName = config.env_name(context.env);
+
+    if config.env.is_some() {
+        info!(
+            "Using environment \"{}\" for queue \"{}\" per its configured override...",
+            env.as_suffix(),
+            queue.as_ref(),
+        );
+    }
+
+    if context.settings.outputs_include_provenance {
+        report::record_queue_provenance(
+            &context.run,
+            queue.as_ref(),
+            config
+                .topics
+                .iter()
+                .map(|topic| {
+                    (
+                        topic.to_string(),
+                        config
+                            .topic_source(topic.as_str())
+                            .unwrap_or("unknown")
+                            .to_string(),
+                    )
+                })
+                .collect(),
+        );
+    }
+
+    // The configured name and ARN of the queue whose stale
+    // subscriptions should be pruned once its own are ensured
+    let mut prune_target: Option<(String, SQSQueueARN)> = None;
+
+    if queue.as_ref() == "unsubscribed" {
+        // If the supplied queue is actually the sentinel value
+        // "unsubscribed", just create the configured topics but
+        // don't attempt to subscribe them to anything
+        config.topics.iter().for_each(|topic| {
+            let (task_context, task_topic, task_config) =
+                (context.clone(), topic.to_string(), config.clone());
+            tasks.push((
+                format!("topic \"{}\"", topic),
+                tokio::spawn(logging::with_queue_context(
+                    "unsubscribed",
+                    logging::with_topic_context(task_topic.clone(), async move {
+                        let resolved_topic: String = resolved_name(
+                            &task_context,
+                            "topic",
+                            &task_topic,
+                            task_config.topic_name_env(&task_topic, env),
+                            task_config.is_fifo(),
+                        );
+                        let result =
+                            create_topic(task_context.clone(), &task_topic, env, &task_config)
+                                .await;
+
+                        if let Err(error) = &result {
+                            report::record_queue_error(
+                                &task_context.run,
+                                "unsubscribed",
+                                format!(
+                                    "topic \"{}\": {}",
+                                    &task_topic,
+                                    logging::error_chain(error)
+                                ),
+                            );
+                        }
+
+                        report::record_topic(
+                            &task_context.run,
+                            "unsubscribed",
+                            report::TopicResult {
+                                name: task_topic,
+                                resolved_name: resolved_topic,
+                                topic_arn: result
+                                    .as_ref()
+                                    .ok()
+                                    .filter(|_| !task_context.settings.dry_run)
+                                    .cloned(),
+                                subscription_arn: None,
+                            },
+                        );
+
+                        match result {
+                            Ok(_) => 0,
+                            Err(_) => 1,
+                        }
+                    }),
+                )),
+            ));
+        });
+    } else {
+        let (queue_name, queue_context) = (
+            queue.as_ref().to_string(),
+            format!("queue \"{}\"", queue.as_ref()),
+        );
+
+        let resolved_queue: String = resolved_name(
+            &context,
+            "queue",
+            &queue_name,
+            config.queue_name_env(env),
+            config.is_fifo(),
+        );
+
+        // Get the specified queue's URL and ARN
+        let (_queue_url, queue_arn) = match stats::timed(
+            &context.run,
+            "queue",
+            &queue_name,
+            create_queue(context.clone(), queue, env, &config),
+        )
+        .await
+        {
+            Ok((url, arn)) => {
+                match context.settings.dry_run {
+                    true => {
+                        report::record_queue(&context.run, &queue_name, resolved_queue, None, None)
+                    }
+                    false => report::record_queue(
+                        &context.run,
+                        &queue_name,
+                        resolved_queue,
+                        Some(url.clone()),
+                        Some(arn.clone()),
+                    ),
+                }
+                (url, arn)
+            }
+            Err(error) => {
+                report::record_queue(
+                    &context.run,
+                    &queue_name,
+                    resolved_queue.clone(),
+                    None,
+                    None,
+                );
+                report::record_queue_error(&context.run, &queue_name, logging::error_chain(&error));
+
+                // Still check a missing queue's topics, so that a
+                // `--check` run reports everything that's missing
+                if let (true, PinnError::MissingResource { .. }) = (context.settings.check, &error)
+                {
+                    let queue_arn: String = match (context.aws_region(), context.aws_account_id()) {
+                        (Some(region), Some(account_id)) => {
+                            format!(
+                                "arn:{}:sqs:{}:{}:{}",
+                                regions::partition(region).name,
+                                region,
+                                account_id,
+                                &resolved_queue
+                            )
+                        }
+                        _ => resolved_queue,
+                    };
+
+                    for topic in config.topics.iter() {
+                        let _ = create_topic(context.clone(), topic.as_str(), env, &config).await;
+
+                        report::record(
+                            &context.run,
+                            "subscription",
+                            format!("{} -> {}", topic, &queue_arn),
+                            ResourceStatus::Missing,
+                            Some("queue does not exist".to_string()),
+                        );
+                    }
+                }
+
+                return Err(());
+            }
+        };
+
+        if context.settings.prune_subscriptions {
+            prune_target = Some((queue_name.clone(), queue_arn.clone()));
+        }
+
+        // Create the queue's required subscriptions
+        config.topics.iter().for_each(|topic| {
+            let (task_context, task_queue, task_topic, task_arn, task_config) = (
+                context.clone(),
+                queue_name.clone(),
+                topic.clone(),
+                queue_arn.clone(),
+                config.clone(),
+            );
+            tasks.push((
+                format!("{} / topic \"{}\"", &queue_context, topic),
+                tokio::spawn(logging::with_queue_context(
+                    queue_name.clone(),
+                    logging::with_topic_context(task_topic.to_string(), async move {
+                        let subscription_name = format!("{} -> {}", &task_topic, &task_arn);
+                        let resolved_topic: String = resolved_name(
+                            &task_context,
+                            "topic",
+                            task_topic.as_str(),
+                            task_config.topic_name_env(task_topic.as_str(), env),
+                            task_config.is_fifo(),
+                        );
+                        let mut topic_result = report::TopicResult {
+                            name: task_topic.to_string(),
+                            resolved_name: resolved_topic.clone(),
+                            topic_arn: None,
+                            subscription_arn: None,
+                        };
+
+                        let result = stats::timed(
+                            &task_context.run,
+                            "subscription",
+                            &subscription_name,
+                            create_subscription(
+                                task_context.clone(),
+                                task_arn.clone(),
+                                task_topic.clone(),
+                                env,
+                                &task_config,
+                            ),
+                        )
+                        .await;
+
+                        if !task_context.settings.dry_run {
+                            topic_result.topic_arn = task_context
+                                .topic_cell(&resolved_topic)
+                                .get()
+                                .and_then(|arn| arn.as_ref().ok().cloned());
+                        }
+
+                        let exit_code: u8 = match result {
+                            Ok((status, subscription_arn)) => {
+                                report::record(
+                                    &task_context.run,
+                                    "subscription",
+                                    &subscription_name,
+                                    status,
+                                    None,
+                                );
+                                topic_result.subscription_arn = subscription_arn;
+                                0
+                            }
+                            Err((status, reason)) => {
+                                report::record_queue_error(
+                                    &task_context.run,
+                                    &task_queue,
+                                    format!("topic \"{}\": {}", &task_topic, &reason),
+                                );
+                                report::record(
+                                    &task_context.run,
+                                    "subscription",
+                                    &subscription_name,
+                                    status,
+                                    Some(reason),
+                                );
+                                1
+                            }
+                        };
+
+                        report::record_topic(&task_context.run, &task_queue, topic_result);
+
+                        exit_code
+                    }),
+                )),
+            ));
+        })
+    }
+
+    let topics: Vec<String> = config.topics.iter().map(TopicConfig::to_string).collect();
+
+    // Await all of the created handles in parallel
+    let results: Vec<u8> = join_tasks(&context.run, tasks).await;
+
+    let mut outcome: QueueOutcome = topics.into_iter().zip(results).fold(
+        QueueOutcome::default(),
+        |mut outcome, (topic, result)| {
+            match result {
+                0 => outcome.succeeded.push(topic),
+                _ => outcome.failed.push(topic),
+            }
+            outcome
+        },
+    );
+
+    if let Some((queue_name, queue_arn)) = prune_target {
+        outcome.prune_failed =
+            !prune_subscriptions(&context, &queue_name, &queue_arn, env, &config).await;
+    }
+
+    Ok(outcome)
+}
+
+/// Apply each of the supplied queue configurations concurrently,
+/// returning each queue's exit code once all of them have finished
+pub(crate) async fn apply_queues(
+    context: &Arc<AppContext>,
+    queues: Vec<(String, SQSQueueConfig)>,
+) -> Vec<u8> {
+    let tasks: Vec<(String, JoinHandle<u8>)> = queues
+        .into_iter()
+        .map(|(task_queue, task_config)| {
+            events::emit(
+                &context.run,
+                PinnEvent::ResourcePlanned {
+                    kind: "queue",
+                    name: task_queue.clone(),
+                    env: task_config.env_name(context.env).as_suffix().to_string(),
+                },
+            );
+            let task_context = context.clone();
+            (
+                format!("queue \"{}\"", &task_queue),
+                tokio::spawn(logging::with_queue_context(
+                    task_queue.clone(),
+                    async move {
+                        let exit_code: u8 = match apply_queue_configuration(
+                            task_context.clone(),
+                            &task_queue,
+                            task_config,
+                        )
+                        .await
+                        {
+                            Ok(outcome) if outcome.failed.is_empty() && !outcome.prune_failed => {
+                                task_context.record_completed(&task_queue);
+                                0
+                            }
+                            Ok(outcome) if outcome.failed.is_empty() => {
+                                error!(
+                                    "Not every stale subscription of queue \"{}\" could be pruned",
+                                    &task_queue,
+                                );
+                                1
+                            }
+                            Ok(outcome) => {
+                                error!(
+                                    "{} of {} topic(s) failed for queue \"{}\": {:?}",
+                                    outcome.failed.len(),
+                                    outcome.failed.len() + outcome.succeeded.len(),
+                                    &task_queue,
+                                    &outcome.failed,
+                                );
+                                1
+                            }
+                            Err(()) => 1,
+                        };
+
+                        control::record_queue_finished(&task_context.run, exit_code == 0);
+
+                        exit_code
+                    },
+                )),
+            )
+        })
+        .collect();
+
+    join_tasks(&context.run, tasks).await
+}
+
+// </editor-fold desc="// SNS->SQS Subscription Utilities ...">
+
+// <editor-fold desc="// Main ...">
+
+/// The exit code of a run whose queues finished with the supplied
+/// per-queue results: each queue contributes at most a single failure,
+/// and the exit code only distinguishes success from failure (the
+/// number of failed queues is reported in the summary), so that any
+/// number of failures can never wrap around to a "successful" exit code
+fn run_exit_code(
+    results: &[u8],
+    drift_found: bool,
+    bootstrap_required: bool,
+    force_success: bool,
+) -> u8 {
+    let failed: bool = results.iter().any(|result| *result > 0);
+
+    let exit_code: u8 = match (failed, drift_found) {
+        (_, true) => 4,
+        (false, false) => 0,
+        (true, false) => 1,
+    };
+
+    match force_success {
+        true => 0,
+        false => exit_code.max(bootstrap_required as u8),
+    }
+}
+
+/// The final tallies of the run, given the exit code of each queue
+/// applied during it, how many queues were skipped (as completed by a
+/// previous run, or paused), and the run's own exit code
+fn run_summary(
+    run: &RunState,
+    results: &[u8],
+    completed_previously: usize,
+    paused: usize,
+    exit_code: u8,
+) -> events::RunSummary {
+    let failed: usize = results.iter().filter(|result| **result > 0).count();
+
+    events::RunSummary {
+        queues: results.len() + completed_previously + paused,
+        succeeded: results.len() - failed,
+        failed,
+        completed_previously,
+        paused,
+        api_calls: stats::api_call_counts(run),
+        kube_calls: stats::kube_call_counts(run),
+        failures: logging::grouped_failures(run),
+        slowest: stats::slowest_resources(run),
+        durations: stats::duration_histogram(run),
+        phases: stats::phase_durations(run),
+        resources: report::resource_reports(run),
+        queues_applied: report::queue_results(run),
+        cancelled: control::is_cancelled(run),
+        env_resolution: report::env_resolution(run),
+        desired_state: plan::desired_state(run),
+        exit_code,
+    }
+}
+
+/// Open the checkpoint file at the supplied path for the supplied
+/// configuration, tied to the fingerprint of it and all of the run's
+/// settings (so changing either, i.e. a `--tag`, starts it afresh)
+fn open_checkpoint(
+    run_settings: &serde_json::Value,
+    pinn_config: &PinnConfig,
+    path: &std::path::Path,
+) -> Result<checkpoint::Checkpoint, Terminator> {
+    checkpoint::fingerprint(pinn_config, run_settings)
+        .and_then(|fingerprint| checkpoint::Checkpoint::open(path, fingerprint))
+}
+
+/// Apply (or purge, or smoke test) the configuration as the
+/// command line directs, returning the run's exit code
+pub async fn run() -> ExitCode {
+    // Parse and store any cli arguments that were supplied
+    let mut args: CLIArgs = <CLIArgs as clap::Parser>::parse();
+
+    // A plan file is only ever written instead of applying the plan
+    args.dry_run |= args.plan_file.is_some();
+
+    // How the configuration will be applied, independent of where
+    // it (and the AWS clients applying it) will come from
+    let mut settings: Settings = Settings::from(&args);
+
+    // A JSON document on stdout speaks for itself, so only
+    // warnings and errors are logged (to stderr) unless asked
+    logging::initialize(
+        logging::Verbosity::from_flags(
+            args.quiet || (args.output == "json" && !args.verbose && !args.explain),
+            args.verbose,
+        ),
+        args.log_level.as_deref(),
+        logging::LogFormat::from(args.log_format.as_str()),
+        args.output == "json",
+    );
+
+    let events: EventStream = match &args.events_stream {
+        None => EventStream::default(),
+        Some(events_path) => match EventStream::open(events_path) {
+            Ok(stream) => stream,
+            Err(error) => {
+                error!(
+                    "Could not open event stream \"{}\": {}",
+                    events_path.display(),
+                    events::error_chain(&error).join(": ")
+                );
+                return startup_failure(
+                    &RunState::default(),
+                    &args,
+                    StartupFailure::Configuration,
+                    events::error_chain(&error),
+                    2,
+                );
+            }
+        },
+    };
+
+    // Everything the run records from here on out
+    let run: Arc<RunState> = Arc::new(RunState::new(&settings, events));
+
+    // Held until the run exits, at which point the socket is removed
+    let _control_socket: Option<control::ControlSocket> = match &args.control_socket {
+        None => None,
+        Some(socket_path) => match control::serve(socket_path, run.clone()) {
+            Ok(socket) => Some(socket),
+            Err(error) => {
+                error!(
+                    "Could not create control socket \"{}\": {}",
+                    socket_path.display(),
+                    error
+                );
+                return startup_failure(
+                    &run,
+                    &args,
+                    StartupFailure::Configuration,
+                    logging::error_causes(&error),
+                    2,
+                );
+            }
+        },
+    };
+
+    control::handle_sigterm(run.clone());
+
+    // Get the SNS/SQS topic & queue configuration from the
+    // cluster (if it exists in the current namespace)
+    let (env_name, mut pinn_config) = match args.pinn_config(&run).await {
+        Ok(Some((name, config))) => (name, config),
+        Ok(None) => return ExitCode::SUCCESS,
+        Err(error) => {
+            error!(
+                "Could not parse or acquire usable pinnothera configuration: {}",
+                logging::error_chain(&error)
+            );
+            return startup_failure(
+                &run,
+                &args,
+                StartupFailure::Configuration,
+                logging::error_causes(&error),
+                error.exit_code(),
+            );
+        }
+    };
+
+    // The region the AWS clients will use, which `--aws-profile`
+    // (or a custom endpoint) may supply in place of `--aws-region`
+    let aws_region: Option<String> = args.effective_aws_region(env_name).await;
+
+    // Queues whose merged tags would exceed AWS's limit are invalid too,
+    // unless their inherited tags can be dropped (`--tag-overflow`)
+    if let Err(error) = pinn_config
+        .validate(
+            settings.sanitize_replacement(),
+            settings.name_hook.is_some(),
+            settings.strict_filters,
+            aws_region.as_deref(),
+        )
+        .and_then(|_| resolve_tag_overflows(&settings, &mut pinn_config))
+    {
+        let problems: Vec<String> = match &error {
+            PinnError::InvalidConfig { problems } => problems.clone(),
+            _ => logging::error_causes(&error),
+        };
+
+        problems
+            .iter()
+            .for_each(|problem| error!("Invalid configuration: {}", problem));
+        error!("Could not validate the acquired pinnothera configuration");
+
+        return startup_failure(
+            &run,
+            &args,
+            StartupFailure::Validation,
+            problems,
+            error.exit_code(),
+        );
+    }
+
+    if !naming::is_aws_safe_char(settings.env_delimiter) {
+        warning!(
+            "AWS does not allow '{}' in topic or queue names, joining the environment with '{}' instead (i.e. \"{}\")",
+            settings.env_delimiter,
+            settings.env_naming().delimiter,
+            naming::apply_env_name("orders", Some("dev"), &settings.env_naming(), false),
+        );
+    }
+
+    info!(
+        queues = pinn_config.queue_names().count(),
+        topics = pinn_config.topic_names().count(),
+        "Applying queue configuration: {}",
+        pinn_config.summary()
+    );
+    debug!("Queue configuration: {}", &pinn_config);
+
+    settings.policy_template = match args.policy_template() {
+        Ok(template) => template,
+        Err(error) => {
+            error!(
+                "Could not load the access policy template: {}",
+                events::error_chain(&error).join(": ")
+            );
+            return startup_failure(
+                &run,
+                &args,
+                StartupFailure::Configuration,
+                events::error_chain(&error),
+                2,
+            );
+        }
+    };
+
+    // Get usable AWS clients for the local environment
+    let (sns_client, sqs_client, sts_client) = match args.aws_clients(env_name).await {
+        Ok((sns, sqs, sts)) => (sns, sqs, sts),
+        Err(error) => {
+            error!(
+                "Could not create usable AWS configuration: {}",
+                events::error_chain(&error).join(": ")
+            );
+            return startup_failure(
+                &run,
+                &args,
+                StartupFailure::AwsConfiguration,
+                events::error_chain(&error),
+                3,
+            );
+        }
+    };
+
+    let account_id: Option<String> = match resolve_aws_account_id(
+        &run,
+        &args,
+        env_name,
+        aws_region.as_deref(),
+        sts_client,
+    )
+    .await
+    {
+        Ok(account_id) => account_id,
+        Err(error) => {
+            error!(
+                "Could not determine the current AWS identity: {}",
+                logging::error_chain(&error)
+            );
+            print_endpoint_error_hint(&run, args.aws_endpoint_url(env_name), &error);
+            return startup_failure(
+                &run,
+                &args,
+                StartupFailure::AwsIdentity,
+                vec![error_message(&error)],
+                3,
+            );
+        }
+    };
+
+    let clients = AwsClients {
+        sns: sns_client,
+        sqs: sqs_client,
+        region: aws_region,
+        account_id,
+        endpoint_url: args.aws_endpoint_url(env_name).map(str::to_string),
+        topic_clients: Some(args.topic_client_factory(env_name)),
+    };
+
+    // The resolved settings of the run, with
+    // any secrets (i.e. credentials) omitted entirely
+    let mut run_settings: serde_json::Value = args.run_settings(
+        env_name,
+        clients.region.as_deref(),
+        clients.account_id.as_deref(),
+    );
+
+    // Bundle the run's environment, clients, and settings
+    // into the context shared by every task spawned below
+    let mut context = AppContext::new(env_name, &clients, settings, run);
+
+    events::emit(
+        &context.run,
+        PinnEvent::RunStarted {
+            settings: run_settings.clone(),
+        },
+    );
+
+    if let Err(error) = resolve_hooked_names(&mut context, &pinn_config) {
+        error!("Could not resolve queue and topic names with the name hook");
+        return startup_failure(
+            &context.run,
+            &args,
+            StartupFailure::NameHook,
+            logging::error_causes(&error),
+            error.exit_code(),
+        );
+    }
+
+    if let Some(PinnCommand::Purge(purge_args)) = &args.command {
+        control::set_phase(&context.run, control::RunPhase::Applying);
+        let (purge_args, force_success) = (purge_args.clone(), args.force_success);
+        let run: Arc<RunState> = context.run.clone();
+        let exit_code = purge_queues(Arc::new(context), &pinn_config, &purge_args).await;
+
+        stats::log_api_call_counts(&run);
+
+        return ExitCode::from(match force_success {
+            true => 0,
+            false => exit_code.min(1),
+        });
+    }
+
+    if let Some(PinnCommand::SmokeTest(smoke_args)) = &args.command {
+        control::set_phase(&context.run, control::RunPhase::Applying);
+        let (smoke_args, force_success) = (smoke_args.clone(), args.force_success);
+        let run: Arc<RunState> = context.run.clone();
+        let exit_code =
+            smoke_test_queues(Arc::new(context), &pinn_config, &smoke_args, &args.output).await;
+
+        stats::log_api_call_counts(&run);
+
+        return ExitCode::from(match force_success {
+            true => 0,
+            false => exit_code,
+        });
+    }
+
+    control::set_phase(&context.run, control::RunPhase::Preflight);
+
+    if let Err(error) = check_env_suffix(&context, &args, &pinn_config).await {
+        return startup_failure(
+            &context.run,
+            &args,
+            StartupFailure::Preflight,
+            events::error_chain(&error),
+            2,
+        );
+    }
+
+    let bootstrap_required: bool = match check_first_run(&context, &args, &pinn_config).await {
+        Ok(value) => value,
+        Err(error) => {
+            return startup_failure(
+                &context.run,
+                &args,
+                StartupFailure::Preflight,
+                events::error_chain(&error),
+                2,
+            )
+        }
+    };
+
+    if bootstrap_required {
+        context.limit_to_dry_run();
+        run_settings["dry_run"] = serde_json::Value::Bool(true);
+    }
+
+    if let (Some(checkpoint_path), false) = (
+        &args.checkpoint_file,
+        context.settings.dry_run || context.settings.check,
+    ) {
+        match open_checkpoint(&run_settings, &pinn_config, checkpoint_path) {
+            Ok(value) => context.checkpoint = Some(value),
+            Err(error) => {
+                error!(
+                    "Could not open checkpoint file \"{}\": {}",
+                    checkpoint_path.display(),
+                    events::error_chain(&error).join(": ")
+                );
+                return startup_failure(
+                    &context.run,
+                    &args,
+                    StartupFailure::Checkpoint,
+                    events::error_chain(&error),
+                    2,
+                );
+            }
+        }
+    }
+
+    let context: Arc<AppContext> = Arc::new(context);
+
+    context
+        .settings
+        .pause_queues
+        .iter()
+        .filter(|queue| !pinn_config.contains_key(*queue))
+        .for_each(|queue| {
+            warning!(
+                "Cannot pause queue \"{}\", it is not in the configuration",
+                queue
+            )
+        });
+
+    // Spawn async tasks to apply the parsed queue & topic configurations
+    // (skipping any that are paused, or that a previous checkpointed run
+    // already completed)
+    let (paused, unpaused): (Vec<_>, Vec<_>) = pinn_config
+        .iter()
+        .map(|(queue, queue_config)| {
+            (
+                queue.to_string(),
+                pinn_config.with_global_settings(queue_config),
+            )
+        })
+        .partition(|(queue, queue_config)| context.settings.is_paused(queue, queue_config));
+
+    paused.iter().for_each(|(queue, _)| {
+        info!("Queue \"{}\" is paused, skipping it", queue);
+        events::emit(
+            &context.run,
+            PinnEvent::ResourcePaused {
+                kind: "queue",
+                name: queue.to_string(),
+            },
+        );
+    });
+
+    let (previously_completed, pending): (Vec<_>, Vec<_>) = unpaused
+        .into_iter()
+        .partition(|(queue, _)| context.previously_completed(queue));
+
+    previously_completed.iter().for_each(|(queue, _)| {
+        info!(
+            "Queue \"{}\" completed previously (checkpoint), skipping it",
+            queue
+        )
+    });
+
+    control::set_queue_counts(
+        &context.run,
+        pending.len(),
+        previously_completed.len() + paused.len(),
+    );
+    control::set_phase(&context.run, control::RunPhase::Applying);
+
+    if !previously_completed.is_empty() {
+        info!(
+            "{} queue configuration(s) completed previously (checkpoint), {} to be applied during this run",
+            previously_completed.len(),
+            pending.len(),
+        );
+    }
+
+    // Apply the remaining queues, waiting for all of them to finish
+    let results: Vec<u8> = apply_queues(&context, pending).await;
+
+    control::set_phase(&context.run, control::RunPhase::Reporting);
+
+    let json_output: bool = args.output == "json";
+
+    stats::log_api_call_counts(&context.run);
+
+    let plan_failed: bool = match &args.plan_file {
+        None => false,
+        Some(plan_path) => match plan::write_plan_file(&context.run, plan_path) {
+            Ok(()) => {
+                info!(
+                    "Wrote the planned desired state to \"{}\"",
+                    plan_path.display()
+                );
+                false
+            }
+            Err(error) => {
+                error!(
+                    "Could not write plan file \"{}\": {}",
+                    plan_path.display(),
+                    error
+                );
+                true
+            }
+        },
+    };
+
+    let failed = results.iter().filter(|result| **result > 0).count();
+    let exit_code: u8 = run_exit_code(
+        &results,
+        context.settings.check && report::drift_found(&context.run),
+        bootstrap_required,
+        args.force_success,
+    )
+    .max((plan_failed && !args.force_success) as u8);
+
+    let summary = run_summary(
+        &context.run,
+        &results,
+        previously_completed.len(),
+        paused.len(),
+        exit_code,
+    );
+
+    if json_output {
+        // Keep stdout to the document alone, but still
+        // surface every failure (on stderr) as it would be
+        summary
+            .resources
+            .iter()
+            .filter(|report| report.status == ResourceStatus::Failed)
+            .for_each(|report| {
+                error!(
+                    "Could not ensure {} \"{}\": {}",
+                    report.kind,
+                    report.name,
+                    report.detail.as_deref().unwrap_or("unknown error")
+                )
+            });
+
+        match serde_json::to_string_pretty(&summary) {
+            Ok(document) => println!("{}", document),
+            Err(error) => error!("Could not serialize the run summary: {}", error),
+        }
+    } else {
+        logging::print_summary(&summary);
+        report::print_resource_reports(&summary.resources);
+
+        if context.settings.dry_run {
+            stats::print_planned_operations(&context.run);
+        }
+    }
+
+    events::emit(
+        &context.run,
+        PinnEvent::RunFinished {
+            summary: Box::new(summary),
+        },
+    );
+
+    if control::is_cancelled(&context.run) && failed > 0 {
+        error!("The run was cancelled before every queue could be applied: re-run pinnothera to apply the rest");
+    }
+
+    if context.settings.check && report::drift_found(&context.run) {
+        error!("AWS does not match the configuration: see the missing and drifted resources above");
+    }
+
+    if bootstrap_required {
+        error!(
+            "Nothing was created: review the plan above, then re-run with `--bootstrap` to create these resources for the first time"
+        );
+    }
+
+    if failed > 0 || bootstrap_required {
+        error_detail!(
+            "The above errors were encountered after running with settings: {}",
+            run_settings
+        );
+    }
+
+    ExitCode::from(exit_code)
+}
+
+// </editor-fold desc="// Main ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, FakeAws, FakeRequest, FakeResponse};
+
+    /// The account id the fake STS reports for the current AWS identity
+    const STS_ACCOUNT_ID: &str = "210987654321";
+
+    fn caller_identity(request: &FakeRequest) -> FakeResponse {
+        match request.action() {
+            "GetCallerIdentity" => FakeResponse::result(
+                "GetCallerIdentity",
+                format!(
+                    "<Arn>arn:aws:iam::{0}:user/ci</Arn><UserId>AIDAFAKE</UserId><Account>{0}</Account>",
+                    STS_ACCOUNT_ID
+                ),
+            ),
+            action => FakeResponse::error("InvalidAction", action),
+        }
+    }
+
+    /// Resolve the account id for a run with the supplied flags against
+    /// the supplied backend, with `AWS_ACCOUNT_ID` set to the supplied value
+    fn resolve_account_id(
+        runtime: &tokio::runtime::Runtime,
+        fake: &FakeAws,
+        flags: &[&str],
+        env_account_id: Option<&str>,
+    ) -> Result<Option<String>, String> {
+        let args: CLIArgs = fake.args(flags);
+        let (_, _, sts) = runtime
+            .block_on(fake.args(&[]).aws_clients(EnvName::Dev))
+            .unwrap();
+        let run = RunState::new(&Settings::from(&args), EventStream::default());
+
+        testing::with_env(&[(ACCOUNT_ID_ENV_VAR, env_account_id)], || {
+            runtime
+                .block_on(resolve_aws_account_id(
+                    &run,
+                    &args,
+                    EnvName::Dev,
+                    Some("us-east-1"),
+                    sts,
+                ))
+                .map_err(|error| logging::error_chain(&error))
+        })
+    }
+
+    #[test]
+    fn env_aws_account_id_cases() {
+        let cases: [(Option<&str>, Option<&str>); 6] = [
+            (None, None),
+            (Some(""), None),
+            (Some("   "), None),
+            (Some("123456789012"), Some("123456789012")),
+            (Some(" 123456789012\n"), Some("123456789012")),
+            (Some("12345678901a"), None),
+        ];
+
+        for (value, expected) in cases {
+            let account_id: Option<String> =
+                testing::with_env(&[(ACCOUNT_ID_ENV_VAR, value)], env_aws_account_id);
+
+            assert_eq!(account_id.as_deref(), expected, "{:?}", value);
+        }
+
+        assert_eq!(
+            testing::with_env(&[(ACCOUNT_ID_ENV_VAR, Some("1234"))], env_aws_account_id),
+            None
+        );
+    }
+
+    #[test]
+    fn account_id_precedence() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let fake: FakeAws = runtime.block_on(FakeAws::start(caller_identity));
+
+        let role: &str = "arn:aws:iam::111111111111:role/deploy";
+        // (flags, AWS_ACCOUNT_ID, expected account id)
+        let cases: [(&[&str], Option<&str>, &str); 4] = [
+            (
+                &["--aws-account-id", "999999999999", "--aws-role-arn", role],
+                Some("333333333333"),
+                "999999999999",
+            ),
+            (
+                &["--aws-role-arn", role],
+                Some("333333333333"),
+                "111111111111",
+            ),
+            (&[], Some("333333333333"), "333333333333"),
+            (&[], Some("not-an-account"), STS_ACCOUNT_ID),
+        ];
+
+        for (flags, env_account_id, expected) in cases {
+            let account_id = resolve_account_id(&runtime, &fake, flags, env_account_id).unwrap();
+
+            assert_eq!(account_id.as_deref(), Some(expected), "{:?}", flags);
+        }
+
+        // Only the last case fell through to STS
+        assert_eq!(fake.calls("GetCallerIdentity").len(), 1);
+    }
+
+    #[test]
+    fn account_id_is_not_resolved_via_sts_when_disabled() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let fake: FakeAws = runtime.block_on(FakeAws::start(caller_identity));
+
+        for flags in [&["--no-sts"][..], &["--dry-run"][..]] {
+            assert_eq!(
+                resolve_account_id(&runtime, &fake, flags, None).unwrap(),
+                None
+            );
+        }
+
+        assert!(fake.calls("GetCallerIdentity").is_empty());
+    }
+
+    #[test]
+    fn sts_failures_other_than_credential_rejections_yield_none() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        for (code, rejected) in [
+            ("AccessDenied", false),
+            ("Throttling", false),
+            ("InvalidClientTokenId", true),
+            ("SignatureDoesNotMatch", true),
+        ] {
+            let fake: FakeAws = runtime.block_on(FakeAws::start(move |_| {
+                FakeResponse::error(code, "the request was refused")
+            }));
+
+            match (resolve_account_id(&runtime, &fake, &[], None), rejected) {
+                (Ok(None), false) | (Err(_), true) => (),
+                (result, _) => panic!("{}: unexpectedly {:?}", code, result),
+            }
+        }
+    }
+
+    /// The desired access policy the merge tests start from
+    fn desired_policy() -> serde_json::Value {
+        serde_json::json!({
+            "Version": "2012-10-17",
+            "Statement": [{
+                "Sid": SNS_SEND_SID,
+                "Effect": "Allow",
+                "Principal": {"Service": "sns.amazonaws.com"},
+                "Action": "sqs:SendMessage",
+                "Resource": "arn:aws:sqs:us-east-1:123456789012:orders-dev",
+                "Condition": {"ArnLike": {"aws:SourceArn": "arn:aws:sns:us-east-1:123456789012:*-dev"}},
+            }],
+        })
+    }
+
+    fn statement_sids(policy: &str) -> Vec<String> {
+        serde_json::from_str::<serde_json::Value>(policy).unwrap()["Statement"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|statement| statement["Sid"].as_str().unwrap_or("<none>").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn merge_queue_policy_into_an_empty_policy() {
+        let desired: String = desired_policy().to_string();
+
+        for existing in ["", "{}", r#"{"Version": "2012-10-17", "Statement": []}"#] {
+            assert_eq!(merge_queue_policy(&desired, existing), desired);
+        }
+    }
+
+    #[test]
+    fn merge_queue_policy_keeps_unrelated_statements() {
+        let desired: String = desired_policy().to_string();
+        let s3_statement = serde_json::json!({
+            "Sid": "s3-events",
+            "Effect": "Allow",
+            "Principal": {"Service": "s3.amazonaws.com"},
+            "Action": "sqs:SendMessage",
+            "Resource": "arn:aws:sqs:us-east-1:123456789012:orders-dev",
+        });
+        let existing = serde_json::json!({
+            "Version": "2012-10-17",
+            "Statement": [s3_statement.clone()],
+        });
+
+        let merged: String = merge_queue_policy(&desired, &existing.to_string());
+
+        assert_eq!(statement_sids(&merged), [SNS_SEND_SID, "s3-events"]);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&merged).unwrap()["Statement"][1],
+            s3_statement
+        );
+
+        // Merging again is a no-op
+        assert_eq!(merge_queue_policy(&desired, &merged), merged);
+    }
+
+    #[test]
+    fn merge_queue_policy_replaces_stale_statements() {
+        let desired: String = desired_policy().to_string();
+
+        // A managed statement with an outdated condition, and one written
+        // before pinnothera assigned `Sid`s (matched by its contents)
+        let mut stale = desired_policy()["Statement"][0].clone();
+        stale["Condition"]["ArnLike"]["aws:SourceArn"] =
+            "arn:aws:sns:us-east-1:123456789012:*".into();
+        let mut unnamed = desired_policy()["Statement"][0].clone();
+        unnamed.as_object_mut().unwrap().remove("Sid");
+        unnamed.as_object_mut().unwrap().remove("Condition");
+
+        for statements in [
+            vec![stale.clone()],
+            vec![unnamed.clone()],
+            vec![
+                stale,
+                unnamed,
+                serde_json::json!({"Sid": "other", "Effect": "Deny"}),
+            ],
+        ] {
+            let foreign: usize = statements.len().saturating_sub(2);
+            let existing = serde_json::json!({"Version": "2012-10-17", "Statement": statements});
+            let merged: String = merge_queue_policy(&desired, &existing.to_string());
+
+            match foreign {
+                0 => assert_eq!(merged, desired),
+                _ => assert_eq!(statement_sids(&merged), [SNS_SEND_SID, "other"]),
+            }
+        }
+    }
+
+    #[test]
+    fn merge_queue_policy_keeps_the_desired_policy_over_invalid_json() {
+        let desired: String = desired_policy().to_string();
+
+        assert_eq!(merge_queue_policy(&desired, "not json"), desired);
+    }
+
+    #[tokio::test]
+    async fn sns_source_arns_per_partition() {
+        let fake: FakeAws = FakeAws::start(caller_identity).await;
+        let config: SQSQueueConfig = PinnConfig::from_yaml("orders: {topics: [placed]}")
+            .unwrap()
+            .get("orders")
+            .cloned()
+            .unwrap();
+
+        for broad_policy in [false, true] {
+            let flags: &[&str] = match broad_policy {
+                true => &["--broad-policy"],
+                false => &[],
+            };
+            let context: AppContext = fake.context(fake.args(flags)).await;
+
+            // (region, partition)
+            for (region, partition) in [
+                ("us-east-1", "aws"),
+                ("cn-north-1", "aws-cn"),
+                ("us-gov-west-1", "aws-us-gov"),
+            ] {
+                let topic: &str = match broad_policy {
+                    true => "*-dev",
+                    false => "placed-dev",
+                };
+
+                assert_eq!(
+                    sns_source_arns(
+                        &context,
+                        region,
+                        testing::FAKE_ACCOUNT_ID,
+                        EnvName::Dev,
+                        &config
+                    ),
+                    Some(match broad_policy {
+                        true => format!(
+                            r#""arn:{}:sns:{}:{}:{}""#,
+                            partition,
+                            region,
+                            testing::FAKE_ACCOUNT_ID,
+                            topic
+                        ),
+                        false => format!(
+                            r#"["arn:{}:sns:{}:{}:{}"]"#,
+                            partition,
+                            region,
+                            testing::FAKE_ACCOUNT_ID,
+                            topic
+                        ),
+                    })
+                );
+            }
+        }
+
+        assert!(fake.requests().is_empty());
+    }
+
+    #[test]
+    fn exit_code_never_wraps_around() {
+        // (per-queue results, drift found, bootstrap required, force success, exit code)
+        let cases: [(Vec<u8>, bool, bool, bool, u8); 9] = [
+            (vec![], false, false, false, 0),
+            (vec![0; 300], false, false, false, 0),
+            (vec![1; 256], false, false, false, 1),
+            (vec![1; 300], false, false, false, 1),
+            (
+                [vec![0; 255], vec![255, 1]].concat(),
+                false,
+                false,
+                false,
+                1,
+            ),
+            (vec![1; 256], true, false, false, 4),
+            (vec![0; 3], false, true, false, 1),
+            (vec![1; 256], false, false, true, 0),
+            (vec![0; 3], true, true, true, 0),
+        ];
+
+        for (results, drift_found, bootstrap_required, force_success, expected) in cases {
+            assert_eq!(
+                run_exit_code(&results, drift_found, bootstrap_required, force_success),
+                expected,
+                "{} result(s), {} failed",
+                results.len(),
+                results.iter().filter(|result| **result > 0).count()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn checkpoints_are_discarded_when_settings_change() {
+        let path: std::path::PathBuf = testing::scratch_dir("checkpoint-settings").join("run");
+        let pinn_config: PinnConfig = PinnConfig::from_yaml("orders: {topics: [placed]}").unwrap();
+        let fake: FakeAws = FakeAws::start(testing::aws_response).await;
+
+        let open = |flags: &[&str], config: &PinnConfig| {
+            let run_settings: serde_json::Value = fake.args(flags).run_settings(
+                EnvName::Dev,
+                Some("us-east-1"),
+                Some(testing::FAKE_ACCOUNT_ID),
+            );
+            open_checkpoint(&run_settings, config, &path).map_err(|error| format!("{:?}", error))
+        };
+
+        open(&["--tag", "team=orders"], &pinn_config)
+            .unwrap()
+            .record_completed("orders-dev");
+
+        // The same configuration and settings resume where the last run left off
+        assert!(open(&["--tag", "team=orders"], &pinn_config)
+            .unwrap()
+            .is_completed("orders-dev"));
+
+        // Changing a tag (or the configuration) starts the checkpoint afresh
+        assert!(!open(&["--tag", "team=payments"], &pinn_config)
+            .unwrap()
+            .is_completed("orders-dev"));
+
+        open(&["--tag", "team=payments"], &pinn_config)
+            .unwrap()
+            .record_completed("orders-dev");
+        let retagged: PinnConfig =
+            PinnConfig::from_yaml("orders: {tags: {team: orders}, topics: [placed]}").unwrap();
+        assert!(!open(&["--tag", "team=payments"], &retagged)
+            .unwrap()
+            .is_completed("orders-dev"));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    /// Apply every queue of the supplied configuration concurrently
+    /// (as a run would), returning each queue's outcome by name
+    async fn apply_config(
+        context: &Arc<AppContext>,
+        pinn_config: &PinnConfig,
+    ) -> BTreeMap<String, Result<QueueOutcome, ()>> {
+        let tasks: Vec<JoinHandle<(String, Result<QueueOutcome, ()>)>> = pinn_config
+            .iter()
+            .map(|(queue, config)| {
+                let (task_context, task_queue, task_config) = (
+                    context.clone(),
+                    queue.to_string(),
+                    pinn_config.with_global_settings(config),
+                );
+                tokio::spawn(async move {
+                    let outcome =
+                        apply_queue_configuration(task_context, &task_queue, task_config).await;
+                    (task_queue, outcome)
+                })
+            })
+            .collect();
+
+        futures_util::future::join_all(tasks)
+            .await
+            .into_iter()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn shared_topics_are_created_once() {
+        let fake: FakeAws = FakeAws::start(testing::aws_response).await;
+        let context: Arc<AppContext> = Arc::new(fake.context(fake.args(&[])).await);
+        let pinn_config = PinnConfig::from_yaml(
+            (1..=5)
+                .map(|idx| format!("queue-{0}: {{topics: [shared, own-{0}]}}\n", idx))
+                .collect::<String>(),
+        )
+        .unwrap();
+
+        let outcomes = apply_config(&context, &pinn_config).await;
+
+        assert_eq!(outcomes.len(), 5);
+        outcomes.iter().for_each(|(queue, outcome)| {
+            let outcome: &QueueOutcome = outcome.as_ref().unwrap();
+
+            assert!(outcome.failed.is_empty(), "{}: {:?}", queue, outcome.failed);
+            assert!(outcome.succeeded.contains(&"shared".to_string()));
+        });
+
+        let created: Vec<String> = fake
+            .calls("CreateTopic")
+            .iter()
+            .map(|request| request.param("Name").unwrap().to_string())
+            .collect();
+
+        assert_eq!(
+            created.iter().filter(|name| *name == "shared-dev").count(),
+            1
+        );
+        assert_eq!(created.len(), 6, "{:?}", created);
+
+        // Every queue still subscribes to the shared topic itself
+        let shared_subscriptions = fake
+            .calls("Subscribe")
+            .into_iter()
+            .filter(|request| {
+                request.param("TopicArn") == Some(&testing::fake_arn("sns", "shared-dev"))
+            })
+            .count();
+        assert_eq!(shared_subscriptions, 5);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn subscription_calls_are_limited_separately() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let pinn_config: PinnConfig = PinnConfig::from_yaml(
+            (0..4)
+                .map(|queue| format!("queue-{}: {{topics: [a, b, c, d]}}\n", queue))
+                .collect::<String>(),
+        )
+        .unwrap();
+
+        // Without a limit of their own, subscriptions share the whole pool
+        for (flags, limit) in [
+            (&["--concurrency", "8"][..], None),
+            (
+                &["--concurrency", "8", "--max-subscription-concurrency", "2"][..],
+                Some(2),
+            ),
+            (
+                &["--concurrency", "8", "--max-subscription-concurrency", "1"][..],
+                Some(1),
+            ),
+        ] {
+            let (in_flight, most) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+            let (handler_in_flight, handler_most) = (in_flight.clone(), most.clone());
+            let fake: FakeAws = FakeAws::start(move |request| {
+                if request.action() != "Subscribe" {
+                    return testing::aws_response(request);
+                }
+
+                let current: usize = handler_in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                handler_most.fetch_max(current, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(20));
+                handler_in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                testing::aws_response(request)
+            })
+            .await;
+            let context: Arc<AppContext> = Arc::new(fake.context(fake.args(flags)).await);
+
+            apply_config(&context, &pinn_config).await;
+
+            assert_eq!(fake.calls("Subscribe").len(), 16);
+            match limit {
+                Some(limit) => assert!(most.load(Ordering::SeqCst) <= limit, "{:?}", flags),
+                None => assert!(most.load(Ordering::SeqCst) > 2, "{:?}", flags),
+            }
+
+            let phases: Vec<&str> = run_summary(&context.run, &[], 0, 0, 0)
+                .phases
+                .iter()
+                .map(|phase| phase.phase)
+                .collect();
+
+            assert_eq!(
+                phases,
+                vec![stats::RESOURCE_PHASE, stats::SUBSCRIPTION_PHASE]
+            );
+        }
+    }
+
+    /// A handler failing the first `failures` `CreateQueue` calls
+    /// as though the queue had only just been deleted
+    fn deleted_recently(failures: usize) -> impl Fn(&FakeRequest) -> FakeResponse {
+        let attempts = std::sync::atomic::AtomicUsize::new(0);
+
+        move |request| {
+            match request.action() {
+            "CreateQueue"
+                if attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < failures =>
+            {
+                FakeResponse::error(
+                    "AWS.SimpleQueueService.QueueDeletedRecently",
+                    "You must wait 60 seconds after deleting a queue before you can create another with the same name.",
+                )
+            }
+            _ => testing::aws_response(request),
+        }
+        }
+    }
+
+    #[tokio::test]
+    async fn recently_deleted_queues_are_recreated_after_the_cooldown() {
+        let fake: FakeAws = FakeAws::start(deleted_recently(1)).await;
+        let context: AppContext = fake
+            .context(fake.args(&["--queue-recreate-wait-secs", "1"]))
+            .await;
+
+        let (queue_url, _) = ensure_queue(
+            &context,
+            "orders-dev".to_string(),
+            HashMap::new(),
+            false,
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(queue_url, testing::fake_queue_url("orders-dev"));
+        assert_eq!(fake.calls("CreateQueue").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn recently_deleted_queues_fail_once_the_wait_is_up() {
+        let fake: FakeAws = FakeAws::start(deleted_recently(usize::MAX)).await;
+        let context: AppContext = fake
+            .context(fake.args(&["--queue-recreate-wait-secs", "0"]))
+            .await;
+
+        let error = ensure_queue(
+            &context,
+            "orders-dev".to_string(),
+            HashMap::new(),
+            false,
+            &HashMap::new(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            matches!(&error, PinnError::QueueConflict { queue, reason }
+                if queue == "orders-dev" && reason.contains("deleted too recently")),
+            "{:?}",
+            error
+        );
+        assert_eq!(fake.calls("CreateQueue").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_stop_once_the_api_budget_is_exhausted() {
+        let fake: FakeAws =
+            FakeAws::start(|_| FakeResponse::error("Throttling", "Rate exceeded")).await;
+        let context: AppContext = fake
+            .context(fake.args(&[
+                "--max-api-calls",
+                "3",
+                "--max-retries",
+                "5",
+                "--retry-base-delay-ms",
+                "1",
+            ]))
+            .await;
+
+        let error = get_queue_url(&context, "orders-dev").await.unwrap_err();
+
+        assert!(
+            matches!(&error, PinnError::BudgetExhausted { operation: "GetQueueUrl", resource }
+                if resource == "orders-dev"),
+            "{:?}",
+            error
+        );
+        assert_eq!(fake.calls("GetQueueUrl").len(), 3);
+        assert_eq!(stats::api_call_counts(&context.run)["GetQueueUrl"], 3);
+    }
+
+    /// A backend where every queue already exists with a visibility
+    /// timeout of 30 seconds (so creating one with any other value fails)
+    fn existing_queues(request: &FakeRequest) -> FakeResponse {
+        match request.action() {
+            "CreateQueue" if request.param("Attribute.1.Value") != Some("30") => {
+                FakeResponse::error(
+                    "QueueAlreadyExists",
+                    "A queue already exists with the same name and a different value for attribute VisibilityTimeout",
+                )
+            }
+            "GetQueueAttributes" => FakeResponse::result(
+                "GetQueueAttributes",
+                format!(
+                    "<Attribute><Name>QueueArn</Name><Value>{}</Value></Attribute>\
+                     <Attribute><Name>VisibilityTimeout</Name><Value>30</Value></Attribute>",
+                    testing::fake_arn(
+                        "sqs",
+                        request
+                            .param("QueueUrl")
+                            .and_then(|url| url.rsplit('/').next())
+                            .unwrap_or_default()
+                    )
+                ),
+            ),
+            _ => testing::aws_response(request),
+        }
+    }
+
+    #[tokio::test]
+    async fn existing_queues_with_differing_attributes_are_reconciled() {
+        let fake: FakeAws = FakeAws::start(existing_queues).await;
+        let context: AppContext = fake.context(fake.args(&[])).await;
+
+        for (visibility_timeout, updated) in [("60", true), ("30", false)] {
+            let updates: usize = fake.calls("SetQueueAttributes").len();
+            let (queue_url, queue_arn) = ensure_queue(
+                &context,
+                "orders-dev".to_string(),
+                HashMap::from([(
+                    QueueAttributeName::VisibilityTimeout,
+                    visibility_timeout.to_string(),
+                )]),
+                false,
+                &HashMap::new(),
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(queue_url, testing::fake_queue_url("orders-dev"));
+            assert_eq!(queue_arn, testing::fake_arn("sqs", "orders-dev"));
+            assert_eq!(
+                fake.calls("SetQueueAttributes").len() > updates,
+                updated,
+                "visibility timeout {}",
+                visibility_timeout
+            );
+        }
+
+        let updates: Vec<FakeRequest> = fake.calls("SetQueueAttributes");
+
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0].param("Attribute.1.Name"),
+            Some("VisibilityTimeout")
+        );
+        assert_eq!(updates[0].param("Attribute.1.Value"), Some("60"));
+        assert_eq!(updates[0].param("Attribute.2.Name"), None);
+    }
+
+    #[tokio::test]
+    async fn existing_queues_with_differing_attributes_fail_without_updates() {
+        let fake: FakeAws = FakeAws::start(existing_queues).await;
+        let context: AppContext = fake.context(fake.args(&["--no-update-existing"])).await;
+
+        let error = ensure_queue(
+            &context,
+            "orders-dev".to_string(),
+            HashMap::from([(QueueAttributeName::VisibilityTimeout, "60".to_string())]),
+            false,
+            &HashMap::new(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            matches!(&error, PinnError::QueueConflict { queue, reason }
+                if queue == "orders-dev" && reason.contains(r#"["VisibilityTimeout"]"#)),
+            "{:?}",
+            error
+        );
+        assert!(fake.calls("SetQueueAttributes").is_empty());
+
+        // Queues that already match their configuration are left alone
+        assert!(ensure_queue(
+            &context,
+            "orders-dev".to_string(),
+            HashMap::from([(QueueAttributeName::VisibilityTimeout, "30".to_string())]),
+            false,
+            &HashMap::new(),
+        )
+        .await
+        .is_ok());
+    }
+
+    #[tokio::test]
+    async fn task_panics_are_reported_with_their_context() {
+        let fake: FakeAws = FakeAws::start(testing::aws_response).await;
+        let context: Arc<AppContext> = Arc::new(fake.context(fake.args(&[])).await);
+
+        let tasks: Vec<(String, JoinHandle<u8>)> = ["orders-dev", "billing-dev"]
+            .iter()
+            .map(|queue| {
+                let (task_context, task_queue) = (context.clone(), queue.to_string());
+
+                (
+                    format!("queue \"{}\"", queue),
+                    tokio::spawn(async move {
+                        let response = task_context
+                            .sqs
+                            .get_queue_url()
+                            .queue_name(&task_queue)
+                            .send()
+                            .await
+                            .unwrap();
+
+                        if task_queue == "orders-dev" {
+                            panic!(
+                                "unexpected queue url: {}",
+                                response.queue_url().unwrap_or_default()
+                            );
+                        }
+
+                        0
+                    }),
+                )
+            })
+            .collect();
+
+        assert_eq!(join_tasks(&context.run, tasks).await, vec![1, 0]);
+
+        let reports: Vec<report::ResourceReport> = report::resource_reports(&context.run);
+
+        assert_eq!(reports.len(), 1, "{:?}", reports);
+        assert_eq!(reports[0].kind, "task");
+        assert_eq!(reports[0].name, "queue \"orders-dev\"");
+        assert_eq!(reports[0].status, report::ResourceStatus::Failed);
+        assert_eq!(
+            reports[0].detail,
+            Some(format!(
+                "task panicked: unexpected queue url: {}",
+                testing::fake_queue_url("orders-dev")
+            ))
+        );
+    }
+
+    /// The errors reported for each queue of the supplied configuration once applied
+    async fn queue_errors(fake: &FakeAws, yaml: &str) -> BTreeMap<String, Vec<String>> {
+        let context: Arc<AppContext> = Arc::new(fake.context(fake.args(&[])).await);
+
+        apply_config(&context, &PinnConfig::from_yaml(yaml).unwrap()).await;
+
+        report::queue_results(&context.run)
+            .into_iter()
+            .map(|result| (result.name, result.errors))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn incompatible_subscriptions_fail_before_subscribing() {
+        let fake: FakeAws = FakeAws::start(testing::aws_response).await;
+        let errors = queue_errors(
+            &fake,
+            "orders:\n  fifo: true\n  topics: [arn:aws:sns:us-east-1:210987654321:placed]\n",
+        )
+        .await;
+
+        assert_eq!(
+            errors["orders"],
+            vec![
+                "topic \"arn:aws:sns:us-east-1:210987654321:placed\": cannot subscribe FIFO queue \"orders-dev.fifo\" to standard topic \"placed\""
+            ]
+        );
+        assert!(fake.calls("Subscribe").is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejected_subscriptions_are_described() {
+        // (AWS's error code and message, the reported reason)
+        let cases: [(&str, &str, &str); 2] = [
+            (
+                "InvalidParameter",
+                "Invalid parameter: Endpoint Reason: FIFO SQS Queues can not be subscribed to standard SNS topics",
+                "cannot subscribe queue \"orders-dev\" to topic \"placed\", as only one of them is FIFO",
+            ),
+            (
+                "AuthorizationError",
+                "User is not authorized to perform: SNS:Subscribe",
+                "subscribe denied: the policy of topic \"arn:aws:sns:us-east-1:210987654321:placed\" in account 210987654321 doesn't allow account 123456789012 to subscribe to it",
+            ),
+        ];
+
+        for (code, message, reason) in cases {
+            let fake: FakeAws =
+                FakeAws::start(move |request: &FakeRequest| match request.action() {
+                    "Subscribe" => FakeResponse::error(code, message),
+                    _ => testing::aws_response(request),
+                })
+                .await;
+            let errors = queue_errors(
+                &fake,
+                "orders:\n  topics: [arn:aws:sns:us-east-1:210987654321:placed]\n",
+            )
+            .await;
+
+            assert_eq!(
+                errors["orders"],
+                vec![format!(
+                    "topic \"arn:aws:sns:us-east-1:210987654321:placed\": {}",
+                    reason
+                )],
+                "{}",
+                code
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn cross_region_topics_are_subscribed_in_their_region() {
+        const SHIPPED: &str = "arn:aws:sns:eu-west-1:210987654321:shipped";
+
+        let fake: FakeAws = FakeAws::start(testing::aws_response).await;
+        let errors = queue_errors(
+            &fake,
+            &format!("orders:\n  topics: [placed, \"{}\"]\n", SHIPPED),
+        )
+        .await;
+
+        assert!(errors["orders"].is_empty(), "{:?}", errors);
+
+        // Each topic is subscribed to in its own region
+        let subscriptions: BTreeMap<String, Option<String>> = fake
+            .calls("Subscribe")
+            .into_iter()
+            .map(|request| {
+                (
+                    request.param("TopicArn").unwrap().to_string(),
+                    request.region.clone(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            subscriptions,
+            BTreeMap::from([
+                (
+                    testing::fake_arn("sns", "placed-dev"),
+                    Some("us-east-1".to_string())
+                ),
+                (SHIPPED.to_string(), Some("eu-west-1".to_string())),
+            ])
+        );
+
+        // And the queue's policy allows each topic by its actual ARN
+        let source_arns: BTreeMap<String, serde_json::Value> = fake
+            .requests()
+            .iter()
+            .flat_map(|request| {
+                request
+                    .params
+                    .iter()
+                    .filter(|(_, value)| value == "Policy")
+                    .filter_map(|(key, _)| request.param(&key.replace(".Name", ".Value")))
+                    .map(|policy| serde_json::from_str::<serde_json::Value>(policy).unwrap())
+                    .collect::<Vec<serde_json::Value>>()
+            })
+            .flat_map(|policy| policy["Statement"].as_array().cloned().unwrap_or_default())
+            .filter_map(|statement| {
+                let condition = statement["Condition"]
+                    .as_object()?
+                    .values()
+                    .next()?
+                    .get("aws:SourceArn")?
+                    .clone();
+                Some((statement["Sid"].as_str()?.to_string(), condition))
+            })
+            .collect();
+
+        assert_eq!(
+            source_arns.get(SNS_SEND_SID),
+            Some(&serde_json::json!([testing::fake_arn("sns", "placed-dev")]))
+        );
+        assert_eq!(
+            source_arns.get(FOREIGN_SNS_SEND_SID),
+            Some(&serde_json::json!([SHIPPED]))
+        );
+    }
+
+    #[tokio::test]
+    async fn event_stream_reconstructs_the_runs_counts() {
+        let path: std::path::PathBuf = testing::scratch_dir("event-stream").join("events.jsonl");
+        let fake: FakeAws = FakeAws::start(|request: &FakeRequest| {
+            match (request.action(), request.param("QueueName")) {
+                ("CreateQueue", Some("billing-dev")) => {
+                    FakeResponse::error("AccessDenied", "Access to the resource is denied.")
+                }
+                _ => testing::aws_response(request),
+            }
+        })
+        .await;
+        let args: CLIArgs = fake.args(&["--events-stream", path.to_str().unwrap()]);
+        let run_settings: serde_json::Value = args.run_settings(
+            EnvName::Dev,
+            Some("us-east-1"),
+            Some(testing::FAKE_ACCOUNT_ID),
+        );
+        let context: Arc<AppContext> = Arc::new(fake.context(args).await);
+
+        events::emit(
+            &context.run,
+            PinnEvent::RunStarted {
+                settings: run_settings,
+            },
+        );
+
+        let results: Vec<u8> = apply_config(
+            &context,
+            &PinnConfig::from_yaml(
+                "orders: {topics: [placed, shipped]}\nbilling: {topics: [invoiced]}\n",
+            )
+            .unwrap(),
+        )
+        .await
+        .into_values()
+        .map(|outcome| match outcome {
+            Ok(outcome) if outcome.failed.is_empty() => 0,
+            _ => 1,
+        })
+        .collect();
+
+        let summary = run_summary(&context.run, &results, 0, 0, 1);
+        events::emit(
+            &context.run,
+            PinnEvent::RunFinished {
+                summary: Box::new(summary),
+            },
+        );
+
+        let stream: String = std::fs::read_to_string(&path).unwrap();
+        let records: Vec<serde_json::Value> = stream
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert!(!stream.contains("fake-backend-secret"));
+        assert!(records.iter().all(|record| record["timestamp_ms"].is_u64()));
+        assert_eq!(records.first().unwrap()["event"], "run_started");
+        assert_eq!(records.last().unwrap()["event"], "run_finished");
+
+        // Reconstruct the outcome of each operation from the stream alone
+        let mut counts: BTreeMap<(String, String), usize> = BTreeMap::new();
+        records
+            .iter()
+            .filter(|record| record["operation"].is_string())
+            .for_each(|record| {
+                *counts
+                    .entry((
+                        record["event"].as_str().unwrap().to_string(),
+                        record["operation"].as_str().unwrap().to_string(),
+                    ))
+                    .or_default() += 1
+            });
+
+        let count = |event: &str, operation: &str| -> usize {
+            counts
+                .get(&(event.to_string(), operation.to_string()))
+                .copied()
+                .unwrap_or_default()
+        };
+
+        let summary: &serde_json::Value = &records.last().unwrap()["summary"];
+
+        assert_eq!(count("operation_succeeded", "CreateQueue"), 1);
+        assert_eq!(count("operation_failed", "CreateQueue"), 1);
+        assert_eq!(count("operation_succeeded", "CreateTopic"), 2);
+        assert_eq!(count("operation_succeeded", "Subscribe"), 2);
+        assert_eq!(
+            summary["succeeded"],
+            count("operation_succeeded", "CreateQueue")
+        );
+        assert_eq!(summary["failed"], count("operation_failed", "CreateQueue"));
+        assert_eq!(
+            summary["queues"],
+            count("operation_succeeded", "CreateQueue") + count("operation_failed", "CreateQueue")
+        );
+    }
+
+    #[tokio::test]
+    async fn first_runs_in_guarded_envs_require_bootstrap() {
+        let config: PinnConfig = PinnConfig::from_yaml(
+            "orders: {topics: [placed]}\nbilling: {env: prod, topics: [invoiced]}\n",
+        )
+        .unwrap();
+
+        // (existing queues, flags, bootstrap required, account listed)
+        let cases: [(&[&str], &[&str], bool, bool); 7] = [
+            // The `billing` queue is always suffixed with (the guarded) `prod`
+            (&[], &[], true, true),
+            (&[], &["--bootstrap"], false, false),
+            (&[], &["--dry-run"], false, false),
+            (&["billing-prod"], &[], false, true),
+            // An existing queue in any guarded env means pinnothera has run before
+            (&["orders-dev"], &["--guarded-env", "dev"], false, true),
+            (&["unrelated-prod"], &["--guarded-env", "dev"], true, true),
+            // Without `billing`, only the unguarded `dev` env is configured
+            (&[], &["--pause-queue", "billing"], false, false),
+        ];
+
+        for (existing, flags, bootstrap_required, account_listed) in cases {
+            let fake: FakeAws =
+                FakeAws::start(move |request: &FakeRequest| match request.action() {
+                    "ListQueues" => FakeResponse::result(
+                        "ListQueues",
+                        existing
+                            .iter()
+                            .map(|queue| {
+                                format!("<QueueUrl>{}</QueueUrl>", testing::fake_queue_url(queue))
+                            })
+                            .collect::<String>(),
+                    ),
+                    _ => testing::aws_response(request),
+                })
+                .await;
+            let args: CLIArgs = fake.args(flags);
+            let context: AppContext = fake.context(args.clone()).await;
+
+            assert_eq!(
+                check_first_run(&context, &args, &config).await.unwrap(),
+                bootstrap_required,
+                "existing queues {:?} with flags {:?}",
+                existing,
+                flags
+            );
+            assert_eq!(
+                !fake.calls("ListQueues").is_empty(),
+                account_listed,
+                "existing queues {:?} with flags {:?}",
+                existing,
+                flags
+            );
+            assert!(fake.calls("CreateQueue").is_empty());
+        }
+    }
+
+    /// Send the supplied command to the control socket at the
+    /// supplied path, returning its (JSON) response
+    async fn control_command(path: &std::path::Path, command: &str) -> serde_json::Value {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+        let stream = tokio::net::UnixStream::connect(path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+
+        writer
+            .write_all(format!("{}\n", command).as_bytes())
+            .await
+            .unwrap();
+
+        let line: String = tokio::io::BufReader::new(reader)
+            .lines()
+            .next_line()
+            .await
+            .unwrap()
+            .unwrap();
+
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn control_socket_reports_and_cancels_a_run() {
+        let dir: std::path::PathBuf = testing::scratch_dir("control-run");
+        let path: std::path::PathBuf = dir.join("pinnothera.sock");
+
+        // Slow queue creation down enough to query (and cancel) the run mid-flight
+        let (started, creating) = std::sync::mpsc::channel::<()>();
+        let fake: FakeAws = FakeAws::start(move |request: &FakeRequest| {
+            if request.action() == "CreateQueue" {
+                started.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(500));
+            }
+            testing::aws_response(request)
+        })
+        .await;
+        let context: Arc<AppContext> = Arc::new(fake.context(fake.args(&[])).await);
+        let socket = control::serve(&path, context.run.clone()).unwrap();
+
+        assert_eq!(
+            control_command(&path, "status").await["status"]["phase"],
+            "starting"
+        );
+
+        control::set_queue_counts(&context.run, 1, 0);
+        control::set_phase(&context.run, control::RunPhase::Applying);
+
+        let run = tokio::spawn({
+            let context: Arc<AppContext> = context.clone();
+            async move {
+                apply_config(
+                    &context,
+                    &PinnConfig::from_yaml("orders: {topics: [placed, shipped]}").unwrap(),
+                )
+                .await
+            }
+        });
+
+        tokio::task::spawn_blocking(move || creating.recv().unwrap())
+            .await
+            .unwrap();
+
+        // In flight: the queue is being created (once every queue has been
+        // listed, to find partially applied ones), nothing has been handled yet
+        let status: serde_json::Value = control_command(&path, "status").await;
+
+        assert_eq!(status["status"]["phase"], "applying");
+        assert_eq!(status["status"]["cancelled"], false);
+        assert_eq!(status["status"]["queues"]["pending"], 1);
+        assert_eq!(status["status"]["api_calls"], 2);
+        assert_eq!(status["status"]["resources"], serde_json::json!({}));
+
+        assert_eq!(
+            control_command(&path, r#"{"command": "cancel"}"#).await["already_cancelled"],
+            false
+        );
+
+        // The in-flight call finishes, but nothing after it is attempted
+        let outcomes = run.await.unwrap();
+        let report: serde_json::Value = control_command(&path, "report").await;
+
+        assert!(outcomes["orders"].is_err());
+        assert_eq!(report["status"]["cancelled"], true);
+        assert_eq!(
+            report["resources"],
+            serde_json::json!([{
+                "kind": "queue",
+                "name": "orders-dev",
+                "status": "failed",
+                "detail": "Not attempting ListQueueTags for \"orders-dev\" (run cancelled)",
+            }])
+        );
+        assert_eq!(
+            fake.requests()
+                .iter()
+                .map(|request| request.action().to_string())
+                .collect::<Vec<String>>(),
+            vec!["ListQueues", "CreateQueue"]
+        );
+
+        drop(socket);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A subscription held by `subscriptions_backend`
+    #[derive(Clone, Debug)]
+    struct FakeSubscription {
+        arn: String,
+        topic_arn: String,
+        endpoint: String,
+        attributes: BTreeMap<String, String>,
+    }
+
+    type FakeSubscriptions = Arc<std::sync::Mutex<Vec<FakeSubscription>>>;
+
+    /// A backend keeping track of the subscriptions made to it, which
+    /// (like SNS) returns the existing subscription when subscribing with
+    /// identical attributes and rejects subscribing with differing ones,
+    /// losing the responses to the first `lost` `Subscribe` calls (as
+    /// though the run had crashed) after the subscription was made
+    fn subscriptions_backend(
+        subscriptions: FakeSubscriptions,
+        lost: usize,
+    ) -> impl Fn(&FakeRequest) -> FakeResponse {
+        let attempts = std::sync::atomic::AtomicUsize::new(0);
+
+        move |request| {
+            let mut subscriptions = subscriptions.lock().unwrap();
+            let param = |name: &str| request.param(name).unwrap_or_default().to_string();
+
+            match request.action() {
+                "Subscribe" => {
+                    let attributes: BTreeMap<String, String> = (1..)
+                        .map_while(|index| {
+                            Some((
+                                request.param(&format!("Attributes.entry.{}.key", index))?,
+                                param(&format!("Attributes.entry.{}.value", index)),
+                            ))
+                        })
+                        .map(|(key, value)| (key.to_string(), value))
+                        .collect();
+
+                    let arn: String = match subscriptions.iter().find(|subscription| {
+                        subscription.topic_arn == param("TopicArn")
+                            && subscription.endpoint == param("Endpoint")
+                    }) {
+                        Some(existing) if existing.attributes != attributes => {
+                            return FakeResponse::error(
+                                "InvalidParameter",
+                                "Invalid parameter: Attributes Reason: Subscription already exists with different attributes",
+                            )
+                        }
+                        Some(existing) => existing.arn.clone(),
+                        None => {
+                            let arn: String =
+                                format!("{}:{}", param("TopicArn"), subscriptions.len() + 1);
+                            subscriptions.push(FakeSubscription {
+                                arn: arn.clone(),
+                                topic_arn: param("TopicArn"),
+                                endpoint: param("Endpoint"),
+                                attributes,
+                            });
+                            arn
+                        }
+                    };
+
+                    match attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < lost {
+                        true => FakeResponse {
+                            status: 500,
+                            ..FakeResponse::error("InternalError", "connection reset")
+                        },
+                        false => FakeResponse::result(
+                            "Subscribe",
+                            format!("<SubscriptionArn>{}</SubscriptionArn>", arn),
+                        ),
+                    }
+                }
+                "ListSubscriptionsByTopic" => FakeResponse::result(
+                    "ListSubscriptionsByTopic",
+                    format!(
+                        "<Subscriptions>{}</Subscriptions>",
+                        subscriptions
+                            .iter()
+                            .filter(|subscription| subscription.topic_arn == param("TopicArn"))
+                            .map(|subscription| format!(
+                                "<member><SubscriptionArn>{}</SubscriptionArn><Protocol>sqs</Protocol><Endpoint>{}</Endpoint><TopicArn>{}</TopicArn></member>",
+                                subscription.arn, subscription.endpoint, subscription.topic_arn
+                            ))
+                            .collect::<String>()
+                    ),
+                ),
+                "GetSubscriptionAttributes" => FakeResponse::result(
+                    "GetSubscriptionAttributes",
+                    format!(
+                        "<Attributes>{}</Attributes>",
+                        subscriptions
+                            .iter()
+                            .filter(|subscription| subscription.arn == param("SubscriptionArn"))
+                            .flat_map(|subscription| subscription.attributes.iter())
+                            .map(|(key, value)| format!(
+                                "<entry><key>{}</key><value>{}</value></entry>",
+                                key,
+                                value.replace('&', "&amp;").replace('<', "&lt;").replace('"', "&quot;")
+                            ))
+                            .collect::<String>()
+                    ),
+                ),
+                "SetSubscriptionAttributes" => {
+                    subscriptions
+                        .iter_mut()
+                        .filter(|subscription| subscription.arn == param("SubscriptionArn"))
+                        .for_each(|subscription| {
+                            subscription
+                                .attributes
+                                .insert(param("AttributeName"), param("AttributeValue"));
+                        });
+                    FakeResponse::empty("SetSubscriptionAttributes")
+                }
+                _ => testing::aws_response(request),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn subscriptions_converge_after_interrupted_runs() {
+        let yaml: &str =
+            r#"orders: {topics: [{name: placed, filter_policy: {"region": ["us-east-1"]}}]}"#;
+        let config: PinnConfig = PinnConfig::from_yaml(yaml).unwrap();
+        let filter_policy = || serde_json::json!({"region": ["us-east-1"]});
+        let subscription = |arn: &str, attributes: &[(&str, &str)]| FakeSubscription {
+            arn: arn.to_string(),
+            topic_arn: testing::fake_arn("sns", "placed-dev"),
+            endpoint: testing::fake_arn("sqs", "orders-dev"),
+            attributes: attributes
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        };
+
+        // (existing subscriptions, lost responses, interrupted, flags, Subscribe calls, updates)
+        type Case<'a> = (
+            Vec<FakeSubscription>,
+            usize,
+            bool,
+            &'a [&'a str],
+            usize,
+            usize,
+        );
+        let cases: [Case; 4] = [
+            // The run is interrupted after subscribing, then re-run
+            (vec![], 1, true, &[], 1, 0),
+            // The interrupted `Subscribe` is retried within the same run
+            (vec![], 1, false, &[], 2, 0),
+            // A subscription left without its filter policy
+            (
+                vec![subscription("arn:left-behind", &[])],
+                0,
+                false,
+                &[],
+                0,
+                1,
+            ),
+            // A forced re-subscription to one with a differing filter policy
+            (
+                vec![subscription(
+                    "arn:differing",
+                    &[("FilterPolicy", r#"{"region": ["eu-west-1"]}"#)],
+                )],
+                0,
+                false,
+                &["--force-resubscribe"],
+                1,
+                1,
+            ),
+        ];
+
+        for (existing, lost, interrupted, flags, subscribe_calls, updates) in cases {
+            let subscriptions: FakeSubscriptions = Arc::new(std::sync::Mutex::new(existing));
+            let fake: FakeAws =
+                FakeAws::start(subscriptions_backend(subscriptions.clone(), lost)).await;
+
+            if interrupted {
+                let context: Arc<AppContext> =
+                    Arc::new(fake.context(fake.args(&["--max-retries", "0"])).await);
+
+                assert!(apply_config(&context, &config).await["orders"]
+                    .as_ref()
+                    .unwrap()
+                    .failed
+                    .contains(&"placed".to_string()));
+            }
+
+            let args: Vec<&str> = ["--retry-base-delay-ms", "1"]
+                .into_iter()
+                .chain(flags.iter().copied())
+                .collect();
+            let context: Arc<AppContext> = Arc::new(fake.context(fake.args(&args)).await);
+            let outcome = apply_config(&context, &config).await;
+
+            assert!(
+                outcome["orders"].as_ref().unwrap().failed.is_empty(),
+                "{:?}",
+                flags
+            );
+
+            let subscriptions: Vec<FakeSubscription> = subscriptions.lock().unwrap().clone();
+
+            assert_eq!(
+                fake.calls("Subscribe").len(),
+                subscribe_calls,
+                "{:?}",
+                flags
+            );
+            assert_eq!(
+                fake.calls("SetSubscriptionAttributes").len(),
+                updates,
+                "{:?}",
+                flags
+            );
+
+            assert_eq!(subscriptions.len(), 1, "{:?}", subscriptions);
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(
+                    &subscriptions[0].attributes["FilterPolicy"]
+                )
+                .unwrap(),
+                filter_policy(),
+                "{:?}",
+                flags
+            );
+        }
+    }
+
+    #[test]
+    fn no_kube_runs_never_touch_kubernetes() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let fake: FakeAws = runtime.block_on(FakeAws::start(testing::aws_response));
+        let home: std::path::PathBuf = testing::scratch_dir("no-kube");
+        let kubeconfig: String = home.join("kubeconfig").display().to_string();
+
+        let mut args: CLIArgs = fake.args(&[
+            "--env-name",
+            "prod",
+            "--yaml-data",
+            "orders: {topics: [placed]}",
+        ]);
+        let mut sourceless: CLIArgs = fake.args(&["--env-name", "prod"]);
+
+        // Neither a kubeconfig nor an in-cluster service account is
+        // available, so any attempt to build a client would fail the run
+        let vars: [(&str, Option<&str>); 4] = [
+            ("HOME", home.to_str()),
+            ("KUBECONFIG", Some(&kubeconfig)),
+            ("KUBERNETES_SERVICE_HOST", None),
+            ("KUBERNETES_SERVICE_PORT", None),
+        ];
+
+        let (run, outcomes, sourceless_error) = testing::with_env(&vars, || {
+            runtime.block_on(async {
+                let settings: Settings = Settings::from(&args);
+                let run: Arc<RunState> = Arc::new(RunState::new(&settings, EventStream::default()));
+                let (env, pinn_config) = args.pinn_config(&run).await.unwrap().unwrap();
+
+                assert_eq!(env, EnvName::Prod);
+                pinn_config
+                    .validate(None, false, false, Some("us-east-1"))
+                    .unwrap();
+
+                let (sns, sqs, _) = args.aws_clients(env).await.unwrap();
+                let clients = AwsClients {
+                    sns,
+                    sqs,
+                    region: Some("us-east-1".to_string()),
+                    account_id: Some(testing::FAKE_ACCOUNT_ID.to_string()),
+                    endpoint_url: None,
+                    topic_clients: None,
+                };
+                let context: Arc<AppContext> =
+                    Arc::new(AppContext::new(env, &clients, settings, run.clone()));
+                let outcomes = apply_config(&context, &pinn_config).await;
+
+                let sourceless_error: PinnError = sourceless
+                    .pinn_config(&RunState::default())
+                    .await
+                    .unwrap_err();
+
+                (run, outcomes, sourceless_error)
+            })
+        });
+
+        assert!(outcomes["orders"].as_ref().unwrap().failed.is_empty());
+        assert_eq!(
+            fake.calls("CreateQueue")
+                .iter()
+                .map(|request| request.param("QueueName").unwrap().to_string())
+                .collect::<Vec<String>>(),
+            vec!["orders-prod"]
+        );
+        assert!(stats::kube_call_counts(&run).is_empty());
+        assert!(!stats::api_call_counts(&run).is_empty());
+
+        // Without a local source there's nothing to read the configuration from
+        assert!(
+            matches!(&sourceless_error, PinnError::ConfigError { message, .. }
+                if message.starts_with("`--no-kube` requires the configuration to be supplied")),
+            "{:?}",
+            sourceless_error
+        );
+
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    /// A backend holding the supplied queues and (SQS) subscriptions,
+    /// which pages through them as AWS does when listing them
+    fn account_backend(
+        queues: Vec<String>,
+        subscriptions: Vec<(String, String)>,
+    ) -> impl Fn(&FakeRequest) -> FakeResponse {
+        move |request| {
+            let offset = |page_size: usize| -> usize {
+                request
+                    .param("NextToken")
+                    .map(|token| token.parse::<usize>().unwrap() * page_size)
+                    .unwrap_or_default()
+            };
+            let next_token = |page: usize, page_size: usize, total: usize| -> String {
+                match (page + 1) * page_size < total {
+                    true => format!("<NextToken>{}</NextToken>", page + 1),
+                    false => String::new(),
+                }
+            };
+
+            match request.action() {
+                "ListQueues" => {
+                    let start: usize = offset(1000);
+                    FakeResponse::result(
+                        "ListQueues",
+                        queues
+                            .iter()
+                            .skip(start)
+                            .take(1000)
+                            .map(|queue| {
+                                format!("<QueueUrl>{}</QueueUrl>", testing::fake_queue_url(queue))
+                            })
+                            .chain([next_token(start / 1000, 1000, queues.len())])
+                            .collect::<String>(),
+                    )
+                }
+                "ListSubscriptions" => {
+                    let start: usize = offset(100);
+                    FakeResponse::result(
+                        "ListSubscriptions",
+                        format!(
+                            "<Subscriptions>{}</Subscriptions>{}",
+                            subscriptions
+                                .iter()
+                                .enumerate()
+                                .skip(start)
+                                .take(100)
+                                .map(|(index, (topic, queue))| format!(
+                                    "<member><SubscriptionArn>{0}:{1}</SubscriptionArn><Protocol>sqs</Protocol><Endpoint>{2}</Endpoint><TopicArn>{0}</TopicArn></member>",
+                                    testing::fake_arn("sns", topic),
+                                    index,
+                                    testing::fake_arn("sqs", queue)
+                                ))
+                                .collect::<String>(),
+                            next_token(start / 100, 100, subscriptions.len())
+                        ),
+                    )
+                }
+                "GetQueueUrl" => match queues
+                    .iter()
+                    .any(|queue| Some(queue.as_str()) == request.param("QueueName"))
+                {
+                    true => testing::aws_response(request),
+                    false => FakeResponse::error(
+                        "AWS.SimpleQueueService.NonExistentQueue",
+                        "The specified queue does not exist.",
+                    ),
+                },
+                _ => testing::aws_response(request),
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn checks_list_queues_and_subscriptions_once() {
+        const QUEUES: usize = 2_000;
+        const TOPICS: usize = 100;
+        const TOPICS_PER_QUEUE: usize = 5;
+
+        // Every configured queue and subscription, among as many
+        // queues and subscriptions that aren't pinnothera's
+        let configured: Vec<(usize, Vec<usize>)> = (0..QUEUES)
+            .map(|queue| {
+                (
+                    queue,
+                    (0..TOPICS_PER_QUEUE)
+                        .map(|offset| (queue + offset) % TOPICS)
+                        .collect(),
+                )
+            })
+            .collect();
+        let queues: Vec<String> = (0..QUEUES)
+            .flat_map(|queue| [format!("queue-{}-dev", queue), format!("other-{}", queue)])
+            .collect();
+        let subscriptions: Vec<(String, String)> = configured
+            .iter()
+            .flat_map(|(queue, topics)| {
+                topics.iter().flat_map(move |topic| {
+                    [
+                        (
+                            format!("topic-{}-dev", topic),
+                            format!("queue-{}-dev", queue),
+                        ),
+                        (format!("topic-{}-dev", topic), format!("other-{}", queue)),
+                    ]
+                })
+            })
+            .collect();
+
+        let pinn_config: PinnConfig = PinnConfig::from_yaml(
+            configured
+                .iter()
+                .map(|(queue, topics)| {
+                    format!(
+                        "queue-{}: {{topics: [{}]}}\n",
+                        queue,
+                        topics
+                            .iter()
+                            .map(|topic| format!("topic-{}", topic))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )
+                })
+                .collect::<String>(),
+        )
+        .unwrap();
+
+        // A queue (and its subscriptions) that doesn't exist is still found missing
+        let missing: usize = QUEUES - 1;
+        let (queues, subscriptions): (Vec<String>, Vec<(String, String)>) = (
+            queues
+                .into_iter()
+                .filter(|queue| *queue != format!("queue-{}-dev", missing))
+                .collect(),
+            subscriptions
+                .into_iter()
+                .filter(|(_, queue)| *queue != format!("queue-{}-dev", missing))
+                .collect(),
+        );
+        let (queue_count, subscription_count) = (queues.len(), subscriptions.len());
+
+        let fake: FakeAws = FakeAws::start(account_backend(queues, subscriptions)).await;
+        let context: Arc<AppContext> = Arc::new(fake.context(fake.args(&["--check"])).await);
+
+        apply_config(&context, &pinn_config).await;
+
+        let calls = stats::api_call_counts(&context.run);
+        let count = |operation: &str| -> u64 { calls.get(operation).copied().unwrap_or_default() };
+        let listings: u64 = (queue_count.div_ceil(1000) + subscription_count.div_ceil(100)) as u64;
+
+        assert_eq!(count("ListQueues"), queue_count.div_ceil(1000) as u64);
+        assert_eq!(
+            count("ListSubscriptions"),
+            subscription_count.div_ceil(100) as u64
+        );
+        assert_eq!(count("GetTopicAttributes"), TOPICS as u64);
+        assert_eq!(count("ListTagsForResource"), TOPICS as u64);
+        assert_eq!(count("GetQueueUrl"), 1);
+        assert_eq!(count("ListSubscriptionsByTopic"), 0);
+        // Beyond the listings, only each existing queue's own lookups
+        // (of its attributes, tags, and ARN) grow with the configuration
+        assert_eq!(count("GetQueueAttributes"), 2 * (QUEUES as u64 - 1));
+        assert_eq!(count("ListQueueTags"), QUEUES as u64 - 1);
+        assert_eq!(
+            calls.keys().copied().collect::<Vec<&str>>(),
+            vec![
+                "GetQueueAttributes",
+                "GetQueueUrl",
+                "GetTopicAttributes",
+                "ListQueueTags",
+                "ListQueues",
+                "ListSubscriptions",
+                "ListTagsForResource"
+            ]
+        );
+
+        // Altogether, the run only makes each resource's own lookups plus the
+        // listings, where looking each queue up by name (and each of its
+        // subscriptions up in its topic's paged subscriptions) would make
+        // several times as many calls
+        let per_resource: u64 = 3 * (QUEUES as u64 - 1) + 2 * TOPICS as u64 + 1;
+        let subscriptions_per_topic: usize = subscription_count.div_ceil(TOPICS);
+        let baseline: u64 = per_resource
+            + (QUEUES - 1) as u64
+            + ((QUEUES - 1) * TOPICS_PER_QUEUE * subscriptions_per_topic.div_ceil(100)) as u64;
+        let total: u64 = calls.values().sum();
+
+        assert_eq!(total, per_resource + listings);
+        assert!(
+            total * 4 < baseline,
+            "{} call(s), {} without listings",
+            total,
+            baseline
+        );
+
+        let statuses: BTreeMap<(&str, String), String> = report::resource_reports(&context.run)
+            .into_iter()
+            .map(|resource| {
+                (
+                    (resource.kind, resource.name),
+                    resource.status.as_str().to_string(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            statuses[&("queue", format!("queue-{}-dev", missing))],
+            "missing"
+        );
+        assert_eq!(
+            statuses
+                .iter()
+                .filter(|((kind, _), status)| *kind == "subscription" && *status == "existing")
+                .count(),
+            (QUEUES - 1) * TOPICS_PER_QUEUE
+        );
+    }
+
+    #[test]
+    fn tag_diffs_describe_missing_changed_and_extra_tags() {
+        let desired: HashMap<String, String> = HashMap::from([
+            ("team".to_string(), "orders".to_string()),
+            ("cost-center".to_string(), "finance".to_string()),
+            ("env".to_string(), "dev".to_string()),
+        ]);
+        let existing: HashMap<String, String> = HashMap::from([
+            ("team".to_string(), "payments".to_string()),
+            ("env".to_string(), "dev".to_string()),
+            ("owner".to_string(), "ops-bot".to_string()),
+        ]);
+
+        let diff: TagDiff = TagDiff::new(&desired, &existing);
+
+        assert_eq!(
+            diff.describe(false),
+            vec![
+                "tag \"cost-center\": missing (configured: \"finance\")",
+                "tag \"team\": \"payments\" (configured: \"orders\")",
+                "tag \"owner\": \"ops-bot\" (not configured)",
+            ]
+        );
+        assert_eq!(diff.describe(true).len(), 2);
+        assert_eq!(TagDiff::new(&desired, &desired), TagDiff::default());
+    }
+
+    #[tokio::test]
+    async fn checks_report_tag_drift() {
+        let pinn_config: PinnConfig = PinnConfig::from_yaml(
+            "tags: {cost-center: finance}\norders: {tags: {team: orders}, topics: [order-placed]}",
+        )
+        .unwrap();
+        let backend = account_backend(vec!["orders-dev".to_string()], Vec::new());
+        let fake: FakeAws = FakeAws::start(move |request| match request.action() {
+            "ListQueueTags" => FakeResponse::result(
+                "ListQueueTags",
+                "<Tag><Key>team</Key><Value>payments</Value></Tag><Tag><Key>owner</Key><Value>ops-bot</Value></Tag>",
+            ),
+            "ListTagsForResource" => FakeResponse::result(
+                "ListTagsForResource",
+                "<Tags><member><Key>team</Key><Value>orders</Value></member><member><Key>cost-center</Key><Value>finance</Value></member></Tags>",
+            ),
+            _ => backend(request),
+        })
+        .await;
+
+        for (flags, extra) in [
+            (&["--check"][..], true),
+            (&["--check", "--ignore-extra-tags"][..], false),
+        ] {
+            let context: Arc<AppContext> = Arc::new(fake.context(fake.args(flags)).await);
+
+            apply_config(&context, &pinn_config).await;
+
+            let reports: Vec<report::ResourceReport> = report::resource_reports(&context.run);
+            let status = |kind: &str, name: &str| -> (String, String) {
+                reports
+                    .iter()
+                    .find(|resource| resource.kind == kind && resource.name == name)
+                    .map(|resource| {
+                        (
+                            resource.status.as_str().to_string(),
+                            resource.detail.clone().unwrap_or_default(),
+                        )
+                    })
+                    .unwrap()
+            };
+
+            let (queue_status, queue_detail) = status("queue", "orders-dev");
+
+            assert_eq!(queue_status, "drifted", "{:?}", flags);
+            assert!(
+                queue_detail.contains("tag \"cost-center\": missing"),
+                "{}",
+                queue_detail
+            );
+            assert!(
+                queue_detail.contains("tag \"team\": \"payments\""),
+                "{}",
+                queue_detail
+            );
+            assert_eq!(
+                queue_detail.contains("tag \"owner\""),
+                extra,
+                "{}",
+                queue_detail
+            );
+            assert_eq!(
+                status("topic", "order-placed-dev").0,
+                "existing",
+                "{:?}",
+                flags
+            );
+        }
+    }
+
+    /// A backend where `billing-dev` doesn't exist, `orders-dev` is
+    /// already being purged, and purging `payments-dev` is denied
+    fn purge_backend(request: &FakeRequest) -> FakeResponse {
+        match (
+            request.action(),
+            request.param("QueueName"),
+            request.param("QueueUrl"),
+        ) {
+            ("GetQueueUrl", Some("billing-dev"), _) => FakeResponse::error(
+                "AWS.SimpleQueueService.NonExistentQueue",
+                "The specified queue does not exist.",
+            ),
+            ("PurgeQueue", _, Some(url)) if url.ends_with("/orders-dev") => FakeResponse::error(
+                "AWS.SimpleQueueService.PurgeQueueInProgress",
+                "Only one PurgeQueue operation on orders-dev is allowed every 60 seconds.",
+            ),
+            ("PurgeQueue", _, Some(url)) if url.ends_with("/payments-dev") => {
+                FakeResponse::error("AccessDenied", "Access to the resource is denied.")
+            }
+            ("PurgeQueue", _, _) => FakeResponse::empty("PurgeQueue"),
+            _ => testing::aws_response(request),
+        }
+    }
+
+    /// The queues purged (or attempted) by a purge of the supplied
+    /// configuration's queues matching the supplied patterns
+    async fn purged(fake: &FakeAws, yaml: &str, queues: &[&str], yes: bool) -> (u8, Vec<String>) {
+        let context: Arc<AppContext> = Arc::new(fake.context(fake.args(&[])).await);
+        let purge_args = PurgeArgs {
+            queues: queues.iter().map(|queue| queue.to_string()).collect(),
+            yes,
+        };
+
+        let before: usize = fake.calls("PurgeQueue").len();
+        let exit_code: u8 =
+            purge_queues(context, &PinnConfig::from_yaml(yaml).unwrap(), &purge_args).await;
+
+        let mut attempted: Vec<String> = fake.calls("PurgeQueue")[before..]
+            .iter()
+            .map(|request| naming::queue_name_from_url(request.param("QueueUrl").unwrap()))
+            .collect();
+        attempted.sort_unstable();
+
+        (exit_code, attempted)
+    }
+
+    #[tokio::test]
+    async fn purges_skip_missing_queues_and_tolerate_purges_in_progress() {
+        let fake: FakeAws = FakeAws::start(purge_backend).await;
+        let yaml: &str = "orders: {topics: [placed]}\nbilling: {topics: [invoiced]}\nshipping: {topics: [shipped]}\npayments: {topics: [paid]}\n";
+
+        assert_eq!(
+            purged(&fake, yaml, &["orders", "billing", "shipping"], true).await,
+            (
+                0,
+                vec!["orders-dev".to_string(), "shipping-dev".to_string()]
+            )
+        );
+        assert!(fake
+            .calls("GetQueueUrl")
+            .iter()
+            .any(|request| request.param("QueueName") == Some("billing-dev")));
+
+        // Only the selected queues are purged, and failed purges fail the run
+        assert_eq!(
+            purged(&fake, yaml, &["pay*"], true).await,
+            (1, vec!["payments-dev".to_string()])
+        );
+        assert_eq!(purged(&fake, yaml, &["billing"], true).await, (0, vec![]));
+    }
+
+    #[tokio::test]
+    async fn declined_purges_purge_nothing() {
+        // Without a terminal to answer the prompt on, confirmation is
+        // declined (run interactively, the prompt would wait for an answer)
+        if std::io::stdin().is_terminal() {
+            return;
+        }
+
+        let fake: FakeAws = FakeAws::start(purge_backend).await;
+
+        assert_eq!(
+            purged(&fake, "orders: {topics: [placed]}", &[], false).await,
+            (1, vec![])
+        );
+        assert_eq!(fake.calls("GetQueueUrl").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn plan_files_hold_the_desired_state() {
+        let fake: FakeAws = FakeAws::start(testing::aws_response).await;
+        let plan_path = testing::scratch_dir("plan-file").join("plan.json");
+        let context: Arc<AppContext> = Arc::new(
+            fake.context(fake.args(&[
+                "--dry-run",
+                "--plan-file",
+                plan_path.to_str().unwrap(),
+                "--tag",
+                "owner=platform",
+            ]))
+            .await,
+        );
+        let yaml: &str = r#"orders: {tags: {team: orders}, topics: [{name: placed, filter_policy: {"region": ["us-east-1"]}}]}"#;
+
+        apply_config(&context, &PinnConfig::from_yaml(yaml).unwrap()).await;
+        plan::write_plan_file(&context.run, &plan_path).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&plan_path).unwrap()).unwrap();
+        let queue = &written["queues"]["orders-dev"];
+
+        // Nothing was created (or looked up) to write the plan
+        assert_eq!(fake.requests().len(), 0);
+
+        assert_eq!(queue["attributes"]["Policy"]["Version"], "2008-10-17");
+        assert_eq!(
+            queue["attributes"]["Policy"]["Statement"][0]["Condition"]["ArnLike"]["aws:SourceArn"],
+            serde_json::json!([testing::fake_arn("sns", "placed-dev")])
+        );
+        assert_eq!(
+            queue["tags"],
+            serde_json::json!({"owner": "platform", "team": "orders"})
+        );
+        assert_eq!(
+            written["topics"]["placed-dev"]["tags"],
+            serde_json::json!({"owner": "platform", "team": "orders"})
+        );
+        assert_eq!(
+            written["subscriptions"],
+            serde_json::json!([{
+                "topic_arn": "<arn of placed-dev>",
+                "queue_arn": "<arn of orders-dev>",
+                "attributes": {"FilterPolicy": {"region": ["us-east-1"]}},
+            }])
+        );
+        assert_eq!(
+            serde_json::to_value(plan::desired_state(&context.run)).unwrap(),
+            written
+        );
+    }
+
+    #[tokio::test]
+    async fn tag_overflows_fail_or_drop_inherited_tags() {
+        let fake: FakeAws = FakeAws::start(testing::aws_response).await;
+        let tags = |prefix: &str, count: usize| -> String {
+            (0..count)
+                .map(|idx| format!("{}-{:02}: x", prefix, idx))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let yaml: String = format!(
+            "tags: {{{}}}\norders: {{tags: {{{}}}, topics: [placed]}}\naudit: {{topics: [placed]}}",
+            tags("global", 30),
+            tags("own", 15),
+        );
+        let run_tags: String = (0..5)
+            .map(|idx| format!("run-{}=x", idx))
+            .collect::<Vec<String>>()
+            .join(",");
+        let args =
+            |mode: &str| -> CLIArgs { fake.args(&["--tag-overflow", mode, "--tag", &run_tags]) };
+
+        // 5 `--tag` tags, 30 global ones, and 15 of its own is one too many
+        let mut pinn_config = PinnConfig::from_yaml(&yaml).unwrap();
+        let error =
+            resolve_tag_overflows(&Settings::from(&args("error")), &mut pinn_config).unwrap_err();
+
+        assert!(
+            matches!(&error, PinnError::InvalidConfig { problems } if problems.len() == 1
+                && problems[0].starts_with("Queue 'orders' would have 50 tags, over the limit of 48")
+                && problems[0].contains("5 from `--tag`, 30 from the configuration's global `tags`, 15 from its own `tags`")),
+            "{:?}",
+            error
+        );
+
+        // The lowest-priority inherited tags are dropped instead, from the queue alone
+        let mut pinn_config = PinnConfig::from_yaml(&yaml).unwrap();
+        let args: CLIArgs = args("drop-inherited");
+        resolve_tag_overflows(&Settings::from(&args), &mut pinn_config).unwrap();
+
+        assert_eq!(
+            pinn_config["orders"].dropped_tags,
+            BTreeSet::from(["run-3".to_string(), "run-4".to_string()])
+        );
+        assert!(pinn_config["audit"].dropped_tags.is_empty());
+
+        let context: AppContext = fake.context(args).await;
+        let tags = resource_tags(
+            &context,
+            &pinn_config.with_global_settings(&pinn_config["orders"]),
+        );
+
+        assert_eq!(tags.len(), types::TagOverflow::LIMIT);
+        assert!(!tags.contains_key("run-3") && tags.contains_key("run-2"));
+        assert_eq!(fake.requests().len(), 0);
+    }
+
+    /// A backend where every queue already exists, tagged by an earlier
+    /// run (with `team` and `stale`), and by other tooling (with `owner`)
+    fn previously_tagged_queues(request: &FakeRequest) -> FakeResponse {
+        match request.action() {
+            "ListQueueTags" => FakeResponse::result(
+                "ListQueueTags",
+                format!(
+                    "<Tag><Key>team</Key><Value>payments</Value></Tag>\
+                     <Tag><Key>stale</Key><Value>yes</Value></Tag>\
+                     <Tag><Key>owner</Key><Value>ops-bot</Value></Tag>\
+                     <Tag><Key>{}</Key><Value>stale,team</Value></Tag>",
+                    types::MANAGED_TAG_KEYS_TAG
+                ),
+            ),
+            _ => testing::aws_response(request),
+        }
+    }
+
+    #[tokio::test]
+    async fn only_tags_pinnothera_set_are_removed() {
+        let fake: FakeAws = FakeAws::start(previously_tagged_queues).await;
+        let context: Arc<AppContext> = Arc::new(fake.context(fake.args(&[])).await);
+
+        apply_config(
+            &context,
+            &PinnConfig::from_yaml("orders: {tags: {team: orders, tier: gold}, topics: []}")
+                .unwrap(),
+        )
+        .await;
+
+        let params = |action: &str| -> Vec<Vec<(String, String)>> {
+            fake.calls(action)
+                .into_iter()
+                .map(|request| {
+                    request
+                        .params
+                        .into_iter()
+                        .filter(|(key, _)| key.starts_with("Tag"))
+                        .collect()
+                })
+                .collect()
+        };
+        let pair = |key: &str, value: &str| (key.to_string(), value.to_string());
+
+        // The queue is created with its tags (and their keys recorded)
+        let created: &FakeRequest = &fake.calls("CreateQueue")[0];
+        assert_eq!(
+            created
+                .params
+                .iter()
+                .filter(|(key, value)| key.ends_with(".Value") && value == "team,tier")
+                .count(),
+            1,
+            "{:?}",
+            created.params
+        );
+
+        // `stale` (which pinnothera set) is removed, `owner` (which it didn't) isn't
+        assert_eq!(params("UntagQueue"), vec![vec![pair("TagKey.1", "stale")]]);
+
+        // Only the tags it's missing or has with another value are set
+        let tagged: Vec<BTreeMap<String, String>> = fake
+            .calls("TagQueue")
+            .iter()
+            .map(|request| {
+                (1..)
+                    .map_while(|idx| {
+                        Some((
+                            request.param(&format!("Tag.{}.Key", idx))?.to_string(),
+                            request.param(&format!("Tag.{}.Value", idx))?.to_string(),
+                        ))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(
+            tagged,
+            vec![BTreeMap::from([
+                pair(types::MANAGED_BY_TAG, types::MANAGED_BY),
+                pair(types::MANAGED_TAG_KEYS_TAG, "team,tier"),
+                pair("team", "orders"),
+                pair("tier", "gold"),
+            ])]
+        );
+    }
+
+    type FakeAttributes = BTreeMap<String, String>;
+
+    /// The queues (with their attributes and tags) and subscriptions
+    /// held by `interruptible_backend`, shared across runs
+    #[derive(Debug, Default)]
+    struct FakeAccount {
+        /// Each queue's attributes and tags, by name
+        queues: BTreeMap<String, (FakeAttributes, FakeAttributes)>,
+        subscriptions: Vec<(SNSTopicARN, SQSQueueARN)>,
+    }
+
+    type SharedAccount = Arc<std::sync::Mutex<FakeAccount>>;
+
+    /// A backend keeping the queues, tags, and subscriptions it's asked to
+    /// create in the supplied account, which (with `interrupted`) goes
+    /// down for good right after making the first of them subscription
+    fn interruptible_backend(
+        account: SharedAccount,
+        interrupted: bool,
+    ) -> impl Fn(&FakeRequest) -> FakeResponse {
+        let down = std::sync::atomic::AtomicBool::new(false);
+
+        move |request| {
+            if down.load(std::sync::atomic::Ordering::SeqCst) {
+                return FakeResponse {
+                    status: 503,
+                    ..FakeResponse::error("ServiceUnavailable", "backend down")
+                };
+            }
+
+            let mut account = account.lock().unwrap();
+            let param = |name: &str| request.param(name).unwrap_or_default().to_string();
+            let pairs = |prefix: &str, key: &str, value: &str| -> BTreeMap<String, String> {
+                (1..)
+                    .map_while(|idx| {
+                        Some((
+                            request
+                                .param(&format!("{}.{}.{}", prefix, idx, key))?
+                                .to_string(),
+                            param(&format!("{}.{}.{}", prefix, idx, value)),
+                        ))
+                    })
+                    .collect()
+            };
+            let queue: String = param("QueueUrl").rsplit('/').next().unwrap().to_string();
+
+            match request.action() {
+                "ListQueues" => FakeResponse::result(
+                    "ListQueues",
+                    account
+                        .queues
+                        .keys()
+                        .map(|queue| format!("<QueueUrl>{}</QueueUrl>", testing::fake_queue_url(queue)))
+                        .collect::<String>(),
+                ),
+                "CreateQueue" => {
+                    account
+                        .queues
+                        .entry(param("QueueName"))
+                        .or_insert_with(|| {
+                            (
+                                pairs("Attribute", "Name", "Value"),
+                                pairs("Tag", "Key", "Value"),
+                            )
+                        });
+                    testing::aws_response(request)
+                }
+                "GetQueueAttributes" => FakeResponse::result(
+                    "GetQueueAttributes",
+                    account.queues[&queue]
+                        .0
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), value.clone()))
+                        .chain([("QueueArn", testing::fake_arn("sqs", &queue))])
+                        .map(|(name, value)| {
+                            format!(
+                                "<Attribute><Name>{}</Name><Value>{}</Value></Attribute>",
+                                name,
+                                value.replace('&', "&amp;").replace('<', "&lt;")
+                            )
+                        })
+                        .collect::<String>(),
+                ),
+                "SetQueueAttributes" => {
+                    let attributes = pairs("Attribute", "Name", "Value");
+                    account.queues.get_mut(&queue).unwrap().0.extend(attributes);
+                    FakeResponse::empty("SetQueueAttributes")
+                }
+                "ListQueueTags" => FakeResponse::result(
+                    "ListQueueTags",
+                    account.queues[&queue]
+                        .1
+                        .iter()
+                        .map(|(key, value)| format!("<Tag><Key>{}</Key><Value>{}</Value></Tag>", key, value))
+                        .collect::<String>(),
+                ),
+                "TagQueue" => {
+                    let tags = pairs("Tag", "Key", "Value");
+                    account.queues.get_mut(&queue).unwrap().1.extend(tags);
+                    FakeResponse::empty("TagQueue")
+                }
+                "ListSubscriptions" | "ListSubscriptionsByTopic" => FakeResponse::result(
+                    request.action(),
+                    format!(
+                        "<Subscriptions>{}</Subscriptions>",
+                        account
+                            .subscriptions
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, (topic_arn, _))| {
+                                request.action() == "ListSubscriptions"
+                                    || *topic_arn == param("TopicArn")
+                            })
+                            .map(|(idx, (topic_arn, queue_arn))| format!(
+                                "<member><SubscriptionArn>{0}:{1}</SubscriptionArn><Protocol>sqs</Protocol><Endpoint>{2}</Endpoint><TopicArn>{0}</TopicArn></member>",
+                                topic_arn, idx, queue_arn
+                            ))
+                            .collect::<String>()
+                    ),
+                ),
+                "Subscribe" => {
+                    account
+                        .subscriptions
+                        .push((param("TopicArn"), param("Endpoint")));
+                    down.store(interrupted, std::sync::atomic::Ordering::SeqCst);
+                    FakeResponse::result(
+                        "Subscribe",
+                        format!(
+                            "<SubscriptionArn>{}:{}</SubscriptionArn>",
+                            param("TopicArn"),
+                            account.subscriptions.len() - 1
+                        ),
+                    )
+                }
+                _ => testing::aws_response(request),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn interrupted_runs_are_repaired_by_the_next_one() {
+        let account: SharedAccount = SharedAccount::default();
+        let pinn_config: PinnConfig =
+            PinnConfig::from_yaml("orders: {tags: {team: orders}, topics: [placed, shipped]}")
+                .unwrap();
+        let statuses =
+            |context: &AppContext| -> BTreeMap<String, (ResourceStatus, Option<String>)> {
+                report::resource_reports(&context.run)
+                    .into_iter()
+                    .map(|report| (report.name, (report.status, report.detail)))
+                    .collect()
+            };
+        let subscription = |topic: &str| -> String {
+            format!("{} -> {}", topic, testing::fake_arn("sqs", "orders-dev"))
+        };
+
+        // The backend goes down once the queue exists, but only one of its
+        // topics has been subscribed to
+        let fake: FakeAws = FakeAws::start(interruptible_backend(account.clone(), true)).await;
+        let context: Arc<AppContext> =
+            Arc::new(fake.context(fake.args(&["--max-retries", "0"])).await);
+
+        apply_config(&context, &pinn_config).await;
+
+        let subscribed: String = {
+            let account = account.lock().unwrap();
+            assert_eq!(account.subscriptions.len(), 1);
+            account.subscriptions[0]
+                .0
+                .rsplit(':')
+                .next()
+                .unwrap()
+                .to_string()
+        };
+        let (subscribed, missing): (&str, &str) = match subscribed.as_str() {
+            "placed-dev" => ("placed", "shipped"),
+            _ => ("shipped", "placed"),
+        };
+
+        assert_eq!(
+            account.lock().unwrap().queues["orders-dev"].1[types::MANAGED_BY_TAG],
+            types::MANAGED_BY
+        );
+
+        // Meanwhile, the queue's policy and tags are changed out from under it
+        {
+            let mut account = account.lock().unwrap();
+            let (attributes, tags) = account.queues.get_mut("orders-dev").unwrap();
+
+            attributes.insert(
+                "Policy".to_string(),
+                r#"{"Version": "2008-10-17", "Statement": []}"#.to_string(),
+            );
+            tags.remove("team");
+        }
+
+        // The next run repairs the queue: re-validating its policy and
+        // tags, and making only the subscription it's missing
+        let fake: FakeAws = FakeAws::start(interruptible_backend(account.clone(), false)).await;
+        let context: Arc<AppContext> = Arc::new(fake.context(fake.args(&[])).await);
+
+        let outcomes = apply_config(&context, &pinn_config).await;
+        let reported = statuses(&context);
+
+        assert!(matches!(&outcomes["orders"], Ok(outcome) if outcome.failed.is_empty()));
+        assert_eq!(
+            reported["orders-dev"],
+            (
+                ResourceStatus::Repairing,
+                Some(format!(
+                    "was missing its subscription(s) to [\"{}-dev\"]",
+                    missing
+                ))
+            )
+        );
+        assert_eq!(reported[&subscription(missing)].0, ResourceStatus::Created);
+        assert_eq!(
+            reported[&subscription(subscribed)].0,
+            ResourceStatus::Existing
+        );
+        assert_eq!(fake.calls("Subscribe").len(), 1);
+        assert_eq!(fake.calls("CreateQueue").len(), 0);
+
+        {
+            let account = account.lock().unwrap();
+            let (attributes, tags) = &account.queues["orders-dev"];
+
+            assert!(attributes["Policy"].contains(SNS_SEND_SID));
+            assert_eq!(tags["team"], "orders");
+            assert_eq!(account.subscriptions.len(), 2);
+        }
+
+        // After which, it's converged
+        let fake: FakeAws = FakeAws::start(interruptible_backend(account.clone(), false)).await;
+        let context: Arc<AppContext> = Arc::new(fake.context(fake.args(&[])).await);
+
+        apply_config(&context, &pinn_config).await;
+
+        assert_eq!(statuses(&context)["orders-dev"].0, ResourceStatus::Ensured);
+        assert!(fake.calls("Subscribe").is_empty());
+        assert!(fake.calls("SetQueueAttributes").is_empty());
+        assert!(fake.calls("TagQueue").is_empty());
+    }
+}
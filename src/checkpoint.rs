@@ -0,0 +1,155 @@
+// Pinnothera's resumable-run checkpointing components
+
+// Standard Library Imports
+use std::collections::BTreeSet;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+// Third Party Imports
+use easy_error::Terminator;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Project-Level Imports
+use crate::logging::{info, warning};
+
+// <editor-fold desc="// Checkpoint ...">
+
+/// A single line of a checkpoint file, the first of which is always
+/// the fingerprint of the configuration and settings it was written for
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CheckpointEntry {
+    Fingerprint(String),
+    Completed(String),
+}
+
+#[derive(Debug)]
+pub(crate) struct Checkpoint {
+    file: Mutex<File>,
+    completed: BTreeSet<String>,
+}
+
+impl Checkpoint {
+    /// Open the checkpoint file at the supplied path, reusing its
+    /// recorded entries if it was written for the same fingerprint
+    /// or starting it afresh if it's missing, stale, or corrupt
+    pub fn open<P: AsRef<Path>, T: AsRef<str>>(
+        path: P,
+        fingerprint: T,
+    ) -> Result<Checkpoint, Terminator> {
+        let (path, fingerprint): (&Path, &str) = (path.as_ref(), fingerprint.as_ref());
+
+        let completed = match std::fs::read_to_string(path) {
+            Ok(data) => Self::parse(&data, fingerprint, path),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+            Err(error) => {
+                warning!(
+                    "Ignoring unreadable checkpoint file \"{}\": {}",
+                    path.display(),
+                    error
+                );
+                None
+            }
+        };
+
+        let file = match &completed {
+            Some(_) => OpenOptions::new().append(true).open(path)?,
+            None => {
+                let mut file = File::create(path)?;
+                writeln!(
+                    file,
+                    "{}",
+                    serde_json::to_string(&CheckpointEntry::Fingerprint(fingerprint.to_string()))?
+                )?;
+                file
+            }
+        };
+
+        Ok(Checkpoint {
+            file: Mutex::new(file),
+            completed: completed.unwrap_or_default(),
+        })
+    }
+
+    fn parse(data: &str, fingerprint: &str, path: &Path) -> Option<BTreeSet<String>> {
+        let entries: Result<Vec<CheckpointEntry>, serde_json::Error> = data
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str::<CheckpointEntry>)
+            .collect();
+
+        match entries.as_deref().map(|entries| entries.split_first()) {
+            Ok(Some((CheckpointEntry::Fingerprint(value), entries))) if value == fingerprint => {
+                Some(
+                    entries
+                        .iter()
+                        .filter_map(|entry| match entry {
+                            CheckpointEntry::Completed(resource) => Some(resource.to_string()),
+                            CheckpointEntry::Fingerprint(_) => None,
+                        })
+                        .collect(),
+                )
+            }
+            Ok(Some((CheckpointEntry::Fingerprint(_), _))) => {
+                info!(
+                    "Discarding checkpoint file \"{}\", it was written for a different configuration or different settings",
+                    path.display()
+                );
+                None
+            }
+            _ => {
+                warning!("Ignoring corrupt checkpoint file \"{}\"", path.display());
+                None
+            }
+        }
+    }
+
+    /// Determine whether the supplied resource reached a
+    /// terminal success state during a previous run
+    pub fn is_completed<T: AsRef<str>>(&self, resource: T) -> bool {
+        self.completed.contains(resource.as_ref())
+    }
+
+    /// Record that the supplied resource has
+    /// reached a terminal success state
+    pub fn record_completed<T: AsRef<str>>(&self, resource: T) {
+        let entry = CheckpointEntry::Completed(resource.as_ref().to_string());
+        let mut file = self.file.lock().unwrap();
+
+        let result = serde_json::to_string(&entry)
+            .map_err(std::io::Error::from)
+            .and_then(|line| writeln!(file, "{}", line))
+            .and_then(|_| file.flush());
+
+        if let Err(error) = result {
+            warning!(
+                "Could not record \"{}\" in the checkpoint file: {}",
+                resource.as_ref(),
+                error
+            );
+        }
+    }
+}
+
+// </editor-fold desc="// Checkpoint ...">
+
+// <editor-fold desc="// Checkpoint Utilities ...">
+
+/// Compute a stable fingerprint of the supplied serializable
+/// configuration and run settings for a checkpoint to be tied to
+pub(crate) fn fingerprint<C: Serialize, S: Serialize>(
+    config: &C,
+    settings: &S,
+) -> Result<String, Terminator> {
+    let mut hasher = Sha256::new();
+
+    hasher.update(serde_json::to_vec(config)?);
+    hasher.update(serde_json::to_vec(settings)?);
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// </editor-fold desc="// Checkpoint Utilities ...">
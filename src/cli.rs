@@ -1,235 +1,1531 @@
 // Pinnothera's command line argument parsing components
 
 // Standard Library Imports
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Formatter;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 // Third Party Imports
-use atomicell::AtomicCell;
-use aws_sdk_sns::config::Config as SNSClientConfig;
-use aws_sdk_sqs::config::Config as SQSClientConfig;
-use aws_sdk_sts::config::Config as STSClientConfig;
+use aws_config::default_provider::{
+    credentials::DefaultCredentialsChain, region::DefaultRegionChain,
+};
+use aws_config::meta::credentials::LazyCachingCredentialsProvider;
+use aws_config::profile::ProfileSet as AWSProfileSet;
+use aws_sdk_sns::Client as SNSClient;
+use aws_sdk_sqs::Client as SQSClient;
+use aws_sdk_sts::Client as STSClient;
+use aws_smithy_client::erase::DynConnector;
+use aws_smithy_types::retry::RetryConfig;
 use aws_types::credentials::{
     future::ProvideCredentials as ProvideAWSCredentials, Credentials as AWSCredentials,
     CredentialsError as AWSCredentialsError, ProvideCredentials as AWSCredentialProvider,
     SharedCredentialsProvider as SharedAWSCredentialsProvider,
 };
+use aws_types::os_shim_internal::{Env as AWSEnv, Fs as AWSFs};
 use aws_types::{region::Region, SdkConfig as AWSConfig};
-use clap::Parser;
-use easy_error::Terminator;
+use clap::{Args, Parser, Subcommand};
+use easy_error::{bail, Terminator};
+use itertools::Itertools;
 use kube::Client as K8sClient;
+use rustls::ProtocolVersion;
 
 // Project-Level Imports
-use crate::{EnvName, PinnConfig, CLUSTER_ENV};
+use crate::context::RunState;
+use crate::errors::PinnError;
+use crate::events;
+use crate::interpolation;
+use crate::logging::{info, warning};
+use crate::naming::{self, DEFAULT_ENV_DELIMITER, DEFAULT_SANITIZE_REPLACEMENT};
+use crate::regions;
+use crate::types::{self, ConfigMapOptions, EnvConflict, MissingSource, NamespaceEnvSource};
+use crate::{EnvName, PinnConfig, TopicClientFactory};
 
 // const CLI_ABOUT: &str = "";
 
+/// The AWS region pinnothera falls back to when a custom
+/// endpoint (i.e. LocalStack) is used without a region
+const DEFAULT_CUSTOM_ENDPOINT_REGION: &str = "us-east-1";
+
+/// The endpoint pinnothera uses for LocalStack in the `local`
+/// environment when `--localstack-endpoint` isn't supplied
+const DEFAULT_LOCALSTACK_ENDPOINT: &str = "http://aws.localstack";
+
 /// A dead simple Kubernetes-native SNS/SQS configurator
-#[derive(Parser, Debug)]
+#[derive(Parser, Clone, Debug)]
 #[clap(version, about, long_about = None)]
 pub(crate) struct CLIArgs {
+    /// An operation to perform instead of applying
+    /// the SNS/SQS configuration
+    #[clap(subcommand)]
+    pub(crate) command: Option<PinnCommand>,
+
     // <editor-fold desc="// Behavioral Settings ...">
     /// Force pinnothera to issue an exit code of 0 (success)
     /// regardless of whether or not it actually way successful
     #[clap(
         short = 'f',
         long = "force-success",
+        env = "PINN_FORCE_SUCCESS",
         default_value_t = false,
-        value_parser
+        value_parser = clap::builder::BoolishValueParser::new()
     )]
     pub(crate) force_success: bool,
 
+    /// Only print warnings, errors (each distinct one printed once),
+    /// and the final summary of the run
+    #[clap(
+        short = 'q',
+        long = "quiet",
+        env = "PINN_QUIET",
+        default_value_t = false,
+        conflicts_with = "verbose",
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) quiet: bool,
+
+    /// Print debug detail (i.e. rendered queue
+    /// policies) in addition to the usual output
+    #[clap(
+        short = 'v',
+        long = "verbose",
+        env = "PINN_VERBOSE",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) verbose: bool,
+
+    /// The most detailed level of log line pinnothera should
+    /// write (overriding the level implied by `--quiet`
+    /// or `--verbose`), `trace` includes the AWS SDK's own
+    #[clap(long = "log-level", env = "PINN_LOG_LEVEL", value_parser = ["error", "warn", "info", "debug", "trace"])]
+    pub(crate) log_level: Option<String>,
+
+    /// The format pinnothera should write its log lines in,
+    /// `json` writes one object per line with structured
+    /// fields (i.e. the queue and topic being configured)
+    #[clap(long = "log-format", env = "PINN_LOG_FORMAT", default_value = "text", value_parser = ["text", "json"])]
+    pub(crate) log_format: String,
+
+    /// The format pinnothera should write its end-of-run summary in,
+    /// `json` writes it to stdout as a single JSON document (with
+    /// log lines written to stderr instead so stdout stays parseable)
+    #[clap(long = "output", env = "PINN_OUTPUT", default_value = "text", value_parser = ["text", "json"])]
+    pub(crate) output: String,
+
+    /// Include, for each queue applied, which configuration source
+    /// (i.e. `ConfigMap` key or file) declared each of its topics
+    /// in the summary written by `--output json` (and the
+    /// `run_finished` event), at the cost of a larger document
+    #[clap(
+        long = "outputs-include-provenance",
+        env = "PINN_OUTPUTS_INCLUDE_PROVENANCE",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) outputs_include_provenance: bool,
+
+    /// Print the topics, queues, and subscriptions pinnothera
+    /// would create (or queues it would purge) without
+    /// actually creating or modifying anything
+    #[clap(
+        long = "dry-run",
+        env = "PINN_DRY_RUN",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) dry_run: bool,
+
+    /// Absolute or relative on-disk path to a file pinnothera
+    /// should write everything it would create (and set on it)
+    /// to as a JSON document, for a policy engine to evaluate
+    /// before it's applied (implies `--dry-run`)
+    #[clap(
+        long = "plan-file",
+        env = "PINN_PLAN_FILE",
+        conflicts_with = "check",
+        value_parser
+    )]
+    pub(crate) plan_file: Option<PathBuf>,
+
+    /// Verify (without creating or modifying anything) that every
+    /// configured topic, queue, and subscription exists with its
+    /// configured attributes, exiting with code 4 if any don't
+    #[clap(
+        long = "check",
+        env = "PINN_CHECK",
+        default_value_t = false,
+        conflicts_with = "dry-run",
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) check: bool,
+
+    /// Print why each topic, queue, and subscription is being
+    /// planned (or ensured) the way it is: where it's configured,
+    /// which settings and flags shaped it, and (for existing
+    /// resources being updated) which attributes differ and how
+    #[clap(
+        long = "explain",
+        env = "PINN_EXPLAIN",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) explain: bool,
+
+    /// Replace any characters AWS does not allow in topic
+    /// and queue names (e.g. '.' or '/') before applying
+    /// the configuration, instead of rejecting it
+    #[clap(
+        long = "sanitize-names",
+        env = "PINN_SANITIZE_NAMES",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) sanitize_names: bool,
+
+    /// The string disallowed characters in topic and queue
+    /// names are replaced with when `--sanitize-names` is set
+    #[clap(long = "sanitize-replacement", env = "PINN_SANITIZE_REPLACEMENT", default_value_t = String::from(DEFAULT_SANITIZE_REPLACEMENT), value_parser = parse_sanitize_replacement)]
+    pub(crate) sanitize_replacement: String,
+
+    /// Which end of topic and queue names the environment
+    /// is applied to (i.e. `orders-dev` or `dev-orders`)
+    #[clap(long = "env-position", env = "PINN_ENV_POSITION", default_value = "suffix", value_parser = ["prefix", "suffix"])]
+    pub(crate) env_position: String,
+
+    /// The character the environment is joined to topic and queue
+    /// names with (`.`, which AWS disallows in names, is replaced
+    /// with the `--sanitize-replacement` string in AWS)
+    #[clap(long = "env-delimiter", env = "PINN_ENV_DELIMITER", default_value_t = DEFAULT_ENV_DELIMITER, value_parser = parse_env_delimiter)]
+    pub(crate) env_delimiter: char,
+
+    /// Report (rather than update) existing queues whose
+    /// attributes differ from the configuration, failing
+    /// the configuration of each such queue
+    #[clap(
+        long = "no-update-existing",
+        env = "PINN_NO_UPDATE_EXISTING",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) no_update_existing: bool,
+
+    /// Allow any SNS topic in the account with the queue's environment to
+    /// send to a queue, rather than only the queue's configured topics
+    #[clap(
+        long = "broad-policy",
+        env = "PINN_BROAD_POLICY",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) broad_policy: bool,
+
+    /// Overwrite the access policy of existing queues entirely,
+    /// rather than only replacing the statements pinnothera
+    /// manages (and keeping any others)
+    #[clap(
+        long = "replace-policy",
+        env = "PINN_REPLACE_POLICY",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) replace_policy: bool,
+
+    /// Enable raw message delivery for every subscription
+    /// whose queue doesn't set `raw_message_delivery` itself
+    #[clap(
+        long = "raw-message-delivery",
+        env = "PINN_RAW_MESSAGE_DELIVERY",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) raw_message_delivery: bool,
+
+    /// Deny non-TLS access in the access policy of every queue
+    /// that doesn't set `enforce_secure_transport` itself
+    #[clap(
+        long = "enforce-secure-transport",
+        env = "PINN_ENFORCE_SECURE_TRANSPORT",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) enforce_secure_transport: bool,
+
+    /// Fail validation (rather than warning) when a subscription filter
+    /// policy matches on a message attribute not declared for its topic
+    #[clap(
+        long = "strict-filters",
+        env = "PINN_STRICT_FILTERS",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) strict_filters: bool,
+
+    /// Call Subscribe for every configured subscription, even
+    /// ones that already exist (i.e. to recover a subscription
+    /// left in a bad state)
+    #[clap(
+        long = "force-resubscribe",
+        env = "PINN_FORCE_RESUBSCRIBE",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) force_resubscribe: bool,
+
+    /// The longest (in seconds) pinnothera should wait for a new
+    /// subscription SNS reports as pending confirmation (i.e. to a
+    /// topic in another account) to be confirmed
+    #[clap(
+        long = "confirmation-timeout-secs",
+        env = "PINN_CONFIRMATION_TIMEOUT_SECS",
+        default_value_t = 0,
+        value_parser
+    )]
+    pub(crate) confirmation_timeout_secs: u64,
+
+    /// Fail subscriptions that are still pending confirmation once
+    /// `--confirmation-timeout-secs` is up, rather than warning about them
+    #[clap(
+        long = "require-confirmed",
+        env = "PINN_REQUIRE_CONFIRMED",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) require_confirmed: bool,
+
+    /// Unsubscribe each queue from any topic (suffixed with the queue's
+    /// environment) that's no longer in its configured list of topics
+    #[clap(
+        long = "prune-subscriptions",
+        env = "PINN_PRUNE_SUBSCRIPTIONS",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) prune_subscriptions: bool,
+
+    /// A `KEY=VALUE` tag to set on every queue and topic pinnothera
+    /// ensures, overridden by any `tags` in the configuration
+    #[clap(long = "tag", env = "PINN_TAG", use_value_delimiter = true, value_delimiter = ',', value_parser = parse_tag)]
+    pub(crate) tags: Vec<(String, String)>,
+
+    /// What pinnothera should do when a queue's merged tags (from
+    /// `--tag`, plus the configuration's global and own `tags`) would
+    /// exceed AWS's limit of 50: fail validation, or drop inherited
+    /// tags (`--tag` first, then global ones) until they don't
+    #[clap(
+        long = "tag-overflow",
+        env = "PINN_TAG_OVERFLOW",
+        default_value = "error",
+        value_parser = ["error", "drop-inherited"]
+    )]
+    pub(crate) tag_overflow: String,
+
+    /// Fail any queue or topic whose tags can't be set,
+    /// rather than only warning about it
+    #[clap(
+        long = "strict-tags",
+        env = "PINN_STRICT_TAGS",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) strict_tags: bool,
+
+    /// Don't report tags that aren't in the configuration (i.e. ones
+    /// added by other automation) as drift in a `--check` run
+    #[clap(
+        long = "ignore-extra-tags",
+        env = "PINN_IGNORE_EXTRA_TAGS",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) ignore_extra_tags: bool,
+
+    /// Name(s) of configured queues pinnothera should leave
+    /// untouched during this run, as if they were configured
+    /// with `paused: true`
+    #[clap(
+        long = "pause-queue",
+        env = "PINN_PAUSE_QUEUE",
+        use_value_delimiter = true,
+        value_delimiter = ',',
+        value_parser
+    )]
+    pub(crate) pause_queues: Vec<String>,
+
+    /// Proceed (without prompting) with creating queues without
+    /// an environment suffix even though identically named ones
+    /// exist suffixed with a known environment
+    #[clap(
+        long = "confirm-new-suffix",
+        env = "PINN_CONFIRM_NEW_SUFFIX",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) confirm_new_suffix: bool,
+
+    /// Skip checking for existing queues suffixed with a known
+    /// environment before creating unsuffixed ones (i.e. for a
+    /// genuinely new environment)
+    #[clap(
+        long = "skip-suffix-check",
+        env = "PINN_SKIP_SUFFIX_CHECK",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) skip_suffix_check: bool,
+
+    /// Environment(s), in addition to `prod`, in which pinnothera
+    /// refuses to create resources during its first-ever run
+    /// (when none of the configured queues exist yet) unless
+    /// `--bootstrap` is passed
+    #[clap(
+        long = "guarded-env",
+        env = "PINN_GUARDED_ENV",
+        use_value_delimiter = true,
+        value_delimiter = ',',
+        value_parser
+    )]
+    pub(crate) guarded_envs: Vec<String>,
+
+    /// Acknowledge that this is pinnothera's first run in a guarded
+    /// environment, and create its queues and topics anyway
+    #[clap(
+        long = "bootstrap",
+        env = "PINN_BOOTSTRAP",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) bootstrap: bool,
+
+    /// A shell command that decides the physical name of each queue and
+    /// topic, invoked once per base name with a JSON document describing
+    /// it on stdin and expected to print the name to use on stdout
+    /// (replacing pinnothera's own sanitization and env suffixing)
+    #[clap(long = "name-hook", env = "PINN_NAME_HOOK", value_parser)]
+    pub(crate) name_hook: Option<String>,
+
+    /// The maximum number of AWS API calls pinnothera may make (retries
+    /// included), after which any remaining work is not attempted
+    #[clap(long = "max-api-calls", env = "PINN_MAX_API_CALLS", value_parser)]
+    pub(crate) max_api_calls: Option<u64>,
+
+    /// The maximum number of AWS API calls pinnothera may
+    /// have in flight at once, across all queues and topics
+    #[clap(long = "concurrency", env = "PINN_CONCURRENCY", default_value_t = 16, value_parser = clap::value_parser!(u64).range(1..))]
+    pub(crate) concurrency: u64,
+
+    /// The maximum number of those AWS API calls that may be made on
+    /// behalf of subscriptions at once, leaving the rest for queues and
+    /// topics (by default, all calls share `--concurrency` alike)
+    #[clap(long = "max-subscription-concurrency", env = "PINN_MAX_SUBSCRIPTION_CONCURRENCY", value_parser = clap::value_parser!(u64).range(1..))]
+    pub(crate) max_subscription_concurrency: Option<u64>,
+
+    /// The number of times pinnothera should retry an AWS API
+    /// call that failed due to throttling or a transient error
+    #[clap(
+        long = "max-retries",
+        env = "PINN_MAX_RETRIES",
+        default_value_t = 3,
+        value_parser
+    )]
+    pub(crate) max_retries: u32,
+
+    /// The delay (in milliseconds) before the first retry of a
+    /// failed AWS API call, doubling with each subsequent retry
+    #[clap(
+        long = "retry-base-delay-ms",
+        env = "PINN_RETRY_BASE_DELAY_MS",
+        default_value_t = 100,
+        value_parser
+    )]
+    pub(crate) retry_base_delay_ms: u64,
+
+    /// The longest (in seconds) pinnothera should wait for SQS to
+    /// allow a recently deleted queue to be recreated (SQS requires
+    /// 60 seconds), before failing the queue
+    #[clap(
+        long = "queue-recreate-wait-secs",
+        env = "PINN_QUEUE_RECREATE_WAIT_SECS",
+        default_value_t = 90,
+        value_parser
+    )]
+    pub(crate) queue_recreate_wait_secs: u64,
+
+    /// Absolute or relative on-disk path to a file pinnothera
+    /// should record successfully applied queue configurations
+    /// in, so that a subsequent run with the same configuration
+    /// and settings only applies the remainder
+    #[clap(long = "checkpoint-file", env = "PINN_CHECKPOINT_FILE", value_parser)]
+    pub(crate) checkpoint_file: Option<PathBuf>,
+
+    /// Absolute or relative on-disk path to a file pinnothera
+    /// should stream JSON Lines-formatted events to as they
+    /// occur (or `-` to stream them to stdout)
+    #[clap(long = "events-stream", env = "PINN_EVENTS_STREAM", value_parser)]
+    pub(crate) events_stream: Option<PathBuf>,
+
+    /// Absolute or relative on-disk path to a (line-delimited
+    /// JSON) Unix socket pinnothera should create, over which
+    /// a supervisor can query its progress (`status`), fetch
+    /// its partial report (`report`), or cancel it (`cancel`)
+    #[clap(long = "control-socket", env = "PINN_CONTROL_SOCKET", value_parser)]
+    pub(crate) control_socket: Option<PathBuf>,
+
     // </editor-fold desc="// Behavioral Settings ...">
 
     // <editor-fold desc="// Kubernetes-related Settings ...">
     /// Name of the Kubernetes `Namespace` containing
     /// the SNS/SQS configuration pinnothera should apply
-    #[clap(short = 'n', long = "namespace", value_parser)]
+    #[clap(short = 'n', long = "namespace", env = "PINN_NAMESPACE", value_parser)]
     pub(crate) namespace: Option<String>,
 
     /// Name of the Kubernetes `ConfigMap` containing
     /// the SNS/SQS configuration pinnothera should apply
-    #[clap(short = 'm', long = "configmap", default_value_t = String::from("sns-sqs-config"), value_parser)]
+    #[clap(short = 'm', long = "configmap", env = "PINN_CONFIGMAP", default_value_t = String::from("sns-sqs-config"), value_parser)]
     pub(crate) configmap_name: String,
 
+    /// Key(s) of the `ConfigMap` (i.e. 'team-a.yaml,team-b.yaml') to
+    /// each parse as an independent configuration document (in the
+    /// format named by its extension) and merge, rather than reading
+    /// the single `json`, `yaml`, or `toml` key
+    #[clap(
+        long = "configmap-keys",
+        env = "PINN_CONFIGMAP_KEYS",
+        use_value_delimiter = true,
+        value_delimiter = ',',
+        value_parser
+    )]
+    pub(crate) configmap_keys: Vec<String>,
+
+    /// Glob pattern (i.e. '*.yaml') selecting (additional) keys of the
+    /// `ConfigMap` to parse as independent configuration documents
+    #[clap(
+        long = "configmap-key-pattern",
+        env = "PINN_CONFIGMAP_KEY_PATTERN",
+        value_parser
+    )]
+    pub(crate) configmap_key_pattern: Option<String>,
+
+    /// Proceed with the `ConfigMap` keys that could be parsed
+    /// when others can't, rather than aborting the run
+    #[clap(
+        long = "keep-going",
+        env = "PINN_KEEP_GOING",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) keep_going: bool,
+
+    /// What pinnothera should do if the `ConfigMap` doesn't
+    /// exist: `skip` treats the namespace as not onboarded
+    /// (having no queues or topics), `warn` logs a warning
+    /// and exits successfully, and `error` fails the run
+    #[clap(long = "missing-source", env = "PINN_MISSING_SOURCE", default_value = "error", value_parser = ["skip", "warn", "error"])]
+    pub(crate) missing_source: String,
+
+    /// Never communicate with a Kubernetes cluster (i.e. in a namespace
+    /// without `ConfigMap` read access), reading the configuration from
+    /// a file or `--*-data` instead and the environment from `--env-name`
+    #[clap(
+        long = "no-kube",
+        env = "PINN_NO_KUBE",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) no_kube: bool,
+
     /// Name of the name of the `kubectl` "context"
     /// pinnothera should use when communicating with
     /// the target cluster
-    #[clap(short = 'c', long = "kube-context", value_parser)]
+    #[clap(
+        short = 'c',
+        long = "kube-context",
+        env = "PINN_KUBE_CONTEXT",
+        value_parser
+    )]
     pub(crate) kube_context: Option<String>,
 
     /// Name of the name of the "environment" the target
     /// cluster is running in (i.e. 'dev' or 'production')
-    #[clap(short = 'e', long = "env-name", value_parser)]
+    #[clap(short = 'e', long = "env-name", env = "PINN_ENV_NAME", value_parser)]
     pub(crate) env_name: Option<String>,
 
+    /// Key of the annotation on the target `Namespace` object
+    /// the name of the "environment" should be read from when
+    /// neither `--env-name` nor the `ConfigMap` supply one
+    #[clap(
+        long = "env-from-namespace-annotation",
+        env = "PINN_ENV_FROM_NAMESPACE_ANNOTATION",
+        conflicts_with = "env-from-namespace-label",
+        value_parser
+    )]
+    pub(crate) env_from_namespace_annotation: Option<String>,
+
+    /// Key of the label on the target `Namespace` object
+    /// the name of the "environment" should be read from when
+    /// neither `--env-name` nor the `ConfigMap` supply one
+    #[clap(
+        long = "env-from-namespace-label",
+        env = "PINN_ENV_FROM_NAMESPACE_LABEL",
+        value_parser
+    )]
+    pub(crate) env_from_namespace_label: Option<String>,
+
+    /// What pinnothera should do when `--env-name` and an
+    /// annotation (or label) name different environments:
+    /// `prefer-cli` uses `--env-name`, `prefer-annotation`
+    /// uses the annotation, and `error` fails the run before
+    /// any AWS calls are made
+    #[clap(long = "env-conflict", env = "PINN_ENV_CONFLICT", default_value = "prefer-cli", value_parser = ["error", "prefer-cli", "prefer-annotation"])]
+    pub(crate) env_conflict: String,
+
     // </editor-fold desc="// Kubernetes-related Settings ...">
 
     // <editor-fold desc="// AWS-related Settings ...">
     /// The AWS region name pinnothera should use
     /// to communicate with SNS/SQS services
-    #[clap(long = "aws-region", value_parser)]
+    #[clap(long = "aws-region", env = "PINN_AWS_REGION", value_parser)]
     pub(crate) aws_region: Option<String>,
 
+    /// The named AWS profile (from `~/.aws/config` or
+    /// `~/.aws/credentials`) pinnothera should source
+    /// its region and credentials from, including SSO
+    /// and `credential_process`-backed profiles
+    #[clap(long = "aws-profile", env = "PINN_AWS_PROFILE", value_parser)]
+    pub(crate) aws_profile: Option<String>,
+
     /// The "endpoint" that pinnothera should use
     /// to communicate with AWS SNS/SQS services
-    #[clap(long = "aws-endpoint", value_parser)]
+    /// (path prefixes like `https://gateway/aws/sqs`
+    /// are preserved, query strings are rejected)
+    #[clap(long = "aws-endpoint", env = "PINN_AWS_ENDPOINT", value_parser = parse_aws_endpoint)]
     pub(crate) aws_endpoint: Option<String>,
 
-    /// The Role ARN that pinnothera should use
-    /// to communicate with AWS SNS/SQS services
-    #[clap(long = "aws-account-id", value_parser)]
+    /// The "endpoint" that pinnothera should use to communicate
+    /// with AWS SNS (overriding `--aws-endpoint` for SNS alone)
+    #[clap(long = "sns-endpoint", env = "PINN_SNS_ENDPOINT", value_parser = parse_aws_endpoint)]
+    pub(crate) sns_endpoint: Option<String>,
+
+    /// The "endpoint" that pinnothera should use to communicate
+    /// with AWS SQS (overriding `--aws-endpoint` for SQS alone)
+    #[clap(long = "sqs-endpoint", env = "PINN_SQS_ENDPOINT", value_parser = parse_aws_endpoint)]
+    pub(crate) sqs_endpoint: Option<String>,
+
+    /// The "endpoint" that pinnothera should use to communicate with
+    /// AWS STS (overriding `--aws-endpoint` for STS alone), which it
+    /// uses to resolve the AWS account id and assume roles
+    #[clap(long = "sts-endpoint", env = "PINN_STS_ENDPOINT", value_parser = parse_aws_endpoint)]
+    pub(crate) sts_endpoint: Option<String>,
+
+    /// The "endpoint" that pinnothera should use to communicate with
+    /// LocalStack in the `local` environment. Endpoints are resolved as:
+    /// `--aws-endpoint` (if supplied), then this endpoint (if the
+    /// environment is `local`), then the default AWS endpoints
+    #[clap(
+        long = "localstack-endpoint",
+        env = "PINN_LOCALSTACK_ENDPOINT",
+        default_value = DEFAULT_LOCALSTACK_ENDPOINT,
+        value_parser = parse_aws_endpoint
+    )]
+    pub(crate) localstack_endpoint: String,
+
+    /// The AWS account id pinnothera should use in queue
+    /// access policies and topic ARNs (instead of resolving
+    /// it via STS)
+    #[clap(long = "aws-account-id", env = "PINN_AWS_ACCOUNT_ID", value_parser)]
     pub(crate) aws_account_id: Option<String>,
 
+    /// Don't resolve the AWS account id (when it isn't supplied)
+    /// from the identity of the configured credentials via STS,
+    /// i.e. in environments where STS is unreachable
+    #[clap(
+        long = "no-sts",
+        env = "PINN_NO_STS",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) no_sts: bool,
+
     /// The Secret Key ID that pinnothera should use
     /// to communicate with AWS SNS/SQS services
-    #[clap(long = "aws-access-key-id", value_parser)]
-    pub(crate) aws_access_key_id: Option<String>,
+    #[clap(long = "aws-access-key-id", env = "AWS_ACCESS_KEY_ID", hide_env_values = true, value_parser = parse_secret)]
+    pub(crate) aws_access_key_id: Option<Secret>,
 
     /// The Secret Access Key that pinnothera should use
     /// to communicate with AWS SNS/SQS services
-    #[clap(long = "aws-secret-access-key", value_parser)]
-    pub(crate) aws_secret_access_key: Option<String>,
+    #[clap(long = "aws-secret-access-key", env = "AWS_SECRET_ACCESS_KEY", hide_env_values = true, value_parser = parse_secret)]
+    pub(crate) aws_secret_access_key: Option<Secret>,
+
+    /// The session token of the (temporary) credentials given
+    /// by `--aws-access-key-id` and `--aws-secret-access-key`
+    #[clap(long = "aws-session-token", env = "AWS_SESSION_TOKEN", hide_env_values = true, value_parser = parse_secret)]
+    pub(crate) aws_session_token: Option<Secret>,
+
+    /// The ARN of an IAM role pinnothera should assume (using
+    /// the otherwise configured credentials) to communicate with
+    /// AWS SNS/SQS services
+    #[clap(long = "aws-role-arn", env = "PINN_AWS_ROLE_ARN", value_parser)]
+    pub(crate) aws_role_arn: Option<String>,
+
+    /// The session name pinnothera should use
+    /// when assuming the `--aws-role-arn` role
+    #[clap(
+        long = "aws-role-session-name",
+        env = "PINN_AWS_ROLE_SESSION_NAME",
+        default_value = "pinnothera",
+        value_parser
+    )]
+    pub(crate) aws_role_session_name: String,
+
+    /// The minimum TLS version pinnothera should
+    /// accept for connections to AWS SNS/SQS/STS
+    #[clap(long = "min-tls-version", env = "PINN_MIN_TLS_VERSION", value_parser = ["1.2", "1.3"])]
+    pub(crate) min_tls_version: Option<String>,
+
+    /// Name(s) of the only cipher suites pinnothera should offer
+    /// for connections to AWS SNS/SQS/STS (i.e. a compliance
+    /// baseline's restricted list), as rustls names them
+    /// (i.e. 'TLS13_AES_256_GCM_SHA384')
+    #[clap(
+        long = "tls-cipher-suite",
+        env = "PINN_TLS_CIPHER_SUITE",
+        use_value_delimiter = true,
+        value_delimiter = ',',
+        value_parser
+    )]
+    pub(crate) tls_cipher_suites: Vec<String>,
 
     // </editor-fold desc="// AWS-related Settings ...">
 
     // <editor-fold desc="// Raw Config Data Settings ...">
     /// JSON-serialized string containing the SNS/SQS
     /// configuration pinnothera should apply
-    #[clap(long = "json-data", value_parser)]
+    #[clap(long = "json-data", env = "PINN_JSON_DATA", value_parser)]
     pub(crate) json_data: Option<String>,
 
     /// YAML-serialized string containing the SNS/SQS
     /// configuration pinnothera should apply
-    #[clap(long = "yaml-data", value_parser)]
+    #[clap(long = "yaml-data", env = "PINN_YAML_DATA", value_parser)]
     pub(crate) yaml_data: Option<String>,
 
+    /// TOML-serialized string containing the SNS/SQS
+    /// configuration pinnothera should apply
+    #[clap(long = "toml-data", env = "PINN_TOML_DATA", value_parser)]
+    pub(crate) toml_data: Option<String>,
+
+    /// Absolute or relative on-disk path to a JSON file used (instead
+    /// of the built-in one) as the template of every queue's access
+    /// policy, with `{region}`, `{account_id}`, `{queue}`, and
+    /// `{suffix}` placeholders
+    #[clap(long = "policy-template", env = "PINN_POLICY_TEMPLATE", value_parser)]
+    pub(crate) policy_template: Option<PathBuf>,
+
     /// Absolute or relative on-disk path to a file
     /// containing JSON-serialized SNS/SQS configuration
     /// data that pinnothera should apply
-    #[clap(long = "json-file", value_parser)]
+    #[clap(long = "json-file", env = "PINN_JSON_FILE", value_parser)]
     pub(crate) json_file: Option<PathBuf>,
 
     /// Absolute or relative on-disk path to a file
     /// containing YAML-serialized SNS/SQS configuration
     /// data that pinnothera should apply
-    #[clap(long = "yaml-file", value_parser)]
+    #[clap(long = "yaml-file", env = "PINN_YAML_FILE", value_parser)]
     pub(crate) yaml_file: Option<PathBuf>,
+
+    /// Absolute or relative on-disk path to a file
+    /// containing TOML-serialized SNS/SQS configuration
+    /// data that pinnothera should apply
+    #[clap(long = "toml-file", env = "PINN_TOML_FILE", value_parser)]
+    pub(crate) toml_file: Option<PathBuf>,
+
+    /// Substitute `${NAME}` and `${NAME:-default}` placeholders
+    /// in the raw configuration data with values from the process
+    /// environment (or `--var`) before parsing it
+    #[clap(
+        long = "interpolate-env",
+        env = "PINN_INTERPOLATE_ENV",
+        default_value_t = false,
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub(crate) interpolate_env: bool,
+
+    /// A `NAME=VALUE` pair to substitute for `${NAME}` placeholders
+    /// (taking precedence over the process environment) when
+    /// `--interpolate-env` is set. Values are substituted verbatim and
+    /// may well contain commas, so `PINN_VAR` carries a single pair
+    /// (set the variables themselves in the environment for more)
+    #[clap(long = "var", env = "PINN_VAR", value_parser = parse_interpolation_var)]
+    pub(crate) vars: Vec<(String, String)>,
     // </editor-fold desc="// Raw Config Data Settings ...">
 }
 
-struct CLICredentialProvider {
-    access_key_id: String,
-    secret_access_key: String,
+fn parse_secret(value: &str) -> Result<Secret, String> {
+    Ok(Secret(value.to_string()))
 }
 
-impl std::fmt::Debug for CLICredentialProvider {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "CLICredentialProvider(access_key_id: {}, secret_access_key: {},",
-            self.access_key_id.as_str(),
-            self.secret_access_key.as_str()
-        )
+fn parse_sanitize_replacement(value: &str) -> Result<String, String> {
+    match naming::is_aws_safe_name(value) {
+        true => Ok(value.to_string()),
+        false => Err(format!(
+            "'{}' contains characters AWS does not allow in topic or queue names",
+            value
+        )),
     }
 }
 
-impl CLICredentialProvider {
-    async fn aws_credentials(&self) -> aws_types::credentials::Result {
-        Ok(AWSCredentials::new(
-            self.access_key_id.as_str(),
-            self.secret_access_key.as_str(),
-            None,
-            None,
-            "Pinnothera CLI arguments",
-        ))
-    }
-}
+fn parse_env_delimiter(value: &str) -> Result<char, String> {
+    let mut characters = value.chars();
 
-impl AWSCredentialProvider for CLICredentialProvider {
-    fn provide_credentials<'a>(&'a self) -> ProvideAWSCredentials<'a>
-    where
-        Self: 'a,
-    {
-        ProvideAWSCredentials::new(self.aws_credentials())
+    match (characters.next(), characters.next()) {
+        (Some(character), None)
+            if naming::is_aws_safe_char(character)
+                || naming::SUBSTITUTED_ENV_DELIMITERS.contains(&character) =>
+        {
+            Ok(character)
+        }
+        (Some(_), None) => Err(format!(
+            "'{}' is not allowed in topic or queue names by AWS (use '-', '_', or '.')",
+            value
+        )),
+        _ => Err(format!("'{}' is not a single character", value)),
     }
 }
 
-impl TryFrom<&CLIArgs> for CLICredentialProvider {
-    type Error = AWSCredentialsError;
+fn parse_aws_endpoint(value: &str) -> Result<String, String> {
+    let uri = http::Uri::from_str(value)
+        .map_err(|error| format!("'{}' is not a valid URL: {}", value, error))?;
 
-    fn try_from(args: &CLIArgs) -> Result<Self, Self::Error> {
-        if !args.aws_access_key_id.is_some() {
-            Err(AWSCredentialsError::provider_error(
-                "Missing or empty access key id!",
+    let authority = match uri.authority() {
+        Some(authority) => authority,
+        None => {
+            return Err(format!(
+                "'{}' must include a scheme and host (i.e. 'https://sqs.us-east-1.amazonaws.com')",
+                value
             ))
-        } else if !args.aws_secret_access_key.is_some() {
-            Err(AWSCredentialsError::provider_error(
-                "Missing or empty secret access key!",
+        }
+    };
+
+    // LocalStack (i.e. `localhost:4566`) is almost always plain HTTP
+    let suggested: String = match authority.port_u16() == Some(4566)
+        || ["localhost", "127.0.0.1", "localstack"].contains(&authority.host())
+    {
+        true => format!("http://{}", authority),
+        false => format!("https://{}", authority),
+    };
+
+    match uri.scheme_str().map(str::to_ascii_lowercase).as_deref() {
+        Some("http" | "https") => (),
+        Some(_) => {
+            return Err(format!(
+                "'{}' must be an http:// or https:// URL (i.e. '{}')",
+                value, suggested
+            ))
+        }
+        None => {
+            return Err(format!(
+                "'{}' must include a scheme (i.e. '{}')",
+                value, suggested
             ))
-        } else {
-            Ok(CLICredentialProvider {
-                access_key_id: args.aws_access_key_id.as_ref().unwrap().clone(),
-                secret_access_key: args.aws_secret_access_key.as_ref().unwrap().clone(),
-            })
         }
     }
+
+    match uri.query() {
+        Some(query) => Err(format!(
+            "'{}' includes a query string ('?{}'), which AWS endpoints do not support",
+            value, query
+        )),
+        None => Ok(value.to_string()),
+    }
 }
 
-impl CLIArgs {
-    // <editor-fold desc="// AWS Configuration Utilities ...">
-    pub async fn aws_client_configs(
-        &self,
-    ) -> Result<(SNSClientConfig, SQSClientConfig, STSClientConfig), Terminator> {
-        // Infer and create an AWS `Config` from the current environment
-        let config: AWSConfig = aws_config::load_from_env().await;
+/// The endpoint pinnothera should use in the supplied environment: the
+/// explicit (`--aws-endpoint`) one if supplied, otherwise the LocalStack
+/// one if the environment is `local`, otherwise none (the AWS defaults)
+pub(crate) fn select_endpoint<'a>(
+    explicit: Option<&'a str>,
+    localstack: &'a str,
+    env: EnvName,
+) -> Option<&'a str> {
+    match (explicit, env.is_local()) {
+        (Some(url), _) => Some(url),
+        (None, true) => Some(localstack),
+        (None, false) => None,
+    }
+}
 
-        let (sns_config, sqs_config, sts_config) = (
-            aws_sdk_sns::config::Builder::from(&config),
-            aws_sdk_sqs::config::Builder::from(&config),
-            aws_sdk_sts::config::Builder::from(&config),
-        );
+/// The contents of the supplied configuration file
+async fn read_config_file(path: &PathBuf) -> Result<String, PinnError> {
+    tokio::fs::read_to_string(path).await.map_err(|error| {
+        PinnError::config_caused_by(format!("Could not read '{}'", path.display()), error)
+    })
+}
 
-        let (mut sns_config, mut sqs_config, mut sts_config) = match &self.aws_region {
-            Some(region) => (
-                sns_config.region(Region::new(region.to_string())),
-                sqs_config.region(Region::new(region.to_string())),
-                sts_config.region(Region::new(region.to_string())),
-            ),
-            None => (sns_config, sqs_config, sts_config),
-        };
+fn parse_interpolation_var(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("'{}' is not a `NAME=VALUE` pair", value)),
+    }
+}
 
-        let endpoint = if let Some(url) = &self.aws_endpoint {
-            Some(url.as_str())
-        } else if CLUSTER_ENV
-            .get()
-            .unwrap_or(&AtomicCell::new(EnvName::Unknown))
-            .borrow()
-            .is_local()
-        {
-            Some("http://aws.localstack")
-        } else {
-            None
+fn parse_tag(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("'{}' is not a `KEY=VALUE` pair", value)),
+    }
+}
+
+/// Operations pinnothera can perform instead of applying its configuration
+#[derive(Subcommand, Clone, Debug)]
+pub(crate) enum PinnCommand {
+    /// Purge (empty, but don't delete) the configured queues
+    Purge(PurgeArgs),
+    /// Publish a test message to each of a configured queue's
+    /// topics and report whether (and how quickly) it arrives
+    SmokeTest(SmokeTestArgs),
+}
+
+#[derive(Args, Clone, Debug)]
+pub(crate) struct PurgeArgs {
+    /// Glob pattern(s) (i.e. 'orders-*') selecting which of the
+    /// configured queues should be purged (defaults to all of them)
+    #[clap(short = 'q', long = "queue", value_parser)]
+    pub(crate) queues: Vec<String>,
+
+    /// Skip the interactive confirmation prompt and
+    /// purge the selected queues immediately
+    #[clap(short = 'y', long = "yes", default_value_t = false, value_parser)]
+    pub(crate) yes: bool,
+}
+
+impl PurgeArgs {
+    /// Determine whether the supplied queue was selected by the
+    /// `--queue` filters (matched against its configured name)
+    pub fn selects<T: AsRef<str>>(&self, queue: T) -> bool {
+        self.queues.is_empty()
+            || self
+                .queues
+                .iter()
+                .any(|pattern| naming::glob_matches(pattern, queue.as_ref()))
+    }
+}
+
+#[derive(Args, Clone, Debug)]
+pub(crate) struct SmokeTestArgs {
+    /// The (configured) name of the queue to smoke test
+    #[clap(value_parser, required_unless_present = "all")]
+    pub(crate) queue: Option<String>,
+
+    /// Smoke test every configured queue
+    #[clap(
+        long = "all",
+        default_value_t = false,
+        value_parser,
+        conflicts_with = "queue"
+    )]
+    pub(crate) all: bool,
+
+    /// How long to wait for each queue's test messages to arrive
+    #[clap(long = "timeout-secs", default_value_t = 60, value_parser)]
+    pub(crate) timeout_secs: u64,
+
+    /// Publish test messages in production environments too
+    #[clap(long = "force", default_value_t = false, value_parser)]
+    pub(crate) force: bool,
+}
+
+impl SmokeTestArgs {
+    /// Determine whether the supplied (configured) queue was selected
+    pub fn selects<T: AsRef<str>>(&self, queue: T) -> bool {
+        self.all || self.queue.as_deref() == Some(queue.as_ref())
+    }
+}
+
+/// The AWS services pinnothera communicates with
+#[derive(Eq, Copy, Clone, Debug, Hash, PartialEq)]
+pub(crate) enum AWSService {
+    Sns,
+    Sqs,
+    Sts,
+}
+
+impl AWSService {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AWSService::Sns => "SNS",
+            AWSService::Sqs => "SQS",
+            AWSService::Sts => "STS",
+        }
+    }
+}
+
+/// What secrets are rendered as in `Debug` output
+const REDACTED: &str = "***REDACTED***";
+
+/// A credential supplied on the command line, which is
+/// never rendered in `Debug` output (i.e. of `CLIArgs`)
+#[derive(Clone)]
+pub(crate) struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// The secret, redacted but for its last 4 characters (enough
+    /// to identify an access key id without disclosing it)
+    fn identifying_suffix(&self) -> String {
+        let start: usize = match self.0.char_indices().rev().nth(3) {
+            Some((index, _)) => index,
+            None => 0,
         };
 
-        if let Some(url) = endpoint {
-            let url = url.to_string();
-            sns_config.set_endpoint_resolver(Some(Arc::new(
-                aws_smithy_http::endpoint::Endpoint::immutable(
-                    http::Uri::from_str(url.as_str()).unwrap(),
+        format!("{}{}", REDACTED, &self.0[start..])
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+struct CLICredentialProvider {
+    access_key_id: Secret,
+    secret_access_key: Secret,
+    session_token: Option<Secret>,
+}
+
+impl std::fmt::Debug for CLICredentialProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "CLICredentialProvider(access_key_id: {}, secret_access_key: {:?}, session_token: {:?})",
+            self.access_key_id.identifying_suffix(),
+            &self.secret_access_key,
+            &self.session_token
+        )
+    }
+}
+
+impl CLICredentialProvider {
+    async fn aws_credentials(&self) -> aws_types::credentials::Result {
+        Ok(AWSCredentials::new(
+            self.access_key_id.expose(),
+            self.secret_access_key.expose(),
+            self.session_token
+                .as_ref()
+                .map(|token| token.expose().to_string()),
+            None,
+            "Pinnothera CLI arguments",
+        ))
+    }
+}
+
+impl AWSCredentialProvider for CLICredentialProvider {
+    fn provide_credentials<'a>(&'a self) -> ProvideAWSCredentials<'a>
+    where
+        Self: 'a,
+    {
+        ProvideAWSCredentials::new(self.aws_credentials())
+    }
+}
+
+impl TryFrom<&CLIArgs> for CLICredentialProvider {
+    type Error = AWSCredentialsError;
+
+    fn try_from(args: &CLIArgs) -> Result<Self, Self::Error> {
+        match (&args.aws_access_key_id, &args.aws_secret_access_key) {
+            (None, _) => Err(AWSCredentialsError::provider_error(
+                "Missing or empty access key id!",
+            )),
+            (_, None) => Err(AWSCredentialsError::provider_error(
+                "Missing or empty secret access key!",
+            )),
+            (Some(access_key_id), Some(secret_access_key)) => Ok(CLICredentialProvider {
+                access_key_id: access_key_id.clone(),
+                secret_access_key: secret_access_key.clone(),
+                session_token: args.aws_session_token.clone(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AssumedRoleCredentialProvider {
+    role_arn: String,
+    session_name: String,
+    sts_client: STSClient,
+}
+
+impl AssumedRoleCredentialProvider {
+    async fn aws_credentials(&self) -> aws_types::credentials::Result {
+        let response = self
+            .sts_client
+            .assume_role()
+            .role_arn(&self.role_arn)
+            .role_session_name(&self.session_name)
+            .send()
+            .await
+            .map_err(AWSCredentialsError::provider_error)?;
+
+        let credentials = match response.credentials() {
+            Some(value) => value,
+            None => {
+                return Err(AWSCredentialsError::provider_error(
+                    "AssumeRole did not return any credentials!",
+                ))
+            }
+        };
+
+        match (credentials.access_key_id(), credentials.secret_access_key()) {
+            (Some(access_key_id), Some(secret_access_key)) => Ok(AWSCredentials::new(
+                access_key_id,
+                secret_access_key,
+                credentials.session_token().map(str::to_string),
+                credentials
+                    .expiration()
+                    .and_then(|expiration| SystemTime::try_from(*expiration).ok()),
+                "Pinnothera assumed role",
+            )),
+            _ => Err(AWSCredentialsError::provider_error(
+                "AssumeRole returned incomplete credentials!",
+            )),
+        }
+    }
+}
+
+impl AWSCredentialProvider for AssumedRoleCredentialProvider {
+    fn provide_credentials<'a>(&'a self) -> ProvideAWSCredentials<'a>
+    where
+        Self: 'a,
+    {
+        ProvideAWSCredentials::new(self.aws_credentials())
+    }
+}
+
+struct ProfileCredentialProvider {
+    profile_name: String,
+    uses_sso: bool,
+    chain: DefaultCredentialsChain,
+}
+
+impl std::fmt::Debug for ProfileCredentialProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ProfileCredentialProvider(profile_name: {}, uses_sso: {})",
+            self.profile_name.as_str(),
+            self.uses_sso
+        )
+    }
+}
+
+impl ProfileCredentialProvider {
+    async fn new<T: AsRef<str>>(profile_name: T) -> Result<Self, Terminator> {
+        let profile_name: &str = profile_name.as_ref();
+        let profiles = aws_config::profile::load(&AWSFs::real(), &AWSEnv::real()).await?;
+
+        if profiles.get_profile(profile_name).is_none() {
+            return Err(PinnError::UnknownProfile {
+                profile: profile_name.to_string(),
+                known: profiles.profiles().map(str::to_string).sorted().collect(),
+                searched: Self::profile_file_paths(),
+            }
+            .into());
+        }
+
+        Ok(ProfileCredentialProvider {
+            profile_name: profile_name.to_string(),
+            uses_sso: Self::profile_uses_sso(&profiles, profile_name),
+            chain: DefaultCredentialsChain::builder()
+                .profile_name(profile_name)
+                .build()
+                .await,
+        })
+    }
+
+    /// The config and credentials files AWS profiles are loaded
+    /// from, honoring the same environment variables the SDK does
+    fn profile_file_paths() -> Vec<String> {
+        let home: Option<String> = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .ok();
+
+        [
+            ("AWS_CONFIG_FILE", "config"),
+            ("AWS_SHARED_CREDENTIALS_FILE", "credentials"),
+        ]
+        .iter()
+        .map(|(variable, file)| match (std::env::var(variable), &home) {
+            (Ok(path), _) => path,
+            (Err(_), Some(home)) => format!("{}/.aws/{}", home.trim_end_matches('/'), file),
+            (Err(_), None) => format!("~/.aws/{}", file),
+        })
+        .collect()
+    }
+
+    /// Determine whether the named profile (or any profile
+    /// it sources its credentials from) is backed by AWS SSO
+    fn profile_uses_sso(profiles: &AWSProfileSet, profile_name: &str) -> bool {
+        let mut visited: Vec<&str> = Vec::new();
+        let mut current = profiles.get_profile(profile_name);
+
+        while let Some(profile) = current {
+            if profile.get("sso_start_url").is_some() {
+                return true;
+            } else if visited.contains(&profile.name()) {
+                break;
+            }
+
+            visited.push(profile.name());
+            current = profile
+                .get("source_profile")
+                .and_then(|source| profiles.get_profile(source));
+        }
+
+        false
+    }
+
+    async fn aws_credentials(&self) -> aws_types::credentials::Result {
+        match self.chain.provide_credentials().await {
+            Ok(credentials) => Ok(credentials),
+            Err(error) if self.uses_sso => Err(AWSCredentialsError::provider_error(format!(
+                "Could not load SSO credentials for AWS profile '{}', the SSO session may be \
+                 expired or missing (run `aws sso login --profile {}`): {}",
+                self.profile_name.as_str(),
+                self.profile_name.as_str(),
+                error,
+            ))),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl AWSCredentialProvider for ProfileCredentialProvider {
+    fn provide_credentials<'a>(&'a self) -> ProvideAWSCredentials<'a>
+    where
+        Self: 'a,
+    {
+        ProvideAWSCredentials::new(self.aws_credentials())
+    }
+}
+
+impl CLIArgs {
+    // <editor-fold desc="// AWS Configuration Utilities ...">
+
+    /// The custom endpoint (if any) pinnothera should use to
+    /// communicate with AWS SNS/SQS services in the supplied environment
+    pub fn aws_endpoint_url(&self, env: EnvName) -> Option<&str> {
+        select_endpoint(self.aws_endpoint.as_deref(), &self.localstack_endpoint, env)
+    }
+
+    /// The custom endpoint (if any) pinnothera should use to communicate
+    /// with the supplied AWS service in the supplied environment, its
+    /// own flag (i.e. `--sqs-endpoint`) taking precedence over the
+    /// shared one (or default)
+    pub fn service_endpoint_url(&self, service: AWSService, env: EnvName) -> Option<&str> {
+        let specific: &Option<String> = match service {
+            AWSService::Sns => &self.sns_endpoint,
+            AWSService::Sqs => &self.sqs_endpoint,
+            AWSService::Sts => &self.sts_endpoint,
+        };
+
+        specific.as_deref().or_else(|| self.aws_endpoint_url(env))
+    }
+
+    /// The TLS configuration AWS clients should use if `--min-tls-version`
+    /// or `--tls-cipher-suite` was specified (otherwise the SDK's default
+    /// connector, and its configuration, is used)
+    pub(crate) fn aws_tls_config(&self) -> Result<Option<rustls::ClientConfig>, Terminator> {
+        let versions: Vec<ProtocolVersion> = match self.min_tls_version.as_deref() {
+            None if self.tls_cipher_suites.is_empty() => return Ok(None),
+            Some("1.3") => vec![ProtocolVersion::TLSv1_3],
+            _ => vec![ProtocolVersion::TLSv1_3, ProtocolVersion::TLSv1_2],
+        };
+
+        let suite_name = |suite: &rustls::SupportedCipherSuite| format!("{:?}", suite.suite);
+
+        if let Some(unknown) = self.tls_cipher_suites.iter().find(|name| {
+            !rustls::ALL_CIPHERSUITES
+                .iter()
+                .any(|suite| &suite_name(suite) == *name)
+        }) {
+            bail!(
+                "Unknown TLS cipher suite \"{}\" (supported: {})",
+                unknown,
+                rustls::ALL_CIPHERSUITES
+                    .iter()
+                    .map(|suite| suite_name(suite))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+        }
+
+        let mut tls_config = rustls::ClientConfig::new();
+        tls_config.ciphersuites.retain(|suite| {
+            versions
+                .iter()
+                .any(|version| suite.usable_for_version(*version))
+                && (self.tls_cipher_suites.is_empty()
+                    || self.tls_cipher_suites.contains(&suite_name(suite)))
+        });
+
+        if tls_config.ciphersuites.is_empty() {
+            bail!(
+                "None of the TLS cipher suites {:?} can be used with TLS {}+",
+                &self.tls_cipher_suites,
+                self.min_tls_version.as_deref().unwrap_or("1.2")
+            );
+        }
+
+        tls_config.versions = versions;
+        tls_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        tls_config.root_store = match rustls_native_certs::load_native_certs() {
+            Ok(store) => store,
+            Err((Some(store), _)) => store,
+            Err((None, error)) => bail!(
+                "Cannot restrict TLS connections without the system's root certificates: {}",
+                error
+            ),
+        };
+
+        Ok(Some(tls_config))
+    }
+
+    /// The HTTPS connector AWS clients should use if `--min-tls-version`
+    /// or `--tls-cipher-suite` was specified (otherwise the SDK's
+    /// default connector is used)
+    fn aws_connector(&self, env: EnvName) -> Result<Option<DynConnector>, Terminator> {
+        let tls_config: rustls::ClientConfig = match self.aws_tls_config()? {
+            None => return Ok(None),
+            Some(tls_config) => tls_config,
+        };
+
+        for url in [AWSService::Sns, AWSService::Sqs, AWSService::Sts]
+            .iter()
+            .filter_map(|service| self.service_endpoint_url(*service, env))
+        {
+            if !url.to_lowercase().starts_with("https://") {
+                bail!(
+                    "Cannot restrict TLS connections (`--min-tls-version` or `--tls-cipher-suite`) to the non-HTTPS AWS endpoint \"{}\"",
+                    url
+                );
+            }
+        }
+
+        let mut http = hyper::client::HttpConnector::new();
+        http.enforce_http(false);
+
+        info!(
+            "Requiring TLS {}+ (cipher suites: {}) for AWS connections",
+            self.min_tls_version.as_deref().unwrap_or("1.2"),
+            tls_config
+                .ciphersuites
+                .iter()
+                .map(|suite| format!("{:?}", suite.suite))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+
+        Ok(Some(DynConnector::new(
+            aws_smithy_client::hyper_ext::Adapter::builder()
+                .build(hyper_rustls::HttpsConnector::from((http, tls_config))),
+        )))
+    }
+
+    pub async fn aws_clients(
+        &self,
+        env: EnvName,
+    ) -> Result<(SNSClient, SQSClient, STSClient), Terminator> {
+        self.aws_clients_as(env, None, None).await
+    }
+
+    /// The AWS region pinnothera should use in the supplied environment:
+    /// `--aws-region` if supplied, otherwise the region of `--aws-profile`
+    /// (or of the environment's default profile and variables), otherwise
+    /// the default for custom endpoints if one is in use
+    pub async fn effective_aws_region(&self, env: EnvName) -> Option<String> {
+        if let Some(region) = &self.aws_region {
+            return Some(region.to_string());
+        }
+
+        let chain: DefaultRegionChain = match &self.aws_profile {
+            Some(profile) => DefaultRegionChain::builder().profile_name(profile).build(),
+            None => DefaultRegionChain::builder().build(),
+        };
+
+        if let Some(region) = chain.region().await {
+            return Some(region.to_string());
+        }
+
+        [AWSService::Sns, AWSService::Sqs, AWSService::Sts]
+            .iter()
+            .any(|service| self.service_endpoint_url(*service, env).is_some())
+            .then(|| DEFAULT_CUSTOM_ENDPOINT_REGION.to_string())
+    }
+
+    /// An SNS client for a topic the run's own SNS client can't subscribe
+    /// to: one acting as the supplied role (i.e. one allowed to subscribe
+    /// to another account's topics), assumed with the run's own credentials
+    /// (those of `--aws-role-arn`, if specified), and/or one in the supplied
+    /// region (i.e. the region of a topic in another region than the queue)
+    pub async fn aws_topic_sns_client(
+        &self,
+        env: EnvName,
+        role_arn: Option<&str>,
+        region: Option<&str>,
+    ) -> Result<SNSClient, Terminator> {
+        Ok(self.aws_clients_as(env, role_arn, region).await?.0)
+    }
+
+    /// Where the SNS clients for topics the run's own SNS client can't
+    /// subscribe to come from (see `aws_topic_sns_client`)
+    pub fn topic_client_factory(&self, env: EnvName) -> TopicClientFactory {
+        let args: Arc<CLIArgs> = Arc::new(self.clone());
+
+        TopicClientFactory::new(move |role_arn: Option<String>, region: Option<String>| {
+            let args: Arc<CLIArgs> = args.clone();
+            async move {
+                args.aws_topic_sns_client(env, role_arn.as_deref(), region.as_deref())
+                    .await
+                    .map_err(|error| events::error_chain(&error).join(": "))
+            }
+        })
+    }
+
+    async fn aws_clients_as(
+        &self,
+        env: EnvName,
+        sns_role_arn: Option<&str>,
+        sns_region: Option<&str>,
+    ) -> Result<(SNSClient, SQSClient, STSClient), Terminator> {
+        // Clients made for a single topic's subscriptions
+        // don't repeat the run's own region and endpoint logging
+        let topic_client: bool = sns_role_arn.is_some() || sns_region.is_some();
+
+        // Infer and create an AWS `Config` from the current environment,
+        // or from the requested profile if one was specified
+        let config: AWSConfig = match &self.aws_profile {
+            None => aws_config::load_from_env().await,
+            Some(profile) => {
+                let credentials = ProfileCredentialProvider::new(profile).await?;
+
+                aws_config::from_env()
+                    .region(DefaultRegionChain::builder().profile_name(profile).build())
+                    .credentials_provider(credentials)
+                    .load()
+                    .await
+            }
+        };
+
+        let (sns_config, sqs_config, sts_config) = (
+            aws_sdk_sns::config::Builder::from(&config),
+            aws_sdk_sqs::config::Builder::from(&config),
+            aws_sdk_sts::config::Builder::from(&config),
+        );
+
+        let endpoints: [(AWSService, Option<&str>); 3] =
+            [AWSService::Sns, AWSService::Sqs, AWSService::Sts]
+                .map(|service| (service, self.service_endpoint_url(service, env)));
+
+        let endpoint: Option<&str> = endpoints.iter().find_map(|(_, url)| *url);
+
+        let region: Option<Region> = self.effective_aws_region(env).await.map(Region::new);
+
+        if let (None, None, Some(url), false) =
+            (&self.aws_region, config.region(), endpoint, topic_client)
+        {
+            info!(
+                "No AWS region configured for custom endpoint \"{}\", defaulting to \"{}\"",
+                url, DEFAULT_CUSTOM_ENDPOINT_REGION,
+            );
+        }
+
+        if let (Some(region), false) = (&region, topic_client) {
+            let shared: bool = endpoints.iter().all(|(_, url)| *url == endpoint);
+
+            for (service, url) in endpoints
+                .iter()
+                .filter_map(|(service, url)| url.map(|url| (service, url)))
+            {
+                match shared {
+                    true if *service != AWSService::Sns => continue,
+                    true => info!(
+                        "Using AWS region \"{}\" with custom endpoint \"{}\"",
+                        region, url
+                    ),
+                    false => info!(
+                        "Using AWS region \"{}\" with custom {} endpoint \"{}\"",
+                        region,
+                        service.as_str(),
+                        url
+                    ),
+                }
+
+                if let Some(reason) = regions::endpoint_mismatch(url, region.as_ref()) {
+                    warning!(
+                        "The custom endpoint \"{}\" may not serve the configured region: {}",
+                        url,
+                        reason
+                    );
+                }
+            }
+        }
+
+        let (mut sns_config, mut sqs_config, mut sts_config) = match region.clone() {
+            Some(region) => (
+                sns_config.region(region.clone()),
+                sqs_config.region(region.clone()),
+                sts_config.region(region),
+            ),
+            None => (sns_config, sqs_config, sts_config),
+        };
+
+        if let Some(sns_region) = sns_region {
+            sns_config = sns_config.region(Region::new(sns_region.to_string()));
+        }
+
+        // The SDK joins the endpoint's path (if any) with each
+        // request's own path, so a path prefix like `/aws/sqs`
+        // survives as long as the endpoint is passed through whole
+        let mut endpoint_uris: HashMap<AWSService, http::Uri> = HashMap::new();
+
+        for (service, url) in endpoints.iter() {
+            match url.map(http::Uri::from_str) {
+                Some(Ok(uri)) => {
+                    endpoint_uris.insert(*service, uri);
+                }
+                Some(Err(error)) => bail!(
+                    "Invalid AWS {} endpoint \"{}\": {}",
+                    service.as_str(),
+                    url.unwrap_or_default(),
+                    error
                 ),
+                None => (),
+            }
+        }
+
+        if let Some(uri) = endpoint_uris.get(&AWSService::Sns) {
+            sns_config.set_endpoint_resolver(Some(Arc::new(
+                aws_smithy_http::endpoint::Endpoint::immutable(uri.clone()),
             )));
+        }
+
+        if let Some(uri) = endpoint_uris.get(&AWSService::Sqs) {
             sqs_config.set_endpoint_resolver(Some(Arc::new(
-                aws_smithy_http::endpoint::Endpoint::immutable(
-                    http::Uri::from_str(url.as_str()).unwrap(),
-                ),
+                aws_smithy_http::endpoint::Endpoint::immutable(uri.clone()),
             )));
+        }
+
+        if let Some(uri) = endpoint_uris.get(&AWSService::Sts) {
             sts_config.set_endpoint_resolver(Some(Arc::new(
-                aws_smithy_http::endpoint::Endpoint::immutable(
-                    http::Uri::from_str(url.as_str()).unwrap(),
-                ),
+                aws_smithy_http::endpoint::Endpoint::immutable(uri.clone()),
             )));
         }
 
@@ -245,63 +1541,1111 @@ impl CLIArgs {
             )));
         }
 
-        Ok((sns_config.build(), sqs_config.build(), sts_config.build()))
+        let connector: Option<DynConnector> = self.aws_connector(env)?;
+
+        let sts_client: STSClient = match &connector {
+            Some(conn) => STSClient::from_conf_conn(sts_config.build(), conn.clone()),
+            None => STSClient::from_conf(sts_config.build()),
+        };
+
+        let role_provider: Option<SharedAWSCredentialsProvider> = match &self.aws_role_arn {
+            Some(role_arn) => Some(self.assumed_role_provider(role_arn, &sts_client).await?),
+            None => None,
+        };
+
+        if let Some(provider) = &role_provider {
+            sns_config.set_credentials_provider(Some(provider.clone()));
+            sqs_config.set_credentials_provider(Some(provider.clone()));
+        }
+
+        if let Some(sns_role_arn) = sns_role_arn {
+            // Assume the SNS client's role as the run's own role (if any),
+            // which requires an STS client using that role's credentials
+            let role_sts_client: STSClient = match role_provider {
+                None => sts_client.clone(),
+                Some(provider) => {
+                    let mut role_sts_config = aws_sdk_sts::config::Builder::from(&config)
+                        .region(region)
+                        .credentials_provider(provider);
+
+                    if let Some(uri) = endpoint_uris.remove(&AWSService::Sts) {
+                        role_sts_config.set_endpoint_resolver(Some(Arc::new(
+                            aws_smithy_http::endpoint::Endpoint::immutable(uri),
+                        )));
+                    }
+
+                    match &connector {
+                        Some(conn) => {
+                            STSClient::from_conf_conn(role_sts_config.build(), conn.clone())
+                        }
+                        None => STSClient::from_conf(role_sts_config.build()),
+                    }
+                }
+            };
+
+            sns_config.set_credentials_provider(Some(
+                self.assumed_role_provider(sns_role_arn, &role_sts_client)
+                    .await?,
+            ));
+        }
+
+        // SNS/SQS calls are retried by pinnothera itself (per `--max-retries`),
+        // so the SDK's own retries would only multiply the attempts made
+        sns_config.set_retry_config(Some(RetryConfig::disabled()));
+        sqs_config.set_retry_config(Some(RetryConfig::disabled()));
+
+        Ok(match connector {
+            Some(conn) => (
+                SNSClient::from_conf_conn(sns_config.build(), conn.clone()),
+                SQSClient::from_conf_conn(sqs_config.build(), conn),
+                sts_client,
+            ),
+            None => (
+                SNSClient::from_conf(sns_config.build()),
+                SQSClient::from_conf(sqs_config.build()),
+                sts_client,
+            ),
+        })
+    }
+
+    /// The AWS account id embedded in the supplied IAM role ARN
+    /// (i.e. `arn:aws:iam::123456789012:role/deploy`), if it is one
+    pub fn role_account_id<T: AsRef<str>>(role_arn: T) -> Option<String> {
+        let parts: Vec<&str> = role_arn.as_ref().splitn(6, ':').collect();
+
+        match parts.as_slice() {
+            ["arn", _, "iam", "", account_id, resource]
+                if account_id.len() == 12
+                    && account_id
+                        .chars()
+                        .all(|character| character.is_ascii_digit())
+                    && resource.starts_with("role/") =>
+            {
+                Some(account_id.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// A (cached and automatically refreshed) provider of credentials
+    /// for the supplied role, assumed via the supplied STS client, that
+    /// has already successfully assumed the role once
+    async fn assumed_role_provider(
+        &self,
+        role_arn: &str,
+        sts_client: &STSClient,
+    ) -> Result<SharedAWSCredentialsProvider, Terminator> {
+        if Self::role_account_id(role_arn).is_none() {
+            bail!(
+                "\"{}\" is not a valid IAM role ARN (expected \"arn:aws:iam::<account-id>:role/<role-name>\")",
+                role_arn
+            );
+        }
+
+        let provider = SharedAWSCredentialsProvider::new(
+            LazyCachingCredentialsProvider::builder()
+                .load(AssumedRoleCredentialProvider {
+                    role_arn: role_arn.to_string(),
+                    session_name: self.aws_role_session_name.clone(),
+                    sts_client: sts_client.clone(),
+                })
+                .build(),
+        );
+
+        // Assume the role up front, so that an unusable role
+        // fails the run before anything else is attempted
+        if let Err(error) = provider.provide_credentials().await {
+            bail!("Could not assume role \"{}\": {}", role_arn, error);
+        }
+
+        info!(
+            "Assumed role \"{}\" as session \"{}\"",
+            role_arn, &self.aws_role_session_name
+        );
+
+        Ok(provider)
     }
 
     // </editor-fold desc="// AWS Configuration Utilities ...">
 
     // <editor-fold desc="// Kubernetes Configuration Utilities ...">
 
-    async fn kube_config(&self) -> Result<kube::Config, Terminator> {
+    async fn kube_config(&self) -> Result<kube::Config, PinnError> {
         let options = kube::config::KubeConfigOptions {
             context: self.kube_context.clone(),
             cluster: None,
             user: None,
         };
 
-        let config =
-            kube::Config::from_custom_kubeconfig(kube::config::Kubeconfig::read()?, &options)
-                .await?;
+        let kubeconfig = kube::config::Kubeconfig::read()
+            .map_err(|error| PinnError::kube("read kubeconfig", error))?;
 
-        Ok(config)
+        kube::Config::from_custom_kubeconfig(kubeconfig, &options)
+            .await
+            .map_err(|error| PinnError::kube("load kubeconfig context", error))
     }
 
     // </editor-fold desc="// Kubernetes Configuration Utilities ...">
 
     // <editor-fold desc="// Pinnothera Configuration Utilities ...">
 
-    pub async fn pinn_config(&mut self) -> Result<(EnvName, PinnConfig), Terminator> {
-        if let Some(json_path) = &self.json_file {
-            self.json_data = Some(tokio::fs::read_to_string(json_path).await?);
-        } else if let Some(yaml_path) = &self.yaml_file {
-            self.yaml_data = Some(tokio::fs::read_to_string(yaml_path).await?);
+    /// Where on the target `Namespace` object the name of
+    /// the environment should be read from (if anywhere)
+    pub fn namespace_env_source(&self) -> Option<NamespaceEnvSource> {
+        match (
+            &self.env_from_namespace_annotation,
+            &self.env_from_namespace_label,
+        ) {
+            (Some(key), _) => Some(NamespaceEnvSource::Annotation(key.clone())),
+            (None, Some(key)) => Some(NamespaceEnvSource::Label(key.clone())),
+            (None, None) => None,
         }
+    }
+
+    /// The resolved settings of the current run, with
+    /// any secrets (i.e. credentials) omitted entirely
+    pub fn run_settings(
+        &self,
+        env: EnvName,
+        region: Option<&str>,
+        account_id: Option<&str>,
+    ) -> serde_json::Value {
+        serde_json::json!({
+            "env": env.as_suffix(),
+            "namespace": &self.namespace,
+            "configmap": &self.configmap_name,
+            "missing_source": &self.missing_source,
+            "env_conflict": &self.env_conflict,
+            "configmap_keys": &self.configmap_keys,
+            "configmap_key_pattern": &self.configmap_key_pattern,
+            "keep_going": self.keep_going,
+            "kube_context": &self.kube_context,
+            "no_kube": self.no_kube,
+            "aws_region": region,
+            "aws_profile": &self.aws_profile,
+            "aws_endpoint": self.aws_endpoint_url(env),
+            "sns_endpoint": self.service_endpoint_url(AWSService::Sns, env),
+            "sqs_endpoint": self.service_endpoint_url(AWSService::Sqs, env),
+            "sts_endpoint": self.service_endpoint_url(AWSService::Sts, env),
+            "localstack_endpoint": &self.localstack_endpoint,
+            "aws_account_id": account_id,
+            "aws_role_arn": &self.aws_role_arn,
+            "aws_role_session_name": &self.aws_role_session_name,
+            "min_tls_version": &self.min_tls_version,
+            "tls_cipher_suites": &self.tls_cipher_suites,
+            "log_level": &self.log_level,
+            "log_format": &self.log_format,
+            "output": &self.output,
+            "outputs_include_provenance": self.outputs_include_provenance,
+            "no_sts": self.no_sts,
+            "sanitize_replacement": self.sanitize_names.then_some(&self.sanitize_replacement),
+            "env_position": &self.env_position,
+            "env_delimiter": self.env_delimiter,
+            "name_hook": &self.name_hook,
+            "max_api_calls": self.max_api_calls,
+            "concurrency": self.concurrency,
+            "max_subscription_concurrency": self.max_subscription_concurrency,
+            "max_retries": self.max_retries,
+            "retry_base_delay_ms": self.retry_base_delay_ms,
+            "queue_recreate_wait_secs": self.queue_recreate_wait_secs,
+            "dry_run": self.dry_run,
+            "plan_file": &self.plan_file,
+            "check": self.check,
+            "explain": self.explain,
+            "pause_queues": &self.pause_queues,
+            "no_update_existing": self.no_update_existing,
+            "replace_policy": self.replace_policy,
+            "broad_policy": self.broad_policy,
+            "policy_template": &self.policy_template,
+            "guarded_envs": &self.guarded_envs,
+            "bootstrap": self.bootstrap,
+            "raw_message_delivery": self.raw_message_delivery,
+            "enforce_secure_transport": self.enforce_secure_transport,
+            "force_resubscribe": self.force_resubscribe,
+            "confirmation_timeout_secs": self.confirmation_timeout_secs,
+            "require_confirmed": self.require_confirmed,
+            "prune_subscriptions": self.prune_subscriptions,
+            "tags": self.tags.iter().cloned().collect::<BTreeMap<String, String>>(),
+            "tag_overflow": &self.tag_overflow,
+            "strict_tags": self.strict_tags,
+            "ignore_extra_tags": self.ignore_extra_tags,
+            "strict_filters": self.strict_filters,
+            "checkpoint_file": &self.checkpoint_file,
+            "control_socket": &self.control_socket,
+            "interpolate_env": self.interpolate_env,
+        })
+    }
+
+    /// Determine whether the supplied environment is guarded against
+    /// first-run resource creation, either as `prod` or via `--guarded-env`
+    pub fn is_guarded_env(&self, env: EnvName) -> bool {
+        env == EnvName::Prod
+            || (!env.is_unknown()
+                && self
+                    .guarded_envs
+                    .iter()
+                    .any(|guarded| EnvName::from(Some(guarded)) == env))
+    }
+
+    /// The variables `${NAME}` placeholders in the raw configuration
+    /// data should be substituted with (if interpolation is enabled)
+    pub fn interpolation_vars(&self) -> Option<BTreeMap<String, String>> {
+        match self.interpolate_env {
+            true => Some(std::env::vars().chain(self.vars.clone()).collect()),
+            false => None,
+        }
+    }
+
+    /// The access policy template supplied via `--policy-template` (if any),
+    /// read and checked for unknown placeholders and invalid JSON up front
+    pub fn policy_template(&self) -> Result<Option<String>, Terminator> {
+        let path: &PathBuf = match &self.policy_template {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let template: String = match std::fs::read_to_string(path) {
+            Ok(value) => value,
+            Err(error) => bail!("Could not read '{}': {}", path.display(), error),
+        };
+
+        match types::render_policy(&template, "us-east-1", "000000000000", "queue", "env") {
+            Ok(_) => Ok(Some(template)),
+            Err(error) => bail!("'{}' is unusable: {}", path.display(), error),
+        }
+    }
+
+    /// Whether the configuration is supplied directly (via a file
+    /// or `--*-data`), rather than read from the cluster
+    fn has_local_source(&self) -> bool {
+        self.json_file.is_some()
+            || self.yaml_file.is_some()
+            || self.toml_file.is_some()
+            || self.json_data.is_some()
+            || self.yaml_data.is_some()
+            || self.toml_data.is_some()
+    }
+
+    /// The Kubernetes-related flags that were specified,
+    /// despite `--no-kube` making them meaningless
+    fn ignored_kube_flags(&self) -> Vec<&'static str> {
+        [
+            ("--namespace", self.namespace.is_some()),
+            ("--kube-context", self.kube_context.is_some()),
+            ("--configmap-keys", !self.configmap_keys.is_empty()),
+            (
+                "--configmap-key-pattern",
+                self.configmap_key_pattern.is_some(),
+            ),
+            (
+                "--env-from-namespace-annotation",
+                self.env_from_namespace_annotation.is_some(),
+            ),
+            (
+                "--env-from-namespace-label",
+                self.env_from_namespace_label.is_some(),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, specified)| *specified)
+        .map(|(flag, _)| flag)
+        .collect()
+    }
+
+    /// The configuration pinnothera should apply (along with the environment
+    /// it should be applied in), or `None` if the namespace has no configuration
+    /// source and `--missing-source warn` was specified
+    pub async fn pinn_config(
+        &mut self,
+        run: &RunState,
+    ) -> Result<Option<(EnvName, PinnConfig)>, PinnError> {
+        if self.no_kube {
+            if !self.has_local_source() {
+                return Err(PinnError::config(
+                    "`--no-kube` requires the configuration to be supplied via `--json-file`, `--yaml-file`, `--toml-file`, or `--*-data` (there's no `ConfigMap` to read it from)",
+                ));
+            }
+
+            let ignored: Vec<&str> = self.ignored_kube_flags();
+
+            info!(
+                "Kubernetes access is disabled (`--no-kube`): the `ConfigMap` and `Namespace` won't be read, and the environment is taken from `--env-name` (or `PINN_ENV_NAME`) alone{}",
+                match ignored.is_empty() {
+                    true => String::new(),
+                    false => format!(", ignoring {}", ignored.join(", ")),
+                }
+            );
+        }
+
+        let source: String = if let Some(json_path) = &self.json_file {
+            self.json_data = Some(read_config_file(json_path).await?);
+            json_path.display().to_string()
+        } else if let Some(yaml_path) = &self.yaml_file {
+            self.yaml_data = Some(read_config_file(yaml_path).await?);
+            yaml_path.display().to_string()
+        } else if let Some(toml_path) = &self.toml_file {
+            self.toml_data = Some(read_config_file(toml_path).await?);
+            toml_path.display().to_string()
+        } else if self.json_data.is_some() {
+            "--json-data".to_string()
+        } else if self.yaml_data.is_some() {
+            "--yaml-data".to_string()
+        } else {
+            "--toml-data".to_string()
+        };
+
+        let variables = self.interpolation_vars();
 
         if let Some(json_data) = &self.json_data {
-            return Ok((
+            let json_data = match &variables {
+                Some(values) => interpolation::interpolate(json_data, &source, values)?,
+                None => json_data.to_string(),
+            };
+            return Ok(Some((
                 EnvName::from(self.env_name.clone()),
-                PinnConfig::from_json(json_data)?,
-            ));
+                PinnConfig::from_json(json_data)?.with_source(&source),
+            )));
         } else if let Some(yaml_data) = &self.yaml_data {
-            return Ok((
+            let yaml_data = match &variables {
+                Some(values) => interpolation::interpolate(yaml_data, &source, values)?,
+                None => yaml_data.to_string(),
+            };
+            return Ok(Some((
                 EnvName::from(self.env_name.clone()),
-                PinnConfig::from_yaml(yaml_data)?,
-            ));
+                PinnConfig::from_yaml(yaml_data)?.with_source(&source),
+            )));
+        } else if let Some(toml_data) = &self.toml_data {
+            let toml_data = match &variables {
+                Some(values) => interpolation::interpolate(toml_data, &source, values)?,
+                None => toml_data.to_string(),
+            };
+            return Ok(Some((
+                EnvName::from(self.env_name.clone()),
+                PinnConfig::from_toml(toml_data)?.with_source(&source),
+            )));
         }
 
         let client = match self.kube_context {
-            None => K8sClient::try_default().await?,
-            Some(_) => K8sClient::try_from(self.kube_config().await?)?,
+            None => K8sClient::try_default()
+                .await
+                .map_err(|error| PinnError::kube("create client", error))?,
+            Some(_) => K8sClient::try_from(self.kube_config().await?)
+                .map_err(|error| PinnError::kube("create client", error))?,
         };
 
         PinnConfig::from_cluster(
+            run,
             client,
             &self.env_name,
             &self.namespace,
             &self.configmap_name,
+            &ConfigMapOptions {
+                keys: self.configmap_keys.clone(),
+                key_pattern: self.configmap_key_pattern.clone(),
+                keep_going: self.keep_going,
+                missing_source: MissingSource::from(self.missing_source.as_str()),
+                env_conflict: EnvConflict::from(self.env_conflict.as_str()),
+            },
+            &self.namespace_env_source(),
+            &variables,
         )
         .await
     }
 
     // </editor-fold desc="// Pinnothera Configuration Utilities ...">
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use rustls::Session;
+
+    const ACCESS_KEY_ID: &str = "AKIAEXAMPLEKEY1234WXYZ";
+    const SECRET_ACCESS_KEY: &str = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY";
+
+    fn try_parse_args(args: &[&str]) -> Result<CLIArgs, clap::Error> {
+        CLIArgs::try_parse_from(std::iter::once("pinnothera").chain(args.iter().copied()))
+    }
+
+    fn parse_args(args: &[&str]) -> CLIArgs {
+        let _guard = testing::env_lock();
+        try_parse_args(args).unwrap()
+    }
+
+    fn credential_args() -> CLIArgs {
+        parse_args(&[
+            "--aws-access-key-id",
+            ACCESS_KEY_ID,
+            "--aws-secret-access-key",
+            SECRET_ACCESS_KEY,
+        ])
+    }
+
+    #[test]
+    fn secret_debug_is_redacted() {
+        let secret = Secret(SECRET_ACCESS_KEY.to_string());
+
+        assert_eq!(format!("{:?}", secret), REDACTED);
+        assert_eq!(format!("{:#?}", secret), REDACTED);
+        assert_eq!(secret.expose(), SECRET_ACCESS_KEY);
+    }
+
+    #[test]
+    fn identifying_suffix_keeps_last_four_characters() {
+        for (secret, expected) in [
+            (ACCESS_KEY_ID, "WXYZ"),
+            ("abcd", "abcd"),
+            ("ab", "ab"),
+            ("", ""),
+            ("ключ-ÄÖÜß", "ÄÖÜß"),
+        ] {
+            assert_eq!(
+                Secret(secret.to_string()).identifying_suffix(),
+                format!("{}{}", REDACTED, expected),
+            );
+        }
+    }
+
+    #[test]
+    fn cli_args_debug_omits_credentials() {
+        let args = credential_args();
+
+        for output in [format!("{:?}", args), format!("{:#?}", args)] {
+            assert!(!output.contains(ACCESS_KEY_ID), "{}", output);
+            assert!(!output.contains(SECRET_ACCESS_KEY), "{}", output);
+            assert!(output.contains(REDACTED), "{}", output);
+        }
+    }
+
+    #[test]
+    fn credential_provider_debug_shows_only_key_id_suffix() {
+        let provider = CLICredentialProvider::try_from(&credential_args()).unwrap();
+
+        for output in [format!("{:?}", provider), format!("{:#?}", provider)] {
+            assert!(!output.contains(ACCESS_KEY_ID), "{}", output);
+            assert!(!output.contains(SECRET_ACCESS_KEY), "{}", output);
+            assert!(output.contains(&format!("{}WXYZ", REDACTED)), "{}", output);
+        }
+    }
+
+    #[tokio::test]
+    async fn session_token_is_forwarded_to_credentials() {
+        let without_token = CLICredentialProvider::try_from(&credential_args()).unwrap();
+        let credentials = without_token.provide_credentials().await.unwrap();
+
+        assert_eq!(credentials.access_key_id(), ACCESS_KEY_ID);
+        assert_eq!(credentials.secret_access_key(), SECRET_ACCESS_KEY);
+        assert_eq!(credentials.session_token(), None);
+
+        let with_token = CLICredentialProvider::try_from(&parse_args(&[
+            "--aws-access-key-id",
+            ACCESS_KEY_ID,
+            "--aws-secret-access-key",
+            SECRET_ACCESS_KEY,
+            "--aws-session-token",
+            "session-token-value",
+        ]))
+        .unwrap();
+        let credentials = with_token.provide_credentials().await.unwrap();
+
+        assert_eq!(credentials.session_token(), Some("session-token-value"));
+
+        let output: String = format!("{:?}", with_token);
+        assert!(!output.contains("session-token-value"), "{}", output);
+    }
+
+    #[test]
+    fn parse_aws_endpoint_cases() {
+        // (endpoint, Ok(parsed) or Err(a fragment of the error))
+        let cases: [(&str, Result<&str, &str>); 11] = [
+            (
+                "https://sqs.us-east-1.amazonaws.com",
+                Ok("https://sqs.us-east-1.amazonaws.com"),
+            ),
+            ("http://localhost:4566", Ok("http://localhost:4566")),
+            ("HTTPS://Gateway.Internal", Ok("HTTPS://Gateway.Internal")),
+            (
+                "https://gateway.internal/aws/sqs",
+                Ok("https://gateway.internal/aws/sqs"),
+            ),
+            (
+                "https://gateway.internal/aws/sqs/",
+                Ok("https://gateway.internal/aws/sqs/"),
+            ),
+            ("localhost:4566", Err("(i.e. 'http://localhost:4566')")),
+            (
+                "sqs.internal:443",
+                Err("must include a scheme (i.e. 'https://sqs.internal:443')"),
+            ),
+            (
+                "ftp://sqs.internal",
+                Err("must be an http:// or https:// URL (i.e. 'https://sqs.internal')"),
+            ),
+            (
+                "https://sqs.internal/?region=us-east-1",
+                Err("includes a query string ('?region=us-east-1')"),
+            ),
+            ("/just/a/path", Err("must include a scheme and host")),
+            ("not a url", Err("is not a valid URL")),
+        ];
+
+        for (endpoint, expected) in cases {
+            match (parse_aws_endpoint(endpoint), expected) {
+                (Ok(parsed), Ok(expected)) => assert_eq!(parsed, expected),
+                (Err(error), Err(fragment)) => {
+                    assert!(error.contains(fragment), "{}: {}", endpoint, error)
+                }
+                (parsed, _) => panic!("{}: unexpectedly {:?}", endpoint, parsed),
+            }
+        }
+    }
+
+    #[test]
+    fn service_endpoint_overrides_shared_endpoint() {
+        let args = parse_args(&[
+            "--aws-endpoint",
+            "https://gateway.internal",
+            "--sqs-endpoint",
+            "https://vpce-sqs.internal",
+        ]);
+
+        for env in [EnvName::Dev, EnvName::Local] {
+            assert_eq!(
+                args.service_endpoint_url(AWSService::Sqs, env),
+                Some("https://vpce-sqs.internal"),
+            );
+            assert_eq!(
+                args.service_endpoint_url(AWSService::Sns, env),
+                Some("https://gateway.internal"),
+            );
+            assert_eq!(
+                args.service_endpoint_url(AWSService::Sts, env),
+                Some("https://gateway.internal"),
+            );
+        }
+
+        let args = parse_args(&["--sns-endpoint", "https://vpce-sns.internal"]);
+
+        assert_eq!(
+            args.service_endpoint_url(AWSService::Sns, EnvName::Prod),
+            Some("https://vpce-sns.internal"),
+        );
+        assert_eq!(
+            args.service_endpoint_url(AWSService::Sqs, EnvName::Prod),
+            None
+        );
+        assert_eq!(
+            args.service_endpoint_url(AWSService::Sqs, EnvName::Local),
+            Some(DEFAULT_LOCALSTACK_ENDPOINT),
+        );
+    }
+
+    #[tokio::test]
+    async fn endpoint_path_prefixes_are_kept_in_requests() {
+        let fake = testing::FakeAws::start(testing::aws_response).await;
+        let (sns_endpoint, sqs_endpoint, sts_endpoint) = (
+            format!("{}/aws/sns", &fake.endpoint),
+            format!("{}/aws/sqs/", &fake.endpoint),
+            format!("{}/aws/sts", &fake.endpoint),
+        );
+        let args: CLIArgs = fake.args(&[
+            "--sns-endpoint",
+            &sns_endpoint,
+            "--sqs-endpoint",
+            &sqs_endpoint,
+            "--sts-endpoint",
+            &sts_endpoint,
+        ]);
+        let (sns, sqs, sts) = args.aws_clients(EnvName::Dev).await.unwrap();
+
+        sns.create_topic().name("placed-dev").send().await.unwrap();
+        sqs.list_queues().send().await.unwrap();
+        sts.get_caller_identity().send().await.unwrap();
+
+        let paths: Vec<(String, String)> = fake
+            .requests()
+            .into_iter()
+            .map(|request| (request.action().to_string(), request.path))
+            .collect();
+
+        // Each request's own path (`/`) is joined to the endpoint's prefix
+        assert_eq!(
+            paths,
+            [
+                ("CreateTopic", "/aws/sns/"),
+                ("ListQueues", "/aws/sqs/"),
+                ("GetCallerIdentity", "/aws/sts/"),
+            ]
+            .map(|(action, path)| (action.to_string(), path.to_string()))
+        );
+    }
+
+    #[test]
+    fn select_endpoint_cases() {
+        let localstack: &str = "http://localstack.default.svc:4566";
+
+        // (explicit endpoint, environment, selected endpoint)
+        let cases: [(Option<&str>, EnvName, Option<&str>); 6] = [
+            (
+                Some("https://gateway.internal"),
+                EnvName::Local,
+                Some("https://gateway.internal"),
+            ),
+            (
+                Some("https://gateway.internal"),
+                EnvName::Prod,
+                Some("https://gateway.internal"),
+            ),
+            (
+                Some("https://gateway.internal"),
+                EnvName::Unknown,
+                Some("https://gateway.internal"),
+            ),
+            (None, EnvName::Local, Some(localstack)),
+            (None, EnvName::Dev, None),
+            (None, EnvName::Unknown, None),
+        ];
+
+        for (explicit, env, expected) in cases {
+            assert_eq!(
+                select_endpoint(explicit, localstack, env),
+                expected,
+                "{:?} in {:?}",
+                explicit,
+                env
+            );
+        }
+    }
+
+    #[test]
+    fn localstack_endpoint_defaults_and_overrides() {
+        assert_eq!(
+            parse_args(&[]).aws_endpoint_url(EnvName::Local),
+            Some(DEFAULT_LOCALSTACK_ENDPOINT),
+        );
+        assert_eq!(parse_args(&[]).aws_endpoint_url(EnvName::QA), None);
+
+        let args = parse_args(&["--localstack-endpoint", "http://localstack:4566"]);
+
+        assert_eq!(
+            args.aws_endpoint_url(EnvName::Local),
+            Some("http://localstack:4566")
+        );
+        assert_eq!(args.aws_endpoint_url(EnvName::Test), None);
+    }
+
+    /// Every environment variable the tests below set
+    const ENV_VARS: [&str; 8] = [
+        "PINN_ENV_NAME",
+        "PINN_AWS_REGION",
+        "PINN_DRY_RUN",
+        "PINN_TAG",
+        "PINN_VAR",
+        "PINN_MAX_RETRIES",
+        "AWS_ACCESS_KEY_ID",
+        "AWS_SECRET_ACCESS_KEY",
+    ];
+
+    /// Parse the supplied arguments with only the supplied
+    /// (pinnothera-related) environment variables set
+    fn parse_with_env(args: &[&str], vars: &[(&str, &str)]) -> Result<CLIArgs, clap::Error> {
+        let vars: Vec<(&str, Option<&str>)> = ENV_VARS
+            .iter()
+            .map(|name| {
+                (
+                    *name,
+                    vars.iter()
+                        .find(|(var, _)| var == name)
+                        .map(|(_, value)| *value),
+                )
+            })
+            .collect();
+
+        testing::with_env(&vars, || try_parse_args(args))
+    }
+
+    #[test]
+    fn flags_fall_back_to_env_vars() {
+        let args = parse_with_env(
+            &[],
+            &[
+                ("PINN_ENV_NAME", "qa"),
+                ("PINN_AWS_REGION", "eu-west-1"),
+                ("PINN_MAX_RETRIES", "7"),
+                ("AWS_ACCESS_KEY_ID", ACCESS_KEY_ID),
+                ("AWS_SECRET_ACCESS_KEY", SECRET_ACCESS_KEY),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(args.env_name.as_deref(), Some("qa"));
+        assert_eq!(args.aws_region.as_deref(), Some("eu-west-1"));
+        assert_eq!(args.max_retries, 7);
+        assert_eq!(
+            args.aws_access_key_id.as_ref().map(Secret::expose),
+            Some(ACCESS_KEY_ID)
+        );
+        assert_eq!(
+            args.aws_secret_access_key.as_ref().map(Secret::expose),
+            Some(SECRET_ACCESS_KEY)
+        );
+    }
+
+    #[test]
+    fn flags_take_precedence_over_env_vars() {
+        let args = parse_with_env(
+            &["--aws-region", "us-west-2", "--tag", "team=search"],
+            &[
+                ("PINN_AWS_REGION", "eu-west-1"),
+                ("PINN_TAG", "team=payments"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(args.aws_region.as_deref(), Some("us-west-2"));
+        assert_eq!(args.tags, vec![("team".to_string(), "search".to_string())]);
+    }
+
+    #[test]
+    fn on_off_env_vars_accept_boolish_values() {
+        for (value, expected) in [
+            ("true", true),
+            ("yes", true),
+            ("on", true),
+            ("1", true),
+            ("false", false),
+            ("no", false),
+            ("off", false),
+            ("0", false),
+        ] {
+            let args = parse_with_env(&[], &[("PINN_DRY_RUN", value)]).unwrap();
+            assert_eq!(args.dry_run, expected, "PINN_DRY_RUN={}", value);
+        }
+
+        assert!(parse_with_env(&[], &[("PINN_DRY_RUN", "maybe")]).is_err());
+    }
+
+    #[test]
+    fn list_env_vars_split_on_commas() {
+        let args = parse_with_env(&[], &[("PINN_TAG", "team=payments,cost-center=1234")]).unwrap();
+
+        assert_eq!(
+            args.tags,
+            vec![
+                ("team".to_string(), "payments".to_string()),
+                ("cost-center".to_string(), "1234".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn var_env_var_is_a_single_pair() {
+        let args = parse_with_env(&[], &[("PINN_VAR", "REPLICAS=[1,2,3]")]).unwrap();
+
+        assert_eq!(
+            args.vars,
+            vec![("REPLICAS".to_string(), "[1,2,3]".to_string())]
+        );
+    }
+
+    #[test]
+    fn parse_env_delimiter_cases() {
+        // (delimiter, Ok(parsed) or Err(a fragment of the error))
+        let cases: [(&str, Result<char, &str>); 6] = [
+            ("-", Ok('-')),
+            ("_", Ok('_')),
+            (".", Ok('.')),
+            ("/", Err("is not allowed in topic or queue names by AWS")),
+            ("--", Err("is not a single character")),
+            ("", Err("is not a single character")),
+        ];
+
+        for (delimiter, expected) in cases {
+            match (parse_env_delimiter(delimiter), expected) {
+                (Ok(parsed), Ok(expected)) => assert_eq!(parsed, expected),
+                (Err(error), Err(fragment)) => {
+                    assert!(error.contains(fragment), "{}: {}", delimiter, error)
+                }
+                (parsed, _) => panic!("{:?}: unexpectedly {:?}", delimiter, parsed),
+            }
+        }
+    }
+
+    const PROFILE_CONFIG: &str = r#"[default]
+region = us-east-1
+
+[profile sso]
+sso_start_url = https://example.awsapps.com/start
+sso_region = us-east-1
+sso_account_id = 123456789012
+sso_role_name = Developer
+
+[profile sourced]
+source_profile = sso
+role_arn = arn:aws:iam::123456789012:role/pinnothera
+
+[profile process]
+credential_process = echo '{"Version":1,"AccessKeyId":"AKIDPROCESS","SecretAccessKey":"process-secret","SessionToken":"process-token","Expiration":"2099-01-01T00:00:00Z"}'
+"#;
+
+    const PROFILE_CREDENTIALS: &str = "[static]
+aws_access_key_id = AKIDSTATIC
+aws_secret_access_key = static-secret
+";
+
+    /// Run the supplied future with `HOME` pointed at a scratch
+    /// directory holding `PROFILE_CONFIG` and `PROFILE_CREDENTIALS`
+    /// (and no other credentials available from the environment)
+    fn with_profiles<T, F: std::future::Future<Output = T>>(
+        name: &str,
+        future: impl FnOnce() -> F,
+    ) -> T {
+        let home: std::path::PathBuf = testing::scratch_dir(name);
+
+        std::fs::create_dir_all(home.join(".aws")).unwrap();
+        std::fs::write(home.join(".aws/config"), PROFILE_CONFIG).unwrap();
+        std::fs::write(home.join(".aws/credentials"), PROFILE_CREDENTIALS).unwrap();
+
+        testing::with_env(
+            &[
+                ("HOME", home.to_str()),
+                ("AWS_CONFIG_FILE", None),
+                ("AWS_SHARED_CREDENTIALS_FILE", None),
+                ("AWS_PROFILE", None),
+                ("AWS_ACCESS_KEY_ID", None),
+                ("AWS_SECRET_ACCESS_KEY", None),
+                ("AWS_SESSION_TOKEN", None),
+                ("AWS_EC2_METADATA_DISABLED", Some("true")),
+            ],
+            || {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap()
+                    .block_on(future())
+            },
+        )
+    }
+
+    #[test]
+    fn profiles_are_recognised_as_sso_backed() {
+        let profiles = with_profiles("sso-profiles", || async {
+            aws_config::profile::load(&AWSFs::real(), &AWSEnv::real()).await
+        })
+        .unwrap();
+
+        for (profile, uses_sso) in [
+            ("sso", true),
+            ("sourced", true),
+            ("process", false),
+            ("static", false),
+            ("default", false),
+        ] {
+            assert_eq!(
+                ProfileCredentialProvider::profile_uses_sso(&profiles, profile),
+                uses_sso,
+                "{}",
+                profile
+            );
+        }
+    }
+
+    #[test]
+    fn profile_credentials_per_source() {
+        for (profile, access_key_id) in [("static", "AKIDSTATIC"), ("process", "AKIDPROCESS")] {
+            let credentials = with_profiles("credential-profiles", || async {
+                ProfileCredentialProvider::new(profile)
+                    .await
+                    .unwrap()
+                    .provide_credentials()
+                    .await
+            })
+            .unwrap();
+
+            assert_eq!(credentials.access_key_id(), access_key_id, "{}", profile);
+        }
+    }
+
+    #[test]
+    fn missing_sso_sessions_name_the_login_command() {
+        let error = with_profiles("expired-sso-profile", || async {
+            ProfileCredentialProvider::new("sso")
+                .await
+                .unwrap()
+                .provide_credentials()
+                .await
+        })
+        .unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .contains("run `aws sso login --profile sso`"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn unknown_profiles_list_the_known_ones_and_files_searched() {
+        let home: String = testing::scratch_dir("unknown-profile")
+            .to_string_lossy()
+            .into_owned();
+        let error: String = format!(
+            "{:?}",
+            with_profiles("unknown-profile", || ProfileCredentialProvider::new("prod"))
+                .unwrap_err()
+        );
+
+        assert!(
+            error.contains("known profiles: [default, process, sourced, sso, static]"),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains(&format!(
+                "searched: {0}/.aws/config, {0}/.aws/credentials",
+                home
+            )),
+            "{}",
+            error
+        );
+    }
+
+    /// A local TLS listener (for "localhost", with a freshly generated
+    /// self-signed certificate) restricted to the supplied versions and
+    /// cipher suites, returning its address and certificate
+    fn tls_listener(
+        versions: Vec<ProtocolVersion>,
+        suites: &[&str],
+    ) -> (std::net::SocketAddr, rustls::Certificate) {
+        let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let certificate = rustls::Certificate(generated.serialize_der().unwrap());
+        let mut server_config = rustls::ServerConfig::new(rustls::NoClientAuth::new());
+        server_config
+            .set_single_cert(
+                vec![certificate.clone()],
+                rustls::PrivateKey(generated.serialize_private_key_der()),
+            )
+            .unwrap();
+        server_config.versions = versions;
+        server_config.ciphersuites.retain(|suite| {
+            suites.is_empty() || suites.contains(&format!("{:?}", suite.suite).as_str())
+        });
+
+        let (server_config, listener) = (
+            Arc::new(server_config),
+            std::net::TcpListener::bind("127.0.0.1:0").unwrap(),
+        );
+        let address = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut session = rustls::ServerSession::new(&server_config);
+
+                // Until the client disconnects (or the handshake fails)
+                while let Ok((read, written)) = session.complete_io(&mut stream) {
+                    if read + written == 0 {
+                        break;
+                    }
+                }
+            }
+        });
+
+        (address, certificate)
+    }
+
+    /// Complete a TLS handshake with the supplied listener as the AWS
+    /// clients of a run with the supplied flags would
+    fn tls_handshake(
+        flags: &[&str],
+        address: std::net::SocketAddr,
+        certificate: &rustls::Certificate,
+    ) -> Result<(), String> {
+        let mut tls_config = parse_args(flags).aws_tls_config().unwrap().unwrap();
+        tls_config.root_store.add(certificate).unwrap();
+
+        let mut session = rustls::ClientSession::new(
+            &Arc::new(tls_config),
+            webpki::DNSNameRef::try_from_ascii_str("localhost").unwrap(),
+        );
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+
+        session
+            .complete_io(&mut stream)
+            .map(drop)
+            .map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn tls_versions_below_the_minimum_are_rejected() {
+        let (address, certificate) = tls_listener(vec![ProtocolVersion::TLSv1_2], &[]);
+
+        assert_eq!(
+            tls_handshake(&["--min-tls-version", "1.2"], address, &certificate),
+            Ok(())
+        );
+
+        let error = tls_handshake(&["--min-tls-version", "1.3"], address, &certificate)
+            .expect_err("a TLS 1.2-only listener should be rejected");
+        assert!(error.to_lowercase().contains("protocol"), "{}", error);
+
+        assert!(parse_args(&[]).aws_tls_config().unwrap().is_none());
+    }
+
+    #[test]
+    fn only_the_listed_cipher_suites_are_offered() {
+        let (address, certificate) = tls_listener(
+            vec![ProtocolVersion::TLSv1_3],
+            &["TLS13_AES_128_GCM_SHA256"],
+        );
+
+        assert_eq!(
+            tls_handshake(
+                &["--tls-cipher-suite", "TLS13_AES_128_GCM_SHA256"],
+                address,
+                &certificate
+            ),
+            Ok(())
+        );
+        assert!(tls_handshake(
+            &[
+                "--tls-cipher-suite",
+                "TLS13_AES_256_GCM_SHA384,TLS13_CHACHA20_POLY1305_SHA256"
+            ],
+            address,
+            &certificate
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn unusable_cipher_suites_fail_up_front() {
+        let error = parse_args(&["--tls-cipher-suite", "TLS_RSA_WITH_RC4_128_SHA"])
+            .aws_tls_config()
+            .map(drop)
+            .unwrap_err();
+        assert!(
+            format!("{:?}", error)
+                .contains("Unknown TLS cipher suite \"TLS_RSA_WITH_RC4_128_SHA\""),
+            "{:?}",
+            error
+        );
+
+        let error = parse_args(&[
+            "--min-tls-version",
+            "1.3",
+            "--tls-cipher-suite",
+            "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+        ])
+        .aws_tls_config()
+        .map(drop)
+        .unwrap_err();
+        assert!(
+            format!("{:?}", error).contains("can be used with TLS 1.3+"),
+            "{:?}",
+            error
+        );
+    }
+}
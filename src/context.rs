@@ -0,0 +1,218 @@
+// Pinnothera's per-run application context
+
+// Standard Library Imports
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+
+// Third Party Imports
+use aws_sdk_sns::Client as SNSClient;
+use aws_sdk_sqs::Client as SQSClient;
+
+// Project-Level Imports
+use crate::checkpoint::Checkpoint;
+use crate::control::RunControl;
+use crate::errors::PinnError;
+use crate::events::EventStream;
+use crate::logging::FailureLog;
+use crate::name_hook::NameHookRequest;
+use crate::plan::PlanLog;
+use crate::report::RunReport;
+use crate::retry::RetryPolicy;
+use crate::settings::Settings;
+use crate::stats::ApiCallStats;
+use crate::{AwsClients, EnvName, SNSTopicARN, SQSQueueARN, SQSQueueURL, TopicClientFactory};
+
+// <editor-fold desc="// Type Aliases ...">
+
+/// The (eventual) outcome of ensuring the existence of a single topic,
+/// shared by every queue subscribing to it
+pub(crate) type TopicCell = tokio::sync::OnceCell<Result<SNSTopicARN, Arc<PinnError>>>;
+
+/// The (eventual) SNS client acting as a single topic's `role_arn` and/or
+/// in a topic's own region, or why it couldn't be created (i.e. the role
+/// couldn't be assumed), shared by every subscription made with it
+pub(crate) type TopicClientCell = tokio::sync::OnceCell<Result<SNSClient, String>>;
+
+/// The role (if any) and region (if any) a `TopicClientCell`'s client uses
+type TopicClientKey = (Option<String>, Option<String>);
+
+/// The URL of every queue visible to the current AWS identity by name,
+/// listed (at most) once per run, or why they couldn't be
+pub(crate) type QueueUrlIndex =
+    tokio::sync::OnceCell<Result<BTreeMap<String, SQSQueueURL>, String>>;
+
+/// The ARN of every (confirmed) SQS subscription in the account by the
+/// ARNs of its topic and queue, listed (at most) once per run, or why
+/// they couldn't be
+pub(crate) type SubscriptionIndex =
+    tokio::sync::OnceCell<Result<HashMap<(SNSTopicARN, SQSQueueARN), String>, String>>;
+
+// </editor-fold desc="// Type Aliases ...">
+
+// <editor-fold desc="// RunState ...">
+
+/// What a single run records as it goes (and the limits it's subject
+/// to), from startup onwards: the AWS clients and the `AppContext`
+/// holding them are only created once the configuration is known, but
+/// startup's API calls and failures are accounted for all the same
+#[derive(Debug, Default)]
+pub(crate) struct RunState {
+    pub events: EventStream,
+    pub stats: ApiCallStats,
+    pub retry: RetryPolicy,
+    pub failures: FailureLog,
+    pub report: RunReport,
+    pub plan: PlanLog,
+    pub control: RunControl,
+    /// Whether the run has already warned about the local clock's skew
+    pub clock_skew_reported: AtomicBool,
+}
+
+impl RunState {
+    /// The state of a run with the supplied settings, streaming its
+    /// events to the supplied stream
+    pub fn new(settings: &Settings, events: EventStream) -> Self {
+        RunState {
+            events,
+            stats: ApiCallStats::new(
+                settings.max_api_calls,
+                settings.concurrency as usize,
+                settings
+                    .max_subscription_concurrency
+                    .map(|limit| limit as usize),
+            ),
+            retry: RetryPolicy::new(settings.max_retries, settings.retry_base_delay_ms),
+            ..Default::default()
+        }
+    }
+}
+
+// </editor-fold desc="// RunState ...">
+
+// <editor-fold desc="// AppContext ...">
+
+/// Everything a single run needs to apply its configuration: the
+/// environment it targets, the AWS clients it uses, and its settings
+#[derive(Debug)]
+pub(crate) struct AppContext {
+    pub env: EnvName,
+    pub sns: SNSClient,
+    pub sqs: SQSClient,
+    pub settings: Settings,
+    /// The AWS region resolved during startup (from `--aws-region`, the
+    /// AWS profile, or the custom endpoint default), if any
+    pub region: Option<String>,
+    /// The AWS account id resolved during startup, if any
+    pub account_id: Option<String>,
+    /// The custom endpoint (if any) the context's AWS clients use
+    pub endpoint_url: Option<String>,
+    /// Where the SNS clients for topics with their own role and/or
+    /// region come from, if anywhere
+    pub topic_client_factory: Option<TopicClientFactory>,
+    pub run: Arc<RunState>,
+    /// The run's checkpoint, if `--checkpoint-file` was specified
+    pub checkpoint: Option<Checkpoint>,
+    /// The names the name hook (if any) supplied for each queue and topic
+    pub hooked_names: BTreeMap<NameHookRequest, String>,
+    /// The queue URLs a `--check` run looks queues up in (and
+    /// that any other run finds partially applied queues in)
+    pub queue_urls: QueueUrlIndex,
+    /// The subscriptions a `--check` run looks subscriptions up in
+    /// (and that any other run finds partially applied queues in)
+    pub subscriptions: SubscriptionIndex,
+    topic_arns: Mutex<HashMap<String, Arc<TopicCell>>>,
+    topic_clients: Mutex<HashMap<TopicClientKey, Arc<TopicClientCell>>>,
+}
+
+impl AppContext {
+    pub fn new(env: EnvName, clients: &AwsClients, settings: Settings, run: Arc<RunState>) -> Self {
+        AppContext {
+            env,
+            sns: clients.sns.clone(),
+            sqs: clients.sqs.clone(),
+            settings,
+            region: clients.region.clone(),
+            account_id: clients.account_id.clone(),
+            endpoint_url: clients.endpoint_url.clone(),
+            topic_client_factory: clients.topic_clients.clone(),
+            run,
+            checkpoint: None,
+            hooked_names: BTreeMap::new(),
+            queue_urls: QueueUrlIndex::new(),
+            subscriptions: SubscriptionIndex::new(),
+            topic_arns: Mutex::new(HashMap::new()),
+            topic_clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn aws_region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    pub fn aws_account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    pub fn aws_endpoint_url(&self) -> Option<&str> {
+        self.endpoint_url.as_deref()
+    }
+
+    /// The physical name the name hook (if any)
+    /// supplied for the supplied request
+    pub fn hooked_name(&self, request: &NameHookRequest) -> Option<String> {
+        self.hooked_names.get(request).cloned()
+    }
+
+    /// Determine whether the supplied resource reached a terminal success
+    /// state during a previous run (if checkpointing is enabled)
+    pub fn previously_completed<T: AsRef<str>>(&self, resource: T) -> bool {
+        self.checkpoint
+            .as_ref()
+            .map(|checkpoint| checkpoint.is_completed(resource))
+            .unwrap_or(false)
+    }
+
+    /// Record that the supplied resource has reached a terminal
+    /// success state (if checkpointing is enabled)
+    pub fn record_completed<T: AsRef<str>>(&self, resource: T) {
+        if let Some(checkpoint) = &self.checkpoint {
+            checkpoint.record_completed(resource);
+        }
+    }
+
+    /// Limit the run to printing its plan, regardless
+    /// of whether `--dry-run` was specified
+    pub fn limit_to_dry_run(&mut self) {
+        self.settings.dry_run = true;
+    }
+
+    /// The cell holding the outcome of ensuring the supplied (resolved)
+    /// topic, which is shared by every queue subscribing to it
+    pub fn topic_cell<T: AsRef<str>>(&self, topic: T) -> Arc<TopicCell> {
+        self.topic_arns
+            .lock()
+            .unwrap()
+            .entry(topic.as_ref().to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// The cell holding the SNS client acting as the supplied role and/or
+    /// in the supplied region, which is shared by every subscription made
+    /// as that role in that region
+    pub fn topic_client_cell(
+        &self,
+        role_arn: Option<&str>,
+        region: Option<&str>,
+    ) -> Arc<TopicClientCell> {
+        self.topic_clients
+            .lock()
+            .unwrap()
+            .entry((role_arn.map(str::to_string), region.map(str::to_string)))
+            .or_default()
+            .clone()
+    }
+}
+
+// </editor-fold desc="// AppContext ...">
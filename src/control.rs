@@ -0,0 +1,480 @@
+// Pinnothera's (local Unix socket) progress and cancellation components
+
+// Standard Library Imports
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Third Party Imports
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::task::JoinHandle;
+
+// Project-Level Imports
+use crate::context::RunState;
+use crate::events::{self, PinnEvent};
+use crate::logging::{debug, warning};
+use crate::report;
+use crate::stats;
+
+// <editor-fold desc="// Run Progress ...">
+
+/// The stage a pinnothera run is currently in
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RunPhase {
+    /// Acquiring and validating the configuration, and the AWS clients
+    #[default]
+    Starting,
+    /// Checking the environment suffix and first-run guards
+    Preflight,
+    /// Applying (or checking, purging, or smoke testing) the configured queues
+    Applying,
+    /// Summarizing the run's outcome
+    Reporting,
+}
+
+/// A single run's progress, whether it's been asked to shut
+/// down, and where its control socket (if any) was created
+#[derive(Debug, Default)]
+pub(crate) struct RunControl {
+    cancelled: AtomicBool,
+    phase: Mutex<RunPhase>,
+    queues_total: AtomicUsize,
+    queues_skipped: AtomicUsize,
+    queues_succeeded: AtomicUsize,
+    queues_failed: AtomicUsize,
+    socket_path: Mutex<Option<PathBuf>>,
+}
+
+/// Record that the run has moved on to the supplied phase
+pub(crate) fn set_phase(run: &RunState, phase: RunPhase) {
+    *run.control.phase.lock().unwrap() = phase;
+}
+
+/// Record the number of configured queues the run will apply,
+/// and the number it skipped (i.e. paused or checkpointed ones)
+pub(crate) fn set_queue_counts(run: &RunState, total: usize, skipped: usize) {
+    run.control.queues_total.store(total, Ordering::SeqCst);
+    run.control.queues_skipped.store(skipped, Ordering::SeqCst);
+}
+
+/// Record that the run has finished applying one of its queues
+pub(crate) fn record_queue_finished(run: &RunState, succeeded: bool) {
+    match succeeded {
+        true => run.control.queues_succeeded.fetch_add(1, Ordering::SeqCst),
+        false => run.control.queues_failed.fetch_add(1, Ordering::SeqCst),
+    };
+}
+
+/// The number of queues the run has (and hasn't yet) applied
+#[derive(Clone, Debug, Serialize)]
+struct QueueCounts {
+    total: usize,
+    pending: usize,
+    succeeded: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+/// The current progress of the run, as returned by the `status` command
+#[derive(Clone, Debug, Serialize)]
+struct RunStatus {
+    phase: RunPhase,
+    cancelled: bool,
+    queues: QueueCounts,
+    /// The number of resources the run has handled so far, by status
+    resources: BTreeMap<&'static str, usize>,
+    api_calls: u64,
+}
+
+fn run_status(run: &RunState) -> RunStatus {
+    let (total, succeeded, failed) = (
+        run.control.queues_total.load(Ordering::SeqCst),
+        run.control.queues_succeeded.load(Ordering::SeqCst),
+        run.control.queues_failed.load(Ordering::SeqCst),
+    );
+
+    let mut resources: BTreeMap<&'static str, usize> = BTreeMap::new();
+
+    report::resource_reports(run)
+        .iter()
+        .for_each(|resource| *resources.entry(resource.status.as_str()).or_default() += 1);
+
+    RunStatus {
+        phase: *run.control.phase.lock().unwrap(),
+        cancelled: is_cancelled(run),
+        queues: QueueCounts {
+            total,
+            pending: total.saturating_sub(succeeded + failed),
+            succeeded,
+            failed,
+            skipped: run.control.queues_skipped.load(Ordering::SeqCst),
+        },
+        resources,
+        api_calls: stats::api_call_counts(run).values().sum(),
+    }
+}
+
+// </editor-fold desc="// Run Progress ...">
+
+// <editor-fold desc="// Cancellation Utilities ...">
+
+/// Request that the run shut down gracefully (letting in-flight AWS
+/// API calls finish, but attempting no new ones), returning whether
+/// cancellation had already been requested
+pub(crate) fn request_cancellation<T: AsRef<str>>(run: &RunState, source: T) -> bool {
+    let already_cancelled: bool = run.control.cancelled.swap(true, Ordering::SeqCst);
+
+    if !already_cancelled {
+        warning!(
+            "Cancellation requested ({}), finishing in-flight operations without attempting any new ones",
+            source.as_ref()
+        );
+        events::emit(
+            run,
+            PinnEvent::RunCancelled {
+                source: source.as_ref().to_string(),
+            },
+        );
+    }
+
+    already_cancelled
+}
+
+/// Determine whether the run has been asked to shut down
+pub(crate) fn is_cancelled(run: &RunState) -> bool {
+    run.control.cancelled.load(Ordering::SeqCst)
+}
+
+/// Shut the run down gracefully when pinnothera receives
+/// a SIGTERM, or immediately if it receives a second one
+pub(crate) fn handle_sigterm(run: Arc<RunState>) {
+    let mut signals = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+    {
+        Ok(value) => value,
+        Err(error) => {
+            warning!("Could not install a SIGTERM handler: {}", error);
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        while signals.recv().await.is_some() {
+            if request_cancellation(&run, "SIGTERM") {
+                warning!("Received a second SIGTERM, exiting immediately");
+                remove_socket_file(&run);
+                std::process::exit(143);
+            }
+        }
+    });
+}
+
+// </editor-fold desc="// Cancellation Utilities ...">
+
+// <editor-fold desc="// Control Socket ...">
+
+/// The control socket a supervisor can query the run's progress (and
+/// request its cancellation) over, removed when it's dropped
+#[derive(Debug)]
+pub(crate) struct ControlSocket {
+    run: Arc<RunState>,
+    listener: JoinHandle<()>,
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        self.listener.abort();
+        remove_socket_file(&self.run);
+    }
+}
+
+fn remove_socket_file(run: &RunState) {
+    if let Some(path) = run.control.socket_path.lock().unwrap().take() {
+        if let Err(error) = std::fs::remove_file(&path) {
+            warning!(
+                "Could not remove control socket \"{}\": {}",
+                path.display(),
+                error
+            );
+        }
+    }
+}
+
+/// Remove a socket left behind at the supplied path by a previous
+/// run (i.e. one that was killed), refusing to remove anything
+/// other than a socket no process is listening on
+fn remove_stale_socket(path: &Path) -> Result<(), Error> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(value) => value,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error),
+    };
+
+    if !metadata.file_type().is_socket() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            "the path exists and is not a socket",
+        ));
+    }
+
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+        return Err(Error::new(
+            ErrorKind::AddrInUse,
+            "the socket is in use by another process",
+        ));
+    }
+
+    debug!("Removing stale control socket \"{}\"", path.display());
+
+    std::fs::remove_file(path)
+}
+
+/// Bind a Unix socket at the supplied path that only the user pinnothera
+/// runs as can connect to, by binding it inside of a private (owner-only)
+/// directory next to the path, restricting the socket's permissions, and
+/// only then moving it into place (so there's no window in which another
+/// user could connect to it, regardless of the process's umask)
+fn bind_private(path: &Path) -> Result<UnixListener, Error> {
+    let (parent, name) = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => (parent, name.to_string_lossy()),
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the path does not name a file",
+            ))
+        }
+    };
+    let private_dir: PathBuf = match parent.as_os_str().is_empty() {
+        true => PathBuf::from("."),
+        false => parent.to_path_buf(),
+    }
+    .join(format!(".{}.{}.tmp", name, std::process::id()));
+
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&private_dir)?;
+
+    let staged: PathBuf = private_dir.join("socket");
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+
+    if bound.is_err() {
+        let _ = std::fs::remove_file(&staged);
+    }
+
+    if let Err(error) = std::fs::remove_dir(&private_dir) {
+        warning!(
+            "Could not remove control socket staging directory \"{}\": {}",
+            private_dir.display(),
+            error
+        );
+    }
+
+    bound
+}
+
+/// Listen for control commands on a (owner-only) Unix
+/// socket created at the supplied path
+pub(crate) fn serve<P: AsRef<Path>>(path: P, run: Arc<RunState>) -> Result<ControlSocket, Error> {
+    let path: &Path = path.as_ref();
+
+    remove_stale_socket(path)?;
+
+    let listener = bind_private(path)?;
+
+    *run.control.socket_path.lock().unwrap() = Some(path.to_path_buf());
+
+    let task_run: Arc<RunState> = run.clone();
+
+    Ok(ControlSocket {
+        run,
+        listener: tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(handle_connection(task_run.clone(), stream));
+                    }
+                    Err(error) => {
+                        warning!("Could not accept control socket connection: {}", error);
+                    }
+                }
+            }
+        }),
+    })
+}
+
+/// Answer each (line-delimited) command sent over the
+/// supplied connection until the client disconnects
+async fn handle_connection(run: Arc<RunState>, stream: UnixStream) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response: String = respond(&run, &line).to_string();
+
+        if writer
+            .write_all(format!("{}\n", response).as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// The response to the supplied command, which may be either a bare
+/// command name (`status`) or a JSON object (`{"command": "status"}`)
+fn respond(run: &RunState, line: &str) -> Value {
+    let command: Option<String> = match serde_json::from_str::<Value>(line) {
+        Ok(Value::Object(fields)) => fields
+            .get("command")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        Ok(Value::String(command)) => Some(command),
+        _ => Some(line.trim().to_string()),
+    };
+
+    match command.as_deref() {
+        Some("status") => serde_json::json!({ "ok": true, "status": run_status(run) }),
+        Some("cancel") => {
+            let already_cancelled: bool = request_cancellation(run, "control socket");
+            serde_json::json!({ "ok": true, "already_cancelled": already_cancelled })
+        }
+        Some("report") => serde_json::json!({
+            "ok": true,
+            "status": run_status(run),
+            "resources": report::resource_reports(run),
+            "queues_applied": report::queue_results(run),
+        }),
+        Some(other) => serde_json::json!({
+            "ok": false,
+            "error": format!("unknown command \"{}\" (expected status, cancel, or report)", other),
+        }),
+        None => serde_json::json!({
+            "ok": false,
+            "error": "expected a command (status, cancel, or report)",
+        }),
+    }
+}
+
+// </editor-fold desc="// Control Socket ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn status_reflects_the_runs_progress() {
+        let run = RunState::default();
+
+        set_phase(&run, RunPhase::Applying);
+        set_queue_counts(&run, 3, 1);
+        record_queue_finished(&run, true);
+        record_queue_finished(&run, false);
+        report::record(&run, "queue", "q", report::ResourceStatus::Created, None);
+
+        let response: Value = respond(&run, "status");
+
+        assert_eq!(response["ok"], true);
+        assert_eq!(response["status"]["phase"], "applying");
+        assert_eq!(response["status"]["cancelled"], false);
+        assert_eq!(
+            response["status"]["queues"],
+            serde_json::json!({"total": 3, "pending": 1, "succeeded": 1, "failed": 1, "skipped": 1})
+        );
+        assert_eq!(response["status"]["resources"]["created"], 1);
+
+        // Another run's progress is its own
+        assert_eq!(
+            respond(&RunState::default(), "status")["status"]["phase"],
+            "starting"
+        );
+    }
+
+    #[test]
+    fn cancel_is_idempotent() {
+        let run = RunState::default();
+
+        assert!(!is_cancelled(&run));
+        assert_eq!(
+            respond(&run, r#"{"command": "cancel"}"#)["already_cancelled"],
+            false
+        );
+        assert_eq!(respond(&run, "\"cancel\"")["already_cancelled"], true);
+        assert!(is_cancelled(&run));
+        assert!(!is_cancelled(&RunState::default()));
+    }
+
+    #[test]
+    fn unknown_commands_are_rejected() {
+        let run = RunState::default();
+
+        for line in ["bogus", r#"{"command": "bogus"}"#] {
+            let response: Value = respond(&run, line);
+
+            assert_eq!(response["ok"], false);
+            assert!(response["error"]
+                .as_str()
+                .unwrap()
+                .starts_with("unknown command \"bogus\""));
+        }
+
+        assert_eq!(respond(&run, r#"{"cmd": "status"}"#)["ok"], false);
+    }
+
+    #[tokio::test]
+    async fn socket_is_owner_only_and_removed_on_drop() {
+        let dir: PathBuf = testing::scratch_dir("control-socket");
+        let path: PathBuf = dir.join("pinnothera.sock");
+        let socket: ControlSocket = serve(&path, Arc::new(RunState::default())).unwrap();
+
+        let metadata = std::fs::symlink_metadata(&path).unwrap();
+        assert!(metadata.file_type().is_socket());
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+
+        // Nothing but the socket is left behind in its directory
+        let entries: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(entries, std::slice::from_ref(&path));
+
+        let stream: UnixStream = UnixStream::connect(&path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        writer.write_all(b"status\n").await.unwrap();
+        let line: String = BufReader::new(reader)
+            .lines()
+            .next_line()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&line).unwrap()["ok"], true);
+
+        // A socket in use can't be replaced by a second run
+        assert_eq!(
+            serve(&path, Arc::new(RunState::default()))
+                .unwrap_err()
+                .kind(),
+            ErrorKind::AddrInUse
+        );
+
+        drop(socket);
+        assert!(!path.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,269 @@
+// Pinnothera's error types
+
+// Standard Library Imports
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+// <editor-fold desc="// Type Aliases ...">
+
+/// An error raised by one of the libraries pinnothera uses
+pub(crate) type BoxedError = Box<dyn Error + Send + Sync + 'static>;
+
+// </editor-fold desc="// Type Aliases ...">
+
+// <editor-fold desc="// PinnError enum ...">
+
+/// The ways pinnothera can fail to acquire its configuration,
+/// or to ensure the existence of the queues and topics it manages
+#[derive(Debug)]
+pub enum PinnError {
+    /// The configuration couldn't be read, parsed, or used
+    ConfigError {
+        message: String,
+        source: Option<BoxedError>,
+    },
+    /// The configuration was parsed, but some of its values are unusable
+    InvalidConfig { problems: Vec<String> },
+    /// The name hook couldn't name (all of) the configured queues and topics
+    NameHookError { failures: Vec<String> },
+    /// An action requiring confirmation (i.e. creating unsuffixed
+    /// queues) wasn't confirmed, so the run was aborted
+    Unconfirmed { action: String, flag: &'static str },
+    /// The named AWS profile isn't defined in any of the files searched
+    UnknownProfile {
+        profile: String,
+        known: Vec<String>,
+        searched: Vec<String>,
+    },
+    /// A Kubernetes API call (or reading the kubeconfig) failed
+    KubeError {
+        operation: &'static str,
+        source: BoxedError,
+    },
+    /// An AWS API call failed
+    AwsError {
+        service: &'static str,
+        operation: &'static str,
+        resource: String,
+        source: BoxedError,
+    },
+    /// An AWS API call succeeded, but its response lacked an expected field
+    MissingField {
+        operation: &'static str,
+        resource: String,
+        field: &'static str,
+    },
+    /// An AWS API call wasn't attempted because
+    /// the run's API call budget was exhausted
+    BudgetExhausted {
+        operation: &'static str,
+        resource: String,
+    },
+    /// An AWS API call wasn't attempted because the run was
+    /// cancelled (via SIGTERM or the control socket)
+    Cancelled {
+        operation: &'static str,
+        resource: String,
+    },
+    /// A topic exists with a type or attributes incompatible
+    /// with its configuration, which must be resolved manually
+    IncompatibleTopic { topic: String },
+    /// A queue's access policy couldn't be rendered
+    PolicyError { queue: String, reason: String },
+    /// An existing queue couldn't be (or wasn't allowed
+    /// to be) brought in line with its configuration
+    QueueConflict { queue: String, reason: String },
+    /// A resource doesn't exist (found by a `--check` run)
+    MissingResource {
+        kind: &'static str,
+        resource: String,
+    },
+    /// A resource exists with attributes differing from
+    /// its configuration (found by a `--check` run)
+    DriftedResource {
+        kind: &'static str,
+        resource: String,
+        differences: Vec<String>,
+    },
+}
+
+impl PinnError {
+    pub fn config<T: Into<String>>(message: T) -> Self {
+        PinnError::ConfigError {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn config_caused_by<T: Into<String>, E: Into<BoxedError>>(message: T, source: E) -> Self {
+        PinnError::ConfigError {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn kube<E: Into<BoxedError>>(operation: &'static str, source: E) -> Self {
+        PinnError::KubeError {
+            operation,
+            source: source.into(),
+        }
+    }
+
+    pub fn aws<R: AsRef<str>, E: Into<BoxedError>>(
+        service: &'static str,
+        operation: &'static str,
+        resource: R,
+        source: E,
+    ) -> Self {
+        PinnError::AwsError {
+            service,
+            operation,
+            resource: resource.as_ref().to_string(),
+            source: source.into(),
+        }
+    }
+
+    pub fn missing_field<R: AsRef<str>>(
+        operation: &'static str,
+        resource: R,
+        field: &'static str,
+    ) -> Self {
+        PinnError::MissingField {
+            operation,
+            resource: resource.as_ref().to_string(),
+            field,
+        }
+    }
+
+    /// The exit code pinnothera should exit with if the
+    /// error prevents it from applying its configuration
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            PinnError::ConfigError { .. }
+            | PinnError::InvalidConfig { .. }
+            | PinnError::NameHookError { .. }
+            | PinnError::Unconfirmed { .. }
+            | PinnError::UnknownProfile { .. }
+            | PinnError::KubeError { .. }
+            | PinnError::PolicyError { .. } => 2,
+            PinnError::AwsError { .. } | PinnError::MissingField { .. } => 3,
+            PinnError::BudgetExhausted { .. }
+            | PinnError::Cancelled { .. }
+            | PinnError::IncompatibleTopic { .. }
+            | PinnError::QueueConflict { .. } => 1,
+            PinnError::MissingResource { .. } | PinnError::DriftedResource { .. } => 4,
+        }
+    }
+}
+
+impl Display for PinnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PinnError::ConfigError { message, .. } => write!(f, "{}", message),
+            PinnError::InvalidConfig { problems } => write!(
+                f,
+                "The configuration is invalid ({} problem(s)): {}",
+                problems.len(),
+                problems.join("; ")
+            ),
+            PinnError::NameHookError { failures } => write!(
+                f,
+                "The name hook could not name every queue and topic: {}",
+                failures.join("; ")
+            ),
+            PinnError::Unconfirmed { action, flag } => write!(
+                f,
+                "Aborted rather than {} without confirmation (pass `{}` to proceed without prompting)",
+                action, flag
+            ),
+            PinnError::UnknownProfile {
+                profile,
+                known,
+                searched,
+            } => write!(
+                f,
+                "No AWS profile named '{}' could be found (known profiles: [{}], searched: {})",
+                profile,
+                known.join(", "),
+                searched.join(", ")
+            ),
+            PinnError::KubeError { operation, .. } => {
+                write!(f, "Kubernetes operation '{}' failed", operation)
+            }
+            PinnError::AwsError {
+                service,
+                operation,
+                resource,
+                ..
+            } => write!(f, "{} {} failed for \"{}\"", service, operation, resource),
+            PinnError::MissingField {
+                operation,
+                resource,
+                field,
+            } => write!(
+                f,
+                "{} for \"{}\" did not return an error, but did not return a {} as expected",
+                operation, resource, field
+            ),
+            PinnError::BudgetExhausted {
+                operation,
+                resource,
+            } => write!(
+                f,
+                "Not attempting {} for \"{}\" (api budget exhausted)",
+                operation, resource
+            ),
+            PinnError::Cancelled {
+                operation,
+                resource,
+            } => write!(
+                f,
+                "Not attempting {} for \"{}\" (run cancelled)",
+                operation, resource
+            ),
+            PinnError::IncompatibleTopic { topic } => write!(
+                f,
+                "Topic \"{}\" exists with incompatible type/attributes",
+                topic
+            ),
+            PinnError::PolicyError { queue, reason } => write!(
+                f,
+                "Cannot create a valid access policy for queue \"{}\": {}",
+                queue, reason
+            ),
+            PinnError::QueueConflict { queue, reason } => {
+                write!(f, "Queue \"{}\" {}", queue, reason)
+            }
+            PinnError::MissingResource { kind, resource } => {
+                write!(f, "The {} \"{}\" does not exist", kind, resource)
+            }
+            PinnError::DriftedResource {
+                kind,
+                resource,
+                differences,
+            } => write!(
+                f,
+                "The {} \"{}\" differs from its configuration: {}",
+                kind,
+                resource,
+                differences.join(", ")
+            ),
+        }
+    }
+}
+
+impl Error for PinnError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PinnError::ConfigError {
+                source: Some(source),
+                ..
+            }
+            | PinnError::KubeError { source, .. }
+            | PinnError::AwsError { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+// </editor-fold desc="// PinnError enum ...">
@@ -0,0 +1,333 @@
+// Pinnothera's real-time (JSON Lines) event streaming components
+
+// Standard Library Imports
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Third Party Imports
+use easy_error::Terminator;
+use serde::Serialize;
+
+// Project-Level Imports
+use crate::context::RunState;
+use crate::logging;
+use crate::plan;
+use crate::report;
+use crate::stats;
+
+// <editor-fold desc="// Event Types ...">
+
+/// The final tallies of a pinnothera run
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct RunSummary {
+    pub queues: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub completed_previously: usize,
+    pub paused: usize,
+    pub api_calls: BTreeMap<&'static str, u64>,
+    pub kube_calls: BTreeMap<&'static str, u64>,
+    pub failures: Vec<logging::FailureGroup>,
+    pub slowest: Vec<stats::ResourceDuration>,
+    pub durations: Vec<stats::DurationBucket>,
+    /// How long ensuring queues and topics, and subscribing them, each took
+    pub phases: Vec<stats::PhaseDuration>,
+    /// The outcome of every queue, topic, and subscription the run handled
+    pub resources: Vec<report::ResourceReport>,
+    /// What each configured queue (and each of its topics) resolved to
+    pub queues_applied: Vec<report::QueueResult>,
+    /// Whether the run was cancelled (via SIGTERM or the control socket)
+    pub cancelled: bool,
+    /// How a conflict between environment sources was settled (if there was one)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_resolution: Option<report::EnvResolution>,
+    /// Everything a dry run would have created (and set on it)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub desired_state: Option<plan::DesiredState>,
+    pub exit_code: u8,
+}
+
+/// The stage of a pinnothera run a fatal failure
+/// occurred in before any work could be done
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StartupFailure {
+    Configuration,
+    Validation,
+    AwsConfiguration,
+    AwsIdentity,
+    Preflight,
+    Checkpoint,
+    NameHook,
+}
+
+/// A fatal failure that occurred before any work could be done, written
+/// to stderr (under `--output json`) in place of the run's summary
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct StartupFailureReport {
+    pub category: StartupFailure,
+    pub messages: Vec<String>,
+    pub exit_code: u8,
+}
+
+impl StartupFailureReport {
+    /// The report as the single-line JSON document written to stderr
+    pub fn to_document(&self) -> String {
+        serde_json::json!({ "error": self }).to_string()
+    }
+}
+
+/// Something noteworthy that happened during a pinnothera run
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum PinnEvent {
+    RunStarted {
+        settings: serde_json::Value,
+    },
+    ResourcePlanned {
+        kind: &'static str,
+        name: String,
+        env: String,
+    },
+    NameResolved {
+        kind: &'static str,
+        base_name: String,
+        env: String,
+        name: String,
+    },
+    ResourcePaused {
+        kind: &'static str,
+        name: String,
+    },
+    OperationStarted {
+        operation: &'static str,
+        resource: String,
+    },
+    OperationSucceeded {
+        operation: &'static str,
+        resource: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        url: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        arn: Option<String>,
+    },
+    OperationFailed {
+        operation: &'static str,
+        resource: String,
+        error: String,
+    },
+    OperationNotAttempted {
+        operation: &'static str,
+        resource: String,
+        reason: String,
+    },
+    RunCancelled {
+        source: String,
+    },
+    RunFinished {
+        summary: Box<RunSummary>,
+    },
+    RunFailed {
+        category: StartupFailure,
+        messages: Vec<String>,
+        exit_code: u8,
+    },
+}
+
+#[derive(Serialize)]
+struct EventRecord<'a> {
+    timestamp_ms: u128,
+    #[serde(flatten)]
+    event: &'a PinnEvent,
+}
+
+// </editor-fold desc="// Event Types ...">
+
+// <editor-fold desc="// Event Stream Utilities ...">
+
+/// Where a run streams its events to (if anywhere)
+#[derive(Default)]
+pub(crate) struct EventStream(Option<Mutex<Box<dyn Write + Send>>>);
+
+impl std::fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EventStream(enabled: {})", self.0.is_some())
+    }
+}
+
+impl EventStream {
+    /// Stream events to the file at the supplied path
+    /// (or to stdout if the path is `-`)
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<EventStream, Terminator> {
+        let path: &Path = path.as_ref();
+
+        let stream: Box<dyn Write + Send> = match path.to_str() {
+            Some("-") => Box::new(std::io::stdout()),
+            _ => Box::new(File::create(path)?),
+        };
+
+        Ok(EventStream(Some(Mutex::new(stream))))
+    }
+}
+
+/// Write the supplied event to the run's event stream (if one is
+/// configured) as a single line of JSON, flushing it immediately
+pub(crate) fn emit(run: &RunState, event: PinnEvent) {
+    let stream = match &run.events.0 {
+        Some(value) => value,
+        None => return,
+    };
+
+    let record = EventRecord {
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default(),
+        event: &event,
+    };
+
+    let mut stream = stream.lock().unwrap();
+
+    let result = serde_json::to_string(&record)
+        .map_err(std::io::Error::from)
+        .and_then(|line| writeln!(stream, "{}", line))
+        .and_then(|_| stream.flush());
+
+    if let Err(error) = result {
+        logging::warning!("Could not write to the event stream: {}", error);
+    }
+}
+
+/// The message of the supplied error followed by those of its causes
+pub(crate) fn error_chain(error: &Terminator) -> Vec<String> {
+    format!("{:?}", error)
+        .lines()
+        .map(|line| line.trim_start_matches("Caused by: ").trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+pub(crate) fn operation_succeeded<R: AsRef<str>>(
+    run: &RunState,
+    operation: &'static str,
+    resource: R,
+    url: Option<&str>,
+    arn: Option<&str>,
+) {
+    emit(
+        run,
+        PinnEvent::OperationSucceeded {
+            operation,
+            resource: resource.as_ref().to_string(),
+            url: url.map(str::to_string),
+            arn: arn.map(str::to_string),
+        },
+    );
+}
+
+pub(crate) fn operation_failed<R: AsRef<str>, E: AsRef<str>>(
+    run: &RunState,
+    operation: &'static str,
+    resource: R,
+    error: E,
+) {
+    logging::record_failure(run, operation, resource.as_ref(), error.as_ref());
+
+    emit(
+        run,
+        PinnEvent::OperationFailed {
+            operation,
+            resource: resource.as_ref().to_string(),
+            error: error.as_ref().to_string(),
+        },
+    );
+}
+
+// </editor-fold desc="// Event Stream Utilities ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use easy_error::ResultExt;
+    use serde_json::{json, Value};
+
+    const CATEGORIES: [(StartupFailure, &str); 7] = [
+        (StartupFailure::Configuration, "configuration"),
+        (StartupFailure::Validation, "validation"),
+        (StartupFailure::AwsConfiguration, "aws_configuration"),
+        (StartupFailure::AwsIdentity, "aws_identity"),
+        (StartupFailure::Preflight, "preflight"),
+        (StartupFailure::Checkpoint, "checkpoint"),
+        (StartupFailure::NameHook, "name_hook"),
+    ];
+
+    #[test]
+    fn startup_failure_document_per_category() {
+        for (category, name) in CATEGORIES {
+            let report = StartupFailureReport {
+                category,
+                messages: vec![format!("{} failed", name), "because of this".to_string()],
+                exit_code: 3,
+            };
+
+            let document: String = report.to_document();
+            assert!(!document.contains('\n'), "{}", document);
+
+            assert_eq!(
+                serde_json::from_str::<Value>(&document).unwrap(),
+                json!({
+                    "error": {
+                        "category": name,
+                        "messages": [format!("{} failed", name), "because of this"],
+                        "exit_code": 3,
+                    }
+                }),
+            );
+        }
+    }
+
+    #[test]
+    fn run_failed_event_per_category() {
+        for (category, name) in CATEGORIES {
+            let event = PinnEvent::RunFailed {
+                category,
+                messages: vec!["a problem".to_string()],
+                exit_code: 2,
+            };
+            let record = EventRecord {
+                timestamp_ms: 1,
+                event: &event,
+            };
+
+            assert_eq!(
+                serde_json::from_str::<Value>(&serde_json::to_string(&record).unwrap()).unwrap(),
+                json!({
+                    "timestamp_ms": 1,
+                    "event": "run_failed",
+                    "category": name,
+                    "messages": ["a problem"],
+                    "exit_code": 2,
+                }),
+            );
+        }
+    }
+
+    #[test]
+    fn error_chain_lists_causes() {
+        let cause = std::io::Error::other("could not x");
+        let error: Terminator = Err::<(), _>(cause)
+            .context("while doing y")
+            .unwrap_err()
+            .into();
+
+        let chain: Vec<String> = error_chain(&error);
+
+        assert_eq!(chain.len(), 2, "{:?}", chain);
+        assert!(chain[0].starts_with("while doing y"), "{:?}", chain);
+        assert_eq!(chain[1], "could not x");
+    }
+}
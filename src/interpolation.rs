@@ -0,0 +1,223 @@
+// Pinnothera's `${NAME}`-style configuration interpolation utilities
+
+// Standard Library Imports
+use std::collections::BTreeMap;
+
+// Project-Level Imports
+use crate::errors::PinnError;
+
+/// Substitute every `${NAME}` and `${NAME:-default}` placeholder in the
+/// supplied raw configuration text with the named variable's value (or
+/// the default, if the variable isn't set), leaving `$${...}` in place as
+/// a literal `${...}`. Every undefined variable without a default is
+/// reported (in the returned error) along with its location in the named source.
+pub(crate) fn interpolate<T: AsRef<str>, S: AsRef<str>>(
+    text: T,
+    source: S,
+    variables: &BTreeMap<String, String>,
+) -> Result<String, PinnError> {
+    let (text, source): (&str, &str) = (text.as_ref(), source.as_ref());
+
+    let mut errors: Vec<String> = Vec::new();
+    let mut expanded = String::with_capacity(text.len());
+    let mut remainder: &str = text;
+
+    while let Some(start) = remainder.find("${") {
+        let (before, placeholder) = remainder.split_at(start);
+
+        if let Some(before) = before.strip_suffix('$') {
+            expanded.push_str(before);
+            expanded.push_str("${");
+            remainder = &placeholder[2..];
+            continue;
+        }
+
+        expanded.push_str(before);
+
+        let end = match placeholder.find('}') {
+            Some(value) => value,
+            None => {
+                errors.push(format!(
+                    "Unterminated placeholder at {}:{}",
+                    source,
+                    location(text, text.len() - remainder.len() + start),
+                ));
+                remainder = "";
+                break;
+            }
+        };
+
+        let (name, default) = match placeholder[2..end].split_once(":-") {
+            Some((name, default)) => (name.trim(), Some(default)),
+            None => (placeholder[2..end].trim(), None),
+        };
+
+        match (variables.get(name), default) {
+            (Some(value), _) => expanded.push_str(value),
+            (None, Some(default)) => expanded.push_str(default),
+            (None, None) => errors.push(format!(
+                "The variable '{}' referenced by '{}' at {}:{} is not set and has no default",
+                name,
+                &placeholder[..=end],
+                source,
+                location(text, text.len() - remainder.len() + start),
+            )),
+        }
+
+        remainder = &placeholder[end + 1..];
+    }
+
+    expanded.push_str(remainder);
+
+    if errors.is_empty() {
+        return Ok(expanded);
+    }
+
+    Err(PinnError::config(format!(
+        "Could not interpolate configuration: {}",
+        errors.join("; ")
+    )))
+}
+
+/// The 1-based "line:column" location of the supplied byte offset in the supplied text
+fn location(text: &str, offset: usize) -> String {
+    let preceding: &str = &text[..offset];
+    let line = preceding.matches('\n').count() + 1;
+    let column = preceding
+        .rsplit('\n')
+        .next()
+        .map(|value| value.chars().count())
+        .unwrap_or_default()
+        + 1;
+
+    format!("{}:{}", line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn variables() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("ENV".to_string(), "staging".to_string()),
+            ("TEAM".to_string(), "orders".to_string()),
+            ("EMPTY".to_string(), String::new()),
+        ])
+    }
+
+    /// The message of the error interpolating the supplied text fails with
+    fn error_message(text: &str) -> String {
+        match interpolate(text, "pinnothera.yaml", &variables()) {
+            Err(PinnError::ConfigError { message, .. }) => message,
+            result => panic!("{:?}: unexpectedly {:?}", text, result),
+        }
+    }
+
+    #[test]
+    fn placeholders_are_substituted() {
+        // (raw text, expanded text)
+        let cases: [(&str, &str); 12] = [
+            ("queue-${ENV}", "queue-staging"),
+            ("${TEAM}-${ENV}", "orders-staging"),
+            ("${ ENV }", "staging"),
+            ("${EMPTY}", ""),
+            ("${MISSING:-prod}", "prod"),
+            ("${MISSING:-}", ""),
+            ("${ENV:-prod}", "staging"),
+            ("${EMPTY:-prod}", ""),
+            ("${MISSING:-a:-b}", "a:-b"),
+            (
+                "no placeholders, $ENV or {ENV}",
+                "no placeholders, $ENV or {ENV}",
+            ),
+            ("$${ENV}", "${ENV}"),
+            ("$${ENV}-${ENV}-$${MISSING}", "${ENV}-staging-${MISSING}"),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(
+                interpolate(text, "pinnothera.yaml", &variables()).unwrap(),
+                expected,
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn missing_variables_are_reported_with_their_location() {
+        assert_eq!(
+            error_message("orders:\n  topics:\n    - placed-${REGION}\n"),
+            "Could not interpolate configuration: The variable 'REGION' referenced by '${REGION}' at pinnothera.yaml:3:14 is not set and has no default",
+        );
+
+        // Every missing variable is reported at once, columns counting characters
+        let message: String = error_message("é: ${A}\n${ENV} ${B}");
+
+        assert!(
+            message.contains("'A' referenced by '${A}' at pinnothera.yaml:1:4"),
+            "{}",
+            message
+        );
+        assert!(
+            message.contains("'B' referenced by '${B}' at pinnothera.yaml:2:8"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn unterminated_placeholders_are_reported_with_their_location() {
+        assert_eq!(
+            error_message("orders:\n  env: ${ENV"),
+            "Could not interpolate configuration: Unterminated placeholder at pinnothera.yaml:2:8",
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_text_never_panics(text in any::<String>()) {
+            let _ = interpolate(&text, "pinnothera.yaml", &variables());
+        }
+
+        #[test]
+        fn text_without_placeholders_is_unchanged(text in "[^$]*") {
+            prop_assert_eq!(interpolate(&text, "pinnothera.yaml", &variables()).unwrap(), text);
+        }
+
+        #[test]
+        fn set_variables_are_substituted(
+            name in "[A-Z_][A-Z0-9_]{0,15}",
+            value in "[^$]*",
+            (before, after) in ("[^$]*", "[^$]*"),
+        ) {
+            let variables = BTreeMap::from([(name.clone(), value.clone())]);
+            let text: String = format!("{}${{{}}}{}", before, name, after);
+
+            prop_assert_eq!(
+                interpolate(text, "pinnothera.yaml", &variables).unwrap(),
+                format!("{}{}{}", before, value, after)
+            );
+        }
+
+        #[test]
+        fn defaults_apply_to_unset_variables(name in "[A-Z_][A-Z0-9_]{0,15}", default in "[^$}]*") {
+            prop_assert_eq!(
+                interpolate(format!("${{{}:-{}}}", name, default), "pinnothera.yaml", &BTreeMap::new())
+                    .unwrap(),
+                default
+            );
+        }
+
+        #[test]
+        fn escaped_placeholders_are_kept_literally(name in "[A-Z_][A-Z0-9_]{0,15}", value in "[^$]*") {
+            let variables = BTreeMap::from([(name.clone(), value)]);
+
+            prop_assert_eq!(
+                interpolate(format!("$${{{}}}", name), "pinnothera.yaml", &variables).unwrap(),
+                format!("${{{}}}", name)
+            );
+        }
+    }
+}
@@ -0,0 +1,261 @@
+// Pinnothera - a dead simple Kubernetes-native SNS/SQS configurator
+
+// The run settings outgrew `serde_json::json!`'s default recursion limit
+#![recursion_limit = "256"]
+
+// Standard Library Imports
+use std::fmt::Formatter;
+use std::future::Future;
+use std::sync::Arc;
+
+// Third Party Imports
+use aws_sdk_sns::Client as SNSClient;
+use aws_sdk_sqs::Client as SQSClient;
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+
+// Project-Level Imports
+pub use app::run;
+pub(crate) use cli::{AWSService, CLIArgs, PinnCommand, PurgeArgs, SmokeTestArgs};
+pub(crate) use context::{AppContext, RunState, TopicCell};
+pub use errors::PinnError;
+pub use report::{ResourceReport, ResourceStatus};
+pub use settings::Settings;
+pub(crate) use types::{
+    DeadLetterConfig, SNSTopicARN, SQSQueueARN, SQSQueueConfig, SQSQueueURL, TopicConfig,
+};
+pub use types::{EnvName, PinnConfig};
+
+mod app;
+pub(crate) mod checkpoint;
+pub(crate) mod cli;
+pub(crate) mod context;
+pub(crate) mod control;
+pub(crate) mod errors;
+pub(crate) mod events;
+pub(crate) mod interpolation;
+pub(crate) mod logging;
+pub(crate) mod name_hook;
+pub(crate) mod naming;
+pub(crate) mod plan;
+pub(crate) mod regions;
+pub(crate) mod report;
+pub(crate) mod retry;
+pub(crate) mod settings;
+pub(crate) mod smoke;
+pub(crate) mod stats;
+#[cfg(test)]
+pub(crate) mod testing;
+pub(crate) mod types;
+
+// <editor-fold desc="// AwsClients ...">
+
+/// The AWS clients a configuration is applied with, and what's
+/// known of the region and account they act in
+#[derive(Clone, Debug)]
+pub struct AwsClients {
+    pub sns: SNSClient,
+    pub sqs: SQSClient,
+    /// The region the clients use, without which queue access
+    /// policies and topic ARNs can't be rendered
+    pub region: Option<String>,
+    /// The AWS account id the clients act in, without which queue
+    /// access policies and topic ARNs can't be rendered
+    pub account_id: Option<String>,
+    /// The custom endpoint (i.e. LocalStack) the clients use, if
+    /// any, which is named in the hints logged alongside errors
+    pub endpoint_url: Option<String>,
+    /// Where the SNS clients for topics configured with their own
+    /// `role_arn` and/or `region` come from, without which
+    /// subscriptions to such topics fail
+    pub topic_clients: Option<TopicClientFactory>,
+}
+
+/// The SNS client to subscribe to a topic with, given the role it should
+/// act as and/or the region it should be in, or why it couldn't be created
+type TopicClientFn = dyn Fn(Option<String>, Option<String>) -> BoxFuture<'static, Result<SNSClient, String>>
+    + Send
+    + Sync;
+
+/// Creates the SNS clients subscriptions to topics configured with their
+/// own `role_arn` (to be assumed) and/or `region` are made with
+#[derive(Clone)]
+pub struct TopicClientFactory(Arc<TopicClientFn>);
+
+impl TopicClientFactory {
+    pub fn new<F, R>(factory: F) -> Self
+    where
+        F: Fn(Option<String>, Option<String>) -> R + Send + Sync + 'static,
+        R: Future<Output = Result<SNSClient, String>> + Send + 'static,
+    {
+        TopicClientFactory(Arc::new(move |role_arn, region| {
+            Box::pin(factory(role_arn, region))
+        }))
+    }
+
+    /// The SNS client acting as the supplied role and/or in the supplied region
+    pub(crate) async fn client(
+        &self,
+        role_arn: Option<&str>,
+        region: Option<&str>,
+    ) -> Result<SNSClient, String> {
+        (self.0)(role_arn.map(str::to_string), region.map(str::to_string)).await
+    }
+}
+
+impl std::fmt::Debug for TopicClientFactory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TopicClientFactory")
+    }
+}
+
+// </editor-fold desc="// AwsClients ...">
+
+// <editor-fold desc="// ApplyReport ...">
+
+/// What applying a configuration did with (or to) each resource
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ApplyReport {
+    /// Each queue, topic, and subscription handled, most noteworthy first
+    pub resources: Vec<ResourceReport>,
+    /// The number of queues whose configuration couldn't be (fully) applied
+    pub failed_queues: usize,
+}
+
+impl ApplyReport {
+    /// The resources that were left with the supplied status
+    pub fn with_status(&self, status: ResourceStatus) -> impl Iterator<Item = &ResourceReport> {
+        self.resources
+            .iter()
+            .filter(move |report| report.status == status)
+    }
+
+    /// Determine whether every queue's configuration was applied
+    /// in full (and, if `check` was set, AWS matches it)
+    pub fn is_success(&self) -> bool {
+        self.failed_queues == 0
+            && !self.resources.iter().any(|report| {
+                matches!(
+                    report.status,
+                    ResourceStatus::Failed | ResourceStatus::Missing | ResourceStatus::Drifted
+                )
+            })
+    }
+}
+
+// </editor-fold desc="// ApplyReport ...">
+
+// <editor-fold desc="// Apply ...">
+
+/// Apply the supplied configuration for the supplied environment with the
+/// supplied clients and settings (ignoring any queues that are paused),
+/// failing outright only if the configuration is invalid or the name hook
+/// can't name its queues and topics, and otherwise reporting what became
+/// of each of its resources
+pub async fn apply(
+    config: &PinnConfig,
+    env: EnvName,
+    clients: &AwsClients,
+    settings: &Settings,
+) -> Result<ApplyReport, PinnError> {
+    let mut config: PinnConfig = config.clone();
+
+    config
+        .validate(
+            settings.sanitize_replacement(),
+            settings.name_hook.is_some(),
+            settings.strict_filters,
+            clients.region.as_deref(),
+        )
+        .and_then(|_| app::resolve_tag_overflows(settings, &mut config))?;
+
+    let run: Arc<RunState> = Arc::new(RunState::new(settings, events::EventStream::default()));
+    let mut context = AppContext::new(env, clients, settings.clone(), run);
+
+    app::resolve_hooked_names(&mut context, &config)?;
+
+    let queues: Vec<(String, SQSQueueConfig)> = config
+        .iter()
+        .map(|(queue, queue_config)| (queue.to_string(), config.with_global_settings(queue_config)))
+        .filter(|(queue, queue_config)| !settings.is_paused(queue, queue_config))
+        .collect();
+
+    let context: Arc<AppContext> = Arc::new(context);
+    let results: Vec<u8> = app::apply_queues(&context, queues).await;
+
+    Ok(ApplyReport {
+        resources: report::resource_reports(&context.run),
+        failed_queues: results.iter().filter(|result| **result > 0).count(),
+    })
+}
+
+// </editor-fold desc="// Apply ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, FakeAws};
+
+    #[tokio::test]
+    async fn configurations_are_applied_without_the_command_line() {
+        let fake: FakeAws = FakeAws::start(testing::aws_response).await;
+        let (sns, sqs, _) = fake.args(&[]).aws_clients(EnvName::Dev).await.unwrap();
+        let clients = AwsClients {
+            sns,
+            sqs,
+            region: Some("us-east-1".to_string()),
+            account_id: Some(testing::FAKE_ACCOUNT_ID.to_string()),
+            endpoint_url: None,
+            topic_clients: None,
+        };
+        let settings = Settings {
+            pause_queues: vec!["billing".to_string()],
+            ..Default::default()
+        };
+        let config: PinnConfig =
+            PinnConfig::from_yaml("orders: {topics: [placed]}\nbilling: {topics: [invoiced]}\n")
+                .unwrap();
+
+        let report: ApplyReport = apply(&config, EnvName::Dev, &clients, &settings)
+            .await
+            .unwrap();
+
+        assert!(report.is_success());
+        assert_eq!(
+            report
+                .resources
+                .iter()
+                .map(|resource| (resource.kind, resource.name.as_str()))
+                .collect::<Vec<(&str, &str)>>(),
+            vec![
+                (
+                    "subscription",
+                    "placed -> arn:aws:sqs:us-east-1:123456789012:orders-dev"
+                ),
+                ("queue", "orders-dev"),
+                ("topic", "placed-dev"),
+            ]
+        );
+        assert_eq!(report.with_status(ResourceStatus::Created).count(), 1);
+
+        // The paused queue is left untouched
+        assert_eq!(
+            fake.calls("CreateQueue")
+                .iter()
+                .map(|request| request.param("QueueName").unwrap().to_string())
+                .collect::<Vec<String>>(),
+            vec!["orders-dev"]
+        );
+
+        // An invalid configuration is rejected before any AWS calls are made
+        let requests: usize = fake.requests().len();
+        let invalid: PinnConfig =
+            PinnConfig::from_yaml("orders: {topics: [placed], delay_secs: 901}").unwrap();
+
+        assert!(matches!(
+            apply(&invalid, EnvName::Dev, &clients, &settings).await,
+            Err(PinnError::InvalidConfig { .. })
+        ));
+        assert_eq!(fake.requests().len(), requests);
+    }
+}
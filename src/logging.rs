@@ -0,0 +1,619 @@
+// Pinnothera's console output verbosity and log formatting components
+
+// Standard Library Imports
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Third Party Imports
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Instrument, Level, Metadata, Subscriber};
+
+// Project-Level Imports
+use crate::context::RunState;
+use crate::events::RunSummary;
+
+// <editor-fold desc="// Global Statics ...">
+
+tokio::task_local! {
+    static QUEUE_CONTEXT: String;
+}
+
+thread_local! {
+    static ENTERED_SPANS: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+// </editor-fold desc="// Global Statics ...">
+
+// <editor-fold desc="// Verbosity ...">
+
+/// How much pinnothera should print to the console
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) enum Verbosity {
+    /// Only warnings, errors, and the final summary
+    Quiet,
+    #[default]
+    Normal,
+    /// Everything, including debug detail (i.e. rendered policies)
+    Verbose,
+}
+
+impl Verbosity {
+    pub fn from_flags(quiet: bool, verbose: bool) -> Verbosity {
+        match (quiet, verbose) {
+            (true, _) => Verbosity::Quiet,
+            (_, true) => Verbosity::Verbose,
+            _ => Verbosity::Normal,
+        }
+    }
+
+    /// The most detailed level of log line a run
+    /// with this verbosity should write
+    fn max_level(&self) -> LevelFilter {
+        match self {
+            Verbosity::Quiet => LevelFilter::WARN,
+            Verbosity::Normal => LevelFilter::INFO,
+            Verbosity::Verbose => LevelFilter::DEBUG,
+        }
+    }
+}
+
+/// The field marking log lines written via `error_detail!`,
+/// which quiet runs leave out
+const DETAIL_FIELD: &str = "detail";
+
+/// Log the supplied informational message
+/// (unless the current run is quiet)
+macro_rules! info {
+    ($($arg:tt)*) => {
+        ::tracing::info!($($arg)*)
+    };
+}
+
+/// Log the supplied debug detail (only
+/// if the current run is verbose)
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        ::tracing::debug!($($arg)*)
+    };
+}
+
+/// Log the supplied warning
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        ::tracing::warn!($($arg)*)
+    };
+}
+
+/// Log the supplied error
+macro_rules! error {
+    ($($arg:tt)*) => {
+        ::tracing::error!($($arg)*)
+    };
+}
+
+/// Log the supplied detailed error description (unless the
+/// current run is quiet, in which case failures are instead
+/// reported once per distinct failure alongside the final summary)
+macro_rules! error_detail {
+    ($($arg:tt)*) => {
+        ::tracing::error!(detail = true, $($arg)*)
+    };
+}
+
+pub(crate) use {debug, error, error_detail, info, warning};
+
+// </editor-fold desc="// Verbosity ...">
+
+// <editor-fold desc="// Log Formatting ...">
+
+/// The format pinnothera writes its log lines in
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) enum LogFormat {
+    /// Plain messages (warnings and errors prefixed as such)
+    #[default]
+    Text,
+    /// One JSON object per line, carrying the message's structured
+    /// fields (and those of the queue/topic it was written for)
+    Json,
+}
+
+impl From<&str> for LogFormat {
+    fn from(value: &str) -> Self {
+        match value {
+            "json" => LogFormat::Json,
+            _ => LogFormat::Text,
+        }
+    }
+}
+
+/// Collects the fields of a span or event as JSON values
+struct FieldVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(
+            field.name().to_string(),
+            Value::from(format!("{:?}", value)),
+        );
+    }
+}
+
+struct LogSpan {
+    /// The span's own fields, along with those of its parent(s)
+    fields: Map<String, Value>,
+    references: usize,
+}
+
+/// Writes pinnothera's log lines to stdout (or stderr) in the configured
+/// format, only letting through other crates' (i.e. the AWS SDK's) log
+/// lines if the run's log level is `trace`
+struct LogWriter {
+    max_level: LevelFilter,
+    verbosity: Verbosity,
+    format: LogFormat,
+    stderr: bool,
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, LogSpan>>,
+}
+
+impl LogWriter {
+    fn current_span(&self) -> Option<u64> {
+        ENTERED_SPANS.with(|entered| entered.borrow().last().copied())
+    }
+
+    fn span_fields(&self, id: Option<u64>) -> Map<String, Value> {
+        id.and_then(|id| {
+            self.spans
+                .lock()
+                .unwrap()
+                .get(&id)
+                .map(|span| span.fields.clone())
+        })
+        .unwrap_or_default()
+    }
+
+    fn format_line(&self, level: &Level, target: &str, mut fields: Map<String, Value>) -> String {
+        let message = match fields.remove("message") {
+            Some(Value::String(message)) => message,
+            Some(value) => value.to_string(),
+            None => String::new(),
+        };
+
+        match self.format {
+            LogFormat::Text => match *level {
+                Level::ERROR => format!("ERROR: {}", message),
+                Level::WARN => format!("WARNING: {}", message),
+                _ => message,
+            },
+            LogFormat::Json => {
+                let mut line = Map::new();
+
+                line.insert(
+                    "timestamp_ms".to_string(),
+                    Value::from(
+                        SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map(|elapsed| elapsed.as_millis() as u64)
+                            .unwrap_or_default(),
+                    ),
+                );
+                line.insert(
+                    "level".to_string(),
+                    Value::from(level.as_str().to_lowercase()),
+                );
+                line.insert("target".to_string(), Value::from(target));
+                line.insert("message".to_string(), Value::from(message));
+                line.extend(fields);
+
+                Value::Object(line).to_string()
+            }
+        }
+    }
+}
+
+impl Subscriber for LogWriter {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        let own = metadata.target().starts_with(env!("CARGO_CRATE_NAME"));
+
+        // pinnothera's own spans only carry context, so they're
+        // always kept regardless of the run's log level
+        if own && metadata.is_span() {
+            return true;
+        }
+
+        if self.verbosity == Verbosity::Quiet && metadata.fields().field(DETAIL_FIELD).is_some() {
+            return false;
+        }
+
+        metadata.level() <= &self.max_level && (own || self.max_level == LevelFilter::TRACE)
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        let parent = match (attributes.parent(), attributes.is_contextual()) {
+            (Some(parent), _) => Some(parent.into_u64()),
+            (None, true) => self.current_span(),
+            (None, false) => None,
+        };
+
+        let mut fields = self.span_fields(parent);
+        attributes.record(&mut FieldVisitor(&mut fields));
+
+        self.spans.lock().unwrap().insert(
+            id,
+            LogSpan {
+                fields,
+                references: 1,
+            },
+        );
+
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            values.record(&mut FieldVisitor(&mut span.fields));
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let parent = match (event.parent(), event.is_contextual()) {
+            (Some(parent), _) => Some(parent.into_u64()),
+            (None, true) => self.current_span(),
+            (None, false) => None,
+        };
+
+        let mut fields = self.span_fields(parent);
+        event.record(&mut FieldVisitor(&mut fields));
+        fields.remove(DETAIL_FIELD);
+
+        let metadata = event.metadata();
+        let line = self.format_line(metadata.level(), metadata.target(), fields);
+
+        match self.stderr {
+            true => writeln!(std::io::stderr().lock(), "{}", line).ok(),
+            false => writeln!(std::io::stdout().lock(), "{}", line).ok(),
+        };
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED_SPANS.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED_SPANS.with(|entered| {
+            let mut entered = entered.borrow_mut();
+            if let Some(position) = entered.iter().rposition(|id| *id == span.into_u64()) {
+                entered.remove(position);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            span.references += 1;
+        }
+
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.spans.lock().unwrap();
+
+        match spans.get_mut(&span.into_u64()) {
+            Some(value) if value.references > 1 => {
+                value.references -= 1;
+                false
+            }
+            Some(_) => {
+                spans.remove(&span.into_u64());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Start writing the log lines of the current run in the supplied format
+/// (to stderr rather than stdout if requested), at the supplied level
+/// (or the one implied by the run's verbosity)
+pub(crate) fn initialize(
+    verbosity: Verbosity,
+    level: Option<&str>,
+    format: LogFormat,
+    stderr: bool,
+) {
+    let max_level = match level.map(str::parse::<LevelFilter>) {
+        Some(Ok(level)) => level,
+        _ => verbosity.max_level(),
+    };
+
+    tracing::subscriber::set_global_default(LogWriter {
+        max_level,
+        verbosity,
+        format,
+        stderr,
+        next_id: AtomicU64::new(1),
+        spans: Mutex::new(HashMap::new()),
+    })
+    .ok();
+}
+
+/// The message of the supplied error followed by those of its
+/// causes, as a single line (i.e. "could not x: because of y")
+pub(crate) fn error_chain<E: std::error::Error>(error: &E) -> String {
+    error_causes(error).join(": ")
+}
+
+/// The (deduplicated) messages of the supplied error and each of its causes
+pub(crate) fn error_causes<E: std::error::Error>(error: &E) -> Vec<String> {
+    let mut messages: Vec<String> = vec![error.to_string()];
+    let mut source = error.source();
+
+    while let Some(cause) = source {
+        let message = cause.to_string();
+        if !messages.contains(&message) {
+            messages.push(message);
+        }
+        source = cause.source();
+    }
+
+    messages
+}
+
+// </editor-fold desc="// Log Formatting ...">
+
+// <editor-fold desc="// Failure Reporting Utilities ...">
+
+/// The most affected resources the summary names for a group of failures
+const MAX_FAILURE_EXAMPLES: usize = 3;
+
+/// The failures recorded during a single run
+#[derive(Debug, Default)]
+pub(crate) struct FailureLog(Mutex<Vec<Failure>>);
+
+/// A failed operation on a single resource
+#[derive(Clone, Debug)]
+struct Failure {
+    queue: String,
+    operation: &'static str,
+    resource: String,
+    error: String,
+}
+
+/// Failures of the same operation with the same error
+/// (once the affected resource is disregarded)
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct FailureGroup {
+    pub operation: &'static str,
+    /// The shared error message, with the affected
+    /// resource (and any ARNs) replaced by placeholders
+    pub error: String,
+    /// The verbatim error message of the first affected resource
+    #[serde(skip)]
+    pub example: String,
+    pub queues: BTreeSet<String>,
+    pub resources: Vec<String>,
+}
+
+/// Run the supplied future with the named (configured) queue as the
+/// context any failures that occur while running it are grouped under
+/// (and as the `queue` field of any log lines it writes)
+pub(crate) async fn with_queue_context<T: AsRef<str>, F: Future>(queue: T, future: F) -> F::Output {
+    let span = tracing::info_span!("queue", queue = queue.as_ref());
+
+    QUEUE_CONTEXT
+        .scope(queue.as_ref().to_string(), future.instrument(span))
+        .await
+}
+
+/// Run the supplied future with the named (configured) topic
+/// as the `topic` field of any log lines it writes
+pub(crate) async fn with_topic_context<T: AsRef<str>, F: Future>(topic: T, future: F) -> F::Output {
+    future
+        .instrument(tracing::info_span!("topic", topic = topic.as_ref()))
+        .await
+}
+
+/// The (configured) queue whose configuration is currently being applied
+pub(crate) fn queue_context() -> Option<String> {
+    QUEUE_CONTEXT.try_with(|queue| queue.clone()).ok()
+}
+
+/// Record the failure of the named operation on the supplied
+/// resource under the queue it was performed on behalf of
+pub(crate) fn record_failure<R: AsRef<str>, E: AsRef<str>>(
+    run: &RunState,
+    operation: &'static str,
+    resource: R,
+    error: E,
+) {
+    let queue = queue_context().unwrap_or_else(|| resource.as_ref().to_string());
+
+    run.failures.0.lock().unwrap().push(Failure {
+        queue,
+        operation,
+        resource: resource.as_ref().to_string(),
+        error: error.as_ref().to_string(),
+    });
+}
+
+/// The supplied error message with any mention of the resource it
+/// occurred on (or of any ARN) replaced by a placeholder, so that
+/// identical failures of different resources can be grouped
+fn error_template(resource: &str, error: &str) -> String {
+    let error = match resource.is_empty() {
+        true => error.to_string(),
+        false => error.replace(resource, "<resource>"),
+    };
+
+    let mut template = String::with_capacity(error.len());
+    let mut remaining: &str = error.as_str();
+
+    while let Some(start) = remaining.find("arn:") {
+        template.push_str(&remaining[..start]);
+        template.push_str("<arn>");
+
+        let rest = &remaining[start..];
+        let end = rest
+            .find(|c: char| c.is_whitespace() || "\"',;()[]{}".contains(c))
+            .unwrap_or(rest.len());
+
+        remaining = &rest[end..];
+    }
+
+    template.push_str(remaining);
+    template
+}
+
+/// Every failure recorded during the supplied run, grouped by
+/// operation and (resource-independent) error message, most
+/// widespread first
+pub(crate) fn grouped_failures(run: &RunState) -> Vec<FailureGroup> {
+    let mut groups: Vec<FailureGroup> = Vec::new();
+
+    run.failures.0.lock().unwrap().iter().for_each(|failure| {
+        let template = error_template(&failure.resource, &failure.error);
+
+        let group = match groups
+            .iter()
+            .position(|group| group.operation == failure.operation && group.error == template)
+        {
+            Some(index) => &mut groups[index],
+            None => {
+                groups.push(FailureGroup {
+                    operation: failure.operation,
+                    error: template,
+                    example: failure.error.clone(),
+                    queues: BTreeSet::new(),
+                    resources: Vec::new(),
+                });
+                groups.last_mut().unwrap()
+            }
+        };
+
+        group.queues.insert(failure.queue.clone());
+        group.resources.push(failure.resource.clone());
+    });
+
+    groups.sort_by_key(|group| std::cmp::Reverse(group.resources.len()));
+
+    groups
+}
+
+/// Print the final tallies of the current run, along with any
+/// failures that occurred (each distinct failure printed once)
+pub(crate) fn print_summary(summary: &RunSummary) {
+    println!(
+        "Summary: {} queue(s) configured, {} succeeded, {} failed, {} completed previously, {} paused",
+        summary.queues,
+        summary.succeeded,
+        summary.failed,
+        summary.completed_previously,
+        summary.paused,
+    );
+
+    if summary.cancelled {
+        println!("  The run was cancelled, so operations it hadn't started were not attempted");
+    }
+
+    if let Some(resolution) = &summary.env_resolution {
+        println!(
+            "  Environment \"{}\" (from {}) was used over \"{}\" (from {}) per `--env-conflict {}`",
+            resolution.chosen,
+            resolution.chosen_source,
+            resolution.overridden,
+            resolution.overridden_source,
+            resolution.policy,
+        );
+    }
+
+    if !summary.phases.is_empty() {
+        println!(
+            "  Phases: {}",
+            summary
+                .phases
+                .iter()
+                .map(|phase| format!("{} {}ms", phase.phase.replace('_', " "), phase.duration_ms))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+
+    if !summary.slowest.is_empty() {
+        println!("  Slowest resources:");
+
+        summary.slowest.iter().for_each(|resource| {
+            println!(
+                "    - {} \"{}\": {}ms",
+                resource.kind, resource.name, resource.duration_ms
+            )
+        });
+    }
+
+    if summary.failures.is_empty() {
+        return;
+    }
+
+    println!("  Failures:");
+
+    summary.failures.iter().for_each(|group| {
+        if let [resource] = group.resources.as_slice() {
+            println!(
+                "    - {} \"{}\": {}",
+                group.operation, resource, group.example
+            );
+            return;
+        }
+
+        println!(
+            "    - {}: {} ({} resources affected across {} queue(s), e.g. {}{})",
+            group.operation,
+            group.error,
+            group.resources.len(),
+            group.queues.len(),
+            group
+                .resources
+                .iter()
+                .take(MAX_FAILURE_EXAMPLES)
+                .map(|resource| format!("\"{}\"", resource))
+                .collect::<Vec<String>>()
+                .join(", "),
+            match group.resources.len() > MAX_FAILURE_EXAMPLES {
+                true => ", ...",
+                false => "",
+            },
+        );
+    });
+}
+
+// </editor-fold desc="// Failure Reporting Utilities ...">
@@ -0,0 +1,279 @@
+// Pinnothera's external name decoration hook components
+
+// Standard Library Imports
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// Third Party Imports
+use serde::Serialize;
+
+// Project-Level Imports
+use crate::context::RunState;
+use crate::errors::PinnError;
+use crate::events::{self, PinnEvent};
+use crate::logging::{error, info};
+use crate::naming;
+
+// <editor-fold desc="// Name Hook Types ...">
+
+/// The longest name AWS allows for SNS topics and SQS queues
+pub(crate) const MAX_AWS_NAME_LENGTH: usize = 80;
+
+/// The document a name hook receives (as JSON) on its stdin
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub(crate) struct NameHookRequest {
+    pub base_name: String,
+    pub kind: &'static str,
+    pub env: String,
+    pub namespace: Option<String>,
+    pub fifo: bool,
+}
+
+// </editor-fold desc="// Name Hook Types ...">
+
+// <editor-fold desc="// Name Hook Utilities ...">
+
+/// Invoke the supplied name hook command once for each of the supplied
+/// (deduplicated) requests, returning the physical names it prints for
+/// the rest of the run to use, and failing if any invocation fails or
+/// prints a name AWS wouldn't accept
+pub(crate) fn resolve_names<C, I>(
+    run: &RunState,
+    command: C,
+    requests: I,
+) -> Result<BTreeMap<NameHookRequest, String>, PinnError>
+where
+    C: AsRef<str>,
+    I: IntoIterator<Item = NameHookRequest>,
+{
+    let mut names: BTreeMap<NameHookRequest, String> = BTreeMap::new();
+    let mut errors: Vec<String> = Vec::new();
+
+    for request in requests {
+        if names.contains_key(&request) {
+            continue;
+        }
+
+        match invoke(command.as_ref(), &request) {
+            Ok(name) if name.is_empty() => errors.push(format!(
+                "The name hook printed an empty name for {} \"{}\"",
+                request.kind, request.base_name
+            )),
+            Ok(name) if request.fifo != name.ends_with(naming::FIFO_EXTENSION) => {
+                errors.push(format!(
+                    "The name hook's name \"{}\" for {} \"{}\" must {}end with \"{}\"",
+                    name,
+                    request.kind,
+                    request.base_name,
+                    if request.fifo { "" } else { "not " },
+                    naming::FIFO_EXTENSION
+                ))
+            }
+            Ok(name)
+                if name.len() > MAX_AWS_NAME_LENGTH
+                    || !naming::is_aws_safe_name(naming::strip_fifo_extension(&name)) =>
+            {
+                errors.push(format!(
+                    "The name hook's name \"{}\" for {} \"{}\" is longer than {} characters or contains characters AWS does not allow",
+                    name, request.kind, request.base_name, MAX_AWS_NAME_LENGTH
+                ))
+            }
+            Ok(name) => {
+                info!(
+                    "Using name \"{}\" for {} \"{}\" per the name hook",
+                    &name, request.kind, request.base_name
+                );
+                events::emit(run, PinnEvent::NameResolved {
+                    kind: request.kind,
+                    base_name: request.base_name.clone(),
+                    env: request.env.clone(),
+                    name: name.clone(),
+                });
+                names.insert(request, name);
+            }
+            Err(error) => errors.push(format!(
+                "The name hook failed for {} \"{}\": {}",
+                request.kind, request.base_name, error
+            )),
+        }
+    }
+
+    if !errors.is_empty() {
+        errors.iter().for_each(|error| error!("{}", error));
+        return Err(PinnError::NameHookError { failures: errors });
+    }
+
+    Ok(names)
+}
+
+fn invoke(command: &str, request: &NameHookRequest) -> Result<String, String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|error| error.to_string())?;
+
+    let input = serde_json::to_vec(request).map_err(|error| error.to_string())?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(&input).map_err(|error| error.to_string())?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|error| error.to_string())?;
+
+    if !output.status.success() {
+        return Err(format!("the command exited with {}", output.status));
+    }
+
+    String::from_utf8(output.stdout)
+        .map(|name| name.trim().to_string())
+        .map_err(|error| error.to_string())
+}
+
+// </editor-fold desc="// Name Hook Utilities ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use std::path::{Path, PathBuf};
+
+    fn request(base_name: &str, kind: &'static str, fifo: bool) -> NameHookRequest {
+        NameHookRequest {
+            base_name: base_name.to_string(),
+            kind,
+            env: "dev".to_string(),
+            namespace: Some("orders".to_string()),
+            fifo,
+        }
+    }
+
+    /// Write a name hook script with the supplied body to the supplied
+    /// directory (logging each invocation there), returning its command
+    fn hook(dir: &Path, body: &str) -> String {
+        let script: PathBuf = dir.join("hook.sh");
+
+        std::fs::write(
+            &script,
+            format!(
+                "echo invoked >> '{}'\n{}\n",
+                dir.join("calls").display(),
+                body
+            ),
+        )
+        .unwrap();
+
+        format!("sh '{}'", script.display())
+    }
+
+    fn invocations(dir: &Path) -> usize {
+        std::fs::read_to_string(dir.join("calls"))
+            .unwrap_or_default()
+            .lines()
+            .count()
+    }
+
+    /// The failures of the supplied hook for the supplied requests
+    fn failures(command: &str, requests: Vec<NameHookRequest>) -> Vec<String> {
+        match resolve_names(&RunState::default(), command, requests) {
+            Err(PinnError::NameHookError { failures }) => failures,
+            result => panic!("{}: unexpectedly {:?}", command, result),
+        }
+    }
+
+    #[test]
+    fn hooked_names_are_resolved_once_per_request() {
+        let dir: PathBuf = testing::scratch_dir("name-hook-success");
+        // Decorate each name with its env and namespace, per the request on stdin
+        let command: String = hook(
+            &dir,
+            r#"sed -n 's/.*"base_name":"\([^"]*\)".*"env":"\([^"]*\)","namespace":"\([^"]*\)".*/\3-\1-\2-x1/p'"#,
+        );
+
+        let names = resolve_names(
+            &RunState::default(),
+            &command,
+            [
+                request("placed", "topic", false),
+                request("orders", "queue", false),
+                request("placed", "topic", false),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            names.values().collect::<Vec<&String>>(),
+            vec!["orders-orders-dev-x1", "orders-placed-dev-x1"]
+        );
+        assert_eq!(
+            names[&request("placed", "topic", false)],
+            "orders-placed-dev-x1"
+        );
+        assert_eq!(invocations(&dir), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failing_hooks_fail_every_request() {
+        let dir: PathBuf = testing::scratch_dir("name-hook-failure");
+        let command: String = hook(&dir, "exit 3");
+
+        let failures: Vec<String> = failures(
+            &command,
+            vec![
+                request("orders", "queue", false),
+                request("placed", "topic", false),
+            ],
+        );
+
+        assert_eq!(failures.len(), 2);
+        assert!(
+            failures[0]
+                .starts_with("The name hook failed for queue \"orders\": the command exited with"),
+            "{:?}",
+            failures
+        );
+        assert_eq!(invocations(&dir), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_hooked_names_are_rejected() {
+        let dir: PathBuf = testing::scratch_dir("name-hook-invalid");
+
+        // (the name the hook prints, whether the request is for a FIFO queue, expected failure)
+        let cases: [(&str, bool, &str); 5] = [
+            ("", false, "printed an empty name"),
+            (
+                "orders dev!",
+                false,
+                "contains characters AWS does not allow",
+            ),
+            (
+                &"o".repeat(MAX_AWS_NAME_LENGTH + 1),
+                false,
+                "longer than 80 characters",
+            ),
+            ("orders-dev", true, "must end with \".fifo\""),
+            ("orders-dev.fifo", false, "must not end with \".fifo\""),
+        ];
+
+        for (name, fifo, expected) in cases {
+            let command: String = hook(&dir, &format!("echo '{}'", name));
+            let failures: Vec<String> = failures(&command, vec![request("orders", "queue", fifo)]);
+
+            assert_eq!(failures.len(), 1, "{:?}", name);
+            assert!(failures[0].contains(expected), "{:?}: {:?}", name, failures);
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,769 @@
+// Pinnothera's SNS/SQS resource naming utilities
+
+// Standard Library Imports
+use std::collections::BTreeMap;
+
+/// The character pinnothera replaces characters AWS
+/// disallows in topic and queue names with by default
+pub(crate) const DEFAULT_SANITIZE_REPLACEMENT: &str = "-";
+
+/// The extension AWS requires the names of FIFO topics and queues to end with
+pub(crate) const FIFO_EXTENSION: &str = ".fifo";
+
+/// Determine whether AWS allows the supplied character
+/// to appear in SNS topic and SQS queue names
+pub(crate) fn is_aws_safe_char(character: char) -> bool {
+    character.is_ascii_alphanumeric() || character == '-' || character == '_'
+}
+
+/// Determine whether every character in the supplied
+/// name is allowed in SNS topic and SQS queue names
+pub(crate) fn is_aws_safe_name<T: AsRef<str>>(name: T) -> bool {
+    name.as_ref().chars().all(is_aws_safe_char)
+}
+
+/// Replace every character AWS disallows in SNS topic
+/// and SQS queue names with the supplied replacement
+pub(crate) fn sanitize_name<T: AsRef<str>, R: AsRef<str>>(name: T, replacement: R) -> String {
+    name.as_ref()
+        .chars()
+        .map(|character| match is_aws_safe_char(character) {
+            true => character.to_string(),
+            false => replacement.as_ref().to_string(),
+        })
+        .collect()
+}
+
+/// The supplied name without its `.fifo` extension (if it has one)
+pub(crate) fn strip_fifo_extension(name: &str) -> &str {
+    name.strip_suffix(FIFO_EXTENSION).unwrap_or(name)
+}
+
+/// The delimiter pinnothera joins the environment
+/// to topic and queue names with by default
+pub(crate) const DEFAULT_ENV_DELIMITER: char = '-';
+
+/// Which end of topic and queue names the environment is applied to
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) enum EnvPosition {
+    /// Before the name (i.e. `dev-orders`)
+    Prefix,
+    /// After the name (i.e. `orders-dev`)
+    #[default]
+    Suffix,
+}
+
+impl From<&str> for EnvPosition {
+    fn from(value: &str) -> Self {
+        match value {
+            "prefix" => EnvPosition::Prefix,
+            _ => EnvPosition::Suffix,
+        }
+    }
+}
+
+impl EnvPosition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnvPosition::Prefix => "prefix",
+            EnvPosition::Suffix => "suffix",
+        }
+    }
+}
+
+/// The `--env-delimiter` characters AWS disallows in topic and queue
+/// names, but pinnothera still accepts: names are joined to the
+/// environment with the (AWS-safe) sanitize replacement in their place
+pub(crate) const SUBSTITUTED_ENV_DELIMITERS: [char; 1] = ['.'];
+
+/// How the environment is applied to topic and queue names:
+/// at which end, and joined to the name with which delimiter
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct EnvNaming {
+    pub position: EnvPosition,
+    /// The (AWS-safe) delimiter names are actually joined with
+    pub delimiter: String,
+}
+
+impl Default for EnvNaming {
+    fn default() -> Self {
+        EnvNaming {
+            position: EnvPosition::default(),
+            delimiter: DEFAULT_ENV_DELIMITER.to_string(),
+        }
+    }
+}
+
+impl EnvNaming {
+    /// The naming scheme applying the environment at the supplied position
+    /// with the supplied delimiter, or (if AWS disallows the delimiter in
+    /// names, i.e. `.`) with the supplied replacement in its place
+    pub fn new(position: EnvPosition, delimiter: char, replacement: &str) -> EnvNaming {
+        let delimiter: String = match is_aws_safe_char(delimiter) {
+            true => delimiter.to_string(),
+            false => match replacement.is_empty() {
+                true => DEFAULT_SANITIZE_REPLACEMENT.to_string(),
+                false => sanitize_name(replacement, DEFAULT_SANITIZE_REPLACEMENT),
+            },
+        };
+
+        EnvNaming {
+            position,
+            delimiter,
+        }
+    }
+}
+
+/// Apply the supplied environment (if any) to the supplied name per the
+/// supplied naming scheme, keeping the `.fifo` extension of FIFO resources
+/// at the very end (i.e. `orders-dev.fifo`, never `orders.fifo-dev`)
+/// whether or not the name was configured with it
+pub(crate) fn apply_env_name<T: AsRef<str>>(
+    name: T,
+    env: Option<&str>,
+    scheme: &EnvNaming,
+    fifo: bool,
+) -> String {
+    let base: &str = strip_fifo_extension(name.as_ref());
+    let extension: &str = if fifo { FIFO_EXTENSION } else { "" };
+
+    match (env, scheme.position) {
+        (None, _) => format!("{}{}", base, extension),
+        (Some(env), EnvPosition::Prefix) => {
+            format!("{}{}{}{}", env, scheme.delimiter, base, extension)
+        }
+        (Some(env), EnvPosition::Suffix) => {
+            format!("{}{}{}{}", base, scheme.delimiter, env, extension)
+        }
+    }
+}
+
+/// Determine whether the supplied (resolved) name carries the
+/// supplied environment per the supplied naming scheme
+pub(crate) fn has_env_name<T: AsRef<str>>(name: T, env: &str, scheme: &EnvNaming) -> bool {
+    let base: &str = strip_fifo_extension(name.as_ref());
+
+    match scheme.position {
+        EnvPosition::Prefix => base.starts_with(&format!("{}{}", env, scheme.delimiter)),
+        EnvPosition::Suffix => base.ends_with(&format!("{}{}", scheme.delimiter, env)),
+    }
+}
+
+/// The AWS partitions SNS topic ARNs may belong to
+const ARN_PARTITIONS: [&str; 3] = ["aws", "aws-cn", "aws-us-gov"];
+
+/// Determine whether the supplied configured topic is an SNS topic
+/// ARN (i.e. one in another account) rather than a topic name
+pub(crate) fn is_topic_arn<T: AsRef<str>>(topic: T) -> bool {
+    topic.as_ref().starts_with("arn:")
+}
+
+/// Why the supplied SNS topic ARN is malformed (if it is), expecting
+/// `arn:PARTITION:sns:REGION:ACCOUNT_ID:TOPIC_NAME`
+pub(crate) fn topic_arn_error<T: AsRef<str>>(arn: T) -> Option<String> {
+    let parts: Vec<&str> = arn.as_ref().split(':').collect();
+
+    let (partition, service, region, account_id, name) = match parts.as_slice() {
+        ["arn", partition, service, region, account_id, name] => {
+            (*partition, *service, *region, *account_id, *name)
+        }
+        _ => return Some("expected 'arn:PARTITION:sns:REGION:ACCOUNT_ID:TOPIC_NAME'".to_string()),
+    };
+
+    if !ARN_PARTITIONS.contains(&partition) {
+        return Some(format!(
+            "unknown partition '{}' (expected one of {:?})",
+            partition, ARN_PARTITIONS
+        ));
+    }
+
+    if service != "sns" {
+        return Some(format!("'{}' is not an SNS ARN", service));
+    }
+
+    if region.is_empty()
+        || !region.chars().all(|character| {
+            character.is_ascii_lowercase() || character.is_ascii_digit() || character == '-'
+        })
+    {
+        return Some(format!("'{}' is not a valid region", region));
+    }
+
+    if account_id.len() != 12
+        || !account_id
+            .chars()
+            .all(|character| character.is_ascii_digit())
+    {
+        return Some(format!("'{}' is not a 12-digit account id", account_id));
+    }
+
+    let base: &str = strip_fifo_extension(name);
+
+    if base.is_empty() || !is_aws_safe_name(base) {
+        return Some(format!("'{}' is not a valid topic name", name));
+    }
+
+    None
+}
+
+/// The partition and name of the resource with the supplied
+/// (`arn:PARTITION:SERVICE:REGION:ACCOUNT_ID:NAME`) ARN
+fn arn_partition_and_name(arn: &str) -> Option<(&str, &str)> {
+    match arn.split(':').collect::<Vec<&str>>().as_slice() {
+        ["arn", partition, _, _, _, name] => Some((*partition, *name)),
+        _ => None,
+    }
+}
+
+/// The region of the resource with the supplied ARN
+pub(crate) fn arn_region(arn: &str) -> Option<&str> {
+    arn.split(':').nth(3).filter(|region| !region.is_empty())
+}
+
+/// The id of the AWS account owning the resource with the supplied ARN
+pub(crate) fn arn_account_id(arn: &str) -> Option<&str> {
+    arn.split(':')
+        .nth(4)
+        .filter(|account_id| !account_id.is_empty())
+}
+
+/// Why the queue with the supplied ARN can't be subscribed to the topic
+/// with the supplied ARN, judging by the ARNs alone (their partitions,
+/// and whether each is FIFO per its name), if it can't
+pub(crate) fn subscription_incompatibility<Q: AsRef<str>, T: AsRef<str>>(
+    queue_arn: Q,
+    topic_arn: T,
+) -> Option<String> {
+    let (queue_partition, queue) = arn_partition_and_name(queue_arn.as_ref())?;
+    let (topic_partition, topic) = arn_partition_and_name(topic_arn.as_ref())?;
+
+    if queue_partition != topic_partition {
+        return Some(format!(
+            "cannot subscribe queue \"{}\" in partition '{}' to topic \"{}\" in partition '{}' (subscriptions can't cross partitions)",
+            queue, queue_partition, topic, topic_partition
+        ));
+    }
+
+    match (
+        queue.ends_with(FIFO_EXTENSION),
+        topic.ends_with(FIFO_EXTENSION),
+    ) {
+        (false, true) => Some(format!(
+            "cannot subscribe standard queue \"{}\" to FIFO topic \"{}\"",
+            queue, topic
+        )),
+        (true, false) => Some(format!(
+            "cannot subscribe FIFO queue \"{}\" to standard topic \"{}\"",
+            queue, topic
+        )),
+        _ => None,
+    }
+}
+
+/// Normalize the supplied SQS queue URL, so that the differing shapes
+/// returned by SQS, LocalStack, and ElasticMQ compare equal for the same
+/// queue: the scheme and host are lowercased, any trailing slash is
+/// stripped, and the (case-sensitive) path is otherwise left untouched
+pub(crate) fn normalize_queue_url<T: AsRef<str>>(url: T) -> String {
+    let url: &str = url.as_ref().trim();
+
+    let uri: http::Uri = match url.parse() {
+        Ok(value) => value,
+        Err(_) => return url.trim_end_matches('/').to_string(),
+    };
+
+    let path: &str = uri.path().trim_end_matches('/');
+
+    match (uri.scheme_str(), uri.authority()) {
+        (Some(scheme), Some(authority)) => format!(
+            "{}://{}{}",
+            scheme.to_ascii_lowercase(),
+            authority.as_str().to_ascii_lowercase(),
+            path
+        ),
+        _ => url.trim_end_matches('/').to_string(),
+    }
+}
+
+/// The name of the queue with the supplied (normalized) URL
+pub(crate) fn queue_name_from_url<T: AsRef<str>>(url: T) -> String {
+    url.as_ref()
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Find any sanitized names produced by more than one distinct
+/// original name, mapped to the (sorted) originals producing them
+pub(crate) fn sanitization_collisions<'a, I, R>(
+    names: I,
+    replacement: R,
+) -> BTreeMap<String, Vec<&'a str>>
+where
+    I: IntoIterator<Item = &'a str>,
+    R: AsRef<str>,
+{
+    let mut mapping: BTreeMap<String, Vec<&'a str>> = BTreeMap::new();
+
+    names.into_iter().for_each(|name| {
+        let originals = mapping
+            .entry(sanitize_name(name, replacement.as_ref()))
+            .or_default();
+
+        if !originals.contains(&name) {
+            originals.push(name);
+        }
+    });
+
+    mapping.retain(|_, originals| originals.len() > 1);
+    mapping
+        .values_mut()
+        .for_each(|originals| originals.sort_unstable());
+
+    mapping
+}
+
+/// The number of single-character insertions, deletions, substitutions,
+/// or adjacent transpositions needed to turn one name into the other
+pub(crate) fn edit_distance<A: AsRef<str>, B: AsRef<str>>(first: A, second: B) -> usize {
+    let (first, second): (Vec<char>, Vec<char>) = (
+        first.as_ref().chars().collect(),
+        second.as_ref().chars().collect(),
+    );
+
+    let mut distances: Vec<Vec<usize>> = (0..=first.len())
+        .map(|row| {
+            (0..=second.len())
+                .map(|column| if row == 0 { column } else { row })
+                .collect()
+        })
+        .collect();
+
+    for row in 1..=first.len() {
+        for column in 1..=second.len() {
+            let cost = usize::from(first[row - 1] != second[column - 1]);
+
+            distances[row][column] = (distances[row - 1][column] + 1)
+                .min(distances[row][column - 1] + 1)
+                .min(distances[row - 1][column - 1] + cost);
+
+            if row > 1
+                && column > 1
+                && first[row - 1] == second[column - 2]
+                && first[row - 2] == second[column - 1]
+            {
+                distances[row][column] =
+                    distances[row][column].min(distances[row - 2][column - 2] + 1);
+            }
+        }
+    }
+
+    distances[first.len()][second.len()]
+}
+
+/// Determine whether the supplied name matches the supplied glob
+/// pattern, where `*` matches any run of characters (including
+/// none) and `?` matches exactly one character
+pub(crate) fn glob_matches<P: AsRef<str>, T: AsRef<str>>(pattern: P, name: T) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (
+        pattern.as_ref().chars().collect(),
+        name.as_ref().chars().collect(),
+    );
+
+    let (mut p_idx, mut n_idx) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n_idx < name.len() {
+        match pattern.get(p_idx) {
+            Some('*') => {
+                backtrack = Some((p_idx, n_idx));
+                p_idx += 1;
+            }
+            Some(character) if *character == '?' || *character == name[n_idx] => {
+                p_idx += 1;
+                n_idx += 1;
+            }
+            _ => match backtrack {
+                Some((star_idx, star_n_idx)) => {
+                    backtrack = Some((star_idx, star_n_idx + 1));
+                    p_idx = star_idx + 1;
+                    n_idx = star_n_idx + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p_idx..].iter().all(|character| *character == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// (position, delimiter, fifo, expected name for `orders` in `dev`)
+    const SCHEMES: [(EnvPosition, char, bool, &str); 12] = [
+        (EnvPosition::Suffix, '-', false, "orders-dev"),
+        (EnvPosition::Suffix, '-', true, "orders-dev.fifo"),
+        (EnvPosition::Suffix, '_', false, "orders_dev"),
+        (EnvPosition::Suffix, '_', true, "orders_dev.fifo"),
+        (EnvPosition::Suffix, '.', false, "orders-dev"),
+        (EnvPosition::Suffix, '.', true, "orders-dev.fifo"),
+        (EnvPosition::Prefix, '-', false, "dev-orders"),
+        (EnvPosition::Prefix, '-', true, "dev-orders.fifo"),
+        (EnvPosition::Prefix, '_', false, "dev_orders"),
+        (EnvPosition::Prefix, '_', true, "dev_orders.fifo"),
+        (EnvPosition::Prefix, '.', false, "dev-orders"),
+        (EnvPosition::Prefix, '.', true, "dev-orders.fifo"),
+    ];
+
+    #[test]
+    fn apply_env_name_per_scheme() {
+        for (position, delimiter, fifo, expected) in SCHEMES {
+            let scheme = EnvNaming::new(position, delimiter, DEFAULT_SANITIZE_REPLACEMENT);
+
+            // The `.fifo` extension stays at the very end, whether or
+            // not the name was configured with it
+            for name in ["orders", "orders.fifo"] {
+                let resolved: String = apply_env_name(name, Some("dev"), &scheme, fifo);
+
+                assert_eq!(resolved, expected, "{:?} {}", scheme, name);
+                assert!(is_aws_safe_name(strip_fifo_extension(&resolved)));
+                assert!(has_env_name(&resolved, "dev", &scheme));
+                assert!(!has_env_name(&resolved, "prod", &scheme));
+            }
+
+            assert_eq!(
+                apply_env_name("orders", None, &scheme, fifo),
+                match fifo {
+                    true => "orders.fifo",
+                    false => "orders",
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn has_env_name_respects_position() {
+        let (prefix, suffix) = (
+            EnvNaming::new(EnvPosition::Prefix, '-', DEFAULT_SANITIZE_REPLACEMENT),
+            EnvNaming::default(),
+        );
+
+        assert!(!has_env_name("orders-dev", "dev", &prefix));
+        assert!(!has_env_name("dev-orders", "dev", &suffix));
+        assert!(!has_env_name("orders-devx", "dev", &suffix));
+        assert!(!has_env_name("ordersdev", "dev", &suffix));
+    }
+
+    #[test]
+    fn broad_policy_wildcard_per_scheme() {
+        for (position, delimiter, fifo, expected) in SCHEMES {
+            let scheme = EnvNaming::new(position, delimiter, DEFAULT_SANITIZE_REPLACEMENT);
+
+            assert_eq!(
+                apply_env_name("*", Some("dev"), &scheme, fifo),
+                expected.replace("orders", "*")
+            );
+        }
+    }
+
+    #[test]
+    fn substituted_delimiter_uses_the_replacement() {
+        for (replacement, expected) in [
+            ("_", "orders_dev"),
+            ("--", "orders--dev"),
+            ("", "orders-dev"),
+        ] {
+            let scheme = EnvNaming::new(EnvPosition::Suffix, '.', replacement);
+
+            assert_eq!(
+                apply_env_name("orders", Some("dev"), &scheme, false),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn sanitization_collisions_list_every_source() {
+        let collisions = sanitization_collisions(
+            [
+                "orders.v2",
+                "orders_v2",
+                "orders:v2",
+                "orders.v2",
+                "billing",
+                "billing.v1",
+            ],
+            "_",
+        );
+
+        assert_eq!(
+            collisions,
+            BTreeMap::from([(
+                "orders_v2".to_string(),
+                vec!["orders.v2", "orders:v2", "orders_v2"]
+            )])
+        );
+
+        // A replacement that keeps the names apart avoids the collision
+        assert_eq!(
+            sanitization_collisions(["orders.v2", "orders_v2"], "-").len(),
+            0
+        );
+    }
+
+    #[test]
+    fn queue_urls_normalize_per_backend() {
+        // (URL as returned by the backend, normalized URL)
+        let cases: [(&str, &str); 8] = [
+            // SQS (legacy and current endpoint shapes)
+            (
+                "https://sqs.us-east-1.amazonaws.com/123456789012/orders-dev",
+                "https://sqs.us-east-1.amazonaws.com/123456789012/orders-dev",
+            ),
+            (
+                "https://queue.amazonaws.com/123456789012/Orders-Dev/",
+                "https://queue.amazonaws.com/123456789012/Orders-Dev",
+            ),
+            (
+                "HTTPS://SQS.US-GOV-WEST-1.AMAZONAWS.COM/123456789012/orders-dev.fifo",
+                "https://sqs.us-gov-west-1.amazonaws.com/123456789012/orders-dev.fifo",
+            ),
+            // LocalStack
+            (
+                "http://localhost:4566/000000000000/orders-dev",
+                "http://localhost:4566/000000000000/orders-dev",
+            ),
+            (
+                "http://sqs.us-east-1.LocalHost.LocalStack.Cloud:4566/000000000000/orders-dev/",
+                "http://sqs.us-east-1.localhost.localstack.cloud:4566/000000000000/orders-dev",
+            ),
+            // ElasticMQ
+            (
+                "http://ElasticMQ:9324/queue/orders-dev",
+                "http://elasticmq:9324/queue/orders-dev",
+            ),
+            (
+                " http://localhost:9324/000000000000/orders-dev// ",
+                "http://localhost:9324/000000000000/orders-dev",
+            ),
+            // Not a URL at all
+            ("orders-dev/", "orders-dev"),
+        ];
+
+        for (url, expected) in cases {
+            let normalized: String = normalize_queue_url(url);
+
+            assert_eq!(normalized, expected, "{:?}", url);
+            assert_eq!(normalize_queue_url(&normalized), normalized);
+            assert_eq!(
+                queue_name_from_url(&normalized),
+                expected.rsplit('/').next().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn normalized_queue_urls_survive_the_report() {
+        let run = crate::context::RunState::default();
+        let url: String = normalize_queue_url("http://LocalHost:4566/000000000000/orders-dev/");
+
+        crate::report::record_queue(
+            &run,
+            "orders",
+            "orders-dev".to_string(),
+            Some(url.clone()),
+            None,
+        );
+
+        assert_eq!(
+            crate::report::queue_results(&run)[0].url.as_deref(),
+            Some(url.as_str())
+        );
+        assert_eq!(
+            serde_json::to_value(crate::report::queue_results(&run)).unwrap()[0]["url"],
+            "http://localhost:4566/000000000000/orders-dev"
+        );
+    }
+
+    #[test]
+    fn topic_arns_per_partition() {
+        for partition in ARN_PARTITIONS {
+            for name in ["placed", "placed-dev.fifo"] {
+                let arn: String = format!("arn:{}:sns:us-east-1:123456789012:{}", partition, name);
+
+                assert!(is_topic_arn(&arn));
+                assert_eq!(topic_arn_error(&arn), None, "{}", arn);
+            }
+        }
+
+        // (ARN, a fragment of the error)
+        let cases: [(&str, &str); 6] = [
+            (
+                "arn:aws-iso:sns:us-east-1:123456789012:placed",
+                "unknown partition 'aws-iso'",
+            ),
+            (
+                "arn:aws:sqs:us-east-1:123456789012:placed",
+                "'sqs' is not an SNS ARN",
+            ),
+            (
+                "arn:aws:sns:US_EAST_1:123456789012:placed",
+                "is not a valid region",
+            ),
+            (
+                "arn:aws:sns:us-east-1:1234:placed",
+                "is not a 12-digit account id",
+            ),
+            (
+                "arn:aws:sns:us-east-1:123456789012:pla.ced",
+                "is not a valid topic name",
+            ),
+            (
+                "arn:aws:sns:us-east-1:placed",
+                "expected 'arn:PARTITION:sns:",
+            ),
+        ];
+
+        for (arn, fragment) in cases {
+            let error: String = topic_arn_error(arn).unwrap();
+
+            assert!(error.contains(fragment), "{}: {}", arn, error);
+        }
+    }
+
+    #[test]
+    fn incompatible_subscriptions_per_arn() {
+        const QUEUE: &str = "arn:aws:sqs:us-east-1:123456789012:orders-dev";
+        const FIFO_QUEUE: &str = "arn:aws:sqs:us-east-1:123456789012:orders-dev.fifo";
+        const TOPIC: &str = "arn:aws:sns:us-east-1:210987654321:placed";
+        const FIFO_TOPIC: &str = "arn:aws:sns:us-east-1:210987654321:placed.fifo";
+
+        // (queue ARN, topic ARN, the incompatibility, if any)
+        let cases: [(&str, &str, Option<&str>); 7] = [
+            (QUEUE, TOPIC, None),
+            (FIFO_QUEUE, FIFO_TOPIC, None),
+            (
+                QUEUE,
+                "arn:aws:sns:eu-west-1:123456789012:placed",
+                None,
+            ),
+            (
+                QUEUE,
+                FIFO_TOPIC,
+                Some("cannot subscribe standard queue \"orders-dev\" to FIFO topic \"placed.fifo\""),
+            ),
+            (
+                FIFO_QUEUE,
+                TOPIC,
+                Some("cannot subscribe FIFO queue \"orders-dev.fifo\" to standard topic \"placed\""),
+            ),
+            (
+                QUEUE,
+                "arn:aws-cn:sns:cn-north-1:210987654321:placed",
+                Some("cannot subscribe queue \"orders-dev\" in partition 'aws' to topic \"placed\" in partition 'aws-cn' (subscriptions can't cross partitions)"),
+            ),
+            // Placeholder ARNs (i.e. of dry runs) reveal nothing
+            ("<arn of orders-dev>", FIFO_TOPIC, None),
+        ];
+
+        for (queue_arn, topic_arn, expected) in cases {
+            assert_eq!(
+                subscription_incompatibility(queue_arn, topic_arn).as_deref(),
+                expected,
+                "{} -> {}",
+                topic_arn,
+                queue_arn
+            );
+        }
+
+        assert_eq!(arn_account_id(TOPIC), Some("210987654321"));
+        assert_eq!(arn_account_id("placed"), None);
+    }
+
+    /// A name AWS allows for a topic or queue (ignoring its length)
+    const SAFE_NAME: &str = "[A-Za-z0-9_-]{1,40}";
+
+    fn schemes() -> impl Strategy<Value = EnvNaming> {
+        (
+            prop_oneof![Just(EnvPosition::Prefix), Just(EnvPosition::Suffix)],
+            prop_oneof![Just('-'), Just('_'), Just('.')],
+        )
+            .prop_map(|(position, delimiter)| {
+                EnvNaming::new(position, delimiter, DEFAULT_SANITIZE_REPLACEMENT)
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn sanitized_names_are_aws_safe(name in any::<String>(), replacement in "[_-]{0,2}") {
+            let sanitized: String = sanitize_name(&name, &replacement);
+
+            prop_assert!(is_aws_safe_name(&sanitized));
+            prop_assert_eq!(
+                sanitized.chars().count(),
+                name.chars().map(|character| match is_aws_safe_char(character) {
+                    true => 1,
+                    false => replacement.chars().count(),
+                }).sum::<usize>()
+            );
+        }
+
+        #[test]
+        fn safe_names_are_never_sanitized(name in SAFE_NAME) {
+            prop_assert_eq!(sanitize_name(&name, "_"), name);
+        }
+
+        #[test]
+        fn applied_env_names_carry_the_env(
+            name in SAFE_NAME,
+            env in "[a-z0-9]{1,10}",
+            scheme in schemes(),
+            fifo in any::<bool>(),
+            configured_fifo in any::<bool>(),
+        ) {
+            let configured: String = match configured_fifo {
+                true => format!("{}{}", name, FIFO_EXTENSION),
+                false => name.clone(),
+            };
+            let resolved: String = apply_env_name(&configured, Some(&env), &scheme, fifo);
+
+            prop_assert!(has_env_name(&resolved, &env, &scheme), "{}", resolved);
+            prop_assert_eq!(resolved.ends_with(FIFO_EXTENSION), fifo);
+            prop_assert!(is_aws_safe_name(strip_fifo_extension(&resolved)), "{}", resolved);
+            prop_assert_eq!(apply_env_name(&configured, None, &scheme, fifo).len() + env.len() + 1, resolved.len());
+        }
+
+        #[test]
+        fn sanitization_collisions_share_a_sanitized_name(names in prop::collection::vec("[a-z.:_-]{1,6}", 0..20)) {
+            let collisions = sanitization_collisions(names.iter().map(String::as_str), "_");
+
+            for (sanitized, originals) in collisions {
+                prop_assert!(originals.len() > 1);
+                prop_assert!(originals.iter().all(|name| sanitize_name(name, "_") == sanitized));
+            }
+        }
+
+        #[test]
+        fn edit_distances_are_a_metric(first in "[a-z]{0,12}", second in "[a-z]{0,12}") {
+            let distance: usize = edit_distance(&first, &second);
+
+            prop_assert_eq!(edit_distance(&first, &first), 0);
+            prop_assert_eq!(distance, edit_distance(&second, &first));
+            prop_assert_eq!(distance == 0, first == second);
+            prop_assert!(distance <= first.len().max(second.len()));
+        }
+
+        #[test]
+        fn globs_match_their_own_literal_names(name in "[^*?]{0,20}", other in any::<String>()) {
+            prop_assert!(glob_matches(&name, &name));
+            prop_assert!(glob_matches("*", &other));
+            let (pattern, suffixed) = (format!("*{}", name), format!("{}{}", other, name));
+
+            prop_assert!(glob_matches(pattern, suffixed));
+        }
+    }
+}
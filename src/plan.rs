@@ -0,0 +1,196 @@
+// Pinnothera's planned (desired) state components
+
+// Standard Library Imports
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Mutex;
+
+// Third Party Imports
+use serde::Serialize;
+use serde_json::Value;
+
+// Project-Level Imports
+use crate::context::RunState;
+
+// <editor-fold desc="// Desired State ...">
+
+/// Every queue, topic, and subscription a dry run would have created,
+/// with everything pinnothera would have set on each of them, for
+/// an external policy engine to evaluate before they're applied
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct DesiredState {
+    /// Each queue, by its resolved (physical) name
+    pub queues: BTreeMap<String, DesiredResource>,
+    /// Each topic pinnothera owns, by its resolved (physical) name
+    pub topics: BTreeMap<String, DesiredResource>,
+    pub subscriptions: Vec<DesiredSubscription>,
+}
+
+impl DesiredState {
+    pub fn is_empty(&self) -> bool {
+        self.queues.is_empty() && self.topics.is_empty() && self.subscriptions.is_empty()
+    }
+}
+
+/// Everything pinnothera would set on a single queue or topic
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub(crate) struct DesiredResource {
+    /// Each attribute, with those holding a JSON document (i.e. an
+    /// access or redrive policy) as the document rather than a string
+    pub attributes: BTreeMap<String, Value>,
+    pub tags: BTreeMap<String, String>,
+}
+
+/// A subscription of a queue to a topic, and every attribute it would
+/// be given (a dry run doesn't look up the ARN of a queue or topic it
+/// would create, so either is a placeholder naming the resource)
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct DesiredSubscription {
+    pub topic_arn: String,
+    pub queue_arn: String,
+    pub attributes: BTreeMap<String, Value>,
+}
+
+/// What a single dry run would have created, as it's planned
+#[derive(Debug, Default)]
+pub(crate) struct PlanLog {
+    desired: Mutex<DesiredState>,
+}
+
+/// The supplied attribute value as the JSON document it holds
+/// (if it holds an object or array), or otherwise as a string
+fn attribute_value(value: String) -> Value {
+    match serde_json::from_str::<Value>(&value) {
+        Ok(document) if document.is_object() || document.is_array() => document,
+        _ => Value::String(value),
+    }
+}
+
+fn attribute_values<K: AsRef<str>>(
+    attributes: impl IntoIterator<Item = (K, String)>,
+) -> BTreeMap<String, Value> {
+    attributes
+        .into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, value)| (name.as_ref().to_string(), attribute_value(value)))
+        .collect()
+}
+
+fn sorted_tags(tags: &HashMap<String, String>) -> BTreeMap<String, String> {
+    tags.iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// Record the supplied (resolved) queue as one the run would have
+/// created with the supplied attributes and tags
+pub(crate) fn record_queue<T: AsRef<str>, K: AsRef<str>>(
+    run: &RunState,
+    queue: T,
+    attributes: impl IntoIterator<Item = (K, String)>,
+    tags: &HashMap<String, String>,
+) {
+    run.plan.desired.lock().unwrap().queues.insert(
+        queue.as_ref().to_string(),
+        DesiredResource {
+            attributes: attribute_values(attributes),
+            tags: sorted_tags(tags),
+        },
+    );
+}
+
+/// Record the supplied (resolved) topic as one the run would have
+/// created with the supplied attributes and tags
+pub(crate) fn record_topic<T: AsRef<str>, K: AsRef<str>>(
+    run: &RunState,
+    topic: T,
+    attributes: impl IntoIterator<Item = (K, String)>,
+    tags: &HashMap<String, String>,
+) {
+    run.plan.desired.lock().unwrap().topics.insert(
+        topic.as_ref().to_string(),
+        DesiredResource {
+            attributes: attribute_values(attributes),
+            tags: sorted_tags(tags),
+        },
+    );
+}
+
+/// Record the supplied subscription as one the run would have
+/// created with the supplied attributes
+pub(crate) fn record_subscription<K: AsRef<str>>(
+    run: &RunState,
+    topic_arn: &str,
+    queue_arn: &str,
+    attributes: impl IntoIterator<Item = (K, String)>,
+) {
+    let mut desired = run.plan.desired.lock().unwrap();
+
+    desired.subscriptions.push(DesiredSubscription {
+        topic_arn: topic_arn.to_string(),
+        queue_arn: queue_arn.to_string(),
+        attributes: attribute_values(attributes),
+    });
+
+    desired.subscriptions.sort_by(|left, right| {
+        (&left.topic_arn, &left.queue_arn).cmp(&(&right.topic_arn, &right.queue_arn))
+    });
+}
+
+/// Everything the run would have created (if it was a dry run
+/// that planned anything), ordered by name
+pub(crate) fn desired_state(run: &RunState) -> Option<DesiredState> {
+    let desired = run.plan.desired.lock().unwrap();
+
+    match desired.is_empty() {
+        true => None,
+        false => Some(desired.clone()),
+    }
+}
+
+/// Write everything the run would have created to the supplied
+/// path as a (pretty-printed) JSON document
+pub(crate) fn write_plan_file(run: &RunState, path: &Path) -> std::io::Result<()> {
+    let desired: DesiredState = desired_state(run).unwrap_or_default();
+
+    std::fs::write(path, serde_json::to_string_pretty(&desired)? + "\n")
+}
+
+// </editor-fold desc="// Desired State ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_attributes_are_recorded_as_documents() {
+        let run = RunState::default();
+
+        record_queue(
+            &run,
+            "orders-dev",
+            [
+                ("Policy", r#"{"Version": "2012-10-17"}"#.to_string()),
+                ("DelaySeconds", "5".to_string()),
+                ("KmsMasterKeyId", String::new()),
+            ],
+            &HashMap::from([("team".to_string(), "payments".to_string())]),
+        );
+
+        let desired = desired_state(&run).unwrap();
+        let queue = &desired.queues["orders-dev"];
+
+        assert_eq!(
+            queue.attributes["Policy"],
+            serde_json::json!({"Version": "2012-10-17"})
+        );
+        assert_eq!(queue.attributes["DelaySeconds"], Value::from("5"));
+        assert!(!queue.attributes.contains_key("KmsMasterKeyId"));
+        assert_eq!(queue.tags["team"], "payments");
+    }
+
+    #[test]
+    fn nothing_is_desired_until_something_is_planned() {
+        assert_eq!(desired_state(&RunState::default()), None);
+    }
+}
@@ -0,0 +1,256 @@
+// Pinnothera's AWS partition and regional feature availability components
+
+// Standard Library Imports
+use std::fmt::{Display, Formatter};
+
+// <editor-fold desc="// AWS Partitions ...">
+
+/// A group of AWS regions sharing an ARN prefix (i.e. `arn:aws-cn:`)
+/// and the DNS suffix of their service endpoints
+#[derive(Debug)]
+pub(crate) struct Partition {
+    /// The partition's name, as used in ARNs
+    pub name: &'static str,
+    /// The DNS suffix of the partition's service endpoints
+    pub dns_suffix: &'static str,
+    /// The prefix shared by the names of the partition's regions
+    region_prefix: &'static str,
+}
+
+/// Every partition pinnothera can target, the commercial (default) one last
+const PARTITIONS: [Partition; 3] = [
+    Partition {
+        name: "aws-cn",
+        dns_suffix: "amazonaws.com.cn",
+        region_prefix: "cn-",
+    },
+    Partition {
+        name: "aws-us-gov",
+        dns_suffix: "amazonaws.com",
+        region_prefix: "us-gov-",
+    },
+    Partition {
+        name: "aws",
+        dns_suffix: "amazonaws.com",
+        region_prefix: "",
+    },
+];
+
+/// The partition the supplied region belongs to
+pub(crate) fn partition(region: &str) -> &'static Partition {
+    PARTITIONS
+        .iter()
+        .find(|partition| region.starts_with(partition.region_prefix))
+        .unwrap_or(&PARTITIONS[PARTITIONS.len() - 1])
+}
+
+/// Why the supplied (explicit) endpoint can't serve the supplied region,
+/// if it's an AWS endpoint in a different partition than the region's
+/// (i.e. a `.amazonaws.com` endpoint for a China region)
+pub(crate) fn endpoint_mismatch(endpoint: &str, region: &str) -> Option<String> {
+    let host: String = http::Uri::try_from(endpoint)
+        .ok()?
+        .host()?
+        .to_ascii_lowercase();
+    let partition: &Partition = partition(region);
+
+    let is_aws_host: bool = PARTITIONS
+        .iter()
+        .any(|other| host.ends_with(&format!(".{}", other.dns_suffix)));
+
+    match is_aws_host && !host.ends_with(&format!(".{}", partition.dns_suffix)) {
+        true => Some(format!(
+            "region \"{}\" is in partition \"{}\", whose endpoints are under \"{}\"",
+            region, partition.name, partition.dns_suffix
+        )),
+        false => None,
+    }
+}
+
+// </editor-fold desc="// AWS Partitions ...">
+
+// <editor-fold desc="// Regional Feature Availability ...">
+
+/// A configuration feature SNS/SQS don't offer in every partition (or region)
+#[derive(Eq, Copy, Clone, Debug, PartialEq)]
+pub(crate) enum Feature {
+    /// FIFO SNS topics (`fifo: true` queues' topics)
+    FifoTopics,
+    /// SQS-managed server-side encryption (`sse: true`)
+    SqsManagedSse,
+    /// Subscription filter policies applied to message bodies
+    /// (a `FilterPolicyScope` of `MessageBody`)
+    MessageBodyFiltering,
+}
+
+impl Display for Feature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Feature::FifoTopics => "FIFO topics",
+                Feature::SqsManagedSse => "SQS-managed server-side encryption",
+                Feature::MessageBodyFiltering => "message body filter policies",
+            }
+        )
+    }
+}
+
+/// A feature that isn't (or might not be) available in a partition
+/// (or in some of its regions), and whether configuring it there
+/// should fail validation or only be warned about
+#[derive(Debug)]
+pub(crate) struct Unavailability {
+    pub feature: Feature,
+    pub partition: &'static str,
+    /// The regions of the partition the feature is unavailable
+    /// in, every one of them if empty
+    regions: &'static [&'static str],
+    /// Whether configuring the feature should fail validation
+    pub reject: bool,
+    /// What's known about the feature's availability
+    pub note: &'static str,
+}
+
+/// Where features are known (or suspected) to be unavailable, amended
+/// as AWS changes their availability: features AWS has announced for
+/// "commercial regions" only are warned about in the other partitions,
+/// and only confirmed gaps are rejected outright
+const UNAVAILABLE: [Unavailability; 6] = [
+    Unavailability {
+        feature: Feature::FifoTopics,
+        partition: "aws-cn",
+        regions: &[],
+        reject: false,
+        note: "launched later in the China regions than in the commercial ones",
+    },
+    Unavailability {
+        feature: Feature::FifoTopics,
+        partition: "aws-us-gov",
+        regions: &[],
+        reject: false,
+        note: "launched later in the GovCloud regions than in the commercial ones",
+    },
+    Unavailability {
+        feature: Feature::SqsManagedSse,
+        partition: "aws-cn",
+        regions: &[],
+        reject: false,
+        note: "announced for the commercial regions only",
+    },
+    Unavailability {
+        feature: Feature::SqsManagedSse,
+        partition: "aws-us-gov",
+        regions: &[],
+        reject: false,
+        note: "announced for the commercial regions only",
+    },
+    Unavailability {
+        feature: Feature::MessageBodyFiltering,
+        partition: "aws-cn",
+        regions: &[],
+        reject: false,
+        note: "announced for the commercial regions only",
+    },
+    Unavailability {
+        feature: Feature::MessageBodyFiltering,
+        partition: "aws-us-gov",
+        regions: &[],
+        reject: false,
+        note: "announced for the commercial regions only",
+    },
+];
+
+/// Whether (and how) the supplied feature is unavailable in the supplied region
+pub(crate) fn unavailability(feature: Feature, region: &str) -> Option<&'static Unavailability> {
+    let partition: &str = partition(region).name;
+
+    UNAVAILABLE.iter().find(|entry| {
+        entry.feature == feature
+            && entry.partition == partition
+            && (entry.regions.is_empty() || entry.regions.contains(&region))
+    })
+}
+
+// </editor-fold desc="// Regional Feature Availability ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (region, partition, DNS suffix)
+    const REGIONS: [(&str, &str, &str); 6] = [
+        ("us-east-1", "aws", "amazonaws.com"),
+        ("eu-west-1", "aws", "amazonaws.com"),
+        ("cn-north-1", "aws-cn", "amazonaws.com.cn"),
+        ("cn-northwest-1", "aws-cn", "amazonaws.com.cn"),
+        ("us-gov-west-1", "aws-us-gov", "amazonaws.com"),
+        ("us-gov-east-1", "aws-us-gov", "amazonaws.com"),
+    ];
+
+    #[test]
+    fn partition_per_region() {
+        for (region, name, dns_suffix) in REGIONS {
+            let partition: &Partition = partition(region);
+
+            assert_eq!(partition.name, name, "{}", region);
+            assert_eq!(partition.dns_suffix, dns_suffix, "{}", region);
+        }
+    }
+
+    #[test]
+    fn endpoint_mismatch_per_partition() {
+        for (region, name, dns_suffix) in REGIONS {
+            let own: String = format!("https://sqs.{}.{}", region, dns_suffix);
+            let other: String = match name {
+                "aws-cn" => format!("https://sqs.{}.amazonaws.com", region),
+                _ => format!("https://sqs.{}.amazonaws.com.cn", region),
+            };
+
+            assert_eq!(endpoint_mismatch(&own, region), None, "{}", own);
+            assert_eq!(
+                endpoint_mismatch(&other, region),
+                Some(format!(
+                    "region \"{}\" is in partition \"{}\", whose endpoints are under \"{}\"",
+                    region, name, dns_suffix
+                )),
+            );
+
+            // Endpoints outside of AWS (i.e. LocalStack) serve any region
+            assert_eq!(endpoint_mismatch("http://localhost:4566", region), None);
+        }
+    }
+
+    #[test]
+    fn unavailability_per_partition() {
+        for feature in [
+            Feature::FifoTopics,
+            Feature::SqsManagedSse,
+            Feature::MessageBodyFiltering,
+        ] {
+            for (region, name, _) in REGIONS {
+                match (unavailability(feature, region), name) {
+                    (None, "aws") => (),
+                    (Some(entry), "aws-cn" | "aws-us-gov") => {
+                        assert_eq!(entry.feature, feature);
+                        assert_eq!(entry.partition, name);
+                        assert!(!entry.reject, "{} in {}", feature, region);
+                    }
+                    (entry, _) => panic!("{} in {}: unexpectedly {:?}", feature, region, entry),
+                }
+            }
+        }
+
+        // Every entry applies to at least one region of its partition
+        UNAVAILABLE.iter().for_each(|entry| {
+            assert!(REGIONS.iter().any(|(region, _, _)| {
+                unavailability(entry.feature, region)
+                    .map(|found| {
+                        (found.feature, found.partition) == (entry.feature, entry.partition)
+                    })
+                    .unwrap_or(false)
+            }))
+        });
+    }
+}
@@ -0,0 +1,487 @@
+// Pinnothera's end-of-run resource report components
+
+// Standard Library Imports
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+// Third Party Imports
+use serde::Serialize;
+
+// Project-Level Imports
+use crate::context::RunState;
+use crate::errors::PinnError;
+use crate::logging;
+
+// <editor-fold desc="// Run Report ...">
+
+/// What a single run did with (or to) each resource it handled, what each
+/// of its configured queues resolved to, and how its environment was settled
+#[derive(Debug, Default)]
+pub(crate) struct RunReport {
+    resources: Mutex<BTreeMap<(&'static str, String), ResourceReport>>,
+    queues: Mutex<BTreeMap<String, QueueResult>>,
+    env_resolution: Mutex<Option<EnvResolution>>,
+}
+
+// </editor-fold desc="// Run Report ...">
+
+// <editor-fold desc="// Resource Report ...">
+
+/// What a run did with (or to) a single resource,
+/// ordered from most to least noteworthy
+#[derive(Eq, Ord, Copy, Clone, Debug, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceStatus {
+    /// The resource could not be ensured (or wasn't
+    /// attempted because a resource it needs failed)
+    Failed,
+    /// The resource doesn't exist (found by a `--check` run)
+    Missing,
+    /// The resource exists with attributes differing from
+    /// its configuration (found by a `--check` run)
+    Drifted,
+    /// The resource (a subscription) was created, but
+    /// SNS hasn't (yet) reported it as confirmed
+    Pending,
+    /// The resource would have been created if the run weren't a dry run
+    Planned,
+    /// The resource (a queue) already existed, but an earlier run was
+    /// interrupted before subscribing it to all of its topics, so the
+    /// run re-validated it in full along with subscribing it to them
+    Repairing,
+    /// The resource was created by the run
+    Created,
+    /// The resource already existed, but with attributes the run updated
+    Updated,
+    /// The resource (a subscription to a topic no longer configured
+    /// for its queue) was removed by `--prune-subscriptions`
+    Pruned,
+    /// The resource already existed as configured (or, in
+    /// a `--check` run, exists as configured)
+    Existing,
+    /// The resource was created, or already existed as configured
+    /// (SNS's `CreateTopic` and SQS's `CreateQueue` don't say which)
+    Ensured,
+}
+
+impl ResourceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResourceStatus::Failed => "failed",
+            ResourceStatus::Missing => "missing",
+            ResourceStatus::Drifted => "drifted",
+            ResourceStatus::Pending => "pending",
+            ResourceStatus::Planned => "planned",
+            ResourceStatus::Repairing => "repairing",
+            ResourceStatus::Created => "created",
+            ResourceStatus::Updated => "updated",
+            ResourceStatus::Pruned => "pruned",
+            ResourceStatus::Existing => "existing",
+            ResourceStatus::Ensured => "ensured",
+        }
+    }
+}
+
+/// The outcome of a run for a single (resolved) queue, topic, or subscription
+#[derive(Clone, Debug, Serialize)]
+pub struct ResourceReport {
+    pub kind: &'static str,
+    pub name: String,
+    pub status: ResourceStatus,
+    /// Why the resource failed, if it did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Record the outcome of the run for the supplied resource (a resource
+/// shared by several queues, i.e. a dead-letter queue, keeps its first
+/// failure, or drift, rather than being reported as ensured by a later
+/// attempt)
+pub(crate) fn record<T: AsRef<str>>(
+    run: &RunState,
+    kind: &'static str,
+    name: T,
+    status: ResourceStatus,
+    detail: Option<String>,
+) {
+    let mut resources = run.report.resources.lock().unwrap();
+    let key = (kind, name.as_ref().to_string());
+
+    if let Some(ResourceStatus::Failed | ResourceStatus::Missing | ResourceStatus::Drifted) =
+        resources.get(&key).map(|report| report.status)
+    {
+        return;
+    }
+
+    resources.insert(
+        key,
+        ResourceReport {
+            kind,
+            name: name.as_ref().to_string(),
+            status,
+            detail,
+        },
+    );
+}
+
+/// Record that the supplied resource could not be ensured, and why
+pub(crate) fn record_failure<T: AsRef<str>, D: AsRef<str>>(
+    run: &RunState,
+    kind: &'static str,
+    name: T,
+    detail: D,
+) {
+    record(
+        run,
+        kind,
+        name,
+        ResourceStatus::Failed,
+        Some(detail.as_ref().to_string()),
+    );
+}
+
+/// Record that the supplied resource could not be ensured because of the
+/// supplied error, distinguishing missing and drifted resources (found by
+/// a `--check` run) from outright failures
+pub(crate) fn record_error<T: AsRef<str>>(
+    run: &RunState,
+    kind: &'static str,
+    name: T,
+    error: &PinnError,
+) {
+    let status: ResourceStatus = match error {
+        PinnError::MissingResource { .. } => ResourceStatus::Missing,
+        PinnError::DriftedResource { .. } => ResourceStatus::Drifted,
+        _ => ResourceStatus::Failed,
+    };
+
+    record(run, kind, name, status, Some(logging::error_chain(error)));
+}
+
+/// Determine whether any resource the run handled
+/// was found to be missing or drifted
+pub(crate) fn drift_found(run: &RunState) -> bool {
+    run.report.resources.lock().unwrap().values().any(|report| {
+        matches!(
+            report.status,
+            ResourceStatus::Missing | ResourceStatus::Drifted
+        )
+    })
+}
+
+/// The outcome of every resource the run handled, failures (and
+/// other noteworthy outcomes) first, then by kind and name
+pub(crate) fn resource_reports(run: &RunState) -> Vec<ResourceReport> {
+    let mut reports: Vec<ResourceReport> = run
+        .report
+        .resources
+        .lock()
+        .unwrap()
+        .values()
+        .cloned()
+        .collect();
+
+    reports.sort_by(|left, right| {
+        (left.status, left.kind, &left.name).cmp(&(right.status, right.kind, &right.name))
+    });
+
+    reports
+}
+
+// </editor-fold desc="// Resource Report ...">
+
+// <editor-fold desc="// Queue Results ...">
+
+/// What a run resolved (and, where it could, looked up) for one of a queue's topics
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct TopicResult {
+    /// The topic's name in the configuration
+    pub name: String,
+    /// The topic's name in AWS
+    pub resolved_name: String,
+    pub topic_arn: Option<String>,
+    pub subscription_arn: Option<String>,
+}
+
+/// What a run resolved (and, where it could, looked up)
+/// for a single configured queue and each of its topics
+#[derive(Clone, Debug, Default, Serialize)]
+pub(crate) struct QueueResult {
+    /// The queue's name in the configuration
+    pub name: String,
+    /// The queue's name in AWS (absent for "unsubscribed")
+    pub resolved_name: Option<String>,
+    pub url: Option<String>,
+    pub arn: Option<String>,
+    pub topics: Vec<TopicResult>,
+    /// The configuration source that declared each of the queue's
+    /// topics, by topic name (with `--outputs-include-provenance`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<BTreeMap<String, String>>,
+    /// Why the queue (or any of its topics) could not be ensured
+    pub errors: Vec<String>,
+}
+
+/// Update the result of the supplied configured queue
+fn update_queue_result<T: AsRef<str>, F: FnOnce(&mut QueueResult)>(
+    run: &RunState,
+    queue: T,
+    update: F,
+) {
+    let mut queues = run.report.queues.lock().unwrap();
+
+    update(
+        queues
+            .entry(queue.as_ref().to_string())
+            .or_insert_with(|| QueueResult {
+                name: queue.as_ref().to_string(),
+                ..QueueResult::default()
+            }),
+    )
+}
+
+/// Record the name, URL, and ARN the supplied configured queue resolved to
+pub(crate) fn record_queue<T: AsRef<str>>(
+    run: &RunState,
+    queue: T,
+    resolved_name: String,
+    url: Option<String>,
+    arn: Option<String>,
+) {
+    update_queue_result(run, queue, |result| {
+        result.resolved_name = Some(resolved_name);
+        result.url = url;
+        result.arn = arn;
+    })
+}
+
+/// Record the configuration source that declared each of the
+/// supplied configured queue's topics, by topic name
+pub(crate) fn record_queue_provenance<T: AsRef<str>>(
+    run: &RunState,
+    queue: T,
+    provenance: BTreeMap<String, String>,
+) {
+    update_queue_result(run, queue, |result| {
+        result
+            .provenance
+            .get_or_insert_with(BTreeMap::new)
+            .extend(provenance)
+    })
+}
+
+/// Record what one of the supplied configured queue's topics resolved to
+pub(crate) fn record_topic<T: AsRef<str>>(run: &RunState, queue: T, topic: TopicResult) {
+    update_queue_result(run, queue, |result| result.topics.push(topic))
+}
+
+/// Record why the supplied configured queue (or one of its topics) failed
+pub(crate) fn record_queue_error<T: AsRef<str>, E: AsRef<str>>(run: &RunState, queue: T, error: E) {
+    update_queue_result(run, queue, |result| {
+        result.errors.push(error.as_ref().to_string())
+    })
+}
+
+/// The result of every configured queue the run applied, by name
+pub(crate) fn queue_results(run: &RunState) -> Vec<QueueResult> {
+    run.report
+        .queues
+        .lock()
+        .unwrap()
+        .values()
+        .cloned()
+        .map(|mut result| {
+            result
+                .topics
+                .sort_by(|left, right| left.name.cmp(&right.name));
+            result
+        })
+        .collect()
+}
+
+// </editor-fold desc="// Queue Results ...">
+
+// <editor-fold desc="// Environment Resolution ...">
+
+/// How a conflict between two of the steps the environment
+/// can be resolved from (i.e. `--env-name` and an annotation)
+/// was settled, so post-hoc audits can see the decision
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct EnvResolution {
+    /// The environment the run used
+    pub chosen: String,
+    /// Where the chosen environment came from
+    pub chosen_source: String,
+    /// The environment the run disregarded
+    pub overridden: String,
+    /// Where the disregarded environment came from
+    pub overridden_source: String,
+    /// The `--env-conflict` policy that settled the conflict
+    pub policy: &'static str,
+}
+
+/// Record how a conflict between environment sources was settled
+pub(crate) fn record_env_resolution(run: &RunState, resolution: EnvResolution) {
+    *run.report.env_resolution.lock().unwrap() = Some(resolution);
+}
+
+/// How a conflict between environment sources was
+/// settled, if the run's sources were in conflict
+pub(crate) fn env_resolution(run: &RunState) -> Option<EnvResolution> {
+    run.report.env_resolution.lock().unwrap().clone()
+}
+
+// </editor-fold desc="// Environment Resolution ...">
+
+// <editor-fold desc="// Report Printing Utilities ...">
+
+/// The widest a resource name can be before the
+/// table truncates it (i.e. long queue ARNs)
+const MAX_NAME_WIDTH: usize = 72;
+
+/// The supplied resource name, truncated (from the left, since the
+/// end of a name is its most distinctive part) to `MAX_NAME_WIDTH`
+fn truncated_name(name: &str) -> String {
+    let length: usize = name.chars().count();
+
+    match length > MAX_NAME_WIDTH {
+        false => name.to_string(),
+        true => format!(
+            "...{}",
+            name.chars()
+                .skip(length - (MAX_NAME_WIDTH - 3))
+                .collect::<String>()
+        ),
+    }
+}
+
+/// Print the supplied resource reports as an aligned table,
+/// followed by the number of resources with each status
+pub(crate) fn print_resource_reports(reports: &[ResourceReport]) {
+    if reports.is_empty() {
+        return;
+    }
+
+    let names: Vec<String> = reports
+        .iter()
+        .map(|report| truncated_name(&report.name))
+        .collect();
+
+    let kind_width: usize = reports
+        .iter()
+        .map(|report| report.kind.len())
+        .max()
+        .unwrap_or_default()
+        .max("KIND".len());
+    let name_width: usize = names
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max("RESOURCE".len());
+
+    println!(
+        "  {:<8}  {:<kind_width$}  {:<name_width$}  DETAIL",
+        "STATUS", "KIND", "RESOURCE"
+    );
+
+    reports.iter().zip(&names).for_each(|(report, name)| {
+        let row = format!(
+            "  {:<8}  {:<kind_width$}  {:<name_width$}  {}",
+            report.status.as_str(),
+            report.kind,
+            name,
+            report.detail.as_deref().unwrap_or_default(),
+        );
+        println!("{}", row.trim_end())
+    });
+
+    let mut counts: BTreeMap<ResourceStatus, usize> = BTreeMap::new();
+
+    reports
+        .iter()
+        .for_each(|report| *counts.entry(report.status).or_default() += 1);
+
+    println!(
+        "  {} resource(s): {}",
+        reports.len(),
+        counts
+            .iter()
+            .map(|(status, count)| format!("{} {}", count, status.as_str()))
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+}
+
+// </editor-fold desc="// Report Printing Utilities ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_keeps_the_first_noteworthy_outcome() {
+        let run = RunState::default();
+
+        record_failure(&run, "queue", "dlq", "no permission");
+        record(&run, "queue", "dlq", ResourceStatus::Ensured, None);
+        record(&run, "queue", "main", ResourceStatus::Planned, None);
+        record(&run, "queue", "main", ResourceStatus::Created, None);
+
+        let reports: Vec<ResourceReport> = resource_reports(&run);
+
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].name, "dlq");
+        assert_eq!(reports[0].status, ResourceStatus::Failed);
+        assert_eq!(reports[0].detail.as_deref(), Some("no permission"));
+        assert_eq!(reports[1].name, "main");
+        assert_eq!(reports[1].status, ResourceStatus::Created);
+        assert!(!drift_found(&run));
+    }
+
+    #[test]
+    fn drift_is_reported_per_run() {
+        let (drifted, clean) = (RunState::default(), RunState::default());
+
+        record(&drifted, "topic", "t", ResourceStatus::Drifted, None);
+        record(&clean, "topic", "t", ResourceStatus::Existing, None);
+
+        assert!(drift_found(&drifted));
+        assert!(!drift_found(&clean));
+    }
+
+    #[test]
+    fn queue_results_sort_topics_by_name() {
+        let run = RunState::default();
+
+        for name in ["b", "a"] {
+            record_topic(
+                &run,
+                "q",
+                TopicResult {
+                    name: name.to_string(),
+                    resolved_name: format!("{}-dev", name),
+                    topic_arn: None,
+                    subscription_arn: None,
+                },
+            );
+        }
+        record_queue(&run, "q", "q-dev".to_string(), None, None);
+        record_queue_error(&run, "q", "topic \"b\": failed");
+
+        let results: Vec<QueueResult> = queue_results(&run);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].resolved_name.as_deref(), Some("q-dev"));
+        assert_eq!(
+            results[0]
+                .topics
+                .iter()
+                .map(|topic| topic.name.as_str())
+                .collect::<Vec<&str>>(),
+            ["a", "b"]
+        );
+        assert_eq!(results[0].errors, ["topic \"b\": failed"]);
+        assert!(env_resolution(&run).is_none());
+    }
+}
@@ -0,0 +1,334 @@
+// Pinnothera's AWS API call retry components
+
+// Standard Library Imports
+use std::future::Future;
+use std::time::Duration;
+
+// Third Party Imports
+use aws_smithy_http::result::SdkError;
+use aws_smithy_types::retry::{ErrorKind, ProvideErrorKind};
+
+// Project-Level Imports
+use crate::context::RunState;
+use crate::control;
+use crate::errors::PinnError;
+use crate::logging::warning;
+use crate::stats;
+
+// <editor-fold desc="// Retry Policy ...">
+
+/// The longest pinnothera will wait before retrying a call
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(20);
+
+/// Error codes SNS/SQS (or an AWS-compatible endpoint) return
+/// when a request was throttled or failed for a transient reason
+const RETRYABLE_ERROR_CODES: [&str; 10] = [
+    "Throttling",
+    "ThrottlingException",
+    "ThrottledException",
+    "RequestThrottled",
+    "RequestThrottledException",
+    "TooManyRequestsException",
+    "RequestLimitExceeded",
+    "ServiceUnavailable",
+    "InternalError",
+    "InternalFailure",
+];
+
+/// How many times (and how patiently) a failed AWS API call is retried
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Retry failed calls up to the supplied number of times, waiting
+    /// (roughly) the supplied number of milliseconds before the first
+    pub fn new(max_retries: u32, base_delay_ms: u64) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(base_delay_ms),
+        }
+    }
+
+    /// The delay before the supplied (1-based) retry attempt, doubling
+    /// with each attempt and "jittered" to somewhere between half and
+    /// all of that so that throttled tasks don't retry in lockstep
+    fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(MAX_RETRY_DELAY);
+
+        let half: u64 = (delay.as_millis() / 2) as u64;
+
+        Duration::from_millis(half + fastrand::u64(0..=half))
+    }
+}
+
+// </editor-fold desc="// Retry Policy ...">
+
+// <editor-fold desc="// Retry Utilities ...">
+
+/// Determine whether the supplied error was caused by throttling or a
+/// transient failure (i.e. a timeout or 5xx response), as opposed to
+/// something retrying won't fix (i.e. bad credentials or an invalid name)
+fn is_retryable<E: ProvideErrorKind>(error: &SdkError<E>) -> bool {
+    match error {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) => true,
+        SdkError::ResponseError { raw, .. } => raw.http().status().is_server_error(),
+        SdkError::ServiceError { err, raw } => {
+            matches!(
+                err.retryable_error_kind(),
+                Some(ErrorKind::ThrottlingError | ErrorKind::TransientError)
+            ) || err
+                .code()
+                .map(|code| RETRYABLE_ERROR_CODES.contains(&code))
+                .unwrap_or(false)
+                || raw.http().status().is_server_error()
+        }
+        _ => false,
+    }
+}
+
+/// A short description of why the supplied (retryable) error occurred
+fn retry_reason<E: ProvideErrorKind>(error: &SdkError<E>) -> String {
+    match error {
+        SdkError::TimeoutError(_) => "request timed out".to_string(),
+        SdkError::DispatchFailure(_) => "request could not be sent".to_string(),
+        SdkError::ResponseError { raw, .. } => format!("HTTP {}", raw.http().status()),
+        SdkError::ServiceError { err, raw } => match err.code() {
+            Some(code) => code.to_string(),
+            None => format!("HTTP {}", raw.http().status()),
+        },
+        _ => "unknown error".to_string(),
+    }
+}
+
+/// Make the AWS API call produced by the supplied closure (subject to the
+/// run's concurrency limit), retrying it with exponential backoff if it
+/// fails due to throttling or a transient error, and otherwise returning
+/// its result (or its last error once the retries are exhausted)
+///
+/// Every attempt (retries included) is counted against the run's API call
+/// budget, and no further attempt is made once the run has been cancelled
+/// or its budget is exhausted, in which case the outer result is the
+/// corresponding `PinnError` rather than the call's result
+pub(crate) async fn with_retries<R, T, E, C, F>(
+    run: &RunState,
+    operation: &'static str,
+    resource: R,
+    mut call: C,
+) -> Result<Result<T, SdkError<E>>, PinnError>
+where
+    R: AsRef<str>,
+    E: ProvideErrorKind,
+    C: FnMut() -> F,
+    F: Future<Output = Result<T, SdkError<E>>>,
+{
+    let policy: RetryPolicy = run.retry;
+    let mut attempt: u32 = 0;
+
+    loop {
+        stats::record_api_call(run, operation, &resource)?;
+
+        match stats::limit_api_call(run, operation, call()).await {
+            Err(error)
+                if attempt < policy.max_retries
+                    && is_retryable(&error)
+                    && !control::is_cancelled(run) =>
+            {
+                attempt += 1;
+
+                let delay = policy.delay(attempt);
+
+                warning!(
+                    "Retrying {} for \"{}\" in {}ms ({}, attempt {} of {})",
+                    operation,
+                    resource.as_ref(),
+                    delay.as_millis(),
+                    retry_reason(&error),
+                    attempt,
+                    policy.max_retries,
+                );
+
+                tokio::time::sleep(delay).await;
+            }
+            result => return Ok(result),
+        }
+    }
+}
+
+// </editor-fold desc="// Retry Utilities ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, FakeAws, FakeResponse};
+    use aws_sdk_sqs::error::GetQueueUrlError;
+    use aws_smithy_http::body::SdkBody;
+    use aws_smithy_http::operation::Response;
+    use aws_smithy_http::result::ConnectorError;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A raw response with the supplied HTTP status
+    fn raw_response(status: u16) -> Response {
+        Response::new(
+            http::Response::builder()
+                .status(status)
+                .body(SdkBody::empty())
+                .unwrap(),
+        )
+    }
+
+    /// A service error with the supplied code and HTTP status
+    fn service_error(code: &str, status: u16) -> SdkError<GetQueueUrlError> {
+        SdkError::ServiceError {
+            err: GetQueueUrlError::generic(aws_smithy_types::Error::builder().code(code).build()),
+            raw: raw_response(status),
+        }
+    }
+
+    type ErrorFn = fn() -> SdkError<GetQueueUrlError>;
+
+    fn timeout_error() -> SdkError<GetQueueUrlError> {
+        SdkError::TimeoutError("timed out".into())
+    }
+
+    #[test]
+    fn delays_double_up_to_the_cap_with_jitter() {
+        let policy = RetryPolicy::new(20, 100);
+
+        for attempt in 1..=20u32 {
+            let full: Duration =
+                Duration::from_millis(100 * 2u64.pow(attempt - 1)).min(MAX_RETRY_DELAY);
+
+            for _ in 0..50 {
+                let delay: Duration = policy.delay(attempt);
+
+                assert!(
+                    delay >= full / 2 && delay <= full,
+                    "attempt {}: {:?} not within {:?}..={:?}",
+                    attempt,
+                    delay,
+                    full / 2,
+                    full
+                );
+            }
+        }
+
+        assert!(policy.delay(u32::MAX) <= MAX_RETRY_DELAY);
+        assert_eq!(RetryPolicy::new(3, 0).delay(1), Duration::ZERO);
+    }
+
+    #[test]
+    fn retryable_error_cases() {
+        // (error, whether it's retryable)
+        let cases: [(SdkError<GetQueueUrlError>, bool); 11] = [
+            (timeout_error(), true),
+            (
+                SdkError::DispatchFailure(ConnectorError::io("connection reset".into())),
+                true,
+            ),
+            (
+                SdkError::ResponseError {
+                    err: "truncated".into(),
+                    raw: raw_response(503),
+                },
+                true,
+            ),
+            (
+                SdkError::ResponseError {
+                    err: "unparseable".into(),
+                    raw: raw_response(400),
+                },
+                false,
+            ),
+            (service_error("Throttling", 400), true),
+            (service_error("RequestThrottled", 403), true),
+            (service_error("ServiceUnavailable", 503), true),
+            (service_error("SomethingUnexpected", 500), true),
+            (service_error("AccessDenied", 403), false),
+            (service_error("InvalidParameterValue", 400), false),
+            (SdkError::ConstructionFailure("no queue name".into()), false),
+        ];
+
+        for (error, expected) in cases {
+            assert_eq!(is_retryable(&error), expected, "{:?}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_give_up_after_max_retries() {
+        let run = RunState {
+            retry: RetryPolicy::new(2, 1),
+            ..Default::default()
+        };
+
+        // (error, attempts made)
+        let cases: [(ErrorFn, usize); 2] = [
+            (timeout_error, 3),
+            (|| service_error("AccessDenied", 403), 1),
+        ];
+
+        for (error, expected) in cases {
+            let attempts = AtomicUsize::new(0);
+
+            let result = with_retries(&run, "GetQueueUrl", "orders-dev", || {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                async move { Err::<(), _>(error()) }
+            })
+            .await
+            .unwrap();
+
+            assert!(result.is_err());
+            assert_eq!(attempts.load(Ordering::SeqCst), expected);
+        }
+
+        assert_eq!(stats::api_call_counts(&run)["GetQueueUrl"], 4);
+    }
+
+    #[tokio::test]
+    async fn throttled_calls_succeed_once_retried() {
+        let requests = AtomicUsize::new(0);
+        let fake: FakeAws =
+            FakeAws::start(
+                move |request| match requests.fetch_add(1, Ordering::SeqCst) {
+                    0 => FakeResponse::error("Throttling", "Rate exceeded"),
+                    1 => FakeResponse {
+                        status: 503,
+                        ..FakeResponse::error("ServiceUnavailable", "Try again")
+                    },
+                    _ => testing::aws_response(request),
+                },
+            )
+            .await;
+        let context = fake
+            .context(fake.args(&["--max-retries", "3", "--retry-base-delay-ms", "1"]))
+            .await;
+
+        let response = with_retries(&context.run, "GetQueueUrl", "orders-dev", || {
+            context.sqs.get_queue_url().queue_name("orders-dev").send()
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            response.queue_url(),
+            Some(testing::fake_queue_url("orders-dev").as_str())
+        );
+        assert_eq!(fake.calls("GetQueueUrl").len(), 3);
+        assert_eq!(stats::api_call_counts(&context.run)["GetQueueUrl"], 3);
+    }
+}
@@ -0,0 +1,245 @@
+// Pinnothera's run settings
+
+// Project-Level Imports
+use crate::logging::info;
+use crate::naming::{
+    self, EnvNaming, EnvPosition, DEFAULT_ENV_DELIMITER, DEFAULT_SANITIZE_REPLACEMENT,
+};
+use crate::{CLIArgs, SQSQueueConfig};
+
+// <editor-fold desc="// Settings ...">
+
+/// How a configuration is applied: everything about a run that shapes
+/// the queues, topics, and subscriptions it ensures (and the AWS calls
+/// it makes to do so), independent of where the configuration and
+/// the AWS clients came from. The defaults match the command line's
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// Plan what would be created without creating or modifying anything
+    pub dry_run: bool,
+    /// Verify (without creating or modifying anything) that every
+    /// resource exists as configured, reporting those that don't
+    pub check: bool,
+    /// Log why each resource is being planned (or ensured) as it is
+    pub explain: bool,
+    /// Replace characters AWS disallows in topic and queue names
+    /// (with `sanitize_replacement`) instead of rejecting them
+    pub sanitize_names: bool,
+    pub sanitize_replacement: String,
+    /// Which end of topic and queue names the environment
+    /// is applied to (`prefix` or `suffix`)
+    pub env_position: String,
+    pub env_delimiter: char,
+    /// A shell command that decides the physical name of each queue and
+    /// topic (replacing pinnothera's own sanitization and env suffixing)
+    pub name_hook: Option<String>,
+    /// The Kubernetes `Namespace` the configuration was read from
+    /// (if any), which the name hook is told about
+    pub namespace: Option<String>,
+    /// The access policy template every queue's policy is rendered from
+    /// (rather than the built-in one), with `{region}`, `{account_id}`,
+    /// `{queue}`, and `{suffix}` placeholders
+    pub policy_template: Option<String>,
+    /// Report (rather than update) existing queues whose
+    /// attributes differ from the configuration
+    pub no_update_existing: bool,
+    pub broad_policy: bool,
+    pub replace_policy: bool,
+    pub raw_message_delivery: bool,
+    pub enforce_secure_transport: bool,
+    /// Fail validation (rather than warning) when a filter policy
+    /// matches on a message attribute not declared for its topic
+    pub strict_filters: bool,
+    pub force_resubscribe: bool,
+    pub confirmation_timeout_secs: u64,
+    pub require_confirmed: bool,
+    pub prune_subscriptions: bool,
+    /// Tags to set on every queue and topic, overridden
+    /// by any `tags` in the configuration
+    pub tags: Vec<(String, String)>,
+    /// What to do when a queue's merged tags would exceed
+    /// AWS's limit (`error` or `drop-inherited`)
+    pub tag_overflow: String,
+    pub strict_tags: bool,
+    pub ignore_extra_tags: bool,
+    /// Queues to leave untouched, as if they were configured with `paused: true`
+    pub pause_queues: Vec<String>,
+    pub outputs_include_provenance: bool,
+    /// The maximum number of AWS API calls to make (retries included)
+    pub max_api_calls: Option<u64>,
+    /// The maximum number of AWS API calls to have in flight at once
+    pub concurrency: u64,
+    pub max_subscription_concurrency: Option<u64>,
+    pub max_retries: u32,
+    pub retry_base_delay_ms: u64,
+    pub queue_recreate_wait_secs: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            dry_run: false,
+            check: false,
+            explain: false,
+            sanitize_names: false,
+            sanitize_replacement: DEFAULT_SANITIZE_REPLACEMENT.to_string(),
+            env_position: String::from("suffix"),
+            env_delimiter: DEFAULT_ENV_DELIMITER,
+            name_hook: None,
+            namespace: None,
+            policy_template: None,
+            no_update_existing: false,
+            broad_policy: false,
+            replace_policy: false,
+            raw_message_delivery: false,
+            enforce_secure_transport: false,
+            strict_filters: false,
+            force_resubscribe: false,
+            confirmation_timeout_secs: 0,
+            require_confirmed: false,
+            prune_subscriptions: false,
+            tags: Vec::new(),
+            tag_overflow: String::from("error"),
+            strict_tags: false,
+            ignore_extra_tags: false,
+            pause_queues: Vec::new(),
+            outputs_include_provenance: false,
+            max_api_calls: None,
+            concurrency: 16,
+            max_subscription_concurrency: None,
+            max_retries: 3,
+            retry_base_delay_ms: 100,
+            queue_recreate_wait_secs: 90,
+        }
+    }
+}
+
+/// The settings given on the command line (the access policy
+/// template, which has yet to be loaded from its file, aside)
+impl From<&CLIArgs> for Settings {
+    fn from(args: &CLIArgs) -> Self {
+        Settings {
+            dry_run: args.dry_run,
+            check: args.check,
+            explain: args.explain,
+            sanitize_names: args.sanitize_names,
+            sanitize_replacement: args.sanitize_replacement.clone(),
+            env_position: args.env_position.clone(),
+            env_delimiter: args.env_delimiter,
+            name_hook: args.name_hook.clone(),
+            namespace: args.namespace.clone(),
+            policy_template: None,
+            no_update_existing: args.no_update_existing,
+            broad_policy: args.broad_policy,
+            replace_policy: args.replace_policy,
+            raw_message_delivery: args.raw_message_delivery,
+            enforce_secure_transport: args.enforce_secure_transport,
+            strict_filters: args.strict_filters,
+            force_resubscribe: args.force_resubscribe,
+            confirmation_timeout_secs: args.confirmation_timeout_secs,
+            require_confirmed: args.require_confirmed,
+            prune_subscriptions: args.prune_subscriptions,
+            tags: args.tags.clone(),
+            tag_overflow: args.tag_overflow.clone(),
+            strict_tags: args.strict_tags,
+            ignore_extra_tags: args.ignore_extra_tags,
+            pause_queues: args.pause_queues.clone(),
+            outputs_include_provenance: args.outputs_include_provenance,
+            max_api_calls: args.max_api_calls,
+            concurrency: args.concurrency,
+            max_subscription_concurrency: args.max_subscription_concurrency,
+            max_retries: args.max_retries,
+            retry_base_delay_ms: args.retry_base_delay_ms,
+            queue_recreate_wait_secs: args.queue_recreate_wait_secs,
+        }
+    }
+}
+
+impl Settings {
+    /// Determine whether the supplied queue is paused, either
+    /// in its configuration or via `pause_queues`
+    pub(crate) fn is_paused<T: AsRef<str>>(&self, queue: T, config: &SQSQueueConfig) -> bool {
+        config.paused
+            || self
+                .pause_queues
+                .iter()
+                .any(|paused| paused.as_str() == queue.as_ref())
+    }
+
+    /// How the environment is applied to topic and queue names
+    pub(crate) fn env_naming(&self) -> EnvNaming {
+        EnvNaming::new(
+            EnvPosition::from(self.env_position.as_str()),
+            self.env_delimiter,
+            &self.sanitize_replacement,
+        )
+    }
+
+    /// The replacement for disallowed characters in topic and
+    /// queue names (if name sanitization has been enabled)
+    pub(crate) fn sanitize_replacement(&self) -> Option<&str> {
+        match self.sanitize_names {
+            true => Some(self.sanitize_replacement.as_str()),
+            false => None,
+        }
+    }
+
+    /// Sanitize the supplied topic or queue name (if name sanitization
+    /// has been enabled), logging the mapping if the name was changed
+    pub(crate) fn sanitize<K: AsRef<str>, T: AsRef<str>>(&self, kind: K, name: T) -> String {
+        let name: &str = name.as_ref();
+
+        match self.sanitize_replacement() {
+            None => name.to_string(),
+            Some(replacement) => {
+                let sanitized = naming::sanitize_name(name, replacement);
+
+                if sanitized != name {
+                    info!(
+                        "Sanitizing {} name \"{}\" as \"{}\"...",
+                        kind.as_ref(),
+                        name,
+                        &sanitized
+                    );
+                }
+
+                sanitized
+            }
+        }
+    }
+}
+
+// </editor-fold desc="// Settings ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use clap::Parser;
+
+    #[test]
+    fn default_settings_match_the_command_lines() {
+        let args: CLIArgs = {
+            let _guard = testing::env_lock();
+            CLIArgs::try_parse_from(["pinnothera"]).unwrap()
+        };
+
+        assert_eq!(Settings::from(&args), Settings::default());
+    }
+
+    #[test]
+    fn dot_delimiter_is_joined_with_the_sanitize_replacement() {
+        let dotted = Settings {
+            env_delimiter: '.',
+            ..Default::default()
+        };
+        let underscored = Settings {
+            env_delimiter: '.',
+            sanitize_replacement: String::from("_"),
+            ..Default::default()
+        };
+
+        assert_eq!(dotted.env_naming().delimiter, "-");
+        assert_eq!(underscored.env_naming().delimiter, "_");
+    }
+}
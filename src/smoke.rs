@@ -0,0 +1,316 @@
+// Pinnothera's test-message round-trip (smoke test) components
+
+// Standard Library Imports
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+// Third Party Imports
+use serde::Serialize;
+use serde_json::Value;
+
+// <editor-fold desc="// Smoke Test Results ...">
+
+/// What became of the test message published to a single topic
+#[derive(Eq, Copy, Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DeliveryStatus {
+    /// The message arrived in the queue (and was deleted from it)
+    Delivered,
+    /// The message didn't arrive before the timeout elapsed
+    TimedOut,
+    /// The message couldn't be published (or the queue couldn't be polled)
+    Failed,
+    /// No message was published to the topic (i.e. it's external)
+    Skipped,
+    /// The message would have been published if the run weren't a dry run
+    Planned,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::TimedOut => "timed out",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::Skipped => "skipped",
+            DeliveryStatus::Planned => "planned",
+        }
+    }
+
+    /// Determine whether the status fails the smoke test
+    pub fn is_failure(&self) -> bool {
+        matches!(self, DeliveryStatus::TimedOut | DeliveryStatus::Failed)
+    }
+}
+
+/// The round trip of the test message published to one of a queue's topics
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct TopicDelivery {
+    /// The topic's name in the configuration
+    pub topic: String,
+    /// The topic's name in AWS
+    pub resolved_topic: String,
+    pub status: DeliveryStatus,
+    /// How long the message took to arrive, if it did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Why the message wasn't delivered (or might not have been)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// The round trips of the test messages published to each of a queue's topics
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct QueueSmokeTest {
+    /// The queue's name in the configuration
+    pub queue: String,
+    /// The queue's name in AWS
+    pub resolved_queue: String,
+    pub topics: Vec<TopicDelivery>,
+    /// Why the queue couldn't be smoke tested at all, if it couldn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl QueueSmokeTest {
+    /// Determine whether every test message published
+    /// for the queue (if any) arrived in it
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && !self.topics.iter().any(|topic| topic.status.is_failure())
+    }
+}
+
+/// The outcome of a `smoke-test` run, as printed with `--output json`
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct SmokeTestReport {
+    pub passed: bool,
+    pub timeout_secs: u64,
+    pub queues: Vec<QueueSmokeTest>,
+}
+
+impl SmokeTestReport {
+    pub fn new(timeout_secs: u64, queues: Vec<QueueSmokeTest>) -> Self {
+        SmokeTestReport {
+            passed: queues.iter().all(QueueSmokeTest::passed),
+            timeout_secs,
+            queues,
+        }
+    }
+}
+
+/// A token unique to a single test message, which (being
+/// alphanumeric) survives intact in SNS's JSON envelope
+pub(crate) fn message_token() -> String {
+    let millis: u128 = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+
+    format!("pinnothera-smoke-{:x}-{:016x}", millis, fastrand::u64(..))
+}
+
+// </editor-fold desc="// Smoke Test Results ...">
+
+// <editor-fold desc="// Filter Policy Sampling Utilities ...">
+
+/// The message attributes (by name, as their SNS data type and value)
+/// a message needs for the supplied subscription filter policy to
+/// match it, or why they can't be derived from the policy
+pub(crate) fn sample_message_attributes(
+    policy: &Value,
+) -> Result<BTreeMap<String, (&'static str, String)>, String> {
+    let fields = match policy.as_object() {
+        Some(fields) => fields,
+        None => return Err("the filter policy is not a JSON object".to_string()),
+    };
+
+    let mut attributes: BTreeMap<String, (&'static str, String)> = BTreeMap::new();
+
+    for (name, conditions) in fields {
+        if name == "$or" {
+            // Any one of the alternatives matching is enough
+            let alternatives: &[Value] = conditions.as_array().map(Vec::as_slice).unwrap_or(&[]);
+            let mut reasons: Vec<String> = Vec::new();
+
+            match alternatives
+                .iter()
+                .map(sample_message_attributes)
+                .find_map(|sample| sample.map_err(|reason| reasons.push(reason)).ok())
+            {
+                Some(sample) => attributes.extend(sample),
+                None => {
+                    return Err(format!(
+                        "none of the `$or` alternatives could be satisfied ({})",
+                        reasons.join("; ")
+                    ))
+                }
+            }
+
+            continue;
+        }
+
+        let conditions: &[Value] = match conditions.as_array() {
+            Some(values) => values.as_slice(),
+            None => {
+                return Err(format!(
+                    "the conditions for attribute '{}' are not a list (nested policies only apply to message bodies)",
+                    name
+                ))
+            }
+        };
+
+        match conditions
+            .iter()
+            .find_map(|condition| sample_value(condition).ok())
+        {
+            Some(Some(value)) => {
+                attributes.insert(name.to_string(), value);
+            }
+            Some(None) => (),
+            None => {
+                return Err(format!(
+                    "no supported condition for attribute '{}' ({})",
+                    name,
+                    Value::from(conditions.to_vec())
+                ))
+            }
+        }
+    }
+
+    Ok(attributes)
+}
+
+/// A message attribute value (as its SNS data type and value) matching the
+/// supplied filter policy condition, `None` if the condition requires the
+/// attribute to be absent, or an error if the condition isn't supported
+fn sample_value(condition: &Value) -> Result<Option<(&'static str, String)>, ()> {
+    let (operator, operand) = match condition {
+        Value::String(value) => return Ok(Some(("String", value.to_string()))),
+        Value::Number(value) => return Ok(Some(("Number", value.to_string()))),
+        Value::Object(fields) if fields.len() == 1 => fields.iter().next().ok_or(())?,
+        _ => return Err(()),
+    };
+
+    match (operator.as_str(), operand) {
+        ("prefix" | "suffix" | "equals-ignore-case", Value::String(value)) => {
+            Ok(Some(("String", value.to_string())))
+        }
+        ("wildcard", Value::String(pattern)) => Ok(Some(("String", pattern.replace('*', "")))),
+        ("cidr", Value::String(block)) => Ok(Some((
+            "String",
+            block.split('/').next().unwrap_or_default().to_string(),
+        ))),
+        ("exists", Value::Bool(true)) => Ok(Some(("String", "pinnothera-smoke-test".to_string()))),
+        ("exists", Value::Bool(false)) => Ok(None),
+        ("numeric", Value::Array(comparisons)) => sample_number(comparisons)
+            .map(|value| Some(("Number", value)))
+            .ok_or(()),
+        ("anything-but", excluded) => {
+            let excluded: Vec<&Value> = match excluded {
+                Value::Array(values) => values.iter().collect(),
+                value => vec![value],
+            };
+
+            match excluded.iter().all(|value| value.is_number()) {
+                true => {
+                    let highest: f64 = excluded
+                        .iter()
+                        .filter_map(|value| value.as_f64())
+                        .fold(0.0, f64::max);
+                    Ok(Some(("Number", format_number(highest.floor() + 1.0))))
+                }
+                false => Ok(Some(("String", "pinnothera-smoke-test".to_string()))),
+            }
+        }
+        _ => Err(()),
+    }
+}
+
+/// A number satisfying the supplied `numeric` filter policy comparisons
+/// (i.e. `[">", 0, "<=", 5]`), if there is one
+fn sample_number(comparisons: &[Value]) -> Option<String> {
+    // Each bound, along with whether it's inclusive
+    let mut lower: Option<(f64, bool)> = None;
+    let mut upper: Option<(f64, bool)> = None;
+
+    for pair in comparisons.chunks(2) {
+        let (operator, value) = match pair {
+            [Value::String(operator), value] => (operator.as_str(), value.as_f64()?),
+            _ => return None,
+        };
+
+        match operator {
+            "=" => return Some(format_number(value)),
+            ">" => lower = Some((value, false)),
+            ">=" => lower = Some((value, true)),
+            "<" => upper = Some((value, false)),
+            "<=" => upper = Some((value, true)),
+            _ => return None,
+        }
+    }
+
+    let value: f64 = match (lower, upper) {
+        (Some((low, _)), Some((high, _))) if low < high => (low + high) / 2.0,
+        (Some((low, true)), Some((high, true))) if low == high => low,
+        (Some(_), Some(_)) => return None,
+        (Some((low, inclusive)), None) => low + if inclusive { 0.0 } else { 1.0 },
+        (None, Some((high, inclusive))) => high - if inclusive { 0.0 } else { 1.0 },
+        (None, None) => return None,
+    };
+
+    Some(format_number(value))
+}
+
+/// The supplied number, without a fractional part if it doesn't need one
+fn format_number(value: f64) -> String {
+    match value.fract() == 0.0 {
+        true => format!("{}", value as i64),
+        false => value.to_string(),
+    }
+}
+
+// </editor-fold desc="// Filter Policy Sampling Utilities ...">
+
+// <editor-fold desc="// Smoke Test Printing Utilities ...">
+
+/// Print the supplied smoke test report as one line per topic,
+/// followed by whether the smoke test passed
+pub(crate) fn print_report(report: &SmokeTestReport) {
+    report.queues.iter().for_each(|queue| {
+        println!("Queue \"{}\":", queue.resolved_queue);
+
+        if let Some(error) = &queue.error {
+            println!("  - could not be smoke tested: {}", error);
+        }
+
+        queue.topics.iter().for_each(|topic| {
+            let latency: String = topic
+                .latency_ms
+                .map(|latency| format!(" in {}ms", latency))
+                .unwrap_or_default();
+            let detail: String = topic
+                .detail
+                .as_ref()
+                .map(|detail| format!(" ({})", detail))
+                .unwrap_or_default();
+
+            println!(
+                "  - topic \"{}\": {}{}{}",
+                topic.resolved_topic,
+                topic.status.as_str(),
+                latency,
+                detail
+            );
+        });
+    });
+
+    println!(
+        "Smoke test {}",
+        match report.passed {
+            true => "passed",
+            false => "failed",
+        }
+    );
+}
+
+// </editor-fold desc="// Smoke Test Printing Utilities ...">
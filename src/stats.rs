@@ -0,0 +1,510 @@
+// Pinnothera's AWS (and Kubernetes) API call accounting
+
+// Standard Library Imports
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Third Party Imports
+use serde::Serialize;
+use tokio::sync::Semaphore;
+
+// Project-Level Imports
+use crate::context::RunState;
+use crate::control;
+use crate::errors::PinnError;
+use crate::events::{self, PinnEvent};
+use crate::logging::{debug, info};
+
+// <editor-fold desc="// API Call Stats ...">
+
+/// The phase of a run subscribing queues to their topics
+pub(crate) const SUBSCRIPTION_PHASE: &str = "subscriptions";
+
+/// The phase of a run ensuring its queues and topics themselves
+pub(crate) const RESOURCE_PHASE: &str = "queues_and_topics";
+
+/// The AWS API operations made on behalf of subscriptions, which count
+/// towards the subscription phase (and `--max-subscription-concurrency`)
+const SUBSCRIPTION_OPERATIONS: [&str; 6] = [
+    "ListSubscriptions",
+    "ListSubscriptionsByTopic",
+    "Subscribe",
+    "Unsubscribe",
+    "GetSubscriptionAttributes",
+    "SetSubscriptionAttributes",
+];
+
+/// The AWS (and Kubernetes) API calls a single run has made (or would
+/// have made, if it weren't a dry run), how long ensuring each resource
+/// (and each phase of the run) took, and the limits the run's AWS API
+/// calls are subject to
+#[derive(Debug, Default)]
+pub(crate) struct ApiCallStats {
+    budget: Option<u64>,
+    permits: Option<Semaphore>,
+    subscription_permits: Option<Semaphore>,
+    api_calls: Mutex<BTreeMap<&'static str, u64>>,
+    kube_calls: Mutex<BTreeMap<&'static str, u64>>,
+    planned_operations: Mutex<BTreeMap<&'static str, BTreeSet<String>>>,
+    resource_durations: Mutex<BTreeMap<(&'static str, String), Duration>>,
+    phase_spans: Mutex<BTreeMap<&'static str, (Instant, Instant)>>,
+}
+
+impl ApiCallStats {
+    /// Stats for a run that may make at most the supplied number of AWS
+    /// API calls (if any), with at most the supplied number in flight (of
+    /// which at most the supplied number, if any, may be subscriptions')
+    pub fn new(
+        budget: Option<u64>,
+        concurrency: usize,
+        subscription_concurrency: Option<usize>,
+    ) -> ApiCallStats {
+        ApiCallStats {
+            budget,
+            permits: Some(Semaphore::new(concurrency)),
+            subscription_permits: subscription_concurrency.map(Semaphore::new),
+            ..Default::default()
+        }
+    }
+}
+
+// </editor-fold desc="// API Call Stats ...">
+
+// <editor-fold desc="// API Call Accounting Utilities ...">
+
+/// The phase of the run the named AWS API operation is made in
+pub(crate) fn operation_phase(operation: &str) -> &'static str {
+    match SUBSCRIPTION_OPERATIONS.contains(&operation) {
+        true => SUBSCRIPTION_PHASE,
+        false => RESOURCE_PHASE,
+    }
+}
+
+/// Await the supplied call to the named AWS API operation once fewer
+/// than the configured number of calls are in flight, regardless of
+/// which queue or topic task it's made from (and, for a subscription's
+/// call, once fewer than `--max-subscription-concurrency` of those are,
+/// so the rest of the permits are left to queues and topics), extending
+/// its phase's span to cover it (permits are only held for the call
+/// itself, so nested calls can't starve each other)
+pub(crate) async fn limit_api_call<F: Future>(
+    run: &RunState,
+    operation: &str,
+    call: F,
+) -> F::Output {
+    let phase: &'static str = operation_phase(operation);
+    let started = Instant::now();
+
+    let _subscription_permit = match (phase, &run.stats.subscription_permits) {
+        (SUBSCRIPTION_PHASE, Some(permits)) => permits.acquire().await.ok(),
+        _ => None,
+    };
+
+    let _permit = match &run.stats.permits {
+        Some(permits) => permits.acquire().await.ok(),
+        None => None,
+    };
+
+    let output = call.await;
+    let finished = Instant::now();
+
+    run.stats
+        .phase_spans
+        .lock()
+        .unwrap()
+        .entry(phase)
+        .and_modify(|(start, end)| {
+            *start = (*start).min(started);
+            *end = (*end).max(finished);
+        })
+        .or_insert((started, finished));
+
+    output
+}
+
+/// Record that the named AWS API operation is about to be called on
+/// behalf of the supplied resource, refusing to do so (without counting
+/// the call) if the run has been cancelled, or if doing so would exceed
+/// the configured API call budget
+pub(crate) fn record_api_call<T: AsRef<str>>(
+    run: &RunState,
+    operation: &'static str,
+    resource: T,
+) -> Result<(), PinnError> {
+    if control::is_cancelled(run) {
+        debug!(
+            "Not attempting {} for \"{}\" (run cancelled)",
+            operation,
+            resource.as_ref(),
+        );
+        events::emit(
+            run,
+            PinnEvent::OperationNotAttempted {
+                operation,
+                resource: resource.as_ref().to_string(),
+                reason: "run cancelled".to_string(),
+            },
+        );
+        return Err(PinnError::Cancelled {
+            operation,
+            resource: resource.as_ref().to_string(),
+        });
+    }
+
+    let mut counts = run.stats.api_calls.lock().unwrap();
+
+    if let Some(budget) = &run.stats.budget {
+        if counts.values().sum::<u64>() >= *budget {
+            info!(
+                "Not attempting {} for \"{}\" (api budget of {} call(s) exhausted)",
+                operation,
+                resource.as_ref(),
+                budget,
+            );
+            events::emit(
+                run,
+                PinnEvent::OperationNotAttempted {
+                    operation,
+                    resource: resource.as_ref().to_string(),
+                    reason: "api budget exhausted".to_string(),
+                },
+            );
+            return Err(PinnError::BudgetExhausted {
+                operation,
+                resource: resource.as_ref().to_string(),
+            });
+        }
+    }
+
+    *counts.entry(operation).or_default() += 1;
+
+    debug!("Calling {} for \"{}\"", operation, resource.as_ref());
+
+    events::emit(
+        run,
+        PinnEvent::OperationStarted {
+            operation,
+            resource: resource.as_ref().to_string(),
+        },
+    );
+
+    Ok(())
+}
+
+/// The number of calls made to each AWS API operation so far
+pub(crate) fn api_call_counts(run: &RunState) -> BTreeMap<&'static str, u64> {
+    run.stats.api_calls.lock().unwrap().clone()
+}
+
+/// Record that the named Kubernetes API operation is about to be called
+/// (Kubernetes calls aren't subject to the AWS API call budget)
+pub(crate) fn record_kube_call(run: &RunState, operation: &'static str) {
+    *run.stats
+        .kube_calls
+        .lock()
+        .unwrap()
+        .entry(operation)
+        .or_default() += 1;
+
+    debug!("Calling Kubernetes {}", operation);
+}
+
+/// The number of calls made to each Kubernetes API operation so far
+pub(crate) fn kube_call_counts(run: &RunState) -> BTreeMap<&'static str, u64> {
+    run.stats.kube_calls.lock().unwrap().clone()
+}
+
+/// Log the number of calls the run made to each AWS (and Kubernetes)
+/// API operation, which (like any other informational log) `--quiet`
+/// and `--output json` suppress
+pub(crate) fn log_api_call_counts(run: &RunState) {
+    let counts = api_call_counts(run);
+
+    info!(
+        "AWS API calls made: {} total{}",
+        counts.values().sum::<u64>(),
+        format_call_counts(&counts)
+    );
+
+    let kube_counts = kube_call_counts(run);
+
+    if kube_counts.is_empty() {
+        return;
+    }
+
+    info!(
+        "Kubernetes API calls made: {} total{}",
+        kube_counts.values().sum::<u64>(),
+        format_call_counts(&kube_counts)
+    );
+}
+
+/// The supplied per-operation call counts, one per (indented) line
+fn format_call_counts(counts: &BTreeMap<&'static str, u64>) -> String {
+    counts
+        .iter()
+        .map(|(operation, count)| format!("\n  - {}: {}", operation, count))
+        .collect()
+}
+
+// </editor-fold desc="// API Call Accounting Utilities ...">
+
+// <editor-fold desc="// Dry-Run Accounting Utilities ...">
+
+/// Record that the supplied resource would have been
+/// created (or otherwise modified) if the current run
+/// weren't a dry run
+pub(crate) fn record_planned_operation<T: AsRef<str>>(
+    run: &RunState,
+    kind: &'static str,
+    resource: T,
+) {
+    run.stats
+        .planned_operations
+        .lock()
+        .unwrap()
+        .entry(kind)
+        .or_default()
+        .insert(resource.as_ref().to_string());
+}
+
+pub(crate) fn print_planned_operations(run: &RunState) {
+    let planned = run.stats.planned_operations.lock().unwrap();
+
+    println!(
+        "[dry-run] Planned operations: {} total",
+        planned.values().map(BTreeSet::len).sum::<usize>()
+    );
+
+    planned
+        .iter()
+        .for_each(|(kind, resources)| println!("  - {}(s): {}", kind, resources.len()));
+}
+
+// </editor-fold desc="// Dry-Run Accounting Utilities ...">
+
+// <editor-fold desc="// Resource Timing Utilities ...">
+
+/// How many of the slowest resources the run summary lists
+pub(crate) const SLOWEST_RESOURCE_COUNT: usize = 5;
+
+/// The upper bounds (in milliseconds) of the buckets
+/// of the run summary's resource duration histogram
+const DURATION_BUCKETS_MS: [u64; 8] = [100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000];
+
+/// How long a single phase of the run took, from when its first AWS
+/// API call was made until its last one returned (the phases overlap
+/// wherever a queue's subscriptions are made before others are ensured)
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct PhaseDuration {
+    pub phase: &'static str,
+    pub duration_ms: u64,
+}
+
+/// How long ensuring a single resource (queue, topic, or subscription) took
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ResourceDuration {
+    pub kind: &'static str,
+    pub name: String,
+    pub duration_ms: u64,
+}
+
+/// The number of resources whose duration fell in a
+/// single bucket of the resource duration histogram
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct DurationBucket {
+    /// The bucket's (inclusive) upper bound, or `None` for the last one
+    pub max_ms: Option<u64>,
+    pub count: usize,
+}
+
+/// Await the supplied future, adding the time it took to
+/// the total duration of the supplied resource
+pub(crate) async fn timed<T: AsRef<str>, F: Future>(
+    run: &RunState,
+    kind: &'static str,
+    resource: T,
+    future: F,
+) -> F::Output {
+    let started = Instant::now();
+    let output = future.await;
+
+    *run.stats
+        .resource_durations
+        .lock()
+        .unwrap()
+        .entry((kind, resource.as_ref().to_string()))
+        .or_default() += started.elapsed();
+
+    output
+}
+
+fn resource_durations(run: &RunState) -> Vec<ResourceDuration> {
+    run.stats
+        .resource_durations
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|((kind, name), duration)| ResourceDuration {
+            kind,
+            name: name.to_string(),
+            duration_ms: duration.as_millis() as u64,
+        })
+        .collect()
+}
+
+/// How long each phase of the run took (in the order they began)
+pub(crate) fn phase_durations(run: &RunState) -> Vec<PhaseDuration> {
+    let mut spans: Vec<(&'static str, (Instant, Instant))> = run
+        .stats
+        .phase_spans
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(phase, span)| (*phase, *span))
+        .collect();
+
+    spans.sort_by_key(|(_, (start, _))| *start);
+
+    spans
+        .into_iter()
+        .map(|(phase, (start, end))| PhaseDuration {
+            phase,
+            duration_ms: end.duration_since(start).as_millis() as u64,
+        })
+        .collect()
+}
+
+/// The resources that took the longest to ensure, slowest first
+pub(crate) fn slowest_resources(run: &RunState) -> Vec<ResourceDuration> {
+    let mut durations = resource_durations(run);
+
+    durations.sort_by_key(|resource| std::cmp::Reverse(resource.duration_ms));
+    durations.truncate(SLOWEST_RESOURCE_COUNT);
+
+    durations
+}
+
+/// How many resources took how long to ensure
+pub(crate) fn duration_histogram(run: &RunState) -> Vec<DurationBucket> {
+    let durations = resource_durations(run);
+
+    DURATION_BUCKETS_MS
+        .iter()
+        .map(|max| Some(*max))
+        .chain(std::iter::once(None))
+        .scan(0u64, |min, max| {
+            let bucket = DurationBucket {
+                max_ms: max,
+                count: durations
+                    .iter()
+                    .filter(|resource| {
+                        resource.duration_ms >= *min
+                            && max.map(|max| resource.duration_ms <= max).unwrap_or(true)
+                    })
+                    .count(),
+            };
+            *min = max.map(|max| max + 1).unwrap_or(u64::MAX);
+            Some(bucket)
+        })
+        .collect()
+}
+
+// </editor-fold desc="// Resource Timing Utilities ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn durations_are_attributed_to_their_own_resource() {
+        let run = RunState::default();
+        let delays_ms: [(&str, u64); 7] = [
+            ("a", 10),
+            ("b", 150),
+            ("c", 30),
+            ("d", 190),
+            ("e", 50),
+            ("f", 170),
+            ("g", 70),
+        ];
+
+        futures_util::future::join_all(delays_ms.iter().map(|(queue, delay)| {
+            timed(
+                &run,
+                "queue",
+                queue,
+                tokio::time::sleep(Duration::from_millis(*delay)),
+            )
+        }))
+        .await;
+
+        let durations: BTreeMap<String, u64> = resource_durations(&run)
+            .into_iter()
+            .map(|resource| (resource.name, resource.duration_ms))
+            .collect();
+
+        for (queue, delay) in delays_ms {
+            assert!(
+                (delay..delay + 100).contains(&durations[queue]),
+                "queue '{}' took {}ms rather than {}ms",
+                queue,
+                durations[queue],
+                delay
+            );
+        }
+
+        let slowest: Vec<String> = slowest_resources(&run)
+            .into_iter()
+            .map(|resource| resource.name)
+            .collect();
+
+        assert_eq!(slowest.len(), SLOWEST_RESOURCE_COUNT);
+        assert_eq!(slowest, ["d", "f", "b", "g", "e"]);
+
+        let histogram: Vec<usize> = duration_histogram(&run)
+            .iter()
+            .map(|bucket| bucket.count)
+            .collect();
+
+        assert_eq!(histogram, [4, 3, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[tokio::test]
+    async fn concurrent_durations_of_a_resource_add_up() {
+        let run = RunState::default();
+
+        tokio::join!(
+            timed(
+                &run,
+                "topic",
+                "orders",
+                tokio::time::sleep(Duration::from_millis(40))
+            ),
+            timed(
+                &run,
+                "topic",
+                "orders",
+                tokio::time::sleep(Duration::from_millis(60))
+            ),
+            timed(
+                &run,
+                "queue",
+                "orders",
+                tokio::time::sleep(Duration::from_millis(20))
+            ),
+        );
+
+        let durations = resource_durations(&run);
+
+        assert_eq!(durations.len(), 2);
+        assert!(durations
+            .iter()
+            .any(|resource| resource.kind == "topic" && resource.duration_ms >= 100));
+        assert!(durations
+            .iter()
+            .any(|resource| resource.kind == "queue" && (20..60).contains(&resource.duration_ms)));
+    }
+}
@@ -0,0 +1,454 @@
+// Pinnothera's shared unit test utilities
+
+// Standard Library Imports
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// Third Party Imports
+use clap::Parser;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+// Project-Level Imports
+use crate::context::{AppContext, RunState};
+use crate::events::EventStream;
+use crate::settings::Settings;
+use crate::{AwsClients, CLIArgs, EnvName};
+
+// <editor-fold desc="// Global Statics ...">
+
+/// Held by every test that reads or writes the process environment
+/// (including by parsing `CLIArgs`, whose flags fall back to it)
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+// </editor-fold desc="// Global Statics ...">
+
+// <editor-fold desc="// Environment Utilities ...">
+
+/// Exclusive access to the process environment (regardless
+/// of whether a previous holder panicked)
+pub(crate) fn env_lock() -> MutexGuard<'static, ()> {
+    ENV_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Run the supplied function with the supplied environment variables
+/// set (or removed, if `None`), restoring their previous values after
+pub(crate) fn with_env<T, F: FnOnce() -> T>(vars: &[(&str, Option<&str>)], function: F) -> T {
+    let _guard = env_lock();
+
+    let previous: Vec<(&str, Option<String>)> = vars
+        .iter()
+        .map(|(name, _)| (*name, std::env::var(name).ok()))
+        .collect();
+
+    let apply = |vars: &mut dyn Iterator<Item = (&str, Option<&str>)>| {
+        vars.for_each(|(name, value)| match value {
+            Some(value) => std::env::set_var(name, value),
+            None => std::env::remove_var(name),
+        })
+    };
+
+    apply(&mut vars.iter().copied());
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(function));
+
+    apply(
+        &mut previous
+            .iter()
+            .map(|(name, value)| (*name, value.as_deref())),
+    );
+
+    match result {
+        Ok(value) => value,
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+// </editor-fold desc="// Environment Utilities ...">
+
+// <editor-fold desc="// Filesystem Utilities ...">
+
+/// An empty directory (under the system's temporary directory)
+/// for the named test to create files in, unique to this process
+pub(crate) fn scratch_dir(name: &str) -> PathBuf {
+    let path: PathBuf =
+        std::env::temp_dir().join(format!("pinnothera-{}-{}", name, std::process::id()));
+
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+
+    path
+}
+
+// </editor-fold desc="// Filesystem Utilities ...">
+
+// <editor-fold desc="// Fake AWS Backend ...">
+
+/// The AWS account id every `FakeAws` resource belongs to
+pub(crate) const FAKE_ACCOUNT_ID: &str = "123456789012";
+
+/// A single (`awsQuery` protocol) request made to a `FakeAws` backend
+#[derive(Clone, Debug)]
+pub(crate) struct FakeRequest {
+    /// The request's form-encoded parameters, in the order they were sent
+    pub params: Vec<(String, String)>,
+    /// The path the request was made to (i.e. `/`, or
+    /// an endpoint's path prefix like `/aws/sqs/`)
+    pub path: String,
+    /// The region the request was signed for (per the
+    /// credential scope of its `Authorization` header)
+    pub region: Option<String>,
+}
+
+impl FakeRequest {
+    /// The API operation the request was made for (i.e. `CreateQueue`)
+    pub fn action(&self) -> &str {
+        self.param("Action").unwrap_or_default()
+    }
+
+    /// The value of the named parameter, if the request included it
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// The response a `FakeAws` backend sends for a single request
+#[derive(Clone, Debug)]
+pub(crate) struct FakeResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl FakeResponse {
+    /// A successful response to the named operation, with the
+    /// supplied XML as the contents of its `<{action}Result>`
+    pub fn result<T: AsRef<str>>(action: &str, result: T) -> FakeResponse {
+        FakeResponse {
+            status: 200,
+            body: format!(
+                "<{action}Response><{action}Result>{}</{action}Result><ResponseMetadata><RequestId>fake</RequestId></ResponseMetadata></{action}Response>",
+                result.as_ref(),
+                action = action,
+            ),
+        }
+    }
+
+    /// A successful response to the named operation, which returns no result
+    pub fn empty(action: &str) -> FakeResponse {
+        FakeResponse {
+            status: 200,
+            body: format!(
+                "<{action}Response><ResponseMetadata><RequestId>fake</RequestId></ResponseMetadata></{action}Response>",
+                action = action,
+            ),
+        }
+    }
+
+    /// A (client) error response with the supplied error code and message
+    pub fn error(code: &str, message: &str) -> FakeResponse {
+        FakeResponse {
+            status: 400,
+            body: format!(
+                "<ErrorResponse><Error><Type>Sender</Type><Code>{}</Code><Message>{}</Message></Error><RequestId>fake</RequestId></ErrorResponse>",
+                code, message
+            ),
+        }
+    }
+}
+
+/// The URL of the fake SQS queue with the supplied name
+pub(crate) fn fake_queue_url(queue: &str) -> String {
+    format!(
+        "https://sqs.us-east-1.amazonaws.com/{}/{}",
+        FAKE_ACCOUNT_ID, queue
+    )
+}
+
+/// The ARN of the fake SNS topic or SQS queue with the supplied name
+pub(crate) fn fake_arn(service: &str, name: &str) -> String {
+    format!("arn:aws:{}:us-east-1:{}:{}", service, FAKE_ACCOUNT_ID, name)
+}
+
+/// The response of an AWS account in which every SNS, SQS, and STS call
+/// succeeds, every queue and topic exists as configured, and no topic
+/// has any subscriptions (yet), for tests to fall back to for any
+/// calls they don't answer themselves
+pub(crate) fn aws_response(request: &FakeRequest) -> FakeResponse {
+    let action: &str = request.action();
+    let param = |name: &str| request.param(name).unwrap_or_default();
+    let queue_name = || param("QueueUrl").rsplit('/').next().unwrap_or_default();
+
+    match action {
+        "CreateQueue" | "GetQueueUrl" => FakeResponse::result(
+            action,
+            format!("<QueueUrl>{}</QueueUrl>", fake_queue_url(param("QueueName"))),
+        ),
+        "GetQueueAttributes" => FakeResponse::result(
+            action,
+            format!(
+                "<Attribute><Name>QueueArn</Name><Value>{}</Value></Attribute>",
+                fake_arn("sqs", queue_name())
+            ),
+        ),
+        "ListQueues" => FakeResponse::result(action, ""),
+        "CreateTopic" => FakeResponse::result(
+            action,
+            format!("<TopicArn>{}</TopicArn>", fake_arn("sns", param("Name"))),
+        ),
+        "GetTopicAttributes" => FakeResponse::result(
+            action,
+            format!(
+                "<Attributes><entry><key>TopicArn</key><value>{}</value></entry></Attributes>",
+                param("TopicArn")
+            ),
+        ),
+        "ListSubscriptionsByTopic" | "ListSubscriptions" => {
+            FakeResponse::result(action, "<Subscriptions></Subscriptions>")
+        }
+        "Subscribe" => FakeResponse::result(
+            action,
+            format!(
+                "<SubscriptionArn>{}:{}</SubscriptionArn>",
+                param("TopicArn"),
+                request.params.len()
+            ),
+        ),
+        "GetSubscriptionAttributes" => FakeResponse::result(action, "<Attributes></Attributes>"),
+        "ListQueueTags" => FakeResponse::result(action, ""),
+        "ListTagsForResource" => FakeResponse::result(action, "<Tags></Tags>"),
+        "GetCallerIdentity" => FakeResponse::result(
+            action,
+            format!(
+                "<Arn>arn:aws:iam::{0}:user/fake</Arn><UserId>AIDAFAKE</UserId><Account>{0}</Account>",
+                FAKE_ACCOUNT_ID
+            ),
+        ),
+        "SetQueueAttributes" | "TagQueue" | "UntagQueue" | "SetTopicAttributes" | "TagResource"
+        | "SetSubscriptionAttributes" | "Unsubscribe" => FakeResponse::empty(action),
+        _ => FakeResponse::error("InvalidAction", action),
+    }
+}
+
+type FakeHandler = dyn Fn(&FakeRequest) -> FakeResponse + Send + Sync;
+
+/// An in-process stand-in for the SNS, SQS, and STS APIs, answering
+/// each request with the supplied handler and recording every request
+/// made to it, so tests can assert which (and how many) calls were made
+pub(crate) struct FakeAws {
+    pub endpoint: String,
+    requests: Arc<Mutex<Vec<FakeRequest>>>,
+    server: JoinHandle<()>,
+}
+
+impl Drop for FakeAws {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+impl FakeAws {
+    /// Start a backend answering every request with the supplied handler
+    pub async fn start<F>(handler: F) -> FakeAws
+    where
+        F: Fn(&FakeRequest) -> FakeResponse + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let requests: Arc<Mutex<Vec<FakeRequest>>> = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<FakeHandler> = Arc::new(handler);
+
+        let server_requests = requests.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(
+                    stream,
+                    handler.clone(),
+                    server_requests.clone(),
+                ));
+            }
+        });
+
+        FakeAws {
+            endpoint,
+            requests,
+            server,
+        }
+    }
+
+    /// Every request made to the backend so far
+    pub fn requests(&self) -> Vec<FakeRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The requests made to the backend so far for the named operation
+    pub fn calls(&self, action: &str) -> Vec<FakeRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.action() == action)
+            .collect()
+    }
+
+    /// Settings for a run against the backend (in `us-east-1`, with static
+    /// credentials, and without Kubernetes), plus the supplied flags
+    pub fn args(&self, flags: &[&str]) -> CLIArgs {
+        let _guard = env_lock();
+
+        CLIArgs::try_parse_from(
+            [
+                "pinnothera",
+                "--no-kube",
+                "--aws-region",
+                "us-east-1",
+                "--aws-access-key-id",
+                "AKIAFAKEBACKEND00000",
+                "--aws-secret-access-key",
+                "fake-backend-secret",
+                "--aws-endpoint",
+                &self.endpoint,
+            ]
+            .iter()
+            .chain(flags.iter())
+            .copied(),
+        )
+        .unwrap()
+    }
+
+    /// The context of a (`dev`) run against the backend in the fake
+    /// account, with the supplied settings (streaming its events to
+    /// the file given by `--events-stream`, if any)
+    pub async fn context(&self, args: CLIArgs) -> AppContext {
+        let env: EnvName = EnvName::from(Some("dev"));
+        let (sns, sqs, _) = args.aws_clients(env).await.unwrap();
+        let events: EventStream = match &args.events_stream {
+            Some(path) => EventStream::open(path).unwrap(),
+            None => EventStream::default(),
+        };
+        let settings: Settings = Settings::from(&args);
+        let run: Arc<RunState> = Arc::new(RunState::new(&settings, events));
+        let clients = AwsClients {
+            sns,
+            sqs,
+            region: Some("us-east-1".to_string()),
+            account_id: Some(FAKE_ACCOUNT_ID.to_string()),
+            endpoint_url: args.aws_endpoint_url(env).map(str::to_string),
+            topic_clients: Some(args.topic_client_factory(env)),
+        };
+
+        AppContext::new(env, &clients, settings, run)
+    }
+}
+
+/// Answer each request made over the supplied connection until the client
+/// disconnects (or sends something other than a well-formed request)
+async fn serve_connection(
+    stream: TcpStream,
+    handler: Arc<FakeHandler>,
+    requests: Arc<Mutex<Vec<FakeRequest>>>,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        let mut content_length: usize = 0;
+        let mut region: Option<String> = None;
+        let mut path: Option<String> = None;
+        let mut line = String::new();
+
+        // The request line, then each header until the blank line ending them
+        loop {
+            line.clear();
+            match reader.read_line(&mut line).await {
+                Ok(0) | Err(_) => return,
+                Ok(_) if line == "\r\n" => break,
+                // `POST /path HTTP/1.1`
+                Ok(_) if path.is_none() => {
+                    path = line.split_whitespace().nth(1).map(str::to_string);
+                }
+                Ok(_) => {
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap_or_default();
+                        } else if name.eq_ignore_ascii_case("authorization") {
+                            // `Credential=KEY/DATE/REGION/SERVICE/aws4_request`
+                            region = value
+                                .split("Credential=")
+                                .nth(1)
+                                .and_then(|scope| scope.split('/').nth(2))
+                                .map(str::to_string);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut body: Vec<u8> = vec![0; content_length];
+        if reader.read_exact(&mut body).await.is_err() {
+            return;
+        }
+
+        let request = FakeRequest {
+            params: form_decode(&String::from_utf8_lossy(&body)),
+            path: path.unwrap_or_default(),
+            region,
+        };
+        let response: FakeResponse = handler(&request);
+        requests.lock().unwrap().push(request);
+
+        let message: String = format!(
+            "HTTP/1.1 {} Fake\r\ncontent-type: text/xml\r\ncontent-length: {}\r\n\r\n{}",
+            response.status,
+            response.body.len(),
+            response.body
+        );
+
+        if writer.write_all(message.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// The key/value pairs of the supplied `application/x-www-form-urlencoded` body
+fn form_decode(body: &str) -> Vec<(String, String)> {
+    let decode = |value: &str| -> String {
+        let bytes: Vec<u8> = value.replace('+', " ").into_bytes();
+        let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+        let mut idx: usize = 0;
+
+        while idx < bytes.len() {
+            match (bytes[idx], bytes.get(idx + 1..idx + 3)) {
+                (b'%', Some(hex)) => match u8::from_str_radix(&String::from_utf8_lossy(hex), 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        idx += 3;
+                    }
+                    Err(_) => {
+                        decoded.push(b'%');
+                        idx += 1;
+                    }
+                },
+                (byte, _) => {
+                    decoded.push(byte);
+                    idx += 1;
+                }
+            }
+        }
+
+        String::from_utf8_lossy(&decoded).into_owned()
+    };
+
+    body.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (decode(key), decode(value)),
+            None => (decode(pair), String::new()),
+        })
+        .collect()
+}
+
+// </editor-fold desc="// Fake AWS Backend ...">
@@ -1,13 +1,25 @@
 // Pinnothera's internal structs and enums
 
 // Standard Library Imports
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 
 // Third Party Imports
-use easy_error::{bail, Terminator};
-use k8s_openapi::api::core::v1::ConfigMap;
+use easy_error::Terminator;
+use k8s_openapi::api::core::v1::{ConfigMap, Namespace};
 use kube::{api::Api as K8sAPI, Client as K8sClient};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+// Project-Level Imports
+use crate::context::RunState;
+use crate::errors::PinnError;
+use crate::interpolation;
+use crate::logging::{debug, error_chain, info, warning};
+use crate::naming;
+use crate::regions;
+use crate::report;
+use crate::stats;
 
 // <editor-fold desc="// Type Aliases ...">
 
@@ -18,12 +30,15 @@ pub(crate) type SQSQueueName = String;
 
 pub(crate) type ParsedPinnConfig = BTreeMap<SQSQueueName, SQSQueueConfig>;
 
+/// The values SQS accepts for a queue attribute, along with a description of them
+pub(crate) type QueueAttributeRange = (RangeInclusive<u32>, &'static str);
+
 // </editor-fold desc="// Type Aliases ...">
 
 // <editor-fold desc="// EnvName enum ...">
 
-#[derive(Eq, Copy, Clone, Debug, PartialEq)]
-pub(crate) enum EnvName {
+#[derive(Eq, Copy, Clone, Debug, Default, PartialEq)]
+pub enum EnvName {
     QA,
     QE,
     Dev,
@@ -31,10 +46,22 @@ pub(crate) enum EnvName {
     Test,
     Local,
     Preview,
+    #[default]
     Unknown,
 }
 
 impl EnvName {
+    /// Every environment pinnothera recognizes
+    pub const KNOWN: [EnvName; 7] = [
+        EnvName::QA,
+        EnvName::QE,
+        EnvName::Dev,
+        EnvName::Prod,
+        EnvName::Test,
+        EnvName::Local,
+        EnvName::Preview,
+    ];
+
     pub fn from<T: AsRef<str>>(value: Option<T>) -> EnvName {
         if value.is_none() {
             return EnvName::Unknown;
@@ -74,12 +101,6 @@ impl EnvName {
     }
 }
 
-impl Default for EnvName {
-    fn default() -> Self {
-        Self::Unknown
-    }
-}
-
 impl<T: AsRef<str>> From<T> for EnvName {
     fn from(value: T) -> Self {
         EnvName::from(Some(value))
@@ -88,133 +109,2998 @@ impl<T: AsRef<str>> From<T> for EnvName {
 
 // </editor-fold desc="// EnvName ...">
 
-// <editor-fold desc="// SQSQueueConfig ...">
+// <editor-fold desc="// NamespaceEnvSource enum ...">
 
-#[derive(Clone, Debug, Default, Deserialize)]
-pub(crate) struct SQSQueueConfig {
-    pub topics: Vec<String>,
+/// Where on the target `Namespace` object the name
+/// of the environment should be read from
+#[derive(Clone, Debug)]
+pub(crate) enum NamespaceEnvSource {
+    Annotation(String),
+    Label(String),
 }
 
-// </editor-fold desc="// SQSQueueConfig struct ...">
+impl NamespaceEnvSource {
+    /// Read the name of the environment from the supplied `Namespace`
+    /// (if it carries the expected annotation or label)
+    pub fn read(&self, namespace: &Namespace) -> Option<String> {
+        let (values, key) = match self {
+            NamespaceEnvSource::Annotation(key) => (&namespace.metadata.annotations, key),
+            NamespaceEnvSource::Label(key) => (&namespace.metadata.labels, key),
+        };
 
-// <editor-fold desc="// PinnConfig ...">
+        values.as_ref().and_then(|values| values.get(key)).cloned()
+    }
+
+    /// A human-readable description of the source
+    pub fn describe<T: AsRef<str>>(&self, namespace: T) -> String {
+        match self {
+            NamespaceEnvSource::Annotation(key) => format!(
+                "the '{}' annotation of `Namespace` '{}'",
+                key,
+                namespace.as_ref()
+            ),
+            NamespaceEnvSource::Label(key) => {
+                format!(
+                    "the '{}' label of `Namespace` '{}'",
+                    key,
+                    namespace.as_ref()
+                )
+            }
+        }
+    }
+}
 
-#[derive(Clone, Debug, Default, Deserialize)]
-pub(crate) struct PinnConfig(ParsedPinnConfig);
+// </editor-fold desc="// NamespaceEnvSource enum ...">
 
-impl std::ops::Deref for PinnConfig {
-    type Target = ParsedPinnConfig;
+// <editor-fold desc="// MissingSource enum ...">
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+/// What pinnothera should do when the namespace
+/// has no configuration source (i.e. `ConfigMap`)
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) enum MissingSource {
+    /// Treat the namespace as "not onboarded" (having no configuration)
+    Skip,
+    /// Log a warning and exit successfully without doing anything
+    Warn,
+    /// Fail the run
+    #[default]
+    Error,
+}
+
+impl From<&str> for MissingSource {
+    fn from(value: &str) -> Self {
+        match value {
+            "skip" => MissingSource::Skip,
+            "warn" => MissingSource::Warn,
+            _ => MissingSource::Error,
+        }
     }
 }
 
-impl PinnConfig {
-    #[allow(dead_code)]
-    pub fn for_unknown_env() -> Result<(EnvName, PinnConfig), Terminator> {
-        Ok((EnvName::Unknown, Self::default()))
+// </editor-fold desc="// MissingSource enum ...">
+
+// <editor-fold desc="// EnvConflict enum ...">
+
+/// What pinnothera should do when `--env-name` and an annotation
+/// (or label) resolve to different (known) environments
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) enum EnvConflict {
+    /// Fail the run (before any AWS calls are made)
+    Error,
+    /// Use the environment supplied via `--env-name`
+    #[default]
+    PreferCli,
+    /// Use the environment supplied via the annotation (or label)
+    PreferAnnotation,
+}
+
+impl From<&str> for EnvConflict {
+    fn from(value: &str) -> Self {
+        match value {
+            "error" => EnvConflict::Error,
+            "prefer-annotation" => EnvConflict::PreferAnnotation,
+            _ => EnvConflict::PreferCli,
+        }
     }
+}
 
-    pub fn from_json<T: AsRef<str>>(data: T) -> Result<PinnConfig, Terminator> {
-        let data: &str = data.as_ref();
-        match serde_json::from_str::<PinnConfig>(data) {
-            Ok(obj) => Ok(obj),
-            Err(error) => {
-                println!("Couldn't deserialize JSON data: {}", data);
-                Err(error.into())
-            }
+impl EnvConflict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnvConflict::Error => "error",
+            EnvConflict::PreferCli => "prefer-cli",
+            EnvConflict::PreferAnnotation => "prefer-annotation",
         }
     }
+}
 
-    pub fn from_yaml<T: AsRef<str>>(data: T) -> Result<PinnConfig, Terminator> {
-        let data: &str = data.as_ref();
-        match serde_yaml::from_str::<PinnConfig>(data) {
-            Ok(obj) => Ok(obj),
-            Err(error) => {
-                println!("Couldn't deserialize YAML data: {}", data);
-                Err(error.into())
-            }
+// </editor-fold desc="// EnvConflict enum ...">
+
+// <editor-fold desc="// ConfigMapOptions struct ...">
+
+/// How the `ConfigMap` should be read: which of its keys (if any)
+/// should each be parsed as an independent configuration document
+/// (instead of the single `json`, `yaml`, or `toml` key), and what
+/// to do if it doesn't exist
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ConfigMapOptions {
+    /// Keys that must exist in the `ConfigMap`
+    pub keys: Vec<String>,
+    /// A glob pattern selecting (additional) keys
+    pub key_pattern: Option<String>,
+    /// Whether to proceed with the keys that could be parsed
+    /// when others can't, rather than failing
+    pub keep_going: bool,
+    /// What to do if the `ConfigMap` doesn't exist
+    pub missing_source: MissingSource,
+    /// What to do if the environment annotated on the `ConfigMap`
+    /// (or its namespace) differs from the one in `--env-name`
+    pub env_conflict: EnvConflict,
+}
+
+impl ConfigMapOptions {
+    /// Determine whether any keys were selected (by name or by pattern)
+    pub fn selects_keys(&self) -> bool {
+        !self.keys.is_empty() || self.key_pattern.is_some()
+    }
+
+    /// Determine whether the supplied key is selected, either by name or by pattern
+    pub fn is_selected<T: AsRef<str>>(&self, key: T) -> bool {
+        self.keys.iter().any(|name| name.as_str() == key.as_ref())
+            || self
+                .key_pattern
+                .as_ref()
+                .map(|pattern| naming::glob_matches(pattern, key.as_ref()))
+                .unwrap_or(false)
+    }
+}
+
+// </editor-fold desc="// ConfigMapOptions struct ...">
+
+// <editor-fold desc="// TopicConfig enum ...">
+
+/// A topic a queue should be subscribed to, either by name alone
+/// or along with the SNS filter policy of the subscription
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum TopicConfig {
+    Name(String),
+    Filtered {
+        name: String,
+        /// The subscription's `FilterPolicy`, kept as (exactly) the
+        /// configured JSON so that numeric types survive intact
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filter_policy: Option<serde_json::Value>,
+        /// The names of the message attributes the topic's producers
+        /// send, which subscription filter policies are checked against
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        message_attributes: Vec<String>,
+        /// Whether the topic is owned outside of pinnothera (i.e. by
+        /// another team's Terraform), so that pinnothera only subscribes
+        /// to it and never creates it or sets any of its attributes
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        external: bool,
+        /// The KMS key (id, alias, or ARN) the topic should be encrypted with
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        kms_key_id: Option<String>,
+        /// Whether the environment should be applied to the topic's name,
+        /// `false` using the name verbatim (i.e. for a company-wide topic
+        /// shared by every environment)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        suffix: Option<bool>,
+        /// The id of the AWS account owning the topic, if not the
+        /// current one, so that pinnothera only subscribes to it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        account_id: Option<String>,
+        /// The IAM role to assume for subscribing to the topic (i.e. one
+        /// in the account owning it, allowed to subscribe to the topic)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        role_arn: Option<String>,
+    },
+}
+
+impl TopicConfig {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TopicConfig::Name(name) => name.as_str(),
+            TopicConfig::Filtered { name, .. } => name.as_str(),
         }
     }
 
-    pub async fn from_cluster<T: AsRef<str>>(
-        client: K8sClient,
-        env_name: &Option<T>,
-        namespace: &Option<T>,
-        configmap_name: &T,
-    ) -> Result<(EnvName, PinnConfig), Terminator> {
-        // Ensure the name of the target configmap is usable
-        let configmap_name: &str = configmap_name.as_ref();
+    pub fn filter_policy(&self) -> Option<&serde_json::Value> {
+        match self {
+            TopicConfig::Name(_) => None,
+            TopicConfig::Filtered { filter_policy, .. } => filter_policy.as_ref(),
+        }
+    }
 
-        // Read `ConfigMap`s in the specified (or default) namespace
-        // into the typed interface from k8s-openapi
-        let (config_maps, namespace): (K8sAPI<ConfigMap>, String) = if let Some(value) = &namespace
-        {
+    pub fn message_attributes(&self) -> &[String] {
+        match self {
+            TopicConfig::Name(_) => &[],
+            TopicConfig::Filtered {
+                message_attributes, ..
+            } => message_attributes.as_slice(),
+        }
+    }
+
+    /// Determine whether the topic is configured by ARN
+    /// (i.e. one in another account) rather than by name
+    pub fn is_arn(&self) -> bool {
+        naming::is_topic_arn(self.as_str())
+    }
+
+    pub fn is_external(&self) -> bool {
+        match self {
+            TopicConfig::Name(_) => false,
+            TopicConfig::Filtered { external, .. } => *external,
+        }
+    }
+
+    pub fn kms_key_id(&self) -> Option<&str> {
+        match self {
+            TopicConfig::Name(_) => None,
+            TopicConfig::Filtered { kms_key_id, .. } => kms_key_id.as_deref(),
+        }
+    }
+
+    /// Determine whether the topic's name should be used verbatim
+    /// (i.e. without the environment applied to it)
+    pub fn is_verbatim(&self) -> bool {
+        match self {
+            TopicConfig::Name(_) => false,
+            TopicConfig::Filtered { suffix, .. } => *suffix == Some(false),
+        }
+    }
+
+    /// The id of the AWS account owning the topic, if configured
+    pub fn account_id(&self) -> Option<&str> {
+        match self {
+            TopicConfig::Name(_) => None,
+            TopicConfig::Filtered { account_id, .. } => account_id.as_deref(),
+        }
+    }
+
+    /// The IAM role to assume for subscribing to the topic, if any
+    pub fn role_arn(&self) -> Option<&str> {
+        match self {
+            TopicConfig::Name(_) => None,
+            TopicConfig::Filtered { role_arn, .. } => role_arn.as_deref(),
+        }
+    }
+
+    /// Determine whether the topic is owned by another account,
+    /// either configured by ARN or with an `account_id`
+    pub fn is_foreign(&self) -> bool {
+        self.is_arn() || self.account_id().is_some()
+    }
+}
+
+/// The names of the message attributes the supplied subscription filter
+/// policy matches on, including those inside any `$or` alternatives
+/// (operators like `prefix` or `anything-but` only appear in the
+/// attributes' values, so they're never mistaken for attribute names)
+pub(crate) fn filter_policy_attributes(policy: &serde_json::Value) -> Vec<&str> {
+    policy
+        .as_object()
+        .map(|fields| {
+            fields
+                .iter()
+                .flat_map(|(key, value)| match key.as_str() {
+                    "$or" => value
+                        .as_array()
+                        .map(|alternatives| {
+                            alternatives
+                                .iter()
+                                .flat_map(filter_policy_attributes)
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    name => vec![name],
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl std::fmt::Display for TopicConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+// </editor-fold desc="// TopicConfig enum ...">
+
+// <editor-fold desc="// FifoConfig enum ...">
+
+/// A queue's FIFO settings, either in their compact form (`fifo: true`,
+/// meaning FIFO with content-based deduplication) or their expanded one
+/// (i.e. `fifo: { content_based_deduplication: false, high_throughput: true }`)
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum FifoConfig {
+    Compact(bool),
+    Expanded(FifoSettings),
+}
+
+impl FifoConfig {
+    /// The queue's (normalized) FIFO settings, or `None` for a standard queue
+    pub fn settings(&self) -> Option<FifoSettings> {
+        match self {
+            FifoConfig::Compact(true) => Some(FifoSettings::default()),
+            FifoConfig::Compact(false) => None,
+            FifoConfig::Expanded(settings) => Some(settings.clone()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FifoSettings {
+    /// Whether SQS should deduplicate messages by a hash of their body
+    /// rather than by the `MessageDeduplicationId` producers supply
+    #[serde(default = "FifoSettings::default_content_based_deduplication")]
+    pub content_based_deduplication: bool,
+    /// Whether the queue should use high throughput mode, which
+    /// deduplicates messages and limits throughput per message group
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub high_throughput: bool,
+    /// Whether messages are deduplicated within their message group
+    /// or across the whole queue, leaving SQS's default if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deduplication_scope: Option<DeduplicationScope>,
+}
+
+impl Default for FifoSettings {
+    fn default() -> Self {
+        FifoSettings {
+            content_based_deduplication: true,
+            high_throughput: false,
+            deduplication_scope: None,
+        }
+    }
+}
+
+impl FifoSettings {
+    fn default_content_based_deduplication() -> bool {
+        true
+    }
+
+    /// The SQS attributes of a FIFO queue with these settings
+    pub fn queue_attributes(&self) -> HashMap<String, String> {
+        let deduplication_scope: Option<DeduplicationScope> = match self.high_throughput {
+            true => Some(DeduplicationScope::MessageGroup),
+            false => self.deduplication_scope,
+        };
+
+        [
+            ("FifoQueue", Some("true")),
             (
-                K8sAPI::namespaced(client, value.as_ref()),
-                format!("cluster namespace '{}'", value.as_ref()),
-            )
-        } else {
+                "ContentBasedDeduplication",
+                Some(match self.content_based_deduplication {
+                    true => "true",
+                    false => "false",
+                }),
+            ),
             (
-                K8sAPI::default_namespaced(client),
-                "the current cluster namespace".to_string(),
-            )
-        };
+                "DeduplicationScope",
+                deduplication_scope.map(DeduplicationScope::as_str),
+            ),
+            (
+                "FifoThroughputLimit",
+                self.high_throughput.then_some("perMessageGroupId"),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name.to_string(), value.to_string())))
+        .collect()
+    }
 
-        // Use the typed interface to pull the namespace's
-        // pinnothera configuration (if it exists)
-        let pinn_confmap: ConfigMap = match config_maps.get_opt(configmap_name).await? {
-            Some(obj) => obj,
-            None => {
-                println!(
-                    "No `ConfigMap` named '{}' in {}!",
-                    configmap_name, &namespace
-                );
-                bail!("")
-            }
+    /// Why the settings can't be used together, if they can't
+    pub fn conflict(&self) -> Option<&'static str> {
+        match (
+            self.high_throughput,
+            self.content_based_deduplication,
+            self.deduplication_scope,
+        ) {
+            (true, _, Some(DeduplicationScope::Queue)) => Some(
+                "high throughput mode deduplicates messages per message group, \
+                 so `deduplication_scope` must be `message_group` (or omitted)",
+            ),
+            (true, false, None) => Some(
+                "without content-based deduplication producers must supply a \
+                 `MessageDeduplicationId`, which high throughput mode only honors \
+                 per message group: set `deduplication_scope: message_group` to \
+                 confirm that's how the queue's producers deduplicate",
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// The scope within which a FIFO queue deduplicates messages
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeduplicationScope {
+    MessageGroup,
+    Queue,
+}
+
+impl DeduplicationScope {
+    /// The scope's value of the `DeduplicationScope` queue attribute
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeduplicationScope::MessageGroup => "messageGroup",
+            DeduplicationScope::Queue => "queue",
+        }
+    }
+}
+
+// </editor-fold desc="// FifoConfig enum ...">
+
+// <editor-fold desc="// QueuePolicy enum ...">
+
+/// A queue's bespoke access policy document (used instead of
+/// pinnothera's built-in one), either inline or read from a file
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum QueuePolicy {
+    File(QueuePolicyFile),
+    /// The policy document as JSON text or as a (YAML/JSON) object
+    Inline(serde_json::Value),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueuePolicyFile {
+    /// The path of a file containing the policy document
+    pub file: PathBuf,
+}
+
+impl QueuePolicy {
+    /// The policy document with its `{region}`, `{account_id}`, `{queue}`,
+    /// and `{suffix}` placeholders substituted with the supplied values,
+    /// or a description of why it isn't a valid JSON document
+    pub fn render(
+        &self,
+        region: &str,
+        account_id: &str,
+        queue: &str,
+        suffix: &str,
+    ) -> Result<String, String> {
+        let template: String = match self {
+            QueuePolicy::Inline(serde_json::Value::String(text)) => text.to_string(),
+            QueuePolicy::Inline(document) => document.to_string(),
+            QueuePolicy::File(QueuePolicyFile { file }) => std::fs::read_to_string(file)
+                .map_err(|error| format!("could not read '{}': {}", file.display(), error))?,
         };
 
-        // Pull out the ConfigMap's `annotations` element (if it exists)
-        let annotations: BTreeMap<String, String> = match pinn_confmap.metadata.annotations {
-            Some(obj) => obj,
-            None => BTreeMap::new(),
+        render_policy(&template, region, account_id, queue, suffix)
+    }
+}
+
+/// The placeholders an access policy document (or template) may contain
+pub(crate) const POLICY_PLACEHOLDERS: [&str; 4] = ["region", "account_id", "queue", "suffix"];
+
+/// The supplied access policy document (or template) with its `{region}`,
+/// `{account_id}`, `{queue}`, and `{suffix}` placeholders substituted with
+/// the supplied values, or a description of why it isn't usable (i.e. it
+/// contains an unknown placeholder or isn't a valid JSON object)
+pub(crate) fn render_policy(
+    template: &str,
+    region: &str,
+    account_id: &str,
+    queue: &str,
+    suffix: &str,
+) -> Result<String, String> {
+    // Anything like `{name}` is a placeholder, which can't be confused
+    // with JSON (whose object keys are quoted) or IAM policy variables
+    // (which are written `${aws:name}`)
+    let unknown: BTreeSet<&str> = template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+        .filter(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|character| character.is_ascii_alphanumeric() || character == '_')
+        })
+        .filter(|name| !POLICY_PLACEHOLDERS.contains(name))
+        .collect();
+
+    if !unknown.is_empty() {
+        return Err(format!(
+            "it contains unknown placeholder(s) {:?} (expected any of {:?})",
+            unknown, POLICY_PLACEHOLDERS
+        ));
+    }
+
+    let rendered: String = template
+        .replace("{region}", region)
+        .replace("{account_id}", account_id)
+        .replace("{queue}", queue)
+        .replace("{suffix}", suffix);
+
+    match serde_json::from_str::<serde_json::Value>(&rendered) {
+        Ok(document) if document.is_object() => Ok(rendered),
+        Ok(_) => Err("it is not a JSON object".to_string()),
+        Err(error) => Err(format!("it is not valid JSON ({})", error)),
+    }
+}
+
+// </editor-fold desc="// QueuePolicy enum ...">
+
+// <editor-fold desc="// SQSQueueConfig ...">
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SQSQueueConfig {
+    pub topics: Vec<TopicConfig>,
+    /// The environment the queue (and its topics) should be suffixed
+    /// with instead of the globally resolved one, i.e. for clusters
+    /// hosting several environments in a single namespace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+    /// Whether the environment should be applied to the queue's name
+    /// (and its dead-letter queue's), `false` using the name verbatim
+    /// (its topics are unaffected, and can opt out on their own)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<bool>,
+    /// Whether pinnothera should temporarily leave the queue (and its
+    /// subscriptions) untouched, without losing its configuration
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+    /// The queue's `VisibilityTimeout`, leaving SQS's default if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility_timeout_secs: Option<u32>,
+    /// The queue's `MessageRetentionPeriod`, leaving SQS's default if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_retention_secs: Option<u32>,
+    /// The queue's `DelaySeconds`, leaving SQS's default if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_secs: Option<u32>,
+    /// The queue's `ReceiveMessageWaitTimeSeconds` (long polling),
+    /// leaving SQS's default if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receive_wait_time_secs: Option<u32>,
+    /// The queue's `MaximumMessageSize`, leaving SQS's default if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_message_size_bytes: Option<u32>,
+    /// Queue attributes passed through to SQS verbatim (i.e. ones
+    /// pinnothera doesn't support yet), overridden by typed settings
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub raw_attributes: BTreeMap<String, String>,
+    /// Topic attributes passed through to SNS verbatim
+    /// for each of the queue's topics
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub raw_topic_attributes: BTreeMap<String, String>,
+    /// Subscription attributes passed through to SNS verbatim
+    /// for each of the queue's subscriptions
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub raw_subscription_attributes: BTreeMap<String, String>,
+    /// Whether the queue's subscriptions should deliver raw message
+    /// payloads (rather than SNS JSON envelopes), defaulting to the
+    /// value of `--raw-message-delivery` if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_message_delivery: Option<bool>,
+    /// Whether the queue's access policy should deny any access
+    /// that doesn't use TLS, defaulting to the value of
+    /// `--enforce-secure-transport` if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enforce_secure_transport: Option<bool>,
+    /// A bespoke access policy document to use (verbatim, aside from
+    /// its placeholders) instead of pinnothera's built-in one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<QueuePolicy>,
+    /// The dead-letter queue messages the queue repeatedly
+    /// fails to process should be moved to (if any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dead_letter: Option<DeadLetterConfig>,
+    /// Whether the queue (along with its dead-letter queue and
+    /// topics) should be a FIFO queue, and how it deduplicates
+    /// messages, defaulting to the document's `defaults` if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fifo: Option<FifoConfig>,
+    /// Tags to set on the queue (along with its dead-letter queue
+    /// and topics), overriding the configuration's global tags
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    /// The KMS key (id, alias, or ARN) the queue (along with its
+    /// dead-letter queue) should be encrypted with, overriding the
+    /// configuration's global encryption settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kms_key_id: Option<String>,
+    /// Whether the queue (along with its dead-letter queue) should
+    /// use SQS-managed server-side encryption, overriding the
+    /// configuration's global encryption settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sse: Option<bool>,
+    /// Where the queue's configuration was read from (i.e. a
+    /// `ConfigMap` key or file), as reported by `--explain`
+    #[serde(skip)]
+    pub source: Option<String>,
+    /// Where each topic declared somewhere other than `source`
+    /// was read from (i.e. "unsubscribed" topics merged from
+    /// several `ConfigMap` keys), by topic name
+    #[serde(skip)]
+    pub topic_sources: BTreeMap<String, String>,
+    /// Inherited tags (i.e. from `--tag`) left off the queue's resources
+    /// to keep them within AWS's limit, per `--tag-overflow drop-inherited`
+    #[serde(skip)]
+    pub dropped_tags: BTreeSet<String>,
+}
+
+impl SQSQueueConfig {
+    /// The queue's (normalized) FIFO settings, or `None` for a standard queue
+    pub fn fifo_settings(&self) -> Option<FifoSettings> {
+        self.fifo.as_ref().and_then(FifoConfig::settings)
+    }
+
+    /// Determine whether the queue (along with its
+    /// dead-letter queue and topics) is a FIFO queue
+    pub fn is_fifo(&self) -> bool {
+        self.fifo_settings().is_some()
+    }
+
+    /// The FIFO attributes of the queue (and its dead-letter
+    /// queue), if it's a FIFO queue
+    pub fn fifo_attributes(&self) -> HashMap<String, String> {
+        self.fifo_settings()
+            .map(|settings| settings.queue_attributes())
+            .unwrap_or_default()
+    }
+
+    /// Where the supplied topic of the queue was declared (i.e.
+    /// a `ConfigMap` key or file), if that's known
+    pub fn topic_source<T: AsRef<str>>(&self, topic: T) -> Option<&str> {
+        self.topic_sources
+            .get(topic.as_ref())
+            .or(self.source.as_ref())
+            .map(String::as_str)
+    }
+
+    /// Each of the queue's typed SQS attributes that's set, as the
+    /// name of its setting, the name of the attribute, its value,
+    /// and the range of values (along with a description of it)
+    /// SQS accepts for the attribute
+    pub fn queue_attributes(&self) -> Vec<(&'static str, &'static str, u32, QueueAttributeRange)> {
+        [
+            (
+                "visibility_timeout_secs",
+                "VisibilityTimeout",
+                self.visibility_timeout_secs,
+                (0..=43_200, "0 seconds to 12 hours"),
+            ),
+            (
+                "message_retention_secs",
+                "MessageRetentionPeriod",
+                self.message_retention_secs,
+                (60..=1_209_600, "1 minute to 14 days"),
+            ),
+            (
+                "delay_secs",
+                "DelaySeconds",
+                self.delay_secs,
+                (0..=900, "0 seconds to 15 minutes"),
+            ),
+            (
+                "receive_wait_time_secs",
+                "ReceiveMessageWaitTimeSeconds",
+                self.receive_wait_time_secs,
+                (0..=20, "0 to 20 seconds"),
+            ),
+            (
+                "max_message_size_bytes",
+                "MaximumMessageSize",
+                self.max_message_size_bytes,
+                (1_024..=262_144, "1 KiB to 256 KiB"),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(setting, attribute, value, range)| {
+            value.map(|value| (setting, attribute, value, range))
+        })
+        .collect()
+    }
+
+    /// The queue's server-side encryption attributes (if it configures
+    /// either kind of encryption), explicitly disabling SQS-managed
+    /// encryption for a KMS-encrypted queue so an existing queue can
+    /// be switched from one to the other
+    pub fn encryption_attributes(&self) -> HashMap<String, String> {
+        let attributes: Vec<(&str, &str)> = match (&self.kms_key_id, self.sse) {
+            (Some(key), _) => vec![("KmsMasterKeyId", key), ("SqsManagedSseEnabled", "false")],
+            (None, Some(true)) => vec![("SqsManagedSseEnabled", "true")],
+            (None, Some(false)) => vec![("SqsManagedSseEnabled", "false")],
+            (None, None) => Vec::new(),
         };
 
-        let env_name: EnvName = match env_name {
+        attributes
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// The KMS key the supplied (configured) topic should be encrypted with, if any
+    pub fn topic_kms_key_id<T: AsRef<str>>(&self, topic: T) -> Option<&str> {
+        self.topics
+            .iter()
+            .filter(|entry| entry.as_str() == topic.as_ref())
+            .find_map(TopicConfig::kms_key_id)
+    }
+
+    /// The id of the AWS account owning the supplied
+    /// (configured) topic, if configured with one
+    pub fn topic_account_id<T: AsRef<str>>(&self, topic: T) -> Option<&str> {
+        self.topics
+            .iter()
+            .filter(|entry| entry.as_str() == topic.as_ref())
+            .find_map(TopicConfig::account_id)
+    }
+
+    /// Determine whether the supplied (configured) topic
+    /// is marked as owned outside of pinnothera
+    pub fn is_external_topic<T: AsRef<str>>(&self, topic: T) -> bool {
+        self.topics
+            .iter()
+            .any(|entry| entry.as_str() == topic.as_ref() && entry.is_external())
+    }
+
+    /// Determine whether the supplied (configured) topic's
+    /// name should be used verbatim (per `suffix: false`)
+    pub fn is_verbatim_topic<T: AsRef<str>>(&self, topic: T) -> bool {
+        self.topics
+            .iter()
+            .any(|entry| entry.as_str() == topic.as_ref() && entry.is_verbatim())
+    }
+
+    /// Determine whether the queue's name should be used verbatim (per `suffix: false`)
+    pub fn is_verbatim(&self) -> bool {
+        self.suffix == Some(false)
+    }
+
+    /// The environment the queue (and its topics) should be suffixed
+    /// with, given the globally resolved environment of the cluster
+    pub fn env_name(&self, cluster_env: EnvName) -> EnvName {
+        match &self.env {
             Some(value) => EnvName::from(Some(value)),
-            None => EnvName::from(annotations.get("app-env")),
-        };
+            None => cluster_env,
+        }
+    }
 
-        // Pull out the ConfigMap's `data` element (if it exists)
-        let confs_map: BTreeMap<String, String> = match pinn_confmap.data {
-            Some(obj) => obj,
-            None => {
-                println!(
-                    "The '{}' `ConfigMap` retrieved from {} has no `data` element!",
-                    configmap_name, &namespace,
-                );
-                bail!("")
-            }
-        };
+    /// The environment that should be applied to the queue's own name,
+    /// given the queue's environment (none if it's used verbatim)
+    pub fn queue_name_env(&self, env: EnvName) -> EnvName {
+        match self.is_verbatim() {
+            true => EnvName::Unknown,
+            false => env,
+        }
+    }
 
-        // Parse the data from the first recognized key and return it
-        if let Some(data) = confs_map.get("json") {
-            return match Self::from_json(data) {
-                Ok(config) => Ok((env_name, config)),
-                Err(error) => Err(error),
-            };
-        } else if let Some(data) = confs_map.get("yaml") {
-            return match Self::from_yaml(data) {
-                Ok(config) => Ok((env_name, config)),
-                Err(error) => Err(error),
-            };
-        };
+    /// The environment that should be applied to the supplied (configured)
+    /// topic's name, given the queue's environment (none if it's used verbatim)
+    pub fn topic_name_env<T: AsRef<str>>(&self, topic: T, env: EnvName) -> EnvName {
+        match self.is_verbatim_topic(topic) {
+            true => EnvName::Unknown,
+            false => env,
+        }
+    }
+}
+
+// </editor-fold desc="// SQSQueueConfig struct ...">
+
+// <editor-fold desc="// DeadLetterConfig struct ...">
 
-        println!("The `data` element in the '{}' ConfigMap retrieved from {} has no pinnothera-recognized keys!", configmap_name, &namespace, );
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DeadLetterConfig {
+    /// The (unsuffixed) name of the dead-letter queue,
+    /// defaulting to `<queue>-dlq` if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The number of times a message may be received before
+    /// being moved to the dead-letter queue
+    pub max_receive_count: u32,
+}
+
+impl DeadLetterConfig {
+    /// The largest `maxReceiveCount` SQS accepts in a redrive policy
+    pub const MAX_RECEIVE_COUNT: u32 = 1000;
+
+    /// The (unsuffixed) name of the dead-letter queue
+    /// for the supplied (configured) queue name
+    pub fn queue_name<T: AsRef<str>>(&self, queue: T) -> String {
+        match &self.name {
+            Some(name) => name.to_string(),
+            None => format!("{}-dlq", naming::strip_fifo_extension(queue.as_ref())),
+        }
+    }
 
-        bail!("")
+    /// The `RedrivePolicy` attribute targeting the dead-letter queue with the supplied ARN
+    pub fn redrive_policy<T: AsRef<str>>(&self, dead_letter_arn: T) -> String {
+        serde_json::json!({
+            "deadLetterTargetArn": dead_letter_arn.as_ref(),
+            "maxReceiveCount": self.max_receive_count,
+        })
+        .to_string()
     }
 }
 
-// </editor-fold desc="// PinnConfig struct ...">
+// </editor-fold desc="// DeadLetterConfig struct ...">
+
+// <editor-fold desc="// TagOverflow struct ...">
+
+/// The most tags AWS allows on a single SQS queue or SNS topic
+pub(crate) const MAX_RESOURCE_TAGS: usize = 50;
+
+/// The tag pinnothera records the keys of the tags it set on a queue in,
+/// so that it only ever removes tags it set itself (never ones set by
+/// other tooling)
+pub(crate) const MANAGED_TAG_KEYS_TAG: &str = "pinnothera.io/managed-tag-keys";
+
+/// The tag marking a queue as one pinnothera manages, so that a later
+/// run can recognise a queue an interrupted run only partially applied
+pub(crate) const MANAGED_BY_TAG: &str = "pinnothera.io/managed-by";
+
+/// The value of the tag marking a queue as one pinnothera manages
+pub(crate) const MANAGED_BY: &str = "pinnothera";
+
+/// The tags pinnothera sets for its own bookkeeping, which count against
+/// AWS's limit (and can't be configured)
+pub(crate) const RESERVED_TAGS: [&str; 2] = [MANAGED_TAG_KEYS_TAG, MANAGED_BY_TAG];
+
+/// The merged tags of a queue exceeding AWS's limit, by where they came from
+#[derive(Clone, Debug, PartialEq)]
+pub struct TagOverflow {
+    /// How many tags each source contributed (counting a tag set by
+    /// several sources only for the one whose value wins), in order
+    /// of increasing priority
+    pub sources: Vec<(&'static str, usize)>,
+    /// The inherited tags (those from `--tag` or the configuration's
+    /// global `tags`) in the order they'd be dropped: lowest priority
+    /// source first, then in reverse key order
+    pub inherited: Vec<String>,
+}
+
+impl TagOverflow {
+    /// The most tags a queue can be configured with, leaving
+    /// room for the ones pinnothera sets for its own bookkeeping
+    pub const LIMIT: usize = MAX_RESOURCE_TAGS - RESERVED_TAGS.len();
+
+    /// The number of tags the queue would have
+    pub fn total(&self) -> usize {
+        self.sources.iter().map(|(_, count)| count).sum()
+    }
+
+    /// The inherited tags to drop to bring the queue within the limit,
+    /// or `None` if its own tags alone exceed it
+    pub fn dropped(&self) -> Option<Vec<String>> {
+        let excess: usize = self.total().saturating_sub(Self::LIMIT);
+
+        (excess <= self.inherited.len()).then(|| self.inherited[..excess].to_vec())
+    }
+
+    /// Describe the overflow of the supplied queue's tags and their sources
+    pub fn describe(&self, queue: &str) -> String {
+        format!(
+            "Queue '{}' would have {} tags, over the limit of {} ({} less {} reserved for pinnothera's bookkeeping): {}",
+            queue,
+            self.total(),
+            Self::LIMIT,
+            MAX_RESOURCE_TAGS,
+            RESERVED_TAGS.len(),
+            self.sources
+                .iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(source, count)| format!("{} from {}", count, source))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+// </editor-fold desc="// TagOverflow struct ...">
+
+// <editor-fold desc="// PinnConfig ...">
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PinnConfig {
+    /// Tags to set on every queue and topic in the configuration,
+    /// overridden by each queue's own `tags` (which makes "tags"
+    /// a reserved name, much like "unsubscribed")
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    tags: BTreeMap<String, String>,
+    /// The KMS key every queue should be encrypted with, unless it
+    /// configures its own encryption (also a reserved name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kms_key_id: Option<String>,
+    /// Whether every queue should use SQS-managed server-side encryption,
+    /// unless it configures its own encryption (also a reserved name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sse: Option<bool>,
+    /// Settings every queue in the document falls back to unless
+    /// it configures its own (also a reserved name)
+    #[serde(default, skip_serializing_if = "QueueDefaults::is_empty")]
+    defaults: QueueDefaults,
+    #[serde(flatten)]
+    queues: ParsedPinnConfig,
+}
+
+/// The `defaults` block of a configuration document
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct QueueDefaults {
+    /// The FIFO settings of every queue that doesn't configure its own `fifo`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fifo: Option<FifoConfig>,
+}
+
+impl QueueDefaults {
+    fn is_empty(&self) -> bool {
+        self.fifo.is_none()
+    }
+}
+
+/// The configuration as a single line of JSON (i.e. for debug logging)
+impl std::fmt::Display for PinnConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&serde_json::to_string(self).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+impl std::ops::Deref for PinnConfig {
+    type Target = ParsedPinnConfig;
+
+    fn deref(&self) -> &Self::Target {
+        &self.queues
+    }
+}
+
+impl PinnConfig {
+    #[allow(dead_code)]
+    pub fn for_unknown_env() -> Result<(EnvName, PinnConfig), Terminator> {
+        Ok((EnvName::Unknown, Self::default()))
+    }
+
+    /// Apply the document's `defaults` to every queue
+    /// that doesn't configure the same setting itself
+    fn with_defaults(mut self) -> PinnConfig {
+        let defaults: &QueueDefaults = &self.defaults;
+
+        self.queues
+            .values_mut()
+            .filter(|config| config.fifo.is_none())
+            .for_each(|config| config.fifo = defaults.fifo.clone());
+
+        self
+    }
+
+    /// Record the supplied source as the origin of every
+    /// queue's configuration (for `--explain`)
+    pub fn with_source<T: AsRef<str>>(mut self, source: T) -> PinnConfig {
+        self.queues
+            .values_mut()
+            .for_each(|config| config.source = Some(source.as_ref().to_string()));
+        self
+    }
+
+    /// The supplied queue's configuration, with the configuration's
+    /// global tags merged into (and overridden by) its own, and the
+    /// global encryption settings applied unless it configures its own
+    pub fn with_global_settings(&self, config: &SQSQueueConfig) -> SQSQueueConfig {
+        let mut tags: BTreeMap<String, String> = self.tags.clone();
+        tags.extend(config.tags.clone());
+
+        let (kms_key_id, sse) = match (&config.kms_key_id, config.sse) {
+            (None, None) => (self.kms_key_id.clone(), self.sse),
+            (key, sse) => (key.clone(), sse),
+        };
+
+        SQSQueueConfig {
+            tags,
+            kms_key_id,
+            sse,
+            ..config.clone()
+        }
+    }
+
+    /// Iterate over the names of every queue in the configuration
+    /// (excluding the "unsubscribed" sentinel entry)
+    pub fn queue_names(&self) -> impl Iterator<Item = &str> {
+        self.keys()
+            .map(|queue| queue.as_str())
+            .filter(|queue| *queue != "unsubscribed")
+    }
+
+    /// A one-line summary of the configuration's size,
+    /// i.e. "3 queue(s), 5 topic(s), 1 dead-letter queue(s)"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} queue(s), {} topic(s), {} dead-letter queue(s)",
+            self.queue_names().count(),
+            self.topic_names().count(),
+            self.dead_letter_queue_names().count(),
+        )
+    }
+
+    /// Each queue whose merged tags (those supplied via `--tag`,
+    /// overridden by the configuration's global `tags`, overridden
+    /// by its own) would exceed AWS's limit, and where they came from
+    pub fn tag_overflows(
+        &self,
+        run_tags: &BTreeMap<String, String>,
+    ) -> BTreeMap<String, TagOverflow> {
+        self.iter()
+            .filter_map(|(queue, config)| {
+                let sources: [(&'static str, Vec<&String>); 3] = [
+                    (
+                        "`--tag`",
+                        run_tags
+                            .keys()
+                            .filter(|key| {
+                                !self.tags.contains_key(*key) && !config.tags.contains_key(*key)
+                            })
+                            .collect(),
+                    ),
+                    (
+                        "the configuration's global `tags`",
+                        self.tags
+                            .keys()
+                            .filter(|key| !config.tags.contains_key(*key))
+                            .collect(),
+                    ),
+                    ("its own `tags`", config.tags.keys().collect()),
+                ];
+
+                let overflow = TagOverflow {
+                    sources: sources
+                        .iter()
+                        .map(|(source, keys)| (*source, keys.len()))
+                        .collect(),
+                    inherited: sources[..2]
+                        .iter()
+                        .flat_map(|(_, keys)| keys.iter().rev().map(|key| key.to_string()))
+                        .collect(),
+                };
+
+                (overflow.total() > TagOverflow::LIMIT).then(|| (queue.clone(), overflow))
+            })
+            .collect()
+    }
+
+    /// Leave the supplied (inherited) tags off the supplied
+    /// queue's resources, per `--tag-overflow drop-inherited`
+    pub fn drop_tags(&mut self, queue: &str, tags: Vec<String>) {
+        if let Some(config) = self.queues.get_mut(queue) {
+            config.dropped_tags.extend(tags);
+        }
+    }
+
+    /// Iterate over the names of every dead-letter queue in the configuration
+    pub fn dead_letter_queue_names(&self) -> impl Iterator<Item = String> + '_ {
+        self.iter().filter_map(|(queue, config)| {
+            config
+                .dead_letter
+                .as_ref()
+                .map(|dead_letter| dead_letter.queue_name(queue))
+        })
+    }
+
+    /// Iterate over the (deduplicated) names of every
+    /// topic referenced anywhere in the configuration
+    pub fn topic_names(&self) -> impl Iterator<Item = &str> {
+        self.values()
+            .flat_map(|config| config.topics.iter().map(|topic| topic.as_str()))
+            .collect::<BTreeSet<&str>>()
+            .into_iter()
+    }
+
+    /// Describe every configured feature that isn't (or might not be)
+    /// available in the supplied region, and every topic configured by
+    /// the ARN of another partition's topic (which can't be subscribed
+    /// to), along with whether each should fail validation
+    fn check_region_capabilities(&self, region: &str) -> Vec<(bool, String)> {
+        let partition: &str = regions::partition(region).name;
+        let mut problems: Vec<(bool, String)> = Vec::new();
+
+        for (queue, config) in self.iter() {
+            let config: SQSQueueConfig = self.with_global_settings(config);
+
+            let features = [
+                (
+                    regions::Feature::FifoTopics,
+                    config.is_fifo()
+                        && config
+                            .topics
+                            .iter()
+                            .any(|topic| !topic.is_external() && !topic.is_foreign()),
+                ),
+                (regions::Feature::SqsManagedSse, config.sse == Some(true)),
+                (
+                    regions::Feature::MessageBodyFiltering,
+                    config
+                        .raw_subscription_attributes
+                        .get("FilterPolicyScope")
+                        .map(|scope| scope == "MessageBody")
+                        .unwrap_or(false),
+                ),
+            ];
+
+            features
+                .iter()
+                .filter(|(_, configured)| *configured)
+                .filter_map(|(feature, _)| regions::unavailability(*feature, region))
+                .for_each(|entry| {
+                    problems.push((
+                        entry.reject,
+                        format!(
+                            "Queue '{}' uses {}, which {} in region '{}' (partition '{}'): {}",
+                            queue,
+                            entry.feature,
+                            match entry.reject {
+                                true => "is not available",
+                                false => "may not be available",
+                            },
+                            region,
+                            entry.partition,
+                            entry.note
+                        ),
+                    ))
+                });
+
+            config
+                .topics
+                .iter()
+                .filter(|topic| topic.is_arn())
+                .filter_map(|topic| {
+                    topic
+                        .as_str()
+                        .split(':')
+                        .nth(1)
+                        .filter(|other| *other != partition)
+                        .map(|other| (topic, other))
+                })
+                .for_each(|(topic, other)| {
+                    problems.push((
+                        true,
+                        format!(
+                            "The topic ARN '{}' of queue '{}' is in partition '{}', but region '{}' is in partition '{}' (subscriptions can't cross partitions)",
+                            topic, queue, other, region, partition
+                        ),
+                    ))
+                });
+
+            config
+                .topics
+                .iter()
+                .filter(|topic| topic.is_arn())
+                .filter_map(|topic| {
+                    naming::arn_region(topic.as_str())
+                        .filter(|other| {
+                            *other != region && regions::partition(other).name == partition
+                        })
+                        .map(|other| (topic, other))
+                })
+                .for_each(|(topic, other)| {
+                    problems.push((
+                        false,
+                        format!(
+                            "The topic ARN '{}' of queue '{}' is in region '{}' rather than '{}', so its messages will be delivered across regions (incurring inter-region data transfer charges)",
+                            topic, queue, other, region
+                        ),
+                    ))
+                });
+        }
+
+        problems
+    }
+
+    /// Describe every message attribute a subscription filter policy
+    /// (either a topic's `filter_policy` or a `FilterPolicy` raw
+    /// subscription attribute) matches on that isn't among the
+    /// message attributes declared for the topic anywhere in the
+    /// configuration (topics without any declared are not checked)
+    fn check_filter_attributes(&self) -> Vec<String> {
+        let mut declared: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+
+        self.values()
+            .flat_map(|config| config.topics.iter())
+            .for_each(|topic| {
+                declared.entry(topic.as_str()).or_default().extend(
+                    topic
+                        .message_attributes()
+                        .iter()
+                        .map(|attribute| attribute.as_str()),
+                )
+            });
+
+        let mut problems: Vec<String> = Vec::new();
+
+        for (queue, config) in self.iter() {
+            let raw_policy: Option<serde_json::Value> = config
+                .raw_subscription_attributes
+                .get("FilterPolicy")
+                .and_then(|value| serde_json::from_str(value).ok());
+
+            for topic in config.topics.iter() {
+                let known = match declared.get(topic.as_str()) {
+                    Some(known) if !known.is_empty() => known,
+                    _ => continue,
+                };
+
+                topic
+                    .filter_policy()
+                    .into_iter()
+                    .chain(raw_policy.iter())
+                    .flat_map(filter_policy_attributes)
+                    .filter(|attribute| !known.contains(attribute))
+                    .collect::<BTreeSet<&str>>()
+                    .into_iter()
+                    .for_each(|attribute| {
+                        problems.push(format!(
+                            "The filter policy of queue '{}' for topic '{}' matches on the message attribute '{}', which isn't declared for the topic (declared: {:?})",
+                            queue, topic, attribute, known
+                        ))
+                    });
+            }
+        }
+
+        problems
+    }
+
+    /// Ensure every queue and topic name in the configuration
+    /// is usable, either as-is or once sanitized with the
+    /// supplied replacement (if name sanitization is enabled),
+    /// unless a name hook will decide the names used in AWS, and
+    /// that every configured feature is available in the supplied
+    /// region (if one is known), logging any warnings and returning
+    /// every problem found (if there are any)
+    pub fn validate(
+        &self,
+        sanitize_replacement: Option<&str>,
+        name_hook: bool,
+        strict_filters: bool,
+        region: Option<&str>,
+    ) -> Result<(), PinnError> {
+        let mut errors: Vec<String> = Vec::new();
+
+        if let Some(region) = region {
+            self.check_region_capabilities(region)
+                .into_iter()
+                .for_each(|(reject, problem)| match reject {
+                    true => errors.push(problem),
+                    false => warning!("{}", problem),
+                });
+        }
+
+        self.check_filter_attributes()
+            .into_iter()
+            .for_each(|problem| match strict_filters {
+                true => errors.push(problem),
+                false => warning!("{}", problem),
+            });
+
+        let rules: [fn(&PinnConfig) -> Vec<String>; 10] = [
+            PinnConfig::env_override_errors,
+            PinnConfig::topic_attribute_errors,
+            PinnConfig::queue_attribute_errors,
+            PinnConfig::policy_errors,
+            PinnConfig::fifo_errors,
+            PinnConfig::tag_errors,
+            PinnConfig::encryption_errors,
+            PinnConfig::foreign_topic_errors,
+            PinnConfig::external_topic_errors,
+            PinnConfig::topic_arn_errors,
+        ];
+
+        errors.extend(rules.iter().flat_map(|rule| rule(self)));
+        errors.extend(self.name_errors(sanitize_replacement, name_hook));
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(PinnError::InvalidConfig { problems: errors }),
+        }
+    }
+
+    /// Queues overriding their environment with one that isn't known
+    fn env_override_errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        self.iter()
+            .filter_map(|(queue, config)| config.env.as_ref().map(|env| (queue, env)))
+            .filter(|(_, env)| EnvName::from(Some(env)).is_unknown())
+            .for_each(|(queue, env)| {
+                errors.push(format!(
+                    "The environment override '{}' for queue '{}' is not a known environment",
+                    env, queue
+                ))
+            });
+
+        errors
+    }
+
+    /// Topics given different raw attributes by different queues
+    fn topic_attribute_errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        let mut topic_attributes: BTreeMap<&str, (&str, &BTreeMap<String, String>)> =
+            BTreeMap::new();
+
+        self.iter()
+            .filter(|(_, config)| !config.raw_topic_attributes.is_empty())
+            .flat_map(|(queue, config)| {
+                config
+                    .topics
+                    .iter()
+                    .map(move |topic| (topic.as_str(), queue.as_str(), &config.raw_topic_attributes))
+            })
+            .for_each(|(topic, queue, attributes)| {
+                match topic_attributes.get(topic) {
+                    Some((other, existing)) if *existing != attributes => errors.push(format!(
+                        "The queues '{}' and '{}' configure different raw attributes for topic '{}'",
+                        other, queue, topic
+                    )),
+                    Some(_) => (),
+                    None => {
+                        topic_attributes.insert(topic, (queue, attributes));
+                    }
+                }
+            });
+
+        errors
+    }
+
+    /// Dead-letter and queue attribute settings outside of the ranges SQS accepts
+    fn queue_attribute_errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        self.iter()
+            .filter_map(|(queue, config)| config.dead_letter.as_ref().map(|value| (queue, value)))
+            .filter(|(_, dead_letter)| {
+                !(1..=DeadLetterConfig::MAX_RECEIVE_COUNT).contains(&dead_letter.max_receive_count)
+            })
+            .for_each(|(queue, dead_letter)| {
+                errors.push(format!(
+                    "The dead-letter `max_receive_count` of {} for queue '{}' must be between 1 and {}",
+                    dead_letter.max_receive_count,
+                    queue,
+                    DeadLetterConfig::MAX_RECEIVE_COUNT
+                ))
+            });
+
+        self.iter()
+            .flat_map(|(queue, config)| {
+                config
+                    .queue_attributes()
+                    .into_iter()
+                    .map(move |attribute| (queue, attribute))
+            })
+            .filter(|(_, (_, _, value, (range, _)))| !range.contains(value))
+            .for_each(|(queue, (setting, _, value, (range, description)))| {
+                errors.push(format!(
+                    "The `{}` of {} for queue '{}' must be between {} and {} ({})",
+                    setting,
+                    value,
+                    queue,
+                    range.start(),
+                    range.end(),
+                    description
+                ))
+            });
+
+        errors
+    }
+
+    /// Access policies that can't be rendered, and filter policies that
+    /// aren't JSON objects or are configured in more than one place
+    fn policy_errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        self.iter()
+            .filter_map(|(queue, config)| config.policy.as_ref().map(|value| (queue, value)))
+            .filter_map(|(queue, policy)| {
+                policy
+                    .render("us-east-1", "000000000000", queue, "env")
+                    .err()
+                    .map(|error| (queue, error))
+            })
+            .for_each(|(queue, error)| {
+                errors.push(format!(
+                    "The access policy for queue '{}' is unusable: {}",
+                    queue, error
+                ))
+            });
+
+        self.iter()
+            .flat_map(|(queue, config)| config.topics.iter().map(move |topic| (queue, topic)))
+            .filter(|(_, topic)| {
+                topic
+                    .filter_policy()
+                    .map(|policy| !policy.is_object())
+                    .unwrap_or(false)
+            })
+            .for_each(|(queue, topic)| {
+                errors.push(format!(
+                    "The filter policy for topic '{}' of queue '{}' must be a JSON object",
+                    topic, queue
+                ))
+            });
+
+        self.iter()
+            .filter(|(_, config)| config.raw_subscription_attributes.contains_key("FilterPolicy"))
+            .filter(|(_, config)| config.topics.iter().any(|topic| topic.filter_policy().is_some()))
+            .for_each(|(queue, _)| {
+                errors.push(format!(
+                    "The queue '{}' configures a `FilterPolicy` in both `raw_subscription_attributes` and its topics",
+                    queue
+                ))
+            });
+
+        errors
+    }
+
+    /// Topics shared by FIFO and standard queues, `.fifo` names
+    /// of queues (or their topics) not configured with `fifo: true`,
+    /// and FIFO settings that can't be used together
+    fn fifo_errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        self.iter()
+            .filter_map(|(queue, config)| {
+                config
+                    .fifo_settings()
+                    .and_then(|settings| settings.conflict())
+                    .map(|conflict| (queue, conflict))
+            })
+            .for_each(|(queue, conflict)| {
+                errors.push(format!(
+                    "The FIFO settings of queue '{}' can't be used together: {}",
+                    queue, conflict
+                ))
+            });
+
+        let mut topic_types: BTreeMap<&str, (&str, bool)> = BTreeMap::new();
+
+        self.iter()
+            .flat_map(|(queue, config)| {
+                config
+                    .topics
+                    .iter()
+                    .map(move |topic| (topic.as_str(), queue.as_str(), config.is_fifo()))
+            })
+            .for_each(|(topic, queue, fifo)| match topic_types.get(topic) {
+                Some((other, other_fifo)) if *other_fifo != fifo => errors.push(format!(
+                    "The topic '{}' is used by both FIFO and standard queues ('{}' and '{}')",
+                    topic, other, queue
+                )),
+                Some(_) => (),
+                None => {
+                    topic_types.insert(topic, (queue, fifo));
+                }
+            });
+
+        self.iter()
+            .filter(|(_, config)| !config.is_fifo())
+            .flat_map(|(queue, config)| {
+                std::iter::once(queue.as_str())
+                    .chain(config.topics.iter().map(TopicConfig::as_str))
+                    .map(move |name| (queue, name))
+            })
+            .filter(|(_, name)| name.ends_with(naming::FIFO_EXTENSION))
+            .for_each(|(queue, name)| {
+                errors.push(format!(
+                    "The name '{}' ends with '{}' but queue '{}' is not configured with `fifo: true`",
+                    name,
+                    naming::FIFO_EXTENSION,
+                    queue
+                ))
+            });
+
+        errors
+    }
+
+    /// Empty tag keys, and topics given different tags by different queues
+    fn tag_errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        self.tags
+            .keys()
+            .map(|tag| (None, tag))
+            .chain(
+                self.iter().flat_map(|(queue, config)| {
+                    config.tags.keys().map(move |tag| (Some(queue), tag))
+                }),
+            )
+            .filter(|(_, tag)| tag.trim().is_empty() || RESERVED_TAGS.contains(&tag.as_str()))
+            .for_each(|(queue, tag)| match (queue, tag.trim().is_empty()) {
+                (Some(queue), true) => {
+                    errors.push(format!("Found an empty tag key for queue '{}'", queue))
+                }
+                (None, true) => errors.push("Found an empty global tag key".to_string()),
+                (Some(queue), false) => errors.push(format!(
+                    "The tag '{}' of queue '{}' is reserved for pinnothera's own use",
+                    tag, queue
+                )),
+                (None, false) => errors.push(format!(
+                    "The global tag '{}' is reserved for pinnothera's own use",
+                    tag
+                )),
+            });
+
+        let mut topic_tags: BTreeMap<&str, (&str, &BTreeMap<String, String>)> = BTreeMap::new();
+
+        self.iter()
+            .filter(|(_, config)| !config.tags.is_empty())
+            .flat_map(|(queue, config)| {
+                config
+                    .topics
+                    .iter()
+                    .filter(|topic| !topic.is_external() && !topic.is_foreign())
+                    .map(move |topic| (topic.as_str(), queue.as_str(), &config.tags))
+            })
+            .for_each(|(topic, queue, tags)| match topic_tags.get(topic) {
+                Some((other, existing)) if *existing != tags => errors.push(format!(
+                    "The queues '{}' and '{}' configure different tags for topic '{}'",
+                    other, queue, topic
+                )),
+                Some(_) => (),
+                None => {
+                    topic_tags.insert(topic, (queue, tags));
+                }
+            });
+
+        errors
+    }
+
+    /// Conflicting, empty, or inapplicable encryption settings
+    fn encryption_errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        if let (Some(_), Some(true)) = (&self.kms_key_id, self.sse) {
+            errors
+                .push("The global `kms_key_id` and `sse: true` are mutually exclusive".to_string());
+        }
+
+        self.iter()
+            .filter(|(_, config)| config.kms_key_id.is_some() && config.sse == Some(true))
+            .for_each(|(queue, _)| {
+                errors.push(format!(
+                    "The `kms_key_id` and `sse: true` of queue '{}' are mutually exclusive",
+                    queue
+                ))
+            });
+
+        self.kms_key_id
+            .iter()
+            .map(|key| (None, key.as_str()))
+            .chain(self.iter().flat_map(|(queue, config)| {
+                config
+                    .kms_key_id
+                    .iter()
+                    .map(move |key| (Some(queue), key.as_str()))
+                    .chain(
+                        config
+                            .topics
+                            .iter()
+                            .filter_map(TopicConfig::kms_key_id)
+                            .map(move |key| (Some(queue), key)),
+                    )
+            }))
+            .filter(|(_, key)| key.trim().is_empty())
+            .for_each(|(queue, _)| match queue {
+                Some(queue) => {
+                    errors.push(format!("Found an empty `kms_key_id` for queue '{}'", queue))
+                }
+                None => errors.push("Found an empty global `kms_key_id`".to_string()),
+            });
+
+        let mut topic_keys: BTreeMap<&str, (&str, &str)> = BTreeMap::new();
+
+        self.iter()
+            .flat_map(|(queue, config)| {
+                config.topics.iter().filter_map(move |topic| {
+                    topic
+                        .kms_key_id()
+                        .map(|key| (topic.as_str(), queue.as_str(), key))
+                })
+            })
+            .for_each(|(topic, queue, key)| match topic_keys.get(topic) {
+                Some((other, existing)) if *existing != key => errors.push(format!(
+                    "The queues '{}' and '{}' configure different KMS keys for topic '{}'",
+                    other, queue, topic
+                )),
+                Some(_) => (),
+                None => {
+                    topic_keys.insert(topic, (queue, key));
+                }
+            });
+
+        self.iter()
+            .flat_map(|(queue, config)| config.topics.iter().map(move |topic| (queue, topic)))
+            .filter(|(_, topic)| topic.is_external() && topic.kms_key_id().is_some())
+            .for_each(|(queue, topic)| {
+                errors.push(format!(
+                    "The topic '{}' of queue '{}' is marked `external: true`, so it can't also be given a `kms_key_id`",
+                    topic, queue
+                ))
+            });
+
+        self.iter()
+            .flat_map(|(queue, config)| config.topics.iter().map(move |topic| (queue, topic)))
+            .filter(|(_, topic)| topic.is_arn() && topic.kms_key_id().is_some())
+            .for_each(|(queue, topic)| {
+                errors.push(format!(
+                    "The topic '{}' of queue '{}' is configured by ARN, so it can't also be given a `kms_key_id`",
+                    topic, queue
+                ))
+            });
+
+        errors
+    }
+
+    /// Topics in other accounts with unusable account ids or roles
+    fn foreign_topic_errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        self.iter()
+            .flat_map(|(queue, config)| config.topics.iter().map(move |topic| (queue, topic)))
+            .for_each(|(queue, topic)| {
+                if let Some(account_id) = topic.account_id() {
+                    if account_id.len() != 12
+                        || !account_id.chars().all(|character| character.is_ascii_digit())
+                    {
+                        errors.push(format!(
+                            "The account id '{}' of topic '{}' of queue '{}' is not a 12-digit AWS account id",
+                            account_id, topic, queue
+                        ));
+                    }
+
+                    if topic.is_arn() {
+                        errors.push(format!(
+                            "The topic '{}' of queue '{}' is configured by ARN, so it can't also be given an `account_id`",
+                            topic, queue
+                        ));
+                    }
+
+                    if topic.is_external() || topic.kms_key_id().is_some() {
+                        errors.push(format!(
+                            "The topic '{}' of queue '{}' is in account '{}', so it can't also be marked `external: true` or given a `kms_key_id`",
+                            topic, queue, account_id
+                        ));
+                    }
+                }
+
+                match topic.role_arn() {
+                    Some(role_arn) if crate::CLIArgs::role_account_id(role_arn).is_none() => {
+                        errors.push(format!(
+                            "The role '{}' of topic '{}' of queue '{}' is not a valid IAM role ARN (expected 'arn:aws:iam::<account-id>:role/<role-name>')",
+                            role_arn, topic, queue
+                        ))
+                    }
+                    Some(_) if !topic.is_foreign() => errors.push(format!(
+                        "The topic '{}' of queue '{}' is given a `role_arn` but not an `account_id` (or an ARN), so it's in the current account",
+                        topic, queue
+                    )),
+                    _ => (),
+                }
+            });
+
+        errors
+    }
+
+    /// Topics marked `external: true` inconsistently, or along with attributes
+    fn external_topic_errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        let mut topic_ownership: BTreeMap<&str, (&str, bool)> = BTreeMap::new();
+
+        self.iter()
+            .flat_map(|(queue, config)| {
+                config
+                    .topics
+                    .iter()
+                    .map(move |topic| (topic.as_str(), queue.as_str(), topic.is_external()))
+            })
+            .for_each(|(topic, queue, external)| match topic_ownership.get(topic) {
+                Some((other, other_external)) if *other_external != external => {
+                    let (marked, unmarked) = match external {
+                        true => (queue, *other),
+                        false => (*other, queue),
+                    };
+                    errors.push(format!(
+                        "The topic '{}' is marked `external: true` by queue '{}' but not by queue '{}'",
+                        topic, marked, unmarked
+                    ))
+                }
+                Some(_) => (),
+                None => {
+                    topic_ownership.insert(topic, (queue, external));
+                }
+            });
+
+        self.iter()
+            .filter(|(_, config)| !config.raw_topic_attributes.is_empty())
+            .flat_map(|(queue, config)| {
+                config
+                    .topics
+                    .iter()
+                    .filter(|topic| topic.is_external())
+                    .map(move |topic| (topic.as_str(), queue.as_str()))
+            })
+            .for_each(|(topic, queue)| {
+                errors.push(format!(
+                    "The topic '{}' of queue '{}' is marked `external: true`, so it can't also be given `raw_topic_attributes`",
+                    topic, queue
+                ))
+            });
+
+        errors
+    }
+
+    /// Topics configured by malformed ARNs
+    fn topic_arn_errors(&self) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        self.topic_names()
+            .filter(|name| naming::is_topic_arn(name))
+            .filter_map(|arn| naming::topic_arn_error(arn).map(|reason| (arn, reason)))
+            .for_each(|(arn, reason)| {
+                errors.push(format!("The topic ARN '{}' is malformed: {}", arn, reason))
+            });
+
+        errors
+    }
+
+    /// Names that are empty or unusable, either as-is or once sanitized
+    /// with the supplied replacement (if name sanitization is enabled),
+    /// unless a name hook will decide the names used in AWS
+    fn name_errors(&self, sanitize_replacement: Option<&str>, name_hook: bool) -> Vec<String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        let dead_letter_queue_names: Vec<String> = self.dead_letter_queue_names().collect();
+
+        for (kind, names) in [
+            (
+                "queue",
+                self.queue_names()
+                    .chain(dead_letter_queue_names.iter().map(|name| name.as_str()))
+                    .collect::<Vec<&str>>(),
+            ),
+            (
+                "topic",
+                self.topic_names()
+                    .filter(|name| !naming::is_topic_arn(name))
+                    .collect::<Vec<&str>>(),
+            ),
+        ] {
+            let names: Vec<&str> = names
+                .into_iter()
+                .map(naming::strip_fifo_extension)
+                .collect();
+
+            names
+                .iter()
+                .filter(|name| name.is_empty())
+                .for_each(|_| errors.push(format!("Found an empty {} name", kind)));
+
+            match sanitize_replacement {
+                _ if name_hook => (),
+                None => names
+                    .iter()
+                    .filter(|name| !naming::is_aws_safe_name(name))
+                    .for_each(|name| {
+                        errors.push(format!(
+                            "The {} name '{}' contains characters AWS does not allow \
+                             (re-run with `--sanitize-names` to replace them)",
+                            kind, name
+                        ))
+                    }),
+                Some(replacement) => naming::sanitization_collisions(names, replacement)
+                    .iter()
+                    .for_each(|(sanitized, originals)| {
+                        errors.push(format!(
+                            "The {} names {:?} would all be sanitized to '{}'",
+                            kind, originals, sanitized
+                        ))
+                    }),
+            }
+        }
+
+        errors
+    }
+
+    pub fn from_json<T: AsRef<str>>(data: T) -> Result<PinnConfig, PinnError> {
+        let data: &str = data.as_ref();
+        match serde_json::from_str::<PinnConfig>(data) {
+            Ok(obj) => Ok(obj.with_defaults()),
+            Err(error) => {
+                debug!("Couldn't deserialize JSON data: {:?}", data);
+                Err(PinnError::config_caused_by(
+                    "Could not parse the JSON configuration",
+                    error,
+                ))
+            }
+        }
+    }
+
+    pub fn from_yaml<T: AsRef<str>>(data: T) -> Result<PinnConfig, PinnError> {
+        let data: &str = data.as_ref();
+        match serde_yaml::from_str::<PinnConfig>(data) {
+            Ok(obj) => Ok(obj.with_defaults()),
+            Err(error) => {
+                debug!("Couldn't deserialize YAML data: {:?}", data);
+                Err(PinnError::config_caused_by(
+                    "Could not parse the YAML configuration",
+                    error,
+                ))
+            }
+        }
+    }
+
+    pub fn from_toml<T: AsRef<str>>(data: T) -> Result<PinnConfig, PinnError> {
+        let data: &str = data.as_ref();
+        match toml::from_str::<PinnConfig>(data) {
+            Ok(obj) => Ok(obj.with_defaults()),
+            Err(error) => {
+                debug!("Couldn't deserialize TOML data: {:?}", data);
+                Err(PinnError::config_caused_by(
+                    "Could not parse the TOML configuration",
+                    error,
+                ))
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn from_cluster<T: AsRef<str>>(
+        run: &RunState,
+        client: K8sClient,
+        env_name: &Option<T>,
+        namespace: &Option<T>,
+        configmap_name: &T,
+        configmap_options: &ConfigMapOptions,
+        namespace_env_source: &Option<NamespaceEnvSource>,
+        variables: &Option<BTreeMap<String, String>>,
+    ) -> Result<Option<(EnvName, PinnConfig)>, PinnError> {
+        // Ensure the name of the target configmap is usable
+        let configmap_name: &str = configmap_name.as_ref();
+
+        // Read `ConfigMap`s in the specified (or default) namespace
+        // into the typed interface from k8s-openapi
+        let (config_maps, namespace): (K8sAPI<ConfigMap>, String) = if let Some(value) = &namespace
+        {
+            (
+                K8sAPI::namespaced(client.clone(), value.as_ref()),
+                format!("cluster namespace '{}'", value.as_ref()),
+            )
+        } else {
+            (
+                K8sAPI::default_namespaced(client.clone()),
+                "the current cluster namespace".to_string(),
+            )
+        };
+
+        // Use the typed interface to pull the namespace's
+        // pinnothera configuration (if it exists)
+        stats::record_kube_call(run, "GetConfigMap");
+
+        let pinn_confmap: ConfigMap = match config_maps
+            .get_opt(configmap_name)
+            .await
+            .map_err(|error| PinnError::kube("get ConfigMap", error))?
+        {
+            Some(obj) => obj,
+            None => match configmap_options.missing_source {
+                MissingSource::Skip => {
+                    info!(
+                        "No `ConfigMap` named '{}' in {}, treating it as not onboarded (no queues or topics)",
+                        configmap_name, &namespace
+                    );
+                    return Ok(Some((
+                        EnvName::from(env_name.as_ref()),
+                        PinnConfig::default(),
+                    )));
+                }
+                MissingSource::Warn => {
+                    warning!(
+                        "No `ConfigMap` named '{}' in {}, nothing to do",
+                        configmap_name,
+                        &namespace
+                    );
+                    return Ok(None);
+                }
+                MissingSource::Error => {
+                    return Err(PinnError::config(format!(
+                        "No `ConfigMap` named '{}' in {}!",
+                        configmap_name, &namespace
+                    )));
+                }
+            },
+        };
+
+        // Pull out the ConfigMap's `annotations` element (if it exists)
+        let annotations: BTreeMap<String, String> =
+            pinn_confmap.metadata.annotations.unwrap_or_default();
+
+        // Resolve the environment annotated on the ConfigMap (via its `app-env`
+        // annotation) or, failing that, on the namespace itself (via the
+        // configured annotation or label), along with where it came from
+        let annotated: Option<(String, String)> = if let Some(value) = annotations.get("app-env") {
+            Some((
+                value.to_string(),
+                format!(
+                    "the 'app-env' annotation of `ConfigMap` '{}'",
+                    configmap_name
+                ),
+            ))
+        } else if let (Some(source), Some(namespace_name)) =
+            (namespace_env_source, &pinn_confmap.metadata.namespace)
+        {
+            Self::env_from_namespace(run, client, namespace_name, source)
+                .await?
+                .map(|value| (value, source.describe(namespace_name)))
+        } else {
+            None
+        };
+
+        let env_name: EnvName = Self::resolve_env(
+            run,
+            env_name.as_ref().map(|value| value.as_ref()),
+            annotated,
+            configmap_options.env_conflict,
+        )?;
+
+        // Pull out the ConfigMap's `data` element (if it exists)
+        let confs_map: BTreeMap<String, String> = match pinn_confmap.data {
+            Some(obj) => obj,
+            None => {
+                return Err(PinnError::config(format!(
+                    "The '{}' `ConfigMap` retrieved from {} has no `data` element!",
+                    configmap_name, &namespace,
+                )));
+            }
+        };
+
+        // Interpolate the data from the first recognized key
+        // (if interpolation is enabled), then parse and return it
+        let interpolate = |key: &str, data: &String| -> Result<String, PinnError> {
+            match variables {
+                Some(values) => interpolation::interpolate(
+                    data,
+                    format!(
+                        "`ConfigMap` '{}' (key '{}') in {}",
+                        configmap_name, key, &namespace
+                    ),
+                    values,
+                ),
+                None => Ok(data.to_string()),
+            }
+        };
+
+        if configmap_options.selects_keys() {
+            let source: String = format!("`ConfigMap` '{}' in {}", configmap_name, &namespace);
+            return Self::from_keys(&confs_map, configmap_options, &source, interpolate)
+                .map(|config| Some((env_name, config)));
+        }
+
+        if let Some(data) = confs_map.get("json") {
+            return match Self::from_json(interpolate("json", data)?) {
+                Ok(config) => Ok(Some((
+                    env_name,
+                    config.with_source(format!(
+                        "key 'json' of `ConfigMap` '{}' in {}",
+                        configmap_name, &namespace
+                    )),
+                ))),
+                Err(error) => Err(error),
+            };
+        } else if let Some(data) = confs_map.get("yaml") {
+            return match Self::from_yaml(interpolate("yaml", data)?) {
+                Ok(config) => Ok(Some((
+                    env_name,
+                    config.with_source(format!(
+                        "key 'yaml' of `ConfigMap` '{}' in {}",
+                        configmap_name, &namespace
+                    )),
+                ))),
+                Err(error) => Err(error),
+            };
+        } else if let Some(data) = confs_map.get("toml") {
+            return match Self::from_toml(interpolate("toml", data)?) {
+                Ok(config) => Ok(Some((
+                    env_name,
+                    config.with_source(format!(
+                        "key 'toml' of `ConfigMap` '{}' in {}",
+                        configmap_name, &namespace
+                    )),
+                ))),
+                Err(error) => Err(error),
+            };
+        };
+
+        Err(PinnError::config(format!(
+            "The `data` element in the '{}' ConfigMap retrieved from {} has no pinnothera-recognized keys!",
+            configmap_name, &namespace,
+        )))
+    }
+
+    /// Parse each of the supplied `ConfigMap` data's selected keys as an
+    /// independent configuration document (in the format named by the
+    /// key's extension) and merge them, reporting every key that can't
+    /// be parsed, and failing unless `keep_going` is set
+    fn from_keys<F>(
+        data: &BTreeMap<String, String>,
+        options: &ConfigMapOptions,
+        source: &str,
+        interpolate: F,
+    ) -> Result<PinnConfig, PinnError>
+    where
+        F: Fn(&str, &String) -> Result<String, PinnError>,
+    {
+        let mut errors: Vec<String> = options
+            .keys
+            .iter()
+            .filter(|key| !data.contains_key(key.as_str()))
+            .map(|key| format!("key '{}': not found", key))
+            .collect();
+
+        let mut documents: Vec<(&str, PinnConfig)> = Vec::new();
+
+        for (key, value) in data.iter().filter(|(key, _)| options.is_selected(key)) {
+            let parsed = interpolate(key, value).and_then(|value| {
+                match key.rsplit_once('.').map(|(_, extension)| extension) {
+                    Some("json") => Self::from_json(value),
+                    Some("yaml" | "yml") => Self::from_yaml(value),
+                    Some("toml") => Self::from_toml(value),
+                    _ => Err(PinnError::config(
+                        "Unrecognized format (expected a '.json', '.yaml', '.yml', or '.toml' extension)",
+                    )),
+                }
+            });
+
+            match parsed {
+                Ok(config) => {
+                    info!(
+                        "Loaded {} queue configuration(s) from key '{}' of {}",
+                        config.len(),
+                        key,
+                        source
+                    );
+                    documents.push((
+                        key.as_str(),
+                        config.with_source(format!("key '{}' of {}", key, source)),
+                    ));
+                }
+                Err(error) => errors.push(format!("key '{}': {}", key, error_chain(&error))),
+            }
+        }
+
+        if !errors.is_empty() {
+            if !options.keep_going {
+                return Err(PinnError::config(format!(
+                    "Could not load {} key(s) of {}: {}",
+                    errors.len(),
+                    source,
+                    errors.join("; ")
+                )));
+            }
+
+            errors.iter().for_each(|error| {
+                warning!(
+                    "Skipping unusable configuration from {} (`--keep-going` was specified): {}",
+                    source,
+                    error
+                )
+            });
+        }
+
+        if documents.is_empty() {
+            return Err(PinnError::config(format!(
+                "No usable configuration keys were selected in {}",
+                source
+            )));
+        }
+
+        Self::merge(documents)
+    }
+
+    /// Merge the supplied (named) configuration documents into one,
+    /// failing if a queue is configured in more than one of them
+    /// (other than the "unsubscribed" sentinel entry, whose topics
+    /// are combined) or if they set a global tag (or the global
+    /// encryption settings) to different values
+    fn merge(documents: Vec<(&str, PinnConfig)>) -> Result<PinnConfig, PinnError> {
+        let mut merged: ParsedPinnConfig = BTreeMap::new();
+        let mut origins: BTreeMap<String, &str> = BTreeMap::new();
+        let mut tags: BTreeMap<String, (String, &str)> = BTreeMap::new();
+        let mut encryption: (Option<String>, Option<bool>) = (None, None);
+        let mut encryption_origin: Option<&str> = None;
+        let mut errors: Vec<String> = Vec::new();
+
+        for (key, document) in documents {
+            let settings = (document.kms_key_id, document.sse);
+
+            match encryption_origin {
+                _ if settings == (None, None) => (),
+                Some(origin) if encryption != settings => errors.push(format!(
+                    "the global encryption settings differ between key '{}' and key '{}'",
+                    origin, key
+                )),
+                Some(_) => (),
+                None => {
+                    encryption = settings;
+                    encryption_origin = Some(key);
+                }
+            }
+
+            for (tag, value) in document.tags {
+                match tags.get(&tag) {
+                    Some((existing, origin)) if *existing != value => errors.push(format!(
+                        "tag '{}' is set to different values in key '{}' and key '{}'",
+                        tag, origin, key
+                    )),
+                    Some(_) => (),
+                    None => {
+                        tags.insert(tag, (value, key));
+                    }
+                }
+            }
+
+            for (queue, config) in document.queues {
+                match (merged.get_mut(&queue), origins.get(&queue)) {
+                    (Some(existing), _) if queue == "unsubscribed" => {
+                        if let Some(source) = &config.source {
+                            existing.topic_sources.extend(
+                                config
+                                    .topics
+                                    .iter()
+                                    .map(|topic| (topic.to_string(), source.to_string())),
+                            );
+                        }
+                        existing.topics.extend(config.topics)
+                    }
+                    (Some(_), Some(origin)) => errors.push(format!(
+                        "queue '{}' is configured in both key '{}' and key '{}'",
+                        queue, origin, key
+                    )),
+                    _ => {
+                        origins.insert(queue.clone(), key);
+                        merged.insert(queue, config);
+                    }
+                }
+            }
+        }
+
+        match errors.is_empty() {
+            true => {
+                let (kms_key_id, sse) = encryption;
+
+                Ok(PinnConfig {
+                    tags: tags
+                        .into_iter()
+                        .map(|(tag, (value, _))| (tag, value))
+                        .collect(),
+                    kms_key_id,
+                    sse,
+                    // Each document's defaults were applied to its own queues as it was parsed
+                    defaults: QueueDefaults::default(),
+                    queues: merged,
+                })
+            }
+            false => Err(PinnError::config(format!(
+                "Could not merge configuration keys: {}",
+                errors.join("; ")
+            ))),
+        }
+    }
+
+    /// Resolve the environment from the first step that yields one, being
+    /// the explicitly supplied name, then the annotated one, unless both
+    /// name (different) known environments, in which case the supplied
+    /// policy settles the conflict (and the decision is recorded)
+    fn resolve_env(
+        run: &RunState,
+        supplied: Option<&str>,
+        annotated: Option<(String, String)>,
+        policy: EnvConflict,
+    ) -> Result<EnvName, PinnError> {
+        let (supplied, (annotated, source)) = match (supplied, annotated) {
+            (Some(supplied), Some((annotated, source)))
+                if EnvName::from(Some(supplied)) != EnvName::from(Some(&annotated))
+                    && EnvName::from(Some(supplied)) != EnvName::Unknown
+                    && EnvName::from(Some(&annotated)) != EnvName::Unknown =>
+            {
+                (supplied, (annotated, source))
+            }
+            (Some(supplied), _) => {
+                info!("Using environment \"{}\" from `--env-name`", supplied);
+                return Ok(EnvName::from(Some(supplied)));
+            }
+            (None, Some((annotated, source))) => {
+                info!("Using environment \"{}\" from {}", annotated, source);
+                return Ok(EnvName::from(Some(annotated)));
+            }
+            (None, None) => return Ok(EnvName::Unknown),
+        };
+
+        warning!(
+            "Conflicting environments: \"{}\" from `--env-name`, but \"{}\" from {}",
+            supplied,
+            annotated,
+            source
+        );
+
+        let ((chosen, chosen_source), (overridden, overridden_source)) = match policy {
+            EnvConflict::Error => {
+                return Err(PinnError::config(format!(
+                    "Refusing to choose between environment \"{}\" from `--env-name` and \"{}\" from {} (`--env-conflict error` was specified)",
+                    supplied, annotated, source
+                )));
+            }
+            EnvConflict::PreferCli => (
+                (supplied.to_string(), "`--env-name`".to_string()),
+                (annotated, source),
+            ),
+            EnvConflict::PreferAnnotation => (
+                (annotated, source),
+                (supplied.to_string(), "`--env-name`".to_string()),
+            ),
+        };
+
+        info!(
+            "Using environment \"{}\" from {} over \"{}\" from {} (`--env-conflict {}`)",
+            &chosen,
+            &chosen_source,
+            &overridden,
+            &overridden_source,
+            policy.as_str()
+        );
+
+        let env_name: EnvName = EnvName::from(Some(&chosen));
+
+        report::record_env_resolution(
+            run,
+            report::EnvResolution {
+                chosen,
+                chosen_source,
+                overridden,
+                overridden_source,
+                policy: policy.as_str(),
+            },
+        );
+
+        Ok(env_name)
+    }
+
+    /// Read the environment from the supplied source on the named
+    /// `Namespace`, skipping it (as if it named no environment) if
+    /// the namespace can't be read due to insufficient permissions
+    async fn env_from_namespace(
+        run: &RunState,
+        client: K8sClient,
+        namespace: &str,
+        source: &NamespaceEnvSource,
+    ) -> Result<Option<String>, PinnError> {
+        let namespaces: K8sAPI<Namespace> = K8sAPI::all(client);
+
+        stats::record_kube_call(run, "GetNamespace");
+
+        let value: Option<String> = match namespaces.get(namespace).await {
+            Ok(obj) => source.read(&obj),
+            Err(kube::Error::Api(response)) if response.code == 403 => {
+                warning!(
+                    "Not permitted to read `Namespace` '{}', skipping environment resolution from {}: {}",
+                    namespace,
+                    source.describe(namespace),
+                    response.message,
+                );
+                return Ok(None);
+            }
+            Err(error) => return Err(PinnError::kube("get Namespace", error)),
+        };
+
+        if value.is_none() {
+            info!("No environment found in {}", source.describe(namespace));
+        }
+
+        Ok(value)
+    }
+}
+
+// </editor-fold desc="// PinnConfig struct ...">
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde_json::Value;
+
+    const YAML: &str = r#"
+tags:
+  team: payments
+kms_key_id: alias/pinnothera
+orders:
+  topics:
+    - placed
+    - name: shipped
+      filter_policy:
+        region: [us, eu]
+        priority: [{numeric: [">", 3]}]
+  visibility_timeout_secs: 30
+  raw_attributes:
+    KmsDataKeyReusePeriodSeconds: "300"
+unsubscribed:
+  topics: [audit]
+"#;
+
+    const JSON: &str = r#"{
+  "tags": {"team": "payments"},
+  "kms_key_id": "alias/pinnothera",
+  "orders": {
+    "topics": [
+      "placed",
+      {"name": "shipped", "filter_policy": {"region": ["us", "eu"], "priority": [{"numeric": [">", 3]}]}}
+    ],
+    "visibility_timeout_secs": 30,
+    "raw_attributes": {"KmsDataKeyReusePeriodSeconds": "300"}
+  },
+  "unsubscribed": {"topics": ["audit"]}
+}"#;
+
+    const TOML: &str = r#"
+kms_key_id = "alias/pinnothera"
+
+[tags]
+team = "payments"
+
+[orders]
+topics = [
+  "placed",
+  { name = "shipped", filter_policy = { region = ["us", "eu"], priority = [{ numeric = [">", 3] }] } },
+]
+visibility_timeout_secs = 30
+
+[orders.raw_attributes]
+KmsDataKeyReusePeriodSeconds = "300"
+
+[unsubscribed]
+topics = ["audit"]
+"#;
+
+    fn as_value(config: &PinnConfig) -> Value {
+        serde_json::to_value(config).unwrap()
+    }
+
+    #[test]
+    fn formats_parse_identically() {
+        let (yaml, json, toml) = (
+            PinnConfig::from_yaml(YAML).unwrap(),
+            PinnConfig::from_json(JSON).unwrap(),
+            PinnConfig::from_toml(TOML).unwrap(),
+        );
+
+        assert_eq!(yaml.len(), 2);
+        assert_eq!(as_value(&yaml), as_value(&json));
+        assert_eq!(as_value(&yaml), as_value(&toml));
+        assert_eq!(
+            as_value(&yaml)["orders"]["topics"][1]["filter_policy"]["priority"][0]["numeric"][1],
+            3
+        );
+    }
+
+    #[test]
+    fn configs_are_summarized_and_displayed_on_one_line() {
+        let config = PinnConfig::from_yaml(YAML).unwrap();
+
+        assert_eq!(
+            config.summary(),
+            "1 queue(s), 3 topic(s), 0 dead-letter queue(s)"
+        );
+
+        let displayed: String = config.to_string();
+        assert!(!displayed.contains('\n'), "{}", displayed);
+        assert_eq!(
+            serde_json::from_str::<Value>(&displayed).unwrap(),
+            as_value(&config)
+        );
+    }
+
+    #[test]
+    fn reserved_tags_are_rejected() {
+        let config = PinnConfig::from_yaml(
+            "tags: {pinnothera.io/managed-tag-keys: x}\norders: {tags: {pinnothera.io/managed-tag-keys: y}, topics: []}",
+        )
+        .unwrap();
+
+        match config.validate(None, false, false, None) {
+            Err(PinnError::InvalidConfig { problems }) => assert_eq!(
+                problems,
+                [
+                    "The global tag 'pinnothera.io/managed-tag-keys' is reserved for pinnothera's own use",
+                    "The tag 'pinnothera.io/managed-tag-keys' of queue 'orders' is reserved for pinnothera's own use",
+                ]
+            ),
+            result => panic!("unexpectedly {:?}", result),
+        }
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        let expected: Value = as_value(&PinnConfig::from_yaml(YAML).unwrap());
+
+        let yaml = PinnConfig::from_yaml(YAML).unwrap();
+        let yaml = PinnConfig::from_yaml(serde_yaml::to_string(&yaml).unwrap()).unwrap();
+
+        let json = PinnConfig::from_json(JSON).unwrap();
+        let json = PinnConfig::from_json(serde_json::to_string(&json).unwrap()).unwrap();
+
+        assert_eq!(as_value(&yaml), expected);
+        assert_eq!(as_value(&json), expected);
+
+        // pinnothera only reads TOML, and the `toml` serializer can't write
+        // arrays mixing plain and filtered topics, so each queue's topics
+        // are all of one kind here (with `toml::Value` taking care of
+        // writing each table's plain values ahead of its sub-tables)
+        let toml = PinnConfig::from_toml(
+            TOML.replace(r#""placed","#, r#"{ name = "placed" },"#)
+                .replace(r#"["audit"]"#, r#"["audit", "compliance"]"#),
+        )
+        .unwrap();
+        let expected: Value = as_value(&toml);
+        let toml =
+            PinnConfig::from_toml(toml::to_string(&toml::Value::try_from(&toml).unwrap()).unwrap())
+                .unwrap();
+
+        assert_eq!(as_value(&toml), expected);
+        assert_eq!(expected["orders"]["topics"][0]["name"], "placed");
+    }
+
+    #[test]
+    fn parse_errors_name_the_format() {
+        let cases: [(&str, Result<PinnConfig, PinnError>); 3] = [
+            ("YAML", PinnConfig::from_yaml("orders: [")),
+            ("JSON", PinnConfig::from_json("{\"orders\": ")),
+            ("TOML", PinnConfig::from_toml("[orders")),
+        ];
+
+        for (format, result) in cases {
+            let message: String = result.unwrap_err().to_string();
+
+            assert_eq!(
+                message,
+                format!("Could not parse the {} configuration", format)
+            );
+        }
+    }
+
+    #[test]
+    fn region_capabilities_per_partition() {
+        let config = PinnConfig::from_yaml(
+            r#"
+orders.fifo:
+  fifo: true
+  sse: true
+  topics: [placed]
+  raw_subscription_attributes:
+    FilterPolicyScope: MessageBody
+"#,
+        )
+        .unwrap();
+
+        assert!(config.check_region_capabilities("us-east-1").is_empty());
+
+        for region in ["cn-north-1", "us-gov-west-1"] {
+            let problems: Vec<(bool, String)> = config.check_region_capabilities(region);
+
+            assert_eq!(problems.len(), 3, "{}: {:?}", region, problems);
+
+            for (feature, (reject, problem)) in [
+                "FIFO topics",
+                "SQS-managed server-side encryption",
+                "message body filter policies",
+            ]
+            .iter()
+            .zip(problems)
+            {
+                assert!(!reject);
+                assert!(
+                    problem.starts_with(&format!(
+                        "Queue 'orders.fifo' uses {}, which may not be available in region '{}'",
+                        feature, region
+                    )),
+                    "{}",
+                    problem
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cross_partition_topic_arns_are_rejected() {
+        let config = PinnConfig::from_yaml(
+            r#"
+orders:
+  topics:
+    - arn:aws:sns:us-east-1:123456789012:placed
+    - arn:aws-cn:sns:cn-north-1:123456789012:shipped
+"#,
+        )
+        .unwrap();
+
+        for (region, rejected) in [
+            (
+                "us-east-1",
+                "arn:aws-cn:sns:cn-north-1:123456789012:shipped",
+            ),
+            ("cn-north-1", "arn:aws:sns:us-east-1:123456789012:placed"),
+        ] {
+            let problems: Vec<(bool, String)> = config.check_region_capabilities(region);
+
+            assert_eq!(problems.len(), 1, "{}: {:?}", region, problems);
+            assert!(problems[0].0);
+            assert!(problems[0]
+                .1
+                .starts_with(&format!("The topic ARN '{}' of queue 'orders'", rejected)));
+        }
+
+        assert_eq!(config.check_region_capabilities("us-gov-west-1").len(), 2);
+    }
+
+    #[test]
+    fn cross_region_topic_arns_are_warned_about() {
+        let config = PinnConfig::from_yaml(
+            r#"
+orders:
+  topics:
+    - placed
+    - arn:aws:sns:us-east-1:210987654321:shipped
+    - arn:aws:sns:eu-west-1:210987654321:returned
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.check_region_capabilities("us-east-1"),
+            vec![(
+                false,
+                "The topic ARN 'arn:aws:sns:eu-west-1:210987654321:returned' of queue 'orders' is in region 'eu-west-1' rather than 'us-east-1', so its messages will be delivered across regions (incurring inter-region data transfer charges)".to_string()
+            )]
+        );
+        assert_eq!(config.check_region_capabilities("us-east-2").len(), 2);
+    }
+
+    #[test]
+    fn filter_policy_attributes_per_operator() {
+        // (filter policy, attributes matched on)
+        let cases: [(serde_json::Value, Vec<&str>); 6] = [
+            (serde_json::json!({"region": ["us-east-1"]}), vec!["region"]),
+            (
+                serde_json::json!({"region": [{"prefix": "us-"}], "tier": [{"exists": true}]}),
+                vec!["region", "tier"],
+            ),
+            (
+                serde_json::json!({"priority": [{"anything-but": {"prefix": "low"}}]}),
+                vec!["priority"],
+            ),
+            (
+                serde_json::json!({"$or": [{"region": ["eu-west-1"]}, {"priority": [{"numeric": [">", 5]}]}]}),
+                vec!["region", "priority"],
+            ),
+            (
+                serde_json::json!({
+                    "source": [{"anything-but": ["test"]}],
+                    "$or": [{"region": [{"prefix": "eu-"}]}, {"$or": [{"tier": ["gold"]}, {"channel": ["web"]}]}],
+                }),
+                vec!["region", "tier", "channel", "source"],
+            ),
+            (serde_json::json!(["region"]), vec![]),
+        ];
+
+        for (policy, expected) in cases {
+            assert_eq!(
+                filter_policy_attributes(&policy)
+                    .into_iter()
+                    .collect::<BTreeSet<&str>>(),
+                expected.into_iter().collect::<BTreeSet<&str>>(),
+                "{}",
+                policy
+            );
+        }
+    }
+
+    #[test]
+    fn undeclared_filter_attributes_are_reported() {
+        let config = PinnConfig::from_yaml(
+            r#"
+orders:
+  topics:
+    - name: placed
+      message_attributes: [region, priority]
+      filter_policy: {"$or": [{"region": [{"prefix": "us-"}]}, {"tier": ["gold"]}]}
+    - name: shipped
+      filter_policy: {"carrier": ["ups"]}
+billing:
+  raw_subscription_attributes:
+    FilterPolicy: '{"priority": [{"anything-but": ["low"]}], "currency": ["usd"]}'
+  topics: [placed]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.check_filter_attributes(),
+            vec![
+                r#"The filter policy of queue 'billing' for topic 'placed' matches on the message attribute 'currency', which isn't declared for the topic (declared: {"priority", "region"})"#,
+                r#"The filter policy of queue 'orders' for topic 'placed' matches on the message attribute 'tier', which isn't declared for the topic (declared: {"priority", "region"})"#,
+            ]
+        );
+
+        assert!(config.validate(None, false, false, None).is_ok());
+        assert!(matches!(
+            config.validate(None, false, true, None),
+            Err(PinnError::InvalidConfig { problems }) if problems.len() == 2
+        ));
+    }
+
+    #[test]
+    fn colliding_sanitized_names_are_rejected() {
+        let config = PinnConfig::from_yaml(
+            r#"
+orders.v2:
+  topics: [placed.v2, placed_v2]
+orders_v2:
+  topics: [shipped]
+billing:
+  topics: [invoiced]
+"#,
+        )
+        .unwrap();
+
+        match config.validate(Some("_"), false, false, None) {
+            Err(PinnError::InvalidConfig { problems }) => assert_eq!(
+                problems,
+                vec![
+                    r#"The queue names ["orders.v2", "orders_v2"] would all be sanitized to 'orders_v2'"#,
+                    r#"The topic names ["placed.v2", "placed_v2"] would all be sanitized to 'placed_v2'"#,
+                ]
+            ),
+            other => panic!("{:?}", other),
+        }
+
+        // A name hook decides the names used in AWS, so nothing is sanitized
+        assert!(config.validate(Some("_"), true, false, None).is_ok());
+    }
+
+    #[test]
+    fn fifo_forms_normalize_to_the_same_settings() {
+        let high_throughput = FifoSettings {
+            high_throughput: true,
+            ..FifoSettings::default()
+        };
+        let producer_ids = FifoSettings {
+            content_based_deduplication: false,
+            high_throughput: true,
+            deduplication_scope: Some(DeduplicationScope::MessageGroup),
+        };
+
+        // (the queue's `fifo` setting, its normalized settings)
+        let cases: [(&str, Option<FifoSettings>); 7] = [
+            ("", None),
+            ("fifo: false", None),
+            ("fifo: true", Some(FifoSettings::default())),
+            ("fifo: {}", Some(FifoSettings::default())),
+            (
+                "fifo: {content_based_deduplication: true}",
+                Some(FifoSettings::default()),
+            ),
+            ("fifo: {high_throughput: true}", Some(high_throughput)),
+            (
+                "fifo: {content_based_deduplication: false, high_throughput: true, deduplication_scope: message_group}",
+                Some(producer_ids),
+            ),
+        ];
+
+        for (setting, expected) in cases {
+            let config =
+                PinnConfig::from_yaml(format!("orders:\n  topics: [placed]\n  {}\n", setting))
+                    .unwrap();
+
+            assert_eq!(config["orders"].fifo_settings(), expected, "{}", setting);
+            assert_eq!(
+                config["orders"].is_fifo(),
+                expected.is_some(),
+                "{}",
+                setting
+            );
+        }
+
+        assert!(
+            PinnConfig::from_yaml("orders:\n  topics: []\n  fifo: {throughput: high}\n").is_err()
+        );
+    }
+
+    #[test]
+    fn fifo_settings_per_queue_attributes() {
+        // (the queue's settings, its FIFO attributes)
+        let cases: [(FifoSettings, Vec<(&str, &str)>); 3] = [
+            (
+                FifoSettings::default(),
+                vec![("FifoQueue", "true"), ("ContentBasedDeduplication", "true")],
+            ),
+            (
+                FifoSettings {
+                    content_based_deduplication: false,
+                    deduplication_scope: Some(DeduplicationScope::Queue),
+                    ..FifoSettings::default()
+                },
+                vec![
+                    ("FifoQueue", "true"),
+                    ("ContentBasedDeduplication", "false"),
+                    ("DeduplicationScope", "queue"),
+                ],
+            ),
+            (
+                FifoSettings {
+                    high_throughput: true,
+                    ..FifoSettings::default()
+                },
+                vec![
+                    ("FifoQueue", "true"),
+                    ("ContentBasedDeduplication", "true"),
+                    ("DeduplicationScope", "messageGroup"),
+                    ("FifoThroughputLimit", "perMessageGroupId"),
+                ],
+            ),
+        ];
+
+        for (settings, expected) in cases {
+            let expected: HashMap<String, String> = expected
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+
+            assert_eq!(settings.queue_attributes(), expected, "{:?}", settings);
+        }
+    }
+
+    #[test]
+    fn queue_attributes_are_validated_at_their_bounds() {
+        // (the setting, its value, the range in the error if it's rejected)
+        let cases: [(&str, u32, Option<&str>); 18] = [
+            ("visibility_timeout_secs", 0, None),
+            ("visibility_timeout_secs", 43_200, None),
+            ("visibility_timeout_secs", 43_201, Some("0 and 43200")),
+            ("message_retention_secs", 59, Some("60 and 1209600")),
+            ("message_retention_secs", 60, None),
+            ("message_retention_secs", 1_209_600, None),
+            ("message_retention_secs", 1_209_601, Some("60 and 1209600")),
+            ("delay_secs", 0, None),
+            ("delay_secs", 900, None),
+            ("delay_secs", 901, Some("0 and 900")),
+            ("receive_wait_time_secs", 0, None),
+            ("receive_wait_time_secs", 20, None),
+            ("receive_wait_time_secs", 21, Some("0 and 20")),
+            ("max_message_size_bytes", 1_023, Some("1024 and 262144")),
+            ("max_message_size_bytes", 1_024, None),
+            ("max_message_size_bytes", 262_144, None),
+            ("max_message_size_bytes", 262_145, Some("1024 and 262144")),
+            ("message_retention_secs", 0, Some("60 and 1209600")),
+        ];
+
+        for (setting, value, expected) in cases {
+            let errors: Vec<String> =
+                PinnConfig::from_yaml(format!("orders:\n  topics: []\n  {}: {}\n", setting, value))
+                    .unwrap()
+                    .queue_attribute_errors();
+
+            match expected {
+                None => assert!(errors.is_empty(), "{}={}: {:?}", setting, value, errors),
+                Some(range) => assert_eq!(
+                    errors
+                        .iter()
+                        .map(|error| error.split(" (").next().unwrap())
+                        .collect::<Vec<&str>>(),
+                    [format!(
+                        "The `{}` of {} for queue 'orders' must be between {}",
+                        setting, value, range
+                    )],
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn conflicting_fifo_settings_are_explained() {
+        // (the queue's `fifo` setting, a fragment of the error, if any)
+        let cases: [(&str, Option<&str>); 5] = [
+            ("{high_throughput: true}", None),
+            (
+                "{content_based_deduplication: false, deduplication_scope: queue}",
+                None,
+            ),
+            (
+                "{content_based_deduplication: false, high_throughput: true, deduplication_scope: message_group}",
+                None,
+            ),
+            (
+                "{high_throughput: true, deduplication_scope: queue}",
+                Some("`deduplication_scope` must be `message_group`"),
+            ),
+            (
+                "{content_based_deduplication: false, high_throughput: true}",
+                Some("producers must supply a `MessageDeduplicationId`"),
+            ),
+        ];
+
+        for (setting, expected) in cases {
+            let errors: Vec<String> = PinnConfig::from_yaml(format!(
+                "orders.fifo:\n  topics: [placed]\n  fifo: {}\n",
+                setting
+            ))
+            .unwrap()
+            .fifo_errors();
+
+            match expected {
+                None => assert!(errors.is_empty(), "{}: {:?}", setting, errors),
+                Some(fragment) => {
+                    assert_eq!(errors.len(), 1, "{}: {:?}", setting, errors);
+                    assert!(
+                        errors[0].starts_with(
+                            "The FIFO settings of queue 'orders.fifo' can't be used together"
+                        ) && errors[0].contains(fragment),
+                        "{}: {}",
+                        setting,
+                        errors[0]
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn queues_override_the_default_fifo_settings() {
+        let yaml: &str = r#"
+defaults:
+  fifo: true
+orders.fifo:
+  topics: [placed]
+billing.fifo:
+  topics: [invoiced]
+  fifo:
+    content_based_deduplication: false
+    high_throughput: true
+    deduplication_scope: message_group
+audit:
+  topics: [audited]
+  fifo: false
+"#;
+        let json: &str = r#"{
+  "defaults": {"fifo": true},
+  "orders.fifo": {"topics": ["placed"]},
+  "billing.fifo": {
+    "topics": ["invoiced"],
+    "fifo": {"content_based_deduplication": false, "high_throughput": true, "deduplication_scope": "message_group"}
+  },
+  "audit": {"topics": ["audited"], "fifo": false}
+}"#;
+
+        for config in [
+            PinnConfig::from_yaml(yaml).unwrap(),
+            PinnConfig::from_json(json).unwrap(),
+        ] {
+            assert!(config.get("defaults").is_none());
+            assert_eq!(
+                config["orders.fifo"].fifo_settings(),
+                Some(FifoSettings::default())
+            );
+            assert_eq!(
+                config["billing.fifo"].fifo_settings(),
+                Some(FifoSettings {
+                    content_based_deduplication: false,
+                    high_throughput: true,
+                    deduplication_scope: Some(DeduplicationScope::MessageGroup),
+                })
+            );
+            assert!(!config["audit"].is_fifo());
+            assert!(
+                config.fifo_errors().is_empty(),
+                "{:?}",
+                config.fifo_errors()
+            );
+        }
+    }
+
+    /// The names the configuration reserves for settings
+    /// (and the sentinel entry) rather than queues
+    const RESERVED_NAMES: [&str; 5] = ["tags", "kms_key_id", "sse", "defaults", "unsubscribed"];
+
+    /// Arbitrary JSON documents, of any shape
+    fn json_values() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::from),
+            any::<i64>().prop_map(Value::from),
+            any::<u64>().prop_map(Value::from),
+            any::<f64>().prop_map(Value::from),
+            ".{0,12}".prop_map(Value::from),
+        ];
+
+        leaf.prop_recursive(6, 64, 8, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..8).prop_map(Value::from),
+                prop::collection::btree_map(
+                    prop_oneof![
+                        "[a-z_]{1,12}",
+                        Just("topics".to_string()),
+                        Just("tags".to_string())
+                    ],
+                    inner,
+                    0..8
+                )
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    /// Valid configurations (as JSON documents) of up to
+    /// a handful of queues, each with a few topics
+    fn configs() -> impl Strategy<Value = Value> {
+        let queue = (
+            prop::collection::btree_set("[a-z][a-z0-9-]{0,10}", 0..4),
+            prop::option::of(0u32..=43_200),
+        )
+            .prop_map(|(topics, visibility_timeout)| {
+                let mut queue = serde_json::json!({ "topics": topics });
+
+                if let Some(seconds) = visibility_timeout {
+                    queue["visibility_timeout_secs"] = Value::from(seconds);
+                }
+
+                queue
+            });
+
+        prop::collection::btree_map(
+            "[a-z][a-z0-9]{0,10}".prop_filter("reserved name", |name| {
+                !RESERVED_NAMES.contains(&name.as_str())
+            }),
+            queue,
+            0..6,
+        )
+        .prop_map(|queues| Value::Object(queues.into_iter().collect()))
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_text_never_panics(text in any::<String>()) {
+            let _ = PinnConfig::from_json(&text);
+            let _ = PinnConfig::from_yaml(&text);
+            let _ = PinnConfig::from_toml(&text);
+        }
+
+        #[test]
+        fn arbitrary_documents_never_panic(document in json_values()) {
+            let json: String = document.to_string();
+
+            // (Every JSON document is a YAML document too)
+            let _ = PinnConfig::from_json(&json);
+            let _ = PinnConfig::from_yaml(&json);
+
+            if let Ok(toml) = toml::to_string(&document) {
+                let _ = PinnConfig::from_toml(toml);
+            }
+        }
+
+        #[test]
+        fn configs_parse_identically_and_round_trip(document in configs()) {
+            let json = PinnConfig::from_json(document.to_string()).unwrap();
+            let yaml = PinnConfig::from_yaml(serde_yaml::to_string(&document).unwrap()).unwrap();
+            let toml = PinnConfig::from_toml(toml::to_string(&document).unwrap()).unwrap();
+
+            prop_assert_eq!(json.len(), document.as_object().unwrap().len());
+            prop_assert_eq!(as_value(&json), as_value(&yaml));
+            prop_assert_eq!(as_value(&json), as_value(&toml));
+
+            let reparsed = PinnConfig::from_json(serde_json::to_string(&json).unwrap()).unwrap();
+
+            prop_assert_eq!(as_value(&reparsed), as_value(&json));
+        }
+    }
+}